@@ -0,0 +1,103 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The harness shared by the examples: window and context setup, the main loop and resize
+//! handling. An example implements `Example` and calls `run` from its `main`.
+//!
+//! Setting the environment variable `HTGL_EXAMPLE_FRAMES` to a number runs the example
+//! headless: the window is hidden and the example exits after drawing that many frames. As
+//! OpenGL errors panic in debug builds, this makes the examples usable as integration tests:
+//!
+//!    HTGL_EXAMPLE_FRAMES=10 cargo run --example triangle
+
+use std::env;
+
+use glfw::Context as GlfwContext;
+
+use htgl::Context;
+
+/// An executable example.
+pub trait Example: Sized {
+    /// The title of the window.
+    const TITLE: &'static str;
+
+    /// Create the resources of the example.
+    fn new(ctx: &mut Context) -> Self;
+
+    /// Called when the size of the drawable area changes, and once before the first frame. The
+    /// viewport has already been set.
+    fn resize(&mut self, _ctx: &mut Context, _width: u32, _height: u32) {
+    }
+
+    /// Draw a frame. The time is in seconds since the start of the example.
+    fn frame(&mut self, ctx: &mut Context, time: f32);
+}
+
+/// Open a window with an OpenGL 3.3 core context and run the example in it until the window is
+/// closed or Escape is pressed.
+pub fn run<E: Example>() {
+    let frame_limit = env::var("HTGL_EXAMPLE_FRAMES").ok().map(|frames| {
+        frames.parse::<u32>().expect("HTGL_EXAMPLE_FRAMES must be a number of frames")
+    });
+
+    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
+    glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
+    glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+    glfw.window_hint(glfw::WindowHint::Visible(frame_limit.is_none()));
+
+    let (mut window, events) = glfw.create_window(640, 480, E::TITLE, glfw::WindowMode::Windowed)
+        .expect("Failed to create GLFW window.");
+    window.set_key_polling(true);
+    window.set_framebuffer_size_polling(true);
+    window.make_current();
+
+    let mut ctx = Context::from_loader(|s| window.get_proc_address(s)).unwrap();
+    let mut example = E::new(&mut ctx);
+    let (width, height) = window.get_framebuffer_size();
+    resize(&mut ctx, &mut example, width, height);
+
+    let start_time = glfw.get_time();
+    let mut frames = 0;
+    while !window.should_close() && frame_limit.is_none_or(|limit| frames < limit) {
+        glfw.poll_events();
+        for (_, event) in glfw::flush_messages(&events) {
+            match event {
+                glfw::WindowEvent::Key(glfw::Key::Escape, _, glfw::Action::Press, _) => {
+                    window.set_should_close(true)
+                },
+                glfw::WindowEvent::FramebufferSize(width, height) => {
+                    resize(&mut ctx, &mut example, width, height)
+                },
+                _ => {}
+            }
+        }
+
+        example.frame(&mut ctx, (glfw.get_time() - start_time) as f32);
+        window.swap_buffers();
+        ctx.delete_queued_objects();
+        frames += 1;
+    }
+
+    if frame_limit.is_some() {
+        println!("{} frames, {:?}", frames, ctx.resource_report());
+    }
+}
+
+fn resize<E: Example>(ctx: &mut Context, example: &mut E, width: i32, height: i32) {
+    ctx.with_raw_gl(|_| unsafe {
+        gl::Viewport(0, 0, width, height);
+    });
+    example.resize(ctx, width as u32, height as u32);
+}
@@ -0,0 +1,152 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Drawing many quads with one draw call: the quad vertices come from one buffer and the
+//! per-instance offsets and colors from another, combined with `VertexLayout`.
+
+mod harness;
+
+use htgl::{Context,
+    VertexArrayHandle,
+    ProgramHandle,
+    VertexAttributeType,
+    VertexFormat,
+    VertexLayout,
+    RenderOption,
+    ShaderType,
+    PrimitiveMode,
+    DrawParams,
+    SimpleUniformTypeFloat};
+
+use harness::Example;
+
+const GRID_SIZE: u32 = 16;
+
+#[allow(dead_code)]
+#[repr(C)]
+struct QuadVertex {
+    position: [f32; 2]
+}
+
+impl VertexFormat for QuadVertex {
+    fn attributes() -> Vec<(u8, VertexAttributeType, bool)> {
+        vec![(2, VertexAttributeType::Float, false)]
+    }
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct Instance {
+    offset: [f32; 2],
+    color: [u8; 4]
+}
+
+impl VertexFormat for Instance {
+    fn attributes() -> Vec<(u8, VertexAttributeType, bool)> {
+        vec![(2, VertexAttributeType::Float, false), (4, VertexAttributeType::UnsignedByte, true)]
+    }
+}
+
+static VS_SOURCE: &str = "
+#version 330 core
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 offset;
+layout(location = 2) in vec4 color;
+
+uniform float time;
+
+out vec4 v_color;
+
+void main() {
+    float scale = 0.75 + 0.25 * sin(time + offset.x * 4.0);
+    gl_Position = vec4(position * scale + offset, 0.0, 1.0);
+    v_color = color;
+}
+";
+
+static FS_SOURCE: &str = "
+#version 330 core
+
+in vec4 v_color;
+out vec4 color;
+
+void main() {
+    color = v_color;
+}
+";
+
+struct Instancing {
+    vao: VertexArrayHandle,
+    program: ProgramHandle,
+    time_location: i32
+}
+
+impl Example for Instancing {
+    const TITLE: &'static str = "Instancing";
+
+    fn new(ctx: &mut Context) -> Instancing {
+        ctx.renderer().set_option(RenderOption::ClearColor(0.1, 0.1, 0.1, 1.0));
+        let half_size = 0.8 / GRID_SIZE as f32;
+        let quad = [
+            QuadVertex { position: [-half_size, -half_size] },
+            QuadVertex { position: [half_size, -half_size] },
+            QuadVertex { position: [-half_size, half_size] },
+            QuadVertex { position: [half_size, half_size] }
+        ];
+        let quad_vbo = ctx.new_buffer();
+        ctx.edit_vertex_buffer(&quad_vbo).data(&quad);
+
+        let mut instances = Vec::new();
+        for y in 0..GRID_SIZE {
+            for x in 0..GRID_SIZE {
+                let to_clip = |i: u32| (i as f32 + 0.5) / GRID_SIZE as f32 * 2.0 - 1.0;
+                let to_color = |i: u32| (i * 255 / (GRID_SIZE - 1)) as u8;
+                instances.push(Instance { offset: [to_clip(x), to_clip(y)], color: [to_color(x), to_color(y), 128, 255] });
+            }
+        }
+        let instance_vbo = ctx.new_buffer();
+        ctx.edit_vertex_buffer(&instance_vbo).data(&instances[..]);
+
+        let layout = VertexLayout::new()
+            .interleaved::<QuadVertex>(quad_vbo)
+            .interleaved_instanced::<Instance>(instance_vbo, 1);
+        let vao = ctx.new_vertex_array_layout(&layout, None).unwrap();
+
+        let shaders = [
+            ctx.new_shader(ShaderType::VertexShader, VS_SOURCE),
+            ctx.new_shader(ShaderType::FragmentShader, FS_SOURCE)
+        ];
+        let program = ctx.new_program(&shaders);
+        if !ctx.program_info(&program).get_link_status() {
+            panic!("{}", ctx.program_info(&program).get_info_log());
+        }
+        let time_location = ctx.program_info(&program).get_uniform_location("time");
+        Instancing { vao, program, time_location }
+    }
+
+    fn frame(&mut self, ctx: &mut Context, time: f32) {
+        let mut renderer = ctx.renderer();
+        renderer.clear();
+        renderer.use_vertex_array(&self.vao);
+        renderer.use_program(&self.program);
+        renderer.uniform_f32(self.time_location, 1, SimpleUniformTypeFloat::Uniform1f, &[time]);
+        renderer.draw(&DrawParams::arrays(PrimitiveMode::TriangleStrip, 0, 4).instances(GRID_SIZE * GRID_SIZE));
+    }
+}
+
+fn main() {
+    harness::run::<Instancing>();
+}
@@ -0,0 +1,107 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! The simplest example: an indexed triangle with per-vertex colors.
+
+mod harness;
+
+use htgl::{Context,
+    VertexArrayHandle,
+    ProgramHandle,
+    VertexAttributeType,
+    RenderOption,
+    ShaderType,
+    PrimitiveMode};
+
+use harness::Example;
+
+#[allow(dead_code)]
+#[repr(C)]
+struct Vertex {
+    position: [f32; 3],
+    color: [u8; 4]
+}
+
+static VS_SOURCE: &str = "
+#version 330 core
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec4 color;
+
+out vec4 v_color;
+
+void main() {
+    gl_Position = vec4(position, 1.0);
+    v_color = color;
+}
+";
+
+static FS_SOURCE: &str = "
+#version 330 core
+
+in vec4 v_color;
+out vec4 color;
+
+void main() {
+    color = v_color;
+}
+";
+
+struct Triangle {
+    vao: VertexArrayHandle,
+    program: ProgramHandle
+}
+
+impl Example for Triangle {
+    const TITLE: &'static str = "Triangle";
+
+    fn new(ctx: &mut Context) -> Triangle {
+        ctx.renderer().set_option(RenderOption::ClearColor(1.0, 1.0, 1.0, 1.0));
+        let vbo = ctx.new_buffer();
+        let vertices = [
+            Vertex { position: [-0.5, -0.5, 0.0], color: [255, 0, 0, 255] },
+            Vertex { position: [0.5, -0.5, 0.0], color: [0, 255, 0, 255] },
+            Vertex { position: [0.0, 0.5, 0.0], color: [0, 0, 255, 255] }
+        ];
+        ctx.edit_vertex_buffer(&vbo).data(&vertices);
+        let ibo = ctx.new_buffer();
+        let attributes = [(3, VertexAttributeType::Float, false), (4, VertexAttributeType::UnsignedByte, true)];
+        let vao = ctx.new_vertex_array_simple(&attributes, vbo, Some(ibo));
+        if let Some(mut editor) = ctx.edit_index_buffer(&vao) {
+            editor.data(&[0u16, 1, 2]);
+        }
+        let shaders = [
+            ctx.new_shader(ShaderType::VertexShader, VS_SOURCE),
+            ctx.new_shader(ShaderType::FragmentShader, FS_SOURCE)
+        ];
+        let program = ctx.new_program(&shaders);
+        if !ctx.program_info(&program).get_link_status() {
+            panic!("{}", ctx.program_info(&program).get_info_log());
+        }
+        Triangle { vao, program }
+    }
+
+    fn frame(&mut self, ctx: &mut Context, _time: f32) {
+        let mut renderer = ctx.renderer();
+        renderer.clear();
+        renderer.use_vertex_array(&self.vao);
+        renderer.use_program(&self.program);
+        renderer.draw_elements_u16(PrimitiveMode::Triangles, 3, 0);
+    }
+}
+
+fn main() {
+    harness::run::<Triangle>();
+}
@@ -0,0 +1,145 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Passing per-frame values in a std140 uniform block, using a `PerFrameUbo` so that writing
+//! the next frame's values doesn't stall on the frames still being drawn. The layout of the
+//! Rust struct is checked against the block with `InterfaceBlock::validate_layout`.
+
+mod harness;
+
+use std::mem;
+
+use htgl::{Context,
+    VertexArrayHandle,
+    ProgramHandle,
+    PerFrameUbo,
+    VertexAttributeType,
+    RenderOption,
+    ShaderType,
+    PrimitiveMode};
+
+use harness::Example;
+
+const FRAME_BINDING: u32 = 0;
+
+/// Matches the `Frame` block of the vertex shader.
+#[repr(C)]
+#[derive(Clone,Copy,Debug)]
+struct FrameBlock {
+    color: [f32; 4],
+    rotation: [f32; 2],
+    scale: f32,
+    _padding: f32
+}
+
+static VS_SOURCE: &str = "
+#version 330 core
+
+layout(std140) uniform Frame {
+    vec4 color;
+    vec2 rotation;
+    float scale;
+};
+
+layout(location = 0) in vec2 position;
+
+void main() {
+    vec2 rotated = vec2(position.x * rotation.x - position.y * rotation.y,
+                        position.x * rotation.y + position.y * rotation.x);
+    gl_Position = vec4(rotated * scale, 0.0, 1.0);
+}
+";
+
+static FS_SOURCE: &str = "
+#version 330 core
+
+layout(std140) uniform Frame {
+    vec4 color;
+    vec2 rotation;
+    float scale;
+};
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = color;
+}
+";
+
+struct UniformBuffer {
+    vao: VertexArrayHandle,
+    program: ProgramHandle,
+    frame_ubo: PerFrameUbo
+}
+
+impl Example for UniformBuffer {
+    const TITLE: &'static str = "Uniform buffer";
+
+    fn new(ctx: &mut Context) -> UniformBuffer {
+        ctx.renderer().set_option(RenderOption::ClearColor(0.0, 0.0, 0.0, 1.0));
+        let vbo = ctx.new_buffer();
+        ctx.edit_vertex_buffer(&vbo).data(&[-0.5f32, -0.5, 0.5, -0.5, 0.0, 0.5]);
+        let vao = ctx.new_vertex_array_simple(&[(2, VertexAttributeType::Float, false)], vbo, None);
+
+        let shaders = [
+            ctx.new_shader(ShaderType::VertexShader, VS_SOURCE),
+            ctx.new_shader(ShaderType::FragmentShader, FS_SOURCE)
+        ];
+        let program = ctx.new_program(&shaders);
+        if !ctx.program_info(&program).get_link_status() {
+            panic!("{}", ctx.program_info(&program).get_info_log());
+        }
+        {
+            let editor = ctx.edit_program(&program);
+            let uniform_info = editor.program_info().get_uniform_info();
+            let block = uniform_info.blocks.iter().find(|block| block.name == "Frame").expect("No Frame block");
+            let field_offsets = [
+                ("color", mem::offset_of!(FrameBlock, color)),
+                ("rotation", mem::offset_of!(FrameBlock, rotation)),
+                ("scale", mem::offset_of!(FrameBlock, scale))
+            ];
+            if let Err(mismatches) = block.validate_layout(mem::size_of::<FrameBlock>(), &field_offsets) {
+                panic!("FrameBlock doesn't match the Frame block: {:?}", mismatches);
+            }
+            editor.uniform_block_binding(block.index, FRAME_BINDING);
+        }
+
+        let frame_ubo = PerFrameUbo::new(ctx, mem::size_of::<FrameBlock>(), 3);
+        UniformBuffer { vao, program, frame_ubo }
+    }
+
+    fn frame(&mut self, ctx: &mut Context, time: f32) {
+        let block = FrameBlock {
+            color: [0.5 + 0.5 * time.sin(), 0.5 + 0.5 * time.cos(), 1.0, 1.0],
+            rotation: [time.cos(), time.sin()],
+            scale: 1.0 + 0.25 * (time * 2.0).sin(),
+            _padding: 0.0
+        };
+        self.frame_ubo.write(ctx, &[block]);
+        {
+            let mut renderer = ctx.renderer();
+            renderer.clear();
+            renderer.use_vertex_array(&self.vao);
+            renderer.use_program(&self.program);
+            self.frame_ubo.bind(&mut renderer, FRAME_BINDING);
+            renderer.draw_arrays(PrimitiveMode::Triangles, 0, 3);
+        }
+        self.frame_ubo.end_frame(ctx);
+    }
+}
+
+fn main() {
+    harness::run::<UniformBuffer>();
+}
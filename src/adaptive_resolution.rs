@@ -0,0 +1,165 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Scaling the render resolution to keep the GPU frame time on target, see
+//! `AdaptiveResolution`.
+//!
+//! The GPU time of the frames is measured with GL_TIME_ELAPSED queries, so this requires
+//! OpenGL 3.3 and is not available on OpenGL ES.
+
+use std::collections::VecDeque;
+
+use super::Context;
+use super::query::{Query,QueryTarget};
+use super::renderer::Renderer;
+
+/// How many frames may be measured before the results are read back.
+const QUERY_COUNT: usize = 4;
+
+/// Picks the scale of the render resolution by how long the GPU takes to draw the frames. When
+/// the frames take longer than the target, the scale goes down, and when they take clearly less,
+/// it goes back up. The scale changes only after the frame time has been off for a number of
+/// frames in a row, and by a step at a time, so that a single slow frame doesn't make the
+/// resolution jump back and forth.
+///
+/// Each frame, draw the scene with `measure` and call `update` for the scale. Multiply the size
+/// of the main render target with it, for example with `TargetDesc::scaled` when the target is
+/// from a `TargetPool`, which hands out a target of the new size and frees the old one once it
+/// has been unused for long enough. The results of the queries become available a few frames
+/// later, so the scale lags behind the frame times a bit.
+#[derive(Debug)]
+pub struct AdaptiveResolution {
+    free_queries: Vec<Query>,
+    pending_queries: VecDeque<Query>,
+    target_frame_time: f32,
+    tolerance: f32,
+    frames_before_change: u32,
+    step: f32,
+    min_scale: f32,
+    max_scale: f32,
+    scale: f32,
+    slow_frames: u32,
+    fast_frames: u32,
+    last_frame_time: Option<f32>
+}
+
+impl AdaptiveResolution {
+    /// Create the controller aiming at the GPU frame time in milliseconds, starting from the
+    /// full resolution. By default the scale goes between 0.5 and 1.0 in steps of 0.1, after
+    /// five frames in a row that are 10% over the target or 10% under the target at the next
+    /// step up.
+    pub fn new(context: &mut Context, target_frame_time: f32) -> AdaptiveResolution {
+        AdaptiveResolution {
+            free_queries: (0..QUERY_COUNT).map(|_| context.new_query(QueryTarget::TimeElapsed)).collect(),
+            pending_queries: VecDeque::with_capacity(QUERY_COUNT),
+            target_frame_time,
+            tolerance: 0.1,
+            frames_before_change: 5,
+            step: 0.1,
+            min_scale: 0.5,
+            max_scale: 1.0,
+            scale: 1.0,
+            slow_frames: 0,
+            fast_frames: 0,
+            last_frame_time: None
+        }
+    }
+
+    /// Set the GPU frame time to aim at, in milliseconds.
+    pub fn set_target_frame_time(&mut self, milliseconds: f32) {
+        self.target_frame_time = milliseconds;
+    }
+
+    /// Set the range of the scale, and clamp the current scale to it.
+    pub fn set_scale_range(&mut self, min_scale: f32, max_scale: f32) {
+        self.min_scale = min_scale;
+        self.max_scale = max_scale;
+        self.scale = self.scale.clamp(min_scale, max_scale);
+    }
+
+    /// Set how much the scale changes at a time.
+    pub fn set_step(&mut self, step: f32) {
+        self.step = step;
+    }
+
+    /// Set the hysteresis: how far from the target, as a fraction of it, the frame time must be
+    /// before it counts as slow or fast, and for how many frames in a row before the scale
+    /// changes.
+    pub fn set_hysteresis(&mut self, tolerance: f32, frames_before_change: u32) {
+        self.tolerance = tolerance;
+        self.frames_before_change = frames_before_change;
+    }
+
+    /// Draw the commands of the closure, measuring the time the GPU takes to execute them. If
+    /// the results of the earlier frames haven't been read with `update` yet and all queries
+    /// are busy, the frame is not measured.
+    pub fn measure<'a, F, R>(&mut self, renderer: &mut Renderer<'a>, commands: F) -> R where F: FnOnce(&mut Renderer<'a>) -> R {
+        match self.free_queries.pop() {
+            Some(query) => {
+                let result = renderer.with_query(&query, commands);
+                self.pending_queries.push_back(query);
+                result
+            },
+            None => commands(renderer)
+        }
+    }
+
+    /// Read the frame times that have become available and return the scale for the next frame.
+    /// Doesn't block.
+    pub fn update(&mut self) -> f32 {
+        while let Some(nanoseconds) = self.pending_queries.front().and_then(|query| query.try_result_u64()) {
+            let query = self.pending_queries.pop_front().unwrap();
+            self.free_queries.push(query);
+            self.add_frame_time(nanoseconds as f32 / 1_000_000.0);
+        }
+        self.scale
+    }
+
+    /// The current scale, between the minimum and the maximum scale.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The GPU time of the latest measured frame in milliseconds, if any has been read yet.
+    pub fn last_frame_time(&self) -> Option<f32> {
+        self.last_frame_time
+    }
+
+    fn add_frame_time(&mut self, milliseconds: f32) {
+        self.last_frame_time = Some(milliseconds);
+        // The frame time goes roughly with the pixel count, the square of the scale
+        let next_scale = (self.scale + self.step).min(self.max_scale);
+        let ratio = next_scale / self.scale;
+        let projected = milliseconds * ratio * ratio;
+        if milliseconds > self.target_frame_time * (1.0 + self.tolerance) {
+            self.slow_frames += 1;
+            self.fast_frames = 0;
+        }
+        else if self.scale < self.max_scale && projected < self.target_frame_time * (1.0 - self.tolerance) {
+            self.fast_frames += 1;
+            self.slow_frames = 0;
+        }
+        else {
+            self.slow_frames = 0;
+            self.fast_frames = 0;
+        }
+        if self.slow_frames >= self.frames_before_change {
+            self.scale = (self.scale - self.step).max(self.min_scale);
+            self.slow_frames = 0;
+        }
+        else if self.fast_frames >= self.frames_before_change {
+            self.scale = next_scale;
+            self.fast_frames = 0;
+        }
+    }
+}
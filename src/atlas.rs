@@ -0,0 +1,303 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packing many small images into a single texture, see `TextureAtlas`.
+
+use super::TextureHandle;
+use super::context::Context;
+use super::image::ImageData;
+use super::info;
+use super::texture::{TextureFormat,TextureFilter};
+
+/// A rectangle allocated from a `TextureAtlas`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct AtlasRegion {
+    /// Left edge of the region in texels.
+    pub x: u32,
+    /// Bottom edge of the region in texels.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The texture coordinates of the region, as `[left, bottom, right, top]`.
+    pub uv_rect: [f32; 4],
+    /// The layer of the texture the region is in. Always zero, as only two-dimensional textures
+    /// are supported for now.
+    pub layer: u32
+}
+
+/// A horizontal strip of the atlas. Regions are placed on the shelf from left to right.
+#[derive(Clone,Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+    live_regions: u32
+}
+
+/// A texture with a simple shelf allocator, for packing glyphs, icons and other small images into
+/// one texture so that they can be drawn without switching textures.
+///
+/// Regions are allocated on horizontal shelves. A region goes on the shortest shelf that is tall
+/// enough and has room left, and a new shelf is started above the others if none fits. Freeing a
+/// region makes its shelf reusable once all regions on it are freed. As this leaves gaps, the
+/// atlas can be compacted with `defragment`.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    texture: TextureHandle,
+    packer: ShelfPacker
+}
+
+impl TextureAtlas {
+    /// Create an atlas of the given size and internal format. The padding is the number of
+    /// texels left empty between the regions, to avoid neighbouring images bleeding into each
+    /// other when filtering. The texture has no mipmaps, and is filtered linearly, or with the
+    /// nearest filter for integer formats.
+    pub fn new(context: &mut Context, width: u32, height: u32, format: TextureFormat, padding: u32) -> TextureAtlas {
+        let texture = context.new_texture_2d();
+        {
+            let filter = if info::is_integer_format(format) { TextureFilter::Nearest } else { TextureFilter::Linear };
+            let mut editor = context.edit_texture(&texture);
+            editor.allocate(0, format, width, height);
+            editor.set_filters(filter, filter);
+            editor.set_max_level(0);
+        }
+        TextureAtlas {
+            texture,
+            packer: ShelfPacker::new(width, height, padding)
+        }
+    }
+
+    /// The texture the atlas packs the images into. Bind this for drawing.
+    pub fn texture(&self) -> &TextureHandle {
+        &self.texture
+    }
+
+    /// The size of the atlas texture.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.packer.width, self.packer.height)
+    }
+
+    /// The regions currently allocated.
+    pub fn regions(&self) -> &[AtlasRegion] {
+        &self.packer.regions[..]
+    }
+
+    /// Allocate a region of the given size. Returns None if there is no room for it.
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<AtlasRegion> {
+        self.packer.allocate(width, height)
+    }
+
+    /// Free a region allocated earlier, so that the space can be reused. Panics if the region
+    /// isn't allocated from this atlas.
+    pub fn free(&mut self, region: &AtlasRegion) {
+        self.packer.free(region);
+    }
+
+    /// Free all regions.
+    pub fn clear(&mut self) {
+        self.packer.clear();
+    }
+
+    /// Upload the image into the region. Panics if the image is larger than the region.
+    pub fn upload(&self, context: &mut Context, region: &AtlasRegion, image: &impl ImageData) {
+        let (width, height) = image.dimensions();
+        if width > region.width || height > region.height {
+            panic!("Image of size {}x{} doesn't fit in an atlas region of size {}x{}",
+                width, height, region.width, region.height);
+        }
+        context.edit_texture(&self.texture).sub_upload(0, region.x, region.y, image);
+    }
+
+    /// Pack the allocated regions again, tallest first, to get rid of the gaps left by freed
+    /// regions. The texture contents are not moved: the returned pairs of old and new regions
+    /// tell what has moved, and the caller must upload the images again to the new regions.
+    ///
+    /// In the rare case the regions don't all fit after repacking, the atlas is left as it was
+    /// and nothing is returned.
+    pub fn defragment(&mut self) -> Vec<(AtlasRegion, AtlasRegion)> {
+        self.packer.defragment()
+    }
+}
+
+/// The allocator of `TextureAtlas`, apart from the texture.
+#[derive(Debug)]
+struct ShelfPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    shelves: Vec<Shelf>,
+    regions: Vec<AtlasRegion>
+}
+
+impl ShelfPacker {
+    fn new(width: u32, height: u32, padding: u32) -> ShelfPacker {
+        ShelfPacker {
+            width,
+            height,
+            padding,
+            shelves: Vec::new(),
+            regions: Vec::new()
+        }
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<AtlasRegion> {
+        let padded_width = width + self.padding;
+        let padded_height = height + self.padding;
+        let atlas_width = self.width;
+        let shelf_index = self.shelves.iter()
+            .enumerate()
+            .filter(|&(_, shelf)| shelf.height >= padded_height && shelf.used_width + padded_width <= atlas_width)
+            .min_by_key(|&(_, shelf)| shelf.height)
+            .map(|(index, _)| index);
+        let shelf_index = match shelf_index {
+            Some(index) => index,
+            None => {
+                let top = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if padded_width > self.width || top + padded_height > self.height {
+                    return None;
+                }
+                self.shelves.push(Shelf { y: top, height: padded_height, used_width: 0, live_regions: 0 });
+                self.shelves.len() - 1
+            }
+        };
+        let region = self.region(self.shelves[shelf_index].used_width, self.shelves[shelf_index].y, width, height);
+        let shelf = &mut self.shelves[shelf_index];
+        shelf.used_width += padded_width;
+        shelf.live_regions += 1;
+        self.regions.push(region);
+        Some(region)
+    }
+
+    fn free(&mut self, region: &AtlasRegion) {
+        let index = match self.regions.iter().position(|r| r == region) {
+            Some(index) => index,
+            None => panic!("Freeing a region that isn't allocated from the atlas: {:?}", region)
+        };
+        self.regions.swap_remove(index);
+        if let Some(shelf) = self.shelves.iter_mut().find(|shelf| shelf.y == region.y) {
+            shelf.live_regions -= 1;
+            if shelf.live_regions == 0 {
+                shelf.used_width = 0;
+            }
+        }
+        while self.shelves.last().is_some_and(|shelf| shelf.live_regions == 0) {
+            self.shelves.pop();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.shelves.clear();
+        self.regions.clear();
+    }
+
+    fn defragment(&mut self) -> Vec<(AtlasRegion, AtlasRegion)> {
+        let old_shelves = ::std::mem::take(&mut self.shelves);
+        let mut old_regions = ::std::mem::take(&mut self.regions);
+        old_regions.sort_by(|a, b| b.height.cmp(&a.height).then(b.width.cmp(&a.width)));
+        let mut moves = Vec::new();
+        for old in old_regions.iter() {
+            match self.allocate(old.width, old.height) {
+                Some(new) => {
+                    if new != *old {
+                        moves.push((*old, new));
+                    }
+                },
+                None => {
+                    self.shelves = old_shelves;
+                    self.regions = old_regions;
+                    return Vec::new();
+                }
+            }
+        }
+        moves
+    }
+
+    fn region(&self, x: u32, y: u32, width: u32, height: u32) -> AtlasRegion {
+        let atlas_width = self.width as f32;
+        let atlas_height = self.height as f32;
+        AtlasRegion {
+            x,
+            y,
+            width,
+            height,
+            uv_rect: [
+                x as f32 / atlas_width,
+                y as f32 / atlas_height,
+                (x + width) as f32 / atlas_width,
+                (y + height) as f32 / atlas_height
+            ],
+            layer: 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShelfPacker;
+
+    #[test]
+    fn regions_go_on_the_shortest_shelf_that_fits() {
+        let mut packer = ShelfPacker::new(32, 64, 1);
+        let tall = packer.allocate(20, 20).unwrap();
+        let short = packer.allocate(20, 8).unwrap();
+        let on_short = packer.allocate(10, 5).unwrap();
+        let on_tall = packer.allocate(10, 15).unwrap();
+        assert_eq!((tall.x, tall.y), (0, 0));
+        assert_eq!((short.x, short.y), (0, 21));
+        assert_eq!((on_short.x, on_short.y), (21, 21));
+        assert_eq!((on_tall.x, on_tall.y), (21, 0));
+        assert_eq!(tall.uv_rect, [0.0, 0.0, 20.0 / 32.0, 20.0 / 64.0]);
+    }
+
+    #[test]
+    fn allocation_fails_when_full() {
+        let mut packer = ShelfPacker::new(32, 32, 0);
+        assert!(packer.allocate(33, 1).is_none());
+        assert!(packer.allocate(32, 32).is_some());
+        assert!(packer.allocate(1, 1).is_none());
+    }
+
+    #[test]
+    fn freed_shelves_are_reused() {
+        let mut packer = ShelfPacker::new(32, 32, 0);
+        let bottom = packer.allocate(16, 16).unwrap();
+        let top = packer.allocate(32, 16).unwrap();
+        packer.free(&bottom);
+        assert_eq!(packer.allocate(32, 16).unwrap().y, 0);
+        packer.free(&top);
+        assert_eq!(packer.shelves.len(), 1);
+    }
+
+    #[test]
+    fn defragment_closes_the_gaps() {
+        let mut packer = ShelfPacker::new(32, 32, 0);
+        let first = packer.allocate(16, 8).unwrap();
+        let second = packer.allocate(16, 8).unwrap();
+        packer.free(&first);
+        let moves = packer.defragment();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].0, second);
+        assert_eq!((moves[0].1.x, moves[0].1.y), (0, 0));
+        assert_eq!(packer.regions, vec![moves[0].1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn freeing_an_unknown_region_panics() {
+        let mut packer = ShelfPacker::new(32, 32, 0);
+        let region = packer.allocate(8, 8).unwrap();
+        packer.free(&region);
+        packer.free(&region);
+    }
+}
@@ -12,10 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-extern crate glfw;
-
-extern crate htgl;
-
 use glfw::Context;
 
 use htgl::{VertexAttributeType,
@@ -54,7 +50,7 @@ impl Vertex {
     }
 }
 
-static VS_SOURCE: &'static str = "
+static VS_SOURCE: &str = "
 #version 330 core
 
 layout(location = 0) in vec3 position;
@@ -71,7 +67,7 @@ void main() {
 }
 ";
 
-static FS_SOURCE: &'static str = "
+static FS_SOURCE: &str = "
 #version 330 core
 
 in vec4 v_color;
@@ -96,9 +92,7 @@ fn main() {
     window.set_key_polling(true);
     window.make_current();
 
-    htgl::load_with(|s| window.get_proc_address(s));
-
-    let mut ctx = htgl::Context::new();
+    let mut ctx = htgl::Context::from_loader(|s| window.get_proc_address(s)).unwrap();
     println!("{:?}", ctx.get_info());
     ctx.renderer().set_option(RenderOption::ClearColor(1f32, 1f32, 1f32, 1f32));
     ctx.renderer().set_option(RenderOption::DepthTest(false));
@@ -118,15 +112,15 @@ fn main() {
     }
     let vs = ctx.new_shader(ShaderType::VertexShader, VS_SOURCE);
     if !ctx.shader_info(&vs).get_compile_status() {
-        panic!(ctx.shader_info(&vs).get_info_log())
+        panic!("{}", ctx.shader_info(&vs).get_info_log())
     }
     let fs = ctx.new_shader(ShaderType::FragmentShader, FS_SOURCE);
     if !ctx.shader_info(&fs).get_compile_status() {
-        panic!(ctx.shader_info(&fs).get_info_log())
+        panic!("{}", ctx.shader_info(&fs).get_info_log())
     }
     let program = ctx.new_program(&[vs, fs]);
     if !ctx.program_info(&program).get_link_status() {
-        panic!(ctx.program_info(&program).get_info_log())
+        panic!("{}", ctx.program_info(&program).get_info_log())
     }
 
     {
@@ -0,0 +1,105 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Assigning binding points to names, see `BindingPointAllocator`.
+
+use std::collections::HashMap;
+
+/// Assigns binding points by name, so that separately written materials and passes agree on the
+/// binding point of, say, the camera uniform block without numbering them by hand. The context
+/// has one for uniform buffers and one for shader storage buffers, see
+/// `Context::uniform_binding_points` and `Context::storage_binding_points`.
+///
+/// Allocating the same name again gives the same binding point. The lowest free binding point is
+/// assigned to a new name, up to the limit given at creation.
+#[derive(Clone,Debug)]
+pub struct BindingPointAllocator {
+    limit: u32,
+    assigned: HashMap<String, u32>
+}
+
+impl BindingPointAllocator {
+    /// Create an allocator of the binding points `0..limit`.
+    pub fn new(limit: u32) -> BindingPointAllocator {
+        BindingPointAllocator { limit, assigned: HashMap::new() }
+    }
+
+    /// The number of binding points that can be assigned.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Get the binding point of the name, assigning the lowest free one if it has none yet.
+    /// Returns None if all the binding points are taken.
+    pub fn alloc(&mut self, name: &str) -> Option<u32> {
+        if let Some(&binding) = self.assigned.get(name) {
+            return Some(binding);
+        }
+        let binding = (0..self.limit).find(|binding| !self.assigned.values().any(|b| b == binding))?;
+        self.assigned.insert(name.to_string(), binding);
+        Some(binding)
+    }
+
+    /// The binding point assigned to the name, if any.
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.assigned.get(name).cloned()
+    }
+
+    /// Free the binding point of the name, so that it can be assigned to another name.
+    pub fn free(&mut self, name: &str) -> Option<u32> {
+        self.assigned.remove(name)
+    }
+
+    /// Free all the binding points.
+    pub fn clear(&mut self) {
+        self.assigned.clear();
+    }
+
+    /// The assigned names and their binding points, in binding point order.
+    pub fn assignments(&self) -> Vec<(&str, u32)> {
+        let mut assignments: Vec<(&str, u32)> = self.assigned.iter().map(|(name, &binding)| (&name[..], binding)).collect();
+        assignments.sort_by_key(|&(_, binding)| binding);
+        assignments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BindingPointAllocator;
+
+    #[test]
+    fn names_keep_their_binding_points() {
+        let mut allocator = BindingPointAllocator::new(4);
+        assert_eq!(allocator.alloc("camera"), Some(0));
+        assert_eq!(allocator.alloc("lights"), Some(1));
+        assert_eq!(allocator.alloc("camera"), Some(0));
+        assert_eq!(allocator.get("lights"), Some(1));
+        assert_eq!(allocator.get("material"), None);
+        assert_eq!(allocator.assignments(), vec![("camera", 0), ("lights", 1)]);
+    }
+
+    #[test]
+    fn freed_binding_points_are_reassigned_lowest_first() {
+        let mut allocator = BindingPointAllocator::new(3);
+        for name in ["a", "b", "c"].iter() {
+            allocator.alloc(name);
+        }
+        assert_eq!(allocator.alloc("d"), None);
+        assert_eq!(allocator.free("b"), Some(1));
+        assert_eq!(allocator.free("b"), None);
+        assert_eq!(allocator.alloc("d"), Some(1));
+        allocator.clear();
+        assert_eq!(allocator.alloc("c"), Some(0));
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Comparing the bindings OpenGL reports with what the binding trackers believe, see
+//! `Context::debug_dump_bindings`.
+
+use gl::types::{GLenum,GLint};
+
+use super::tracker::IndexedBufferTarget;
+use super::info::{ContextInfo,ExtensionFeature};
+use super::texture::TextureTarget;
+
+/// A range of a buffer bound to an indexed binding point. See glBindBufferRange.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct IndexedBufferBinding {
+    pub binding: u32,
+    /// The OpenGL name of the buffer.
+    pub buffer: u32,
+    pub offset: usize,
+    pub size: usize
+}
+
+/// A binding point where OpenGL has a different object bound than the context believes. Usually
+/// the result of calling OpenGL directly outside `Context::with_raw_gl`.
+#[derive(Clone,Debug,PartialEq)]
+pub struct BindingDivergence {
+    /// The binding point, for example "GL_ARRAY_BUFFER" or "GL_TEXTURE_BINDING_2D of unit 3".
+    pub binding: String,
+    /// The OpenGL name the context believes to be bound.
+    pub believed: u32,
+    /// The OpenGL name actually bound.
+    pub actual: u32
+}
+
+/// The objects bound to the binding points of the context, as OpenGL reports them. The values
+/// are OpenGL object names, zero meaning nothing bound. See `Context::debug_dump_bindings`.
+#[derive(Clone,Debug,PartialEq)]
+pub struct BindingReport {
+    /// GL_CURRENT_PROGRAM
+    pub program: u32,
+    /// GL_VERTEX_ARRAY_BINDING
+    pub vertex_array: u32,
+    /// GL_ARRAY_BUFFER_BINDING
+    pub array_buffer: u32,
+    /// GL_ELEMENT_ARRAY_BUFFER_BINDING, which is part of the vertex array state.
+    pub element_array_buffer: u32,
+    /// GL_UNIFORM_BUFFER_BINDING, the generic binding point.
+    pub uniform_buffer: u32,
+    /// The indexed uniform buffer binding points that have a buffer bound.
+    pub uniform_buffer_ranges: Vec<IndexedBufferBinding>,
+    /// The indexed shader storage buffer binding points that have a buffer bound. Empty if
+    /// storage buffers are not supported.
+    pub storage_buffer_ranges: Vec<IndexedBufferBinding>,
+    /// The indexed atomic counter buffer binding points that have a buffer bound. Empty if
+    /// atomic counters are not supported.
+    pub atomic_counter_buffer_ranges: Vec<IndexedBufferBinding>,
+    /// The indexed transform feedback buffer binding points that have a buffer bound.
+    pub transform_feedback_buffer_ranges: Vec<IndexedBufferBinding>,
+    /// The texture units that have a texture bound, as (unit, target, texture), an entry for
+    /// each target of a unit that has a texture bound. Buffer textures are only queried if the
+    /// context has them.
+    pub textures: Vec<(u32, TextureTarget, u32)>,
+    /// GL_DRAW_FRAMEBUFFER_BINDING
+    pub draw_framebuffer: u32,
+    /// GL_READ_FRAMEBUFFER_BINDING
+    pub read_framebuffer: u32,
+    /// The binding points where OpenGL disagrees with the context. Binding points the context
+    /// doesn't know the state of, like after `Context::with_raw_gl`, are not compared.
+    pub divergences: Vec<BindingDivergence>
+}
+
+impl BindingReport {
+    /// Does OpenGL agree with the context on all the compared binding points?
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    /// The texture bound to the target of the texture unit, zero if none.
+    pub fn texture(&self, unit: u32, target: TextureTarget) -> u32 {
+        self.textures.iter()
+            .find(|&&(texture_unit, texture_target, _)| texture_unit == unit && texture_target == target)
+            .map_or(0, |&(_, _, texture)| texture)
+    }
+
+    /// The indexed binding points of the target that have a buffer bound.
+    pub fn ranges(&self, target: IndexedBufferTarget) -> &[IndexedBufferBinding] {
+        match target {
+            IndexedBufferTarget::Uniform => &self.uniform_buffer_ranges,
+            IndexedBufferTarget::ShaderStorage => &self.storage_buffer_ranges,
+            IndexedBufferTarget::AtomicCounter => &self.atomic_counter_buffer_ranges,
+            IndexedBufferTarget::TransformFeedback => &self.transform_feedback_buffer_ranges
+        }
+    }
+
+    /// Compare a binding point with what the context believes to be bound, recording a
+    /// divergence if they differ.
+    pub fn compare(&mut self, binding: &str, believed: Option<u32>, actual: u32) {
+        match believed {
+            Some(believed) if believed != actual => {
+                self.divergences.push(BindingDivergence { binding: binding.to_string(), believed, actual });
+            },
+            _ => ()
+        }
+    }
+}
+
+/// Query the bindings from OpenGL. The texture units are gone through with glActiveTexture,
+/// which is restored afterwards. Nothing is compared yet.
+pub fn query_bindings(info: &ContextInfo, texture_units: u32, uniform_buffer_bindings: u32) -> BindingReport {
+    let uniform_buffer_ranges = query_ranges(IndexedBufferTarget::Uniform, uniform_buffer_bindings);
+    let storage_buffer_ranges = query_ranges(IndexedBufferTarget::ShaderStorage,
+        info.extension_limits.max_storage_buffer_bindings as u32);
+    let atomic_counter_buffer_ranges = if info.supports(ExtensionFeature::AtomicCounterBuffer) {
+        query_ranges(IndexedBufferTarget::AtomicCounter, get_integer(gl::MAX_ATOMIC_COUNTER_BUFFER_BINDINGS) as u32)
+    }
+    else {
+        Vec::new()
+    };
+    let transform_feedback_buffer_ranges = query_ranges(IndexedBufferTarget::TransformFeedback,
+        info.shader_stages.max_transform_feedback_buffers as u32);
+    let mut texture_targets = vec![TextureTarget::Texture2d];
+    if info.texture.max_buffer_size > 0 {
+        texture_targets.push(TextureTarget::Buffer);
+    }
+    if info.texture.max_color_samples > 0 {
+        texture_targets.push(TextureTarget::Texture2dMultisample);
+    }
+    let active_texture = get_integer(gl::ACTIVE_TEXTURE) as GLenum;
+    let mut textures = Vec::new();
+    for unit in 0..texture_units {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+        }
+        check_error!();
+        for &target in texture_targets.iter() {
+            match get_integer(texture_binding_name(target)) as u32 {
+                0 => (),
+                texture => textures.push((unit, target, texture))
+            }
+        }
+    }
+    unsafe {
+        gl::ActiveTexture(active_texture);
+    }
+    check_error!();
+    BindingReport {
+        program: get_integer(gl::CURRENT_PROGRAM) as u32,
+        vertex_array: get_integer(gl::VERTEX_ARRAY_BINDING) as u32,
+        array_buffer: get_integer(gl::ARRAY_BUFFER_BINDING) as u32,
+        element_array_buffer: get_integer(gl::ELEMENT_ARRAY_BUFFER_BINDING) as u32,
+        uniform_buffer: get_integer(gl::UNIFORM_BUFFER_BINDING) as u32,
+        uniform_buffer_ranges,
+        storage_buffer_ranges,
+        atomic_counter_buffer_ranges,
+        transform_feedback_buffer_ranges,
+        textures,
+        draw_framebuffer: get_integer(gl::DRAW_FRAMEBUFFER_BINDING) as u32,
+        read_framebuffer: get_integer(gl::READ_FRAMEBUFFER_BINDING) as u32,
+        divergences: Vec::new()
+    }
+}
+
+/// The binding query of the texture target, such as GL_TEXTURE_BINDING_2D.
+fn texture_binding_name(target: TextureTarget) -> GLenum {
+    match target {
+        TextureTarget::Texture2d => gl::TEXTURE_BINDING_2D,
+        TextureTarget::Buffer => gl::TEXTURE_BINDING_BUFFER,
+        TextureTarget::Texture2dMultisample => gl::TEXTURE_BINDING_2D_MULTISAMPLE
+    }
+}
+
+/// Query the binding points of the target that have a buffer bound.
+fn query_ranges(target: IndexedBufferTarget, bindings: u32) -> Vec<IndexedBufferBinding> {
+    let (binding_property, start_property, size_property) = match target {
+        IndexedBufferTarget::Uniform =>
+            (gl::UNIFORM_BUFFER_BINDING, gl::UNIFORM_BUFFER_START, gl::UNIFORM_BUFFER_SIZE),
+        IndexedBufferTarget::ShaderStorage =>
+            (gl::SHADER_STORAGE_BUFFER_BINDING, gl::SHADER_STORAGE_BUFFER_START, gl::SHADER_STORAGE_BUFFER_SIZE),
+        IndexedBufferTarget::AtomicCounter =>
+            (gl::ATOMIC_COUNTER_BUFFER_BINDING, gl::ATOMIC_COUNTER_BUFFER_START, gl::ATOMIC_COUNTER_BUFFER_SIZE),
+        IndexedBufferTarget::TransformFeedback =>
+            (gl::TRANSFORM_FEEDBACK_BUFFER_BINDING, gl::TRANSFORM_FEEDBACK_BUFFER_START, gl::TRANSFORM_FEEDBACK_BUFFER_SIZE)
+    };
+    (0..bindings).filter_map(|binding| {
+        let buffer = get_indexed_integer(binding_property, binding) as u32;
+        if buffer == 0 {
+            return None;
+        }
+        Some(IndexedBufferBinding {
+            binding,
+            buffer,
+            offset: get_indexed_integer(start_property, binding) as usize,
+            size: get_indexed_integer(size_property, binding) as usize
+        })
+    }).collect()
+}
+
+fn get_integer(property: GLenum) -> GLint {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(property, &mut value);
+    }
+    check_error!();
+    value
+}
+
+fn get_indexed_integer(property: GLenum, index: u32) -> GLint {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegeri_v(property, index, &mut value);
+    }
+    check_error!();
+    value
+}
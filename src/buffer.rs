@@ -25,15 +25,19 @@
 //! Note that to draw with a vertex buffer and an index buffer, they must be attached to an
 //! vertex array object, and then use (read: bind) it to have the buffers in use when drawing.
 
-use gl;
-use gl::types::{GLenum,GLsizeiptr,GLvoid};
+use gl::types::{GLenum,GLint,GLintptr,GLsizei,GLsizeiptr,GLvoid};
 
-use std::mem::size_of;
+use std::mem::{size_of,size_of_val};
+use std::cell::Cell;
+use std::fmt;
+use std::ptr;
 
 use super::tracker::Bind;
-use super::context::{Context,ContextEditingSupport,RegistrationHandle};
+use super::context::{Context,ContextEditingSupport,RegistrationHandle,QueuedDeletion,ContextResource};
 use super::vertexarray::VertexArray;
 use super::tracker::TrackerId;
+use super::raw::Ownership;
+use super::texture::{self,TextureFormat};
 
 /// The different recognized buffer types.
 #[derive(Clone,Copy,Debug)]
@@ -43,14 +47,39 @@ pub enum BufferType {
     /// GL_ELEMENT_ARRAY_BUFFER
     IndexBuffer,
     /// GL_UNIFORM_BUFFER
-    UniformBuffer
+    UniformBuffer,
+    /// GL_DRAW_INDIRECT_BUFFER
+    DrawIndirectBuffer
+}
+
+/// Types of the indices in an index buffer.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum IndexType {
+    /// GL_UNSIGNED_BYTE
+    U8,
+    /// GL_UNSIGNED_SHORT
+    U16,
+    /// GL_UNSIGNED_INT
+    U32
+}
+
+impl IndexType {
+    /// Size of a single index in bytes.
+    pub fn size(&self) -> usize {
+        match *self {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4
+        }
+    }
 }
 
 fn type_to_target(buffer_type: BufferType) -> GLenum {
     match buffer_type {
         BufferType::VertexBuffer => gl::ARRAY_BUFFER,
         BufferType::IndexBuffer => gl::ELEMENT_ARRAY_BUFFER,
-        BufferType::UniformBuffer => gl::UNIFORM_BUFFER
+        BufferType::UniformBuffer => gl::UNIFORM_BUFFER,
+        BufferType::DrawIndirectBuffer => gl::DRAW_INDIRECT_BUFFER
     }
 }
 
@@ -58,7 +87,12 @@ fn type_to_target(buffer_type: BufferType) -> GLenum {
 pub struct BufferObject {
     pub id: u32,
     tracker_id: TrackerId,
-    registration: RegistrationHandle
+    registration: RegistrationHandle,
+    /// Size of the data store in bytes, as recorded by `data`.
+    size: Cell<usize>,
+    /// Type of the indices, as recorded by `BufferEditor::data_indices`.
+    index_type: Cell<Option<IndexType>>,
+    ownership: Ownership
 }
 
 /// Create a new buffer object.
@@ -66,6 +100,28 @@ pub fn new_buffer(tracker_id: TrackerId, registration: RegistrationHandle) -> Bu
     BufferObject::new(tracker_id, registration)
 }
 
+/// Wrap an existing buffer object. The size of its data store is queried from OpenGL, which
+/// requires binding it.
+pub fn adopt_buffer(context: &mut Context, gl_id: u32, tracker_id: TrackerId, registration: RegistrationHandle, ownership: Ownership) -> BufferObject {
+    let buffer = BufferObject {
+        id: gl_id,
+        tracker_id,
+        registration,
+        size: Cell::new(0),
+        index_type: Cell::new(None),
+        ownership
+    };
+    context.bind_vbo_for_editing(&buffer);
+    let mut size: GLint = 0;
+    unsafe {
+        gl::GetBufferParameteriv(gl::ARRAY_BUFFER, gl::BUFFER_SIZE, &mut size);
+        check_error!();
+    }
+    buffer.size.set(size as usize);
+    buffer.registration.set_memory(size as usize);
+    buffer
+}
+
 impl BufferObject {
     fn new(tracker_id: TrackerId, registration: RegistrationHandle) -> BufferObject {
         let mut id: u32 = 0;
@@ -76,7 +132,10 @@ impl BufferObject {
         BufferObject {
             id: id,
             tracker_id: tracker_id,
-            registration: registration
+            registration,
+            size: Cell::new(0),
+            index_type: Cell::new(None),
+            ownership: Ownership::Owned
         }
     }
 
@@ -86,14 +145,129 @@ impl BufferObject {
             gl::BufferData(type_to_target(buffer_type), data_size, data.as_ptr() as *const GLvoid, gl::STATIC_DRAW);
             check_error!();
         }
+        self.size.set(data_size as usize);
+        self.index_type.set(None);
+        self.registration.set_memory(data_size as usize);
+    }
+
+    /// Replace the data store with an uninitialized one of the size. See glBufferData.
+    pub fn allocate(&self, buffer_type: BufferType, size: usize) {
+        unsafe {
+            gl::BufferData(type_to_target(buffer_type), size as GLsizeiptr, ptr::null(), gl::STATIC_DRAW);
+            check_error!();
+        }
+        self.size.set(size);
+        self.index_type.set(None);
+        self.registration.set_memory(size);
+    }
+
+    /// Size of the buffer's data store in bytes.
+    pub fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    /// The type of the indices written with `BufferEditor::data_indices`. None if the data was
+    /// written some other way.
+    pub fn index_type(&self) -> Option<IndexType> {
+        self.index_type.get()
     }
 
     pub fn sub_data<D>(&self, buffer_type: BufferType, data: &[D], byte_offset: usize) {
         let data_size = (size_of::<D>() * data.len()) as GLsizeiptr;
+        let end = byte_offset + data_size as usize;
+        if end > self.size() {
+            panic!("Writing bytes {}..{} of a buffer of {} bytes", byte_offset, end, self.size());
+        }
+        unsafe {
+            gl::BufferSubData(type_to_target(buffer_type), byte_offset as GLintptr, data_size, data.as_ptr() as *const GLvoid);
+            check_error!();
+        }
+    }
+
+    /// Fill the whole data store by repeating the value, an element of the given format. See
+    /// `BufferEditor::clear`.
+    pub fn clear(&self, buffer_type: BufferType, format: TextureFormat, value: &[u8], use_clear_data: bool) {
+        let (pixel_format, pixel_type, element_size) = clear_format(format);
+        if value.len() != element_size {
+            panic!("A value of the format {:?} takes {} bytes, got {}", format, element_size, value.len());
+        }
+        let size = self.size();
+        if !size.is_multiple_of(element_size) {
+            panic!("The buffer size {} is not a multiple of the size {} of the format {:?}", size, element_size, format);
+        }
+        if size == 0 {
+            return;
+        }
+        let target = type_to_target(buffer_type);
         unsafe {
-            gl::BufferSubData(type_to_target(buffer_type), data_size, byte_offset as GLsizeiptr, data.as_ptr() as *const GLvoid);
+            if use_clear_data {
+                gl::ClearBufferData(target, texture::texture_format_to_enum(format), pixel_format, pixel_type,
+                    value.as_ptr() as *const GLvoid);
+                check_error!();
+            }
+            else {
+                let access = gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT;
+                let mapped = gl::MapBufferRange(target, 0, size as GLsizeiptr, access) as *mut u8;
+                check_error!();
+                if mapped.is_null() {
+                    panic!("Failed to map the buffer for clearing");
+                }
+                for offset in (0..size).step_by(element_size) {
+                    ptr::copy_nonoverlapping(value.as_ptr(), mapped.add(offset), element_size);
+                }
+                gl::UnmapBuffer(target);
+                check_error!();
+            }
+        }
+    }
+
+    /// Replace the data store with an uninitialized one of the size, for reading pixels into.
+    /// The buffer is bound to GL_PIXEL_PACK_BUFFER for the duration of the call, which is not
+    /// tracked. See `CaptureHelper`.
+    pub fn allocate_for_reading(&self, size: usize) {
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.id);
+            gl::BufferData(gl::PIXEL_PACK_BUFFER, size as GLsizeiptr, ptr::null(), gl::STREAM_READ);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
             check_error!();
         }
+        self.size.set(size);
+        self.index_type.set(None);
+        self.registration.set_memory(size);
+    }
+
+    /// Start reading the pixels of the rectangle of the framebuffer bound for reading into the
+    /// beginning of the buffer. The reading completes asynchronously. See glReadPixels.
+    pub fn read_pixels(&self, x: i32, y: i32, width: u32, height: u32, format: GLenum, pixel_type: GLenum) {
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.id);
+            gl::ReadPixels(x, y, width as GLsizei, height as GLsizei, format, pixel_type, ptr::null_mut());
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            check_error!();
+        }
+    }
+
+    /// Copy the whole contents of the buffer into client memory, by mapping the buffer for
+    /// reading. Blocks until any reading into the buffer is done.
+    pub fn read_back(&self) -> Vec<u8> {
+        let size = self.size();
+        let mut bytes = vec![0u8; size];
+        if size == 0 {
+            return bytes;
+        }
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.id);
+            let mapped = gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, size as GLsizeiptr, gl::MAP_READ_BIT) as *const u8;
+            check_error!();
+            if mapped.is_null() {
+                panic!("Failed to map the buffer for reading");
+            }
+            ptr::copy_nonoverlapping(mapped, bytes.as_mut_ptr(), size);
+            gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            check_error!();
+        }
+        bytes
     }
 
     /// Bind the buffer. Not really to be used directly!
@@ -105,13 +279,27 @@ impl BufferObject {
     }
 }
 
+impl fmt::Debug for BufferObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferObject")
+            .field("id", &self.id)
+            .field("tracker_id", &self.tracker_id)
+            .field("size", &self.size.get())
+            .field("ownership", &self.ownership)
+            .finish()
+    }
+}
+
+impl ContextResource for BufferObject {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
 impl Drop for BufferObject {
     fn drop(&mut self) {
-        if self.registration.context_alive() {
-            unsafe {
-                gl::DeleteBuffers(1, &self.id);
-                check_error!();
-            }
+        if self.ownership == Ownership::Owned {
+            self.registration.queue_deletion(QueuedDeletion::Buffer(self.id, self.tracker_id));
         }
     }
 }
@@ -138,9 +326,20 @@ impl Bind<BufferObject> for BufferBinder {
         buffer.bind(self.buffer_type);
     }
 
+    fn unbind(&self) {
+        unsafe {
+            gl::BindBuffer(type_to_target(self.buffer_type), 0);
+        }
+        check_error!();
+    }
+
     fn get_id(&self, buffer: &BufferObject) -> TrackerId {
         buffer.tracker_id
     }
+
+    fn get_name(&self, buffer: &BufferObject) -> u32 {
+        buffer.id
+    }
 }
 
 /// Bind buffer as VBO and edit it.
@@ -155,20 +354,42 @@ pub fn new_index_buffer_editor<'a>(context: &'a mut Context, vertex_array: &'a V
     BufferEditor { context: context, buffer: buffer, buffer_type: BufferType::IndexBuffer }
 }
 
+/// Bind a vertex array of the context's own, and the buffer as its IBO, and edit it. For filling
+/// index buffers that aren't attached to a vertex array yet.
+pub fn new_direct_index_buffer_editor<'a>(context: &'a mut Context, scratch_vertex_array: &VertexArray, buffer: &'a BufferObject) -> BufferEditor<'a> {
+    context.bind_vao_for_editing(scratch_vertex_array);
+    buffer.bind(BufferType::IndexBuffer);
+    BufferEditor { context, buffer, buffer_type: BufferType::IndexBuffer }
+}
+
 /// Bind buffer as UBO and edit it.
 pub fn new_uniform_buffer_editor<'a>(context: &'a mut Context, buffer: &'a BufferObject) -> BufferEditor<'a> {
     context.bind_ubo_for_editing(buffer);
     BufferEditor { context: context, buffer: buffer, buffer_type: BufferType::UniformBuffer }
 }
 
+/// Bind buffer as the draw indirect buffer and edit it.
+pub fn new_indirect_buffer_editor<'a>(context: &'a mut Context, buffer: &'a BufferObject) -> BufferEditor<'a> {
+    context.bind_indirect_buffer_for_editing(buffer);
+    BufferEditor { context, buffer, buffer_type: BufferType::DrawIndirectBuffer }
+}
+
 /// Buffer editor is used to edit contents of a buffer object of any type.
 pub struct BufferEditor<'a> {
-    #[allow(dead_code)]
     context: &'a mut Context,
     buffer: &'a BufferObject,
     buffer_type: BufferType
 }
 
+impl<'a> fmt::Debug for BufferEditor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferEditor")
+            .field("buffer", self.buffer)
+            .field("buffer_type", &self.buffer_type)
+            .finish()
+    }
+}
+
 impl<'a> BufferEditor<'a> {
     /// Replace the data store of the buffer object. This effectively resizes the buffer, but the
     /// old contents are lost.
@@ -178,10 +399,163 @@ impl<'a> BufferEditor<'a> {
         self.buffer.data(self.buffer_type, data);
     }
 
-    /// Replace a region of values within the buffer.
+    /// Like `data`, but upload the data in chunks of at most `chunk_size` bytes (rounded down to
+    /// whole values, but at least one value), calling the progress callback with the bytes
+    /// uploaded so far and the total after each chunk. Splitting a multi-megabyte upload keeps
+    /// any single call from blocking for long, and lets the callback report the progress.
+    ///
+    /// See glBufferData and glBufferSubData.
+    pub fn data_chunked<D, F>(&mut self, data: &[D], chunk_size: usize, mut progress: F) where F: FnMut(usize, usize) {
+        let value_size = size_of::<D>();
+        let total = size_of_val(data);
+        self.buffer.allocate(self.buffer_type, total);
+        let values_per_chunk = (chunk_size / value_size.max(1)).max(1);
+        let mut uploaded = 0;
+        for chunk in data.chunks(values_per_chunk) {
+            self.buffer.sub_data(self.buffer_type, chunk, uploaded);
+            uploaded += size_of_val(chunk);
+            progress(uploaded, total);
+        }
+    }
+
+    /// Replace a region of values within the buffer. Panics if the region doesn't fit in the
+    /// buffer.
     ///
     /// See glBufferSubData.
     pub fn sub_data<D>(&mut self, data: &[D], byte_offset: usize) {
         self.buffer.sub_data(self.buffer_type, data, byte_offset);
     }
+
+    /// Like `sub_data`, but for writing a region of a uniform buffer that is to be bound with
+    /// `Renderer::use_uniform_buffer_range`. Panics also if this is not a uniform buffer, or if
+    /// the offset is not a multiple of the uniform buffer offset alignment of the context, which
+    /// binding the region would require.
+    pub fn sub_data_aligned<D>(&mut self, data: &[D], byte_offset: usize) {
+        match self.buffer_type {
+            BufferType::UniformBuffer => (),
+            _ => panic!("Aligned regions can only be written to a uniform buffer, not to a {:?}", self.buffer_type)
+        }
+        let info = &self.context.get_info().uniform_buffer;
+        if !info.is_offset_aligned(byte_offset) {
+            panic!("Uniform buffer offset {} is not a multiple of the offset alignment {}, the next aligned offset is {}",
+                byte_offset, info.offset_alignment, info.align_offset(byte_offset));
+        }
+        self.sub_data(data, byte_offset);
+    }
+
+    /// Fill the whole buffer by repeating the value, which is a single element of the given
+    /// format as it is stored, for example four bytes for `R32ui` or eight for `Rg32f`. Useful
+    /// for zeroing a buffer without uploading a buffer worth of zeros. Panics if the value is not
+    /// of the size of an element, if the buffer size is not a multiple of it, or if the format
+    /// can't be used for buffers: the three-component formats other than `Rgb32f`, the sRGB
+    /// formats and the depth formats.
+    ///
+    /// See glClearBufferData. If it is not supported (see
+    /// `ContextInfo::supports_clear_buffer_data`), the buffer is mapped and filled instead.
+    pub fn clear(&mut self, format: TextureFormat, value: &[u8]) {
+        let use_clear_data = self.context.get_info().supports_clear_buffer_data();
+        self.buffer.clear(self.buffer_type, format, value, use_clear_data);
+    }
+
+    /// Replace the data store of an index buffer with the strips, joined together with the
+    /// primitive restart index 0xFFFF between them. Returns the number of indices written, to be
+    /// given to `Renderer::draw_elements_restart_u16`. Panics if this is not an index buffer or
+    /// if a strip contains the restart index.
+    pub fn data_strips_u16(&mut self, strips: &[&[u16]]) -> u32 {
+        let indices = self.join_strips(strips, u16::MAX);
+        self.data(&indices);
+        indices.len() as u32
+    }
+
+    /// Like `data_strips_u16`, but with u32 indices and the restart index 0xFFFFFFFF. To be drawn
+    /// with `Renderer::draw_elements_restart_u32`.
+    pub fn data_strips_u32(&mut self, strips: &[&[u32]]) -> u32 {
+        let indices = self.join_strips(strips, u32::MAX);
+        self.data(&indices);
+        indices.len() as u32
+    }
+
+    /// Replace the data store of an index buffer with the indices, using the smallest index type
+    /// that can hold the largest of them: u16 if all of them are below 0xFFFF, u32 otherwise. The
+    /// largest value of each type is left for the primitive restart index. The chosen type is
+    /// returned and recorded in the buffer, so that `Renderer::draw_indexed` can use it. Panics
+    /// if this is not an index buffer or if an index doesn't fit in u32.
+    pub fn data_indices(&mut self, indices: &[usize]) -> IndexType {
+        match self.buffer_type {
+            BufferType::IndexBuffer => (),
+            _ => panic!("Indices can only be written to an index buffer, not to a {:?}", self.buffer_type)
+        }
+        let max_index = indices.iter().cloned().max().unwrap_or(0);
+        let index_type = if max_index < u16::MAX as usize {
+            let short_indices: Vec<u16> = indices.iter().map(|&index| index as u16).collect();
+            self.data(&short_indices[..]);
+            IndexType::U16
+        }
+        else if max_index < u32::MAX as usize {
+            let int_indices: Vec<u32> = indices.iter().map(|&index| index as u32).collect();
+            self.data(&int_indices[..]);
+            IndexType::U32
+        }
+        else {
+            panic!("Index {} doesn't fit in an index buffer", max_index);
+        };
+        self.buffer.index_type.set(Some(index_type));
+        index_type
+    }
+
+    fn join_strips<T: Copy + PartialEq>(&self, strips: &[&[T]], restart_index: T) -> Vec<T> {
+        match self.buffer_type {
+            BufferType::IndexBuffer => (),
+            _ => panic!("Strips can only be written to an index buffer, not to a {:?}", self.buffer_type)
+        }
+        let mut indices = Vec::with_capacity(strips.iter().map(|strip| strip.len() + 1).sum());
+        for (i, strip) in strips.iter().enumerate() {
+            if strip.contains(&restart_index) {
+                panic!("Strip {} contains the primitive restart index", i);
+            }
+            if i > 0 {
+                indices.push(restart_index);
+            }
+            indices.extend_from_slice(strip);
+        }
+        indices
+    }
+}
+
+/// The client format and type describing an element of the internal format as it is stored,
+/// and the size of the element in bytes.
+fn clear_format(format: TextureFormat) -> (GLenum, GLenum, usize) {
+    match format {
+        TextureFormat::R8 => (gl::RED, gl::UNSIGNED_BYTE, 1),
+        TextureFormat::Rg8 => (gl::RG, gl::UNSIGNED_BYTE, 2),
+        TextureFormat::Rgba8 => (gl::RGBA, gl::UNSIGNED_BYTE, 4),
+        TextureFormat::R16f => (gl::RED, gl::HALF_FLOAT, 2),
+        TextureFormat::Rg16f => (gl::RG, gl::HALF_FLOAT, 4),
+        TextureFormat::Rgba16f => (gl::RGBA, gl::HALF_FLOAT, 8),
+        TextureFormat::R32f => (gl::RED, gl::FLOAT, 4),
+        TextureFormat::Rg32f => (gl::RG, gl::FLOAT, 8),
+        TextureFormat::Rgb32f => (gl::RGB, gl::FLOAT, 12),
+        TextureFormat::Rgba32f => (gl::RGBA, gl::FLOAT, 16),
+        TextureFormat::R8ui => (gl::RED_INTEGER, gl::UNSIGNED_BYTE, 1),
+        TextureFormat::R8i => (gl::RED_INTEGER, gl::BYTE, 1),
+        TextureFormat::R16ui => (gl::RED_INTEGER, gl::UNSIGNED_SHORT, 2),
+        TextureFormat::R16i => (gl::RED_INTEGER, gl::SHORT, 2),
+        TextureFormat::R32ui => (gl::RED_INTEGER, gl::UNSIGNED_INT, 4),
+        TextureFormat::R32i => (gl::RED_INTEGER, gl::INT, 4),
+        TextureFormat::Rg8ui => (gl::RG_INTEGER, gl::UNSIGNED_BYTE, 2),
+        TextureFormat::Rg8i => (gl::RG_INTEGER, gl::BYTE, 2),
+        TextureFormat::Rg16ui => (gl::RG_INTEGER, gl::UNSIGNED_SHORT, 4),
+        TextureFormat::Rg16i => (gl::RG_INTEGER, gl::SHORT, 4),
+        TextureFormat::Rg32ui => (gl::RG_INTEGER, gl::UNSIGNED_INT, 8),
+        TextureFormat::Rg32i => (gl::RG_INTEGER, gl::INT, 8),
+        TextureFormat::Rgb32ui => (gl::RGB_INTEGER, gl::UNSIGNED_INT, 12),
+        TextureFormat::Rgb32i => (gl::RGB_INTEGER, gl::INT, 12),
+        TextureFormat::Rgba8ui => (gl::RGBA_INTEGER, gl::UNSIGNED_BYTE, 4),
+        TextureFormat::Rgba8i => (gl::RGBA_INTEGER, gl::BYTE, 4),
+        TextureFormat::Rgba16ui => (gl::RGBA_INTEGER, gl::UNSIGNED_SHORT, 8),
+        TextureFormat::Rgba16i => (gl::RGBA_INTEGER, gl::SHORT, 8),
+        TextureFormat::Rgba32ui => (gl::RGBA_INTEGER, gl::UNSIGNED_INT, 16),
+        TextureFormat::Rgba32i => (gl::RGBA_INTEGER, gl::INT, 16),
+        _ => panic!("Buffers can't be cleared with the format {:?}", format)
+    }
 }
@@ -0,0 +1,92 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading rendered frames back without stalling, see `CaptureHelper`.
+
+use super::{Context,BufferHandle};
+use super::handle::HandleAccess;
+use super::context::ContextEditingSupport;
+use super::options::PixelStoreParam;
+use super::image::{ImageFormat,PixelFormat,PixelType,RawImage};
+use super::sync::Fence;
+
+/// Captures rendered frames, for recording video or comparing screenshots in tests.
+///
+/// Reading pixels straight into client memory waits for the GPU to finish the frame. Instead,
+/// the pixels are read into one of two pixel pack buffers, alternating between them, and each
+/// frame is handed out on the following `capture`, when the GPU has most likely finished with
+/// it. So the frames come one frame late; `finish` gets the last one.
+///
+/// Call `capture` when the frame is rendered, before presenting it. The frames are RGBA with
+/// one byte per channel, rows bottom first as OpenGL has them.
+#[derive(Debug)]
+pub struct CaptureHelper {
+    buffers: [BufferHandle; 2],
+    fences: [Option<Fence>; 2],
+    width: u32,
+    height: u32,
+    current: usize
+}
+
+impl CaptureHelper {
+    /// Create the buffers for capturing frames of the given size from the lower left corner of
+    /// the framebuffer.
+    pub fn new(context: &mut Context, width: u32, height: u32) -> CaptureHelper {
+        let buffers = [context.new_buffer(), context.new_buffer()];
+        let frame_size = width as usize * height as usize * 4;
+        for buffer in buffers.iter() {
+            buffer.access().allocate_for_reading(frame_size);
+        }
+        CaptureHelper {
+            buffers,
+            fences: [None, None],
+            width,
+            height,
+            current: 0
+        }
+    }
+
+    /// The size of the captured frames.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Start reading the framebuffer bound for rendering, the default framebuffer outside render
+    /// passes, and return the frame captured by the previous call, if any. Waits for the
+    /// previous frame if the GPU is not done with it yet.
+    pub fn capture(&mut self, context: &mut Context) -> Option<RawImage> {
+        let previous = 1 - self.current;
+        let frame = self.take_frame(previous);
+        context.set_pixel_store(PixelStoreParam::PackAlignment(1));
+        self.buffers[self.current].access().read_pixels(0, 0, self.width, self.height, gl::RGBA, gl::UNSIGNED_BYTE);
+        self.fences[self.current] = Some(context.insert_fence());
+        self.current = previous;
+        frame
+    }
+
+    /// Return the frame captured by the last `capture`, waiting for it. None if there is no
+    /// frame left.
+    pub fn finish(&mut self) -> Option<RawImage> {
+        let previous = 1 - self.current;
+        self.take_frame(previous)
+    }
+
+    fn take_frame(&mut self, index: usize) -> Option<RawImage> {
+        let fence = self.fences[index].take()?;
+        fence.wait();
+        let bytes = self.buffers[index].access().read_back();
+        let format = ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte);
+        Some(RawImage::new(self.width, self.height, format, bytes))
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use super::options::RenderOption;
+
+/// A color with linear components, the values OpenGL blends, clears and renders with. Clear
+/// colors, blend constants and the float colors of vertex data are linear, and with sRGB
+/// framebuffers the conversion to sRGB is done by OpenGL when writing.
+///
+/// Colors picked from images or color pickers are usually sRGB encoded; use `SrgbRgba` for them
+/// and convert with `to_linear`. Only linear colors convert to the `[f32; 4]` arrays the rest of
+/// the library takes, so an sRGB color can't be passed where a linear one is expected by mistake.
+/// The alpha component is linear in both.
+#[derive(Copy,Clone,Debug,PartialEq,Default)]
+pub struct LinearRgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32
+}
+
+/// A color with sRGB encoded color components and a linear alpha component. See `LinearRgba`.
+#[derive(Copy,Clone,Debug,PartialEq,Default)]
+pub struct SrgbRgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32
+}
+
+impl LinearRgba {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> LinearRgba {
+        LinearRgba { r, g, b, a }
+    }
+
+    /// The color from normalized bytes, as stored in an `UnsignedByte` vertex attribute or a
+    /// linear RGBA8 texture.
+    pub fn from_bytes(bytes: [u8; 4]) -> LinearRgba {
+        LinearRgba::new(byte_to_float(bytes[0]), byte_to_float(bytes[1]), byte_to_float(bytes[2]), byte_to_float(bytes[3]))
+    }
+
+    /// The color as normalized bytes for an `UnsignedByte` vertex attribute that the shader
+    /// reads as linear. The components are clamped to [0.0, 1.0]. Note that eight bits are too
+    /// few for dark linear colors without banding, `SrgbRgba::to_bytes` spends them better if
+    /// the shader decodes the colors.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        [float_to_byte(self.r), float_to_byte(self.g), float_to_byte(self.b), float_to_byte(self.a)]
+    }
+
+    /// The components as an array, for vertex data, `ClearState` or uniforms.
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// The color with the color components multiplied by alpha, for premultiplied alpha blending.
+    pub fn premultiplied(&self) -> LinearRgba {
+        LinearRgba::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// The color sRGB encoded.
+    pub fn to_srgb(&self) -> SrgbRgba {
+        SrgbRgba::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a)
+    }
+
+    /// The color as a `RenderOption::ClearColor`.
+    pub fn clear_color(&self) -> RenderOption {
+        RenderOption::ClearColor(self.r, self.g, self.b, self.a)
+    }
+
+    /// The color as a `RenderOption::BlendColor`.
+    pub fn blend_color(&self) -> RenderOption {
+        RenderOption::BlendColor(self.r, self.g, self.b, self.a)
+    }
+}
+
+impl SrgbRgba {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> SrgbRgba {
+        SrgbRgba { r, g, b, a }
+    }
+
+    /// The color from sRGB encoded bytes, for example a pixel of an sRGB image or `0xff8000`
+    /// style colors from a color picker.
+    pub fn from_bytes(bytes: [u8; 4]) -> SrgbRgba {
+        SrgbRgba::new(byte_to_float(bytes[0]), byte_to_float(bytes[1]), byte_to_float(bytes[2]), byte_to_float(bytes[3]))
+    }
+
+    /// The color as sRGB encoded bytes, for an `UnsignedByte` vertex attribute that the shader
+    /// decodes, for example with `htgl_srgb_to_linear` of the `htgl/srgb.glsl` built-in source.
+    /// The components are clamped to [0.0, 1.0].
+    pub fn to_bytes(&self) -> [u8; 4] {
+        [float_to_byte(self.r), float_to_byte(self.g), float_to_byte(self.b), float_to_byte(self.a)]
+    }
+
+    /// The color decoded to linear.
+    pub fn to_linear(&self) -> LinearRgba {
+        LinearRgba::new(srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a)
+    }
+}
+
+impl From<SrgbRgba> for LinearRgba {
+    fn from(color: SrgbRgba) -> LinearRgba {
+        color.to_linear()
+    }
+}
+
+impl From<LinearRgba> for SrgbRgba {
+    fn from(color: LinearRgba) -> SrgbRgba {
+        color.to_srgb()
+    }
+}
+
+impl From<LinearRgba> for [f32; 4] {
+    fn from(color: LinearRgba) -> [f32; 4] {
+        color.to_array()
+    }
+}
+
+fn byte_to_float(byte: u8) -> f32 {
+    byte as f32 / 255.0
+}
+
+fn float_to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// The sRGB transfer function, the same as the GPU uses when writing to sRGB framebuffers.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    }
+    else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The inverse of `linear_to_srgb`, the same as the GPU uses when sampling sRGB textures.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    }
+    else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_functions_match_the_standard() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-5);
+        assert!((linear_to_srgb(0.001) - 0.012_92).abs() < 1e-6);
+    }
+
+    #[test]
+    fn every_srgb_byte_survives_a_round_trip() {
+        for value in 0..=255u8 {
+            let color = SrgbRgba::from_bytes([value, value, value, value]);
+            assert_eq!(color.to_linear().to_srgb().to_bytes(), [value; 4]);
+        }
+    }
+
+    #[test]
+    fn alpha_stays_linear() {
+        let color = SrgbRgba::new(0.5, 0.5, 0.5, 0.5).to_linear();
+        assert_eq!(color.a, 0.5);
+        assert_eq!(color.premultiplied().to_array(), [color.r * 0.5, color.g * 0.5, color.b * 0.5, 0.5]);
+        assert_eq!(LinearRgba::new(2.0, -1.0, 0.5, 1.0).to_bytes(), [255, 0, 128, 255]);
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording rendering commands for replaying them later, see `CommandList`.
+
+use std::ops::Range;
+
+use super::{VertexArrayHandle,ProgramHandle,TextureHandle};
+use super::handle::HandleAccess;
+use super::buffer::IndexType;
+use super::options::RenderOption;
+use super::program::{self,SimpleUniformTypeFloat,SimpleUniformTypeMatrix,SimpleUniformTypeU32,SimpleUniformTypeI32};
+use super::renderer::{self,Renderer,PrimitiveMode};
+
+/// A single recorded command. Uniform values are stored in the value vectors of the list, and
+/// the commands refer to them by range.
+#[derive(Clone,Debug)]
+enum Command {
+    UseVertexArray(VertexArrayHandle),
+    UseProgram(ProgramHandle),
+    UseTexture(u32, TextureHandle),
+    UniformF32(i32, usize, SimpleUniformTypeFloat, Range<usize>),
+    UniformMatrix(i32, usize, SimpleUniformTypeMatrix, bool, Range<usize>),
+    UniformU32(i32, usize, SimpleUniformTypeU32, Range<usize>),
+    UniformI32(i32, usize, SimpleUniformTypeI32, Range<usize>),
+    DrawArrays(PrimitiveMode, u32, u32),
+    DrawElements(PrimitiveMode, u32, IndexType, u32),
+    SetOption(RenderOption),
+    Clear
+}
+
+/// A list of rendering commands that can be recorded once and replayed on a `Renderer` any
+/// number of times. Useful for static scenery, where the same draw calls are made every frame.
+///
+/// The methods mirror the ones of `Renderer`. The commands are checked when recording as far as
+/// possible: uniforms can't be set before a program is used, drawing requires a program and a
+/// vertex array, and drawing indexed vertices requires the vertex array to have an index buffer.
+/// The list keeps the resources it refers to alive.
+#[derive(Clone,Debug,Default)]
+pub struct CommandList {
+    commands: Vec<Command>,
+    f32_values: Vec<f32>,
+    u32_values: Vec<u32>,
+    i32_values: Vec<i32>,
+    program_used: bool,
+    index_buffer_used: Option<bool>
+}
+
+impl CommandList {
+    /// Create an empty command list.
+    pub fn new() -> CommandList {
+        CommandList::default()
+    }
+
+    /// How many commands have been recorded.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Has nothing been recorded yet?
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Remove all the commands, so that the list can be recorded again.
+    pub fn clear_commands(&mut self) {
+        *self = CommandList::new();
+    }
+
+    /// Record `Renderer::use_vertex_array`.
+    pub fn use_vertex_array(&mut self, vao: &VertexArrayHandle) {
+        self.index_buffer_used = Some(vao.access().index_buffer().is_some());
+        self.commands.push(Command::UseVertexArray(vao.clone()));
+    }
+
+    /// Record `Renderer::use_program`.
+    pub fn use_program(&mut self, program: &ProgramHandle) {
+        self.program_used = true;
+        self.commands.push(Command::UseProgram(program.clone()));
+    }
+
+    /// Record `Renderer::use_texture`.
+    pub fn use_texture(&mut self, unit: u32, texture: &TextureHandle) {
+        self.commands.push(Command::UseTexture(unit, texture.clone()));
+    }
+
+    /// Record `Renderer::uniform_f32`. The values are copied into the list.
+    pub fn uniform_f32(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeFloat, values: &[f32]) {
+        self.check_program("set a uniform");
+        program::validate_uniform_f32(count, uniform_type, values);
+        let range = push_values(&mut self.f32_values, values);
+        self.commands.push(Command::UniformF32(location, count, uniform_type, range));
+    }
+
+    /// Record `Renderer::uniform_matrix`. The values are copied into the list.
+    pub fn uniform_matrix(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeMatrix, transpose: bool, values: &[f32]) {
+        self.check_program("set a uniform");
+        program::validate_uniform_matrix(count, uniform_type, values);
+        let range = push_values(&mut self.f32_values, values);
+        self.commands.push(Command::UniformMatrix(location, count, uniform_type, transpose, range));
+    }
+
+    /// Record `Renderer::uniform_u32`. The values are copied into the list.
+    pub fn uniform_u32(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeU32, values: &[u32]) {
+        self.check_program("set a uniform");
+        program::validate_uniform_u32(count, uniform_type, values);
+        let range = push_values(&mut self.u32_values, values);
+        self.commands.push(Command::UniformU32(location, count, uniform_type, range));
+    }
+
+    /// Record `Renderer::uniform_i32`. The values are copied into the list.
+    pub fn uniform_i32(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeI32, values: &[i32]) {
+        self.check_program("set a uniform");
+        program::validate_uniform_i32(count, uniform_type, values);
+        let range = push_values(&mut self.i32_values, values);
+        self.commands.push(Command::UniformI32(location, count, uniform_type, range));
+    }
+
+    /// Record `Renderer::draw_arrays`.
+    #[track_caller]
+    pub fn draw_arrays(&mut self, primitive_mode: PrimitiveMode, first: u32, count: u32) {
+        self.check_draw(false);
+        if renderer::validate_primitive_count(primitive_mode, count) {
+                self.commands.push(Command::DrawArrays(primitive_mode, first, count));
+        }
+    }
+
+    /// Record `Renderer::draw_elements_u8`.
+    #[track_caller]
+    pub fn draw_elements_u8(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        self.draw_elements(primitive_mode, count, IndexType::U8, start);
+    }
+
+    /// Record `Renderer::draw_elements_u16`.
+    #[track_caller]
+    pub fn draw_elements_u16(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        self.draw_elements(primitive_mode, count, IndexType::U16, start);
+    }
+
+    /// Record `Renderer::draw_elements_u32`.
+    #[track_caller]
+    pub fn draw_elements_u32(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        self.draw_elements(primitive_mode, count, IndexType::U32, start);
+    }
+
+    /// Record `Renderer::set_option`.
+    pub fn set_option(&mut self, option: RenderOption) {
+        self.commands.push(Command::SetOption(option));
+    }
+
+    /// Record `Renderer::clear`.
+    pub fn clear(&mut self) {
+        self.commands.push(Command::Clear);
+    }
+
+    /// Execute the recorded commands with the renderer.
+    pub fn replay(&self, renderer: &mut Renderer) {
+        for command in self.commands.iter() {
+            match *command {
+                Command::UseVertexArray(ref vao) => renderer.use_vertex_array(vao),
+                Command::UseProgram(ref program) => renderer.use_program(program),
+                Command::UseTexture(unit, ref texture) => renderer.use_texture(unit, texture),
+                Command::UniformF32(location, count, uniform_type, ref range) => {
+                    renderer.uniform_f32(location, count, uniform_type, &self.f32_values[range.clone()])
+                },
+                Command::UniformMatrix(location, count, uniform_type, transpose, ref range) => {
+                    renderer.uniform_matrix(location, count, uniform_type, transpose, &self.f32_values[range.clone()])
+                },
+                Command::UniformU32(location, count, uniform_type, ref range) => {
+                    renderer.uniform_u32(location, count, uniform_type, &self.u32_values[range.clone()])
+                },
+                Command::UniformI32(location, count, uniform_type, ref range) => {
+                    renderer.uniform_i32(location, count, uniform_type, &self.i32_values[range.clone()])
+                },
+                Command::DrawArrays(primitive_mode, first, count) => renderer.draw_arrays(primitive_mode, first, count),
+                Command::DrawElements(primitive_mode, count, index_type, start) => {
+                    match index_type {
+                        IndexType::U8 => renderer.draw_elements_u8(primitive_mode, count, start),
+                        IndexType::U16 => renderer.draw_elements_u16(primitive_mode, count, start),
+                        IndexType::U32 => renderer.draw_elements_u32(primitive_mode, count, start)
+                    }
+                },
+                Command::SetOption(option) => renderer.set_option(option),
+                Command::Clear => renderer.clear()
+            }
+        }
+    }
+
+    #[track_caller]
+    fn draw_elements(&mut self, primitive_mode: PrimitiveMode, count: u32, index_type: IndexType, start: u32) {
+        self.check_draw(true);
+        if renderer::validate_primitive_count(primitive_mode, count) {
+            self.commands.push(Command::DrawElements(primitive_mode, count, index_type, start));
+        }
+    }
+
+    fn check_program(&self, action: &str) {
+        if !self.program_used {
+            panic!("Can't {} in a command list before using a program", action);
+        }
+    }
+
+    fn check_draw(&self, indexed: bool) {
+        self.check_program("draw");
+        match self.index_buffer_used {
+            None => panic!("Can't draw in a command list before using a vertex array"),
+            Some(false) if indexed => panic!("Can't draw indexed vertices in a command list, the vertex array has no index buffer"),
+            Some(_) => ()
+        }
+    }
+}
+
+/// Append the values to the vector, returning their range in it.
+fn push_values<T: Copy>(vec: &mut Vec<T>, values: &[T]) -> Range<usize> {
+    let start = vec.len();
+    vec.extend_from_slice(values);
+    start..vec.len()
+}
@@ -14,18 +14,44 @@
 
 //! See the struct `Context` for documentation on how the context is meant to be used.
 
-use std::cell::RefCell;
+use std::cell::{Cell,RefCell};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize,Ordering};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::os::raw::c_void;
+use std::path::PathBuf;
 
-use super::{BufferHandle,VertexArrayHandle,ProgramHandle,ShaderHandle};
-use super::handle::{new_handle,HandleAccess};
+use gl::types::GLsync;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::{BufferHandle,VertexArrayHandle,ProgramHandle,ShaderHandle,TextureHandle,RenderTargetHandle};
+use super::handle::{Handle,new_handle,HandleAccess};
 use super::program::{self,Program,ProgramEditor,ProgramInfoAccessor,ProgramBinder};
+use super::program::cache::{self,ProgramCache,ProgramKey};
 use super::shader::{self,Shader,ShaderInfoAccessor,ShaderType};
 use super::buffer::{self,BufferObject,BufferBinder,BufferEditor,BufferType};
-use super::vertexarray::{VertexArray,VertexAttribute,VertexAttributeType,VertexArrayBinder};
+use super::vertexarray::{self,VertexArray,VertexAttribute,VertexAttributeType,VertexArrayBinder,VertexFormat,VertexLayout,VertexLayoutError,AttributeDefault,AttributeDefaultCache};
+use super::mesh::INSTANCE_ATTRIBUTE_LOCATION;
+use super::texture::{self,Texture,TextureBinder,TextureBufferError,TextureEditor,TextureFormat,TextureTarget,SrgbPolicy};
+use super::framebuffer::{RenderTarget,RenderTargetError,FramebufferBinder};
 use super::renderer::Renderer;
-use super::tracker::{SimpleBindingTracker,RenderBindingTracker,TrackerIdGenerator};
-use super::info::{ContextInfo,build_info};
+use super::tracker::{SimpleBindingTracker,RenderBindingTracker,IndexedRangeTracker,IndexedBufferTarget,TrackerId,TrackerIdGenerator,TrackerMode};
+use super::info::{ContextInfo,ExtensionFeature,VersionInfo,TextureFormatProperties,build_info,query_version};
+use super::options::{self,PixelStoreCache,PixelStoreParam,ClearState,ClearValueCache,RenderOption,RenderOptionCache,ClipOrigin,ClipDepth};
+use super::surface::Surface;
+use super::raw::{self,Ownership,RawGlScope};
+use super::sync::{self,Fence};
+use super::query::{self,Query,QueryTarget};
+use super::binding::BindingPointAllocator;
+use super::bindingreport::{self,BindingReport,BindingDivergence,IndexedBufferBinding};
+use super::debug;
+use super::util::{self,HtglError,ErrorHandlerSlot};
+use super::shaderlib::{self,ShaderSourceLibrary,IncludeError};
+use super::namedstring::NamedStringFunctions;
+use super::snapshot::{DebugSnapshot,LiveResources,SnapshotResource};
 
 /// Context is a central concept in OpenGL, even though it's not a concrete item in the GL API.
 /// This struct is meant to be a stand-in for the GL context, but also the starting point for all
@@ -100,7 +126,51 @@ pub struct Context {
     program_tracker: RenderBindingTracker<ProgramBinder, Program>,
     vbo_tracker: SimpleBindingTracker<BufferBinder, BufferObject>,
     ubo_tracker: SimpleBindingTracker<BufferBinder, BufferObject>,
+    /// The draw indirect buffer is bound both for editing and for drawing from it.
+    indirect_tracker: SimpleBindingTracker<BufferBinder, BufferObject>,
     vao_tracker: RenderBindingTracker<VertexArrayBinder, VertexArray>,
+    /// One tracker per texture unit. Textures are edited through the first unit.
+    texture_trackers: Vec<RenderBindingTracker<TextureBinder, Texture>>,
+    /// The render target bound for rendering is the one of the current render pass, none meaning
+    /// the default framebuffer.
+    draw_framebuffer_tracker: RenderBindingTracker<FramebufferBinder, RenderTarget>,
+    /// The read framebuffer is tracked separately, so that reading back from or blitting from
+    /// one render target while drawing into another doesn't rebind them back and forth.
+    read_framebuffer_tracker: RenderBindingTracker<FramebufferBinder, RenderTarget>,
+    pixel_store: PixelStoreCache,
+    render_options: RenderOptionCache,
+    clear_values: ClearValueCache,
+    /// The persistent clear state of the renderer.
+    clear_state: ClearState,
+    attribute_defaults: AttributeDefaultCache,
+    /// The vertex array index buffers are bound to by `edit_index_buffer_direct`.
+    scratch_vertex_array: Option<VertexArrayHandle>,
+    /// The default sRGB policy of texture editors.
+    srgb_policy: SrgbPolicy,
+    /// The clip control state set through the renderer.
+    clip_control: (ClipOrigin, ClipDepth),
+    program_cache: ProgramCache,
+    /// The functions of GL_ARB_shading_language_include, if loaded by `from_loader` or
+    /// `from_loader_unvalidated`.
+    named_string_functions: Option<NamedStringFunctions>,
+    /// The names of the sources registered as named strings, without the leading slash.
+    named_strings: Vec<(String, String)>,
+    uniform_binding_points: BindingPointAllocator,
+    /// Assigns shader storage buffer binding points by name, see `storage_binding_points`.
+    storage_binding_points: BindingPointAllocator,
+    /// The buffer ranges bound to the indexed uniform, storage, atomic counter and transform
+    /// feedback buffer binding points.
+    indexed_ranges: IndexedRangeTracker,
+    /// The surface the default framebuffer is presented on, if given.
+    surface: Option<Box<dyn Surface>>,
+    /// The viewport size set according to the surface dimensions.
+    viewport_size: (u32, u32),
+    /// The viewport of the current render pass as (x, y, width, height), None outside passes.
+    /// Takes the place of the surface sized viewport while a pass is going on.
+    pass_viewport: Option<[i32; 4]>,
+    /// The resources created by the context, for `export_debug_snapshot`.
+    live_resources: LiveResources,
+    error_handler: Rc<ErrorHandlerSlot>,
     /// Shared state is a way for context to communicate things to resources - mainly that the
     /// context is alive (or is not)
     shared_state: Rc<RefCell<SharedContextState>>
@@ -110,26 +180,93 @@ impl Context {
     /// Creates a new Context. Do not create more than one (per actual OpenGL context, anyway).
     /// See the documentation for the struct for more details on what creating a `Context` means.
     pub fn new() -> Context {
+        let info = build_info();
+        let texture_units = info.texture.max_combined_units as u32;
+        let uniform_bindings = info.uniform_buffer.max_bindings as u32;
+        let storage_bindings = info.extension_limits.max_storage_buffer_bindings as u32;
         Context {
-            info: build_info(),
+            info,
             id_generator: TrackerIdGenerator::new(),
             program_tracker: RenderBindingTracker::new(ProgramBinder::new()),
             vbo_tracker: SimpleBindingTracker::new(BufferBinder::new(BufferType::VertexBuffer)),
             ubo_tracker: SimpleBindingTracker::new(BufferBinder::new(BufferType::UniformBuffer)),
+            indirect_tracker: SimpleBindingTracker::new(BufferBinder::new(BufferType::DrawIndirectBuffer)),
             vao_tracker: RenderBindingTracker::new(VertexArrayBinder::new()),
+            texture_trackers: (0..texture_units).map(|unit| RenderBindingTracker::new(TextureBinder::new(unit))).collect(),
+            draw_framebuffer_tracker: RenderBindingTracker::new(FramebufferBinder::new_draw()),
+            read_framebuffer_tracker: RenderBindingTracker::new(FramebufferBinder::new_read()),
+            pixel_store: PixelStoreCache::new(),
+            render_options: RenderOptionCache::new(),
+            clear_values: ClearValueCache::new(),
+            clear_state: ClearState::default(),
+            attribute_defaults: AttributeDefaultCache::new(),
+            scratch_vertex_array: None,
+            srgb_policy: SrgbPolicy::Linear,
+            clip_control: (ClipOrigin::LowerLeft, ClipDepth::NegativeOneToOne),
+            program_cache: ProgramCache::new(),
+            named_string_functions: None,
+            named_strings: Vec::new(),
+            uniform_binding_points: BindingPointAllocator::new(uniform_bindings),
+            storage_binding_points: BindingPointAllocator::new(storage_bindings),
+            indexed_ranges: IndexedRangeTracker::new(),
+            surface: None,
+            viewport_size: (0, 0),
+            pass_viewport: None,
+            live_resources: LiveResources::new(),
+            error_handler: ErrorHandlerSlot::new(),
             shared_state: Rc::new(RefCell::new(SharedContextState::new()))
         }
     }
 
+    /// Load the OpenGL functions with the given loader function, check that the context is
+    /// suitable and create a new Context. This is what you most likely want to do right after
+    /// making the OpenGL context current with your windowing library:
+    ///
+    ///    let ctx = Context::from_loader(|symbol| window.get_proc_address(symbol))?;
+    ///
+    /// The context must be an OpenGL 3.3 (or newer) core profile context, or an OpenGL ES 3.0 (or
+    /// newer) context with the `es3` feature, otherwise an error describing the problem is
+    /// returned. To skip the version and profile checks, use `from_loader_unvalidated`.
+    pub fn from_loader<F>(loader: F) -> Result<Context, ContextCreationError>
+        where F: FnMut(&str) -> *const c_void {
+        Context::load_and_create(loader, true)
+    }
+
+    /// Like `from_loader`, but accept a context of any version and profile, for example an
+    /// OpenGL 3.2 or compatibility profile context that has the extensions needed. Using
+    /// functionality the context doesn't have is then up to you to avoid. Fails only if the
+    /// functions can't be loaded or the version can't be queried.
+    pub fn from_loader_unvalidated<F>(loader: F) -> Result<Context, ContextCreationError>
+        where F: FnMut(&str) -> *const c_void {
+        Context::load_and_create(loader, false)
+    }
+
+    fn load_and_create<F>(mut loader: F, validate: bool) -> Result<Context, ContextCreationError>
+        where F: FnMut(&str) -> *const c_void {
+        gl::load_with(&mut loader);
+        let version = match query_version() {
+            Some(version) => version,
+            None => return Err(ContextCreationError::LoadingFailed)
+        };
+        if validate {
+            validate_version(&version)?;
+        }
+        let mut context = Context::new();
+        if context.info.supports(ExtensionFeature::ShadingLanguageInclude) {
+            context.named_string_functions = NamedStringFunctions::load(&mut loader);
+        }
+        Ok(context)
+    }
+
     // Construct new objects
 
     /// Create a new buffer object.
     ///
     /// Returns a handle to the created buffer object.
     pub fn new_buffer(&mut self) -> BufferHandle {
-        let registration = self.registration_handle();
+        let registration = self.registration_handle(ResourceKind::Buffer);
         let id = self.id_generator.new_id();
-        new_handle(buffer::new_buffer(id, registration))
+        self.track(new_handle(buffer::new_buffer(id, registration)))
     }
 
     /// Create a new vertex array object.
@@ -143,9 +280,10 @@ impl Context {
     pub fn new_vertex_array(&mut self,
                             attributes: &[VertexAttribute],
                             index_buffer: Option<BufferHandle>) -> VertexArrayHandle {
-        let registration = self.registration_handle();
+        let registration = self.registration_handle(ResourceKind::VertexArray);
         let id = self.id_generator.new_id();
-        new_handle(VertexArray::new(self, id, attributes, index_buffer, registration))
+        let vertex_array = VertexArray::new(self, id, attributes, index_buffer, registration);
+        self.track(new_handle(vertex_array))
     }
 
     /// Create a new vertex array object that only uses contents of one vertex buffer.
@@ -157,29 +295,420 @@ impl Context {
                                    attributes: &[(u8, VertexAttributeType, bool)],
                                    vertex_buffer: BufferHandle,
                                    index_buffer: Option<BufferHandle>) -> VertexArrayHandle {
-        let registration = self.registration_handle();
+        let registration = self.registration_handle(ResourceKind::VertexArray);
+        let id = self.id_generator.new_id();
+        let vertex_array = VertexArray::new_single_vbo(self, id, attributes, vertex_buffer, index_buffer, registration);
+        self.track(new_handle(vertex_array))
+    }
+
+    /// Create a new vertex array object from a layout of several vertex buffers, see
+    /// `VertexLayout`. Fails if the layout is invalid, for example if two attributes are given
+    /// the same location.
+    pub fn new_vertex_array_layout(&mut self,
+                                   layout: &VertexLayout,
+                                   index_buffer: Option<BufferHandle>) -> Result<VertexArrayHandle, VertexLayoutError> {
+        let attributes = layout.build()?;
+        Ok(self.new_vertex_array(&attributes[..], index_buffer))
+    }
+
+    /// Create a new vertex array object that only uses contents of one vertex buffer, like
+    /// `new_vertex_array_simple`, but with the attributes given by name. The attribute indices
+    /// are looked up from the program, so the layout doesn't depend on the attribute locations
+    /// the shader happens to get:
+    ///
+    ///    ctx.new_vertex_array_named(&[("position", (3, Float, false)), ("color", (4, UnsignedByte, true))], &program, vbo, None)
+    ///
+    /// Fails with `VertexLayoutError::MissingAttribute` if the program doesn't have one of the
+    /// attributes, or it was optimized out.
+    pub fn new_vertex_array_named(&mut self,
+                                  attributes: &[(&str, (u8, VertexAttributeType, bool))],
+                                  program: &ProgramHandle,
+                                  vertex_buffer: BufferHandle,
+                                  index_buffer: Option<BufferHandle>) -> Result<VertexArrayHandle, VertexLayoutError> {
+        self.check_owner(program.access());
+        let registration = self.registration_handle(ResourceKind::VertexArray);
+        let id = self.id_generator.new_id();
+        let vertex_array = VertexArray::new_named(self, id, attributes, program.access(), vertex_buffer, index_buffer, registration)?;
+        Ok(self.track(new_handle(vertex_array)))
+    }
+
+    /// Create a new vertex array object from mesh streams named by semantic, one attribute per
+    /// buffer, tightly packed. Each stream gets the location of the program attribute annotated
+    /// with its semantic (see the `semantic` module), and streams the program has no attribute
+    /// for are left out:
+    ///
+    ///    ctx.new_vertex_array_from_semantics(&program, &[("POSITION", positions, (3, Float, false)), ("TEXCOORD0", uvs, (2, Float, false))], None)
+    ///
+    /// Fails if the program has an annotated attribute that none of the streams provide, or if
+    /// it has no annotations the library knows of, see `VertexLayoutError::NoSemantics`. The
+    /// attributes get the names of the program attributes as their semantics, see
+    /// `new_vertex_array_with_semantics`.
+    pub fn new_vertex_array_from_semantics(&mut self,
+                                           program: &ProgramHandle,
+                                           streams: &[(&str, BufferHandle, (u8, VertexAttributeType, bool))],
+                                           index_buffer: Option<BufferHandle>) -> Result<VertexArrayHandle, VertexLayoutError> {
+        self.check_owner(program.access());
+        let registration = self.registration_handle(ResourceKind::VertexArray);
+        let id = self.id_generator.new_id();
+        let vertex_array = VertexArray::new_from_semantics(self, id, program.access(), streams, index_buffer, registration)?;
+        Ok(self.track(new_handle(vertex_array)))
+    }
+
+    /// Create a new vertex array object like `new_vertex_array`, giving the attributes semantic
+    /// names in order. Vertex arrays with semantics can be used with programs that give the
+    /// attributes different locations, see `vertex_array_for_program`. The vertex arrays of
+    /// `new_vertex_array_named` get the attribute names as their semantics.
+    pub fn new_vertex_array_with_semantics(&mut self,
+                                           attributes: &[VertexAttribute],
+                                           semantics: &[&str],
+                                           index_buffer: Option<BufferHandle>) -> VertexArrayHandle {
+        let registration = self.registration_handle(ResourceKind::VertexArray);
         let id = self.id_generator.new_id();
-        new_handle(VertexArray::new_single_vbo(self, id, attributes, vertex_buffer, index_buffer, registration))
+        let semantics = semantics.iter().map(|&name| Some(name.to_string())).collect();
+        let vertex_array = VertexArray::new(self, id, attributes, index_buffer, registration).with_semantics(semantics);
+        self.track(new_handle(vertex_array))
+    }
+
+    /// Get a variant of the vertex array with the attribute locations matching the program: each
+    /// attribute with a semantic name gets the location of the program attribute of that name,
+    /// and is left out if the program has none. If the locations already match, the vertex
+    /// array itself is returned. Otherwise the variant is created the first time, and cached in
+    /// the vertex array for other programs with the same locations. See
+    /// `Renderer::use_vertex_array_with`.
+    ///
+    /// Panics if two attributes would end up at the same location, for example an attribute
+    /// moved to the location an attribute without a semantic keeps.
+    pub fn vertex_array_for_program(&mut self, vao: &VertexArrayHandle, program: &ProgramHandle) -> VertexArrayHandle {
+        let locations = vao.access().locations_for_program(program.access());
+        if let Some(location) = vertexarray::duplicate_location(&locations) {
+            panic!("More than one attribute of the vertex array would be at the location {} of the program", location);
+        }
+        if vao.access().has_locations(&locations) {
+            return vao.clone();
+        }
+        if let Some(variant) = vao.access().variant(&locations) {
+            return variant;
+        }
+        let (attributes, semantics) = vao.access().remapped_attributes(&locations);
+        let index_buffer = vao.access().index_buffer_handle();
+        let registration = self.registration_handle(ResourceKind::VertexArray);
+        let id = self.id_generator.new_id();
+        let variant = VertexArray::new(self, id, &attributes, index_buffer, registration).with_semantics(semantics);
+        let variant = self.track(new_handle(variant));
+        vao.access().add_variant(locations, variant.clone());
+        variant
+    }
+
+    /// Get a variant of the vertex array that also reads per-instance attributes of the type `I`
+    /// from the instance buffer, at the locations starting from `INSTANCE_ATTRIBUTE_LOCATION`
+    /// and with a divisor of one. The variant is created the first time and cached in the vertex
+    /// array by the layout of `I`. A cached variant reading another instance buffer is pointed
+    /// to this one, so a new instance buffer every frame doesn't pile up vertex arrays. The
+    /// variant keeps its current instance buffer alive. See `Mesh::draw_instanced`.
+    ///
+    /// Panics if an attribute of the vertex array is at one of the instance locations.
+    pub fn vertex_array_with_instances<I: VertexFormat>(&mut self, vao: &VertexArrayHandle, instance_buffer: &BufferHandle) -> VertexArrayHandle {
+        let instance_count = I::attributes().len() as u32;
+        let locations: Vec<u32> = (INSTANCE_ATTRIBUTE_LOCATION..INSTANCE_ATTRIBUTE_LOCATION + instance_count).collect();
+        let layout = VertexLayout::new()
+            .interleaved_instanced::<I>(instance_buffer.clone(), 1)
+            .at_locations(&locations);
+        let instance_attributes = match layout.build() {
+            Ok(instance_attributes) => instance_attributes,
+            Err(error) => panic!("Invalid instance format: {:?}", error)
+        };
+        if let Some(variant) = vao.access().instanced_variant(&instance_attributes) {
+            let current = instance_attributes.iter().all(|attribute| variant.access().attributes().contains(attribute));
+            if !current {
+                variant.access().replace_attributes(self, &instance_attributes);
+            }
+            return variant;
+        }
+        let mut attributes = vao.access().attributes().to_vec();
+        if let Some(attribute) = attributes.iter().find(|attribute| locations.contains(&attribute.index)) {
+            panic!("The vertex array has an attribute at location {}, reserved for the instance attributes", attribute.index);
+        }
+        attributes.extend(instance_attributes.iter().cloned());
+        let semantics = vao.access().semantics().to_vec();
+        let index_buffer = vao.access().index_buffer_handle();
+        let registration = self.registration_handle(ResourceKind::VertexArray);
+        let id = self.id_generator.new_id();
+        let variant = VertexArray::new(self, id, &attributes, index_buffer, registration).with_semantics(semantics);
+        let variant = self.track(new_handle(variant));
+        vao.access().add_instanced_variant(variant.clone());
+        variant
     }
 
     /// Create and compile a new shader object.
     pub fn new_shader(&mut self, shader_type: ShaderType, source: &str) -> ShaderHandle {
-        let registration = self.registration_handle();
-        new_handle(Shader::new(shader_type, source, registration))
+        let registration = self.registration_handle(ResourceKind::Shader);
+        self.track(new_handle(Shader::new(shader_type, source, registration)))
+    }
+
+    /// Create and compile a shader from the source, with the `#include` lines resolved from the
+    /// library first. See `ShaderSourceLibrary::preprocess`.
+    pub fn new_shader_from_library(&mut self, shader_type: ShaderType, source: &str, library: &ShaderSourceLibrary) -> Result<ShaderHandle, IncludeError> {
+        let source = library.preprocess(source)?;
+        Ok(self.new_shader(shader_type, &source))
+    }
+
+    /// Does the context resolve shader includes natively? True if the context supports
+    /// `ExtensionFeature::ShadingLanguageInclude` and was created with `from_loader` or
+    /// `from_loader_unvalidated`.
+    pub fn supports_named_strings(&self) -> bool {
+        self.named_string_functions.is_some()
+    }
+
+    /// Register the sources of the library as named strings, so that the driver resolves
+    /// `#include` lines naming them, each source `name` becoming the named string `/name`.
+    /// Named strings registered earlier and not in the library are left in place, replaced if
+    /// they are. Returns false and does nothing if named strings are not supported, in which
+    /// case `new_shader_with_includes` preprocesses the sources instead. See glNamedStringARB.
+    ///
+    /// The sources are wrapped in include guards, so that the driver includes each at most
+    /// once per shader, as `ShaderSourceLibrary::preprocess` does.
+    ///
+    /// Named strings are shared by all the contexts of the share group.
+    pub fn register_named_strings(&mut self, library: &ShaderSourceLibrary) -> bool {
+        let functions = match self.named_string_functions {
+            Some(functions) => functions,
+            None => return false
+        };
+        for name in library.names() {
+            let source = library.get(name).unwrap_or("");
+            functions.set(&format!("/{}", name), &shaderlib::guarded_source(name, source));
+            match self.named_strings.iter_mut().find(|registered| registered.0 == name) {
+                Some(registered) => registered.1 = source.to_string(),
+                None => self.named_strings.push((name.to_string(), source.to_string()))
+            }
+        }
+        true
+    }
+
+    /// Delete the named strings registered with `register_named_strings`. See
+    /// glDeleteNamedStringARB.
+    pub fn unregister_named_strings(&mut self) {
+        if let Some(functions) = self.named_string_functions {
+            for (name, _) in self.named_strings.drain(..) {
+                functions.delete(&format!("/{}", name));
+            }
+        }
+    }
+
+    /// Create and compile a shader from the source and its `#include` lines naming sources of
+    /// the library. If all the sources of the library are registered as named strings (see
+    /// `register_named_strings`) and haven't changed since, the driver resolves the includes:
+    /// the GL_ARB_shading_language_include extension is enabled after the `#version` line and
+    /// the shader is compiled with the search path `/`. Otherwise this is the same as
+    /// `new_shader_from_library`. Either way each source is included at most once.
+    ///
+    /// The includes are checked against the library either way, so a missing source is an error
+    /// here instead of a compile error.
+    pub fn new_shader_with_includes(&mut self, shader_type: ShaderType, source: &str, library: &ShaderSourceLibrary) -> Result<ShaderHandle, IncludeError> {
+        let preprocessed = library.preprocess(source)?;
+        let registered = library.names().iter().all(|&name| {
+            self.named_strings.iter().any(|registered| registered.0 == name && library.get(name) == Some(&registered.1[..]))
+        });
+        match self.named_string_functions {
+            Some(functions) if registered => {
+                let source = cache::inject_after_version(source, "#extension GL_ARB_shading_language_include : require\n");
+                let registration = self.registration_handle(ResourceKind::Shader);
+                Ok(self.track(new_handle(Shader::new_with_include_paths(shader_type, &source, registration, &functions, &["/"]))))
+            },
+            _ => Ok(self.new_shader(shader_type, &preprocessed))
+        }
     }
 
     /// Create and link a shader program from the specified shaders.
     pub fn new_program(&mut self, shaders: &[ShaderHandle]) -> ProgramHandle {
-        let registration = self.registration_handle();
+        for shader in shaders.iter() {
+            self.check_owner(shader.access());
+        }
+        let registration = self.registration_handle(ResourceKind::Program);
+        let id = self.id_generator.new_id();
+        self.track(new_handle(Program::new(id, shaders, registration)))
+    }
+
+    /// Get a program of the given vertex and fragment shader sources from the program cache of
+    /// the context, or create it if it's not there yet. The defines are injected into both
+    /// sources as `#define NAME VALUE` lines, after the `#version` line.
+    ///
+    /// If a binary directory is set with `set_program_binary_directory`, and the context
+    /// supports program binaries, the binaries of the programs are stored in the directory, and
+    /// a program not yet in the cache is loaded from its binary instead of compiling it.
+    pub fn get_or_create_program(&mut self, vertex_source: &str, fragment_source: &str, defines: &[(&str, &str)]) -> ProgramHandle {
+        let key = ProgramKey::new(vertex_source, fragment_source, defines);
+        if let Some(program) = self.program_cache.get(&key) {
+            return program;
+        }
+        let use_binaries = self.program_cache.has_binary_directory() && self.info.supports_program_binary();
+        if use_binaries {
+            let driver = self.info.version.driver_string();
+            if let Some((format, binary)) = self.program_cache.load_binary(&key, &driver) {
+                let registration = self.registration_handle(ResourceKind::Program);
+                let id = self.id_generator.new_id();
+                if let Some(program) = Program::from_binary(id, format, &binary, registration) {
+                    let program = self.track(new_handle(program));
+                    self.program_cache.insert(key, program.clone());
+                    return program;
+                }
+            }
+        }
+        let shaders = [
+            self.new_shader(ShaderType::VertexShader, &cache::inject_defines(vertex_source, defines)),
+            self.new_shader(ShaderType::FragmentShader, &cache::inject_defines(fragment_source, defines))
+        ];
+        let registration = self.registration_handle(ResourceKind::Program);
+        let id = self.id_generator.new_id();
+        let program = if use_binaries {
+            let program = Program::new_retrievable(id, &shaders, registration);
+            if program.get_link_status() {
+                if let Some((format, binary)) = program.get_binary() {
+                    self.program_cache.store_binary(&key, &self.info.version.driver_string(), format, &binary);
+                }
+            }
+            program
+        }
+        else {
+            Program::new(id, &shaders, registration)
+        };
+        let program = self.track(new_handle(program));
+        self.program_cache.insert(key, program.clone());
+        program
+    }
+
+    /// Set the directory the program cache stores program binaries in, or None to not store
+    /// them. See `get_or_create_program`.
+    pub fn set_program_binary_directory(&mut self, directory: Option<PathBuf>) {
+        self.program_cache.set_binary_directory(directory);
+    }
+
+    /// Remove all programs from the program cache. The programs stay alive as long as there
+    /// are handles to them elsewhere.
+    pub fn clear_program_cache(&mut self) {
+        self.program_cache.clear();
+    }
+
+    /// Create a new two-dimensional texture object. The texture has no storage until an image
+    /// is uploaded to it with a texture editor.
+    pub fn new_texture_2d(&mut self) -> TextureHandle {
+        let registration = self.registration_handle(ResourceKind::Texture);
+        let id = self.id_generator.new_id();
+        self.track(new_handle(texture::new_texture(TextureTarget::Texture2d, id, registration)))
+    }
+
+    /// Create a multisampled two-dimensional texture of the format, size and number of samples
+    /// per texel, for rendering into with a render target. Multisampled textures are read in
+    /// shaders with `texelFetch` from a `sampler2DMS`, or resolved into a single-sampled render
+    /// target by blitting, see `Renderer::use_read_framebuffer`. Panics as
+    /// `TextureEditor::allocate_multisample` does.
+    pub fn new_texture_2d_multisample(&mut self, format: TextureFormat, width: u32, height: u32, samples: u32) -> TextureHandle {
+        let registration = self.registration_handle(ResourceKind::Texture);
+        let id = self.id_generator.new_id();
+        let texture = self.track(new_handle(texture::new_texture(TextureTarget::Texture2dMultisample, id, registration)));
+        self.edit_texture(&texture).allocate_multisample(format, width, height, samples);
+        texture
+    }
+
+    /// Create a buffer texture reading its texels of the given format from the buffer, for
+    /// data too large for uniform blocks. The buffer can be changed later with
+    /// `TextureEditor::set_buffer`. Buffer textures have no mipmaps or sampling parameters, and
+    /// are bound with `Renderer::use_texture` as any other texture. Fails if the buffer has
+    /// more texels than `TextureInfo::max_buffer_size`.
+    pub fn new_buffer_texture(&mut self, buffer: &BufferHandle, format: TextureFormat) -> Result<TextureHandle, TextureBufferError> {
+        let registration = self.registration_handle(ResourceKind::Texture);
+        let id = self.id_generator.new_id();
+        let texture = self.track(new_handle(texture::new_texture(TextureTarget::Buffer, id, registration)));
+        self.edit_texture(&texture).set_buffer(buffer, format)?;
+        Ok(texture)
+    }
+
+    /// Create a render target with the given textures attached, for rendering into them in a
+    /// render pass, see `Renderer::pass`. The color textures are drawn into in the given order,
+    /// and the depth texture, if given, is used as the depth buffer (and the stencil buffer, if
+    /// it is of the format `Depth24Stencil8`).
+    ///
+    /// The base levels of the textures must have been allocated or uploaded, all of the same
+    /// size. Fails if they are not, or if OpenGL doesn't accept the combination of formats.
+    pub fn new_render_target(&mut self,
+                             color_textures: &[TextureHandle],
+                             depth_texture: Option<&TextureHandle>) -> Result<RenderTargetHandle, RenderTargetError> {
+        self.new_render_target_level(color_textures, depth_texture, 0)
+    }
+
+    /// Like `new_render_target`, but attaches the given mipmap level of the textures instead of
+    /// the base level, for rendering into the levels one by one, as when downsampling a bloom
+    /// chain. The level must have been allocated or uploaded in all the textures, of the same
+    /// size in each. There is no layer to choose, as the textures are two-dimensional, see
+    /// `RenderTarget`. See glFramebufferTexture2D.
+    pub fn new_render_target_level(&mut self,
+                                   color_textures: &[TextureHandle],
+                                   depth_texture: Option<&TextureHandle>,
+                                   level: u32) -> Result<RenderTargetHandle, RenderTargetError> {
+        for texture in color_textures.iter().chain(depth_texture) {
+            self.check_owner(texture.access());
+        }
+        let registration = self.registration_handle(ResourceKind::RenderTarget);
         let id = self.id_generator.new_id();
-        new_handle(Program::new(id, shaders, registration))
+        let render_target = RenderTarget::new(self, id, color_textures, depth_texture, level, registration);
+        self.restore_framebuffer();
+        render_target.map(|render_target| self.track(new_handle(render_target)))
+    }
+
+    /// Discard the contents of the textures of the render target, as they won't be read before
+    /// being drawn over. Does nothing without `ExtensionFeature::InvalidateFramebuffer`. See
+    /// `RenderTarget::invalidate`.
+    pub fn invalidate_render_target(&mut self, render_target: &RenderTargetHandle) {
+        if !self.info.supports(ExtensionFeature::InvalidateFramebuffer) {
+            return;
+        }
+        self.bind_framebuffer_for_editing(render_target.access());
+        render_target.access().invalidate();
+        self.restore_framebuffer();
+    }
+
+    /// Choose the first of the candidate texture formats that the context supports, returning it
+    /// with its properties. List the preferred format first and the fallbacks after it. Returns
+    /// None if none of the formats is supported. See `ContextInfo::texture_format_properties`.
+    pub fn choose_texture_format(&self, candidates: &[TextureFormat]) -> Option<(TextureFormat, TextureFormatProperties)> {
+        candidates.iter()
+            .map(|&format| (format, self.info.texture_format_properties(format)))
+            .find(|&(_, properties)| properties.supported)
+    }
+
+    // Adopt objects created outside the library
+
+    /// Wrap a buffer object created outside this library in a handle. The size of the buffer is
+    /// queried from OpenGL. With `Ownership::Owned`, the buffer is deleted when the last handle
+    /// is dropped.
+    pub fn adopt_raw_buffer(&mut self, gl_id: u32, ownership: Ownership) -> BufferHandle {
+        let registration = self.registration_handle(ResourceKind::Buffer);
+        let id = self.id_generator.new_id();
+        let buffer = buffer::adopt_buffer(self, gl_id, id, registration, ownership);
+        self.track(new_handle(buffer))
+    }
+
+    /// Wrap a linked program object created outside this library in a handle. See
+    /// `adopt_raw_buffer` for the ownership.
+    pub fn adopt_raw_program(&mut self, gl_id: u32, ownership: Ownership) -> ProgramHandle {
+        let registration = self.registration_handle(ResourceKind::Program);
+        let id = self.id_generator.new_id();
+        self.track(new_handle(Program::adopt(gl_id, id, registration, ownership)))
+    }
+
+    /// Wrap a two-dimensional texture object created outside this library in a handle. See
+    /// `adopt_raw_buffer` for the ownership.
+    pub fn adopt_raw_texture_2d(&mut self, gl_id: u32, ownership: Ownership) -> TextureHandle {
+        let registration = self.registration_handle(ResourceKind::Texture);
+        let id = self.id_generator.new_id();
+        self.track(new_handle(texture::adopt_texture(gl_id, TextureTarget::Texture2d, id, registration, ownership)))
     }
 
     // Modify object contents with the help of editor objects
 
     /// Edit a vertex buffer. Returns an editor object that can be used to modify the buffer
     /// contents.
-    pub fn edit_vertex_buffer<'a>(&'a mut self, vbo: &'a BufferHandle) -> BufferEditor {
+    pub fn edit_vertex_buffer<'a>(&'a mut self, vbo: &'a BufferHandle) -> BufferEditor<'a> {
         buffer::new_vertex_buffer_editor(self, vbo.access())
     }
 
@@ -191,7 +720,7 @@ impl Context {
     /// an vertex array. The returned value is wrapped in an Option, because vertex arrays do not
     /// necessarily contain an index buffer. Still, it would be silly to call this function with a
     /// VAO that does not have an index buffer attached to it.
-    pub fn edit_index_buffer<'a>(&'a mut self, vao: &'a VertexArrayHandle) -> Option<BufferEditor> {
+    pub fn edit_index_buffer<'a>(&'a mut self, vao: &'a VertexArrayHandle) -> Option<BufferEditor<'a>> {
         let vao = vao.access();
         match vao.index_buffer() {
             Some(ref mut ibo) => Some(buffer::new_index_buffer_editor(self, vao, ibo)),
@@ -199,36 +728,304 @@ impl Context {
         }
     }
 
+    /// Edit an index buffer without a vertex array of yours, for filling index buffers before
+    /// creating the vertex arrays using them. The buffer is bound to a scratch vertex array
+    /// owned by the context, created on first use.
+    pub fn edit_index_buffer_direct<'a>(&'a mut self, ibo: &'a BufferHandle) -> BufferEditor<'a> {
+        self.check_owner(ibo.access());
+        let scratch = match self.scratch_vertex_array {
+            Some(ref scratch) => scratch.clone(),
+            None => {
+                let scratch = self.new_vertex_array(&[], None);
+                self.scratch_vertex_array = Some(scratch.clone());
+                scratch
+            }
+        };
+        buffer::new_direct_index_buffer_editor(self, scratch.access(), ibo.access())
+    }
+
     /// Edit an uniform buffer. Returns an editor object that can be used to modify the buffer
     /// contents.
-    pub fn edit_uniform_buffer<'a>(&'a mut self, ubo: &'a BufferHandle) -> BufferEditor {
+    pub fn edit_uniform_buffer<'a>(&'a mut self, ubo: &'a BufferHandle) -> BufferEditor<'a> {
         buffer::new_uniform_buffer_editor(self, ubo.access())
     }
 
+    /// Edit a draw indirect buffer, the source of the draw commands of
+    /// `Renderer::multi_draw_elements_indirect`. See also `IndirectCommandBuffer`.
+    pub fn edit_indirect_buffer<'a>(&'a mut self, buffer: &'a BufferHandle) -> BufferEditor<'a> {
+        buffer::new_indirect_buffer_editor(self, buffer.access())
+    }
+
+    /// Edit a texture. Returns an editor object that can be used to upload images to the texture
+    /// and to set its parameters.
+    pub fn edit_texture<'a>(&'a mut self, texture: &'a TextureHandle) -> TextureEditor<'a> {
+        texture::new_texture_editor(self, texture.access())
+    }
+
     /// Lets you edit uniform bindings of a program with the returned editor.
-    pub fn edit_program<'a>(&'a mut self, program: &'a ProgramHandle) -> ProgramEditor {
+    pub fn edit_program<'a>(&'a mut self, program: &'a ProgramHandle) -> ProgramEditor<'a> {
         program::new_program_editor(self, program.access())
     }
 
     /// Returns and "info accessor" that can figure out the attribute, uniform and fragment data
     /// locations and other related information.
-    pub fn program_info<'a>(&'a self, program: &'a ProgramHandle) -> ProgramInfoAccessor {
-        program::new_program_info_accessor(program.access())
+    pub fn program_info<'a>(&'a self, program: &'a ProgramHandle) -> ProgramInfoAccessor<'a> {
+        program::new_program_info_accessor(program.access(), &self.info)
     }
 
     /// Returns an "info accessor" that can tell if shader compilation succeeded and return the
     /// compilation info log.
-    pub fn shader_info<'a>(&'a self, shader: &'a ShaderHandle) -> ShaderInfoAccessor {
+    pub fn shader_info<'a>(&'a self, shader: &'a ShaderHandle) -> ShaderInfoAccessor<'a> {
         shader::new_shader_info_accessor(shader.access())
     }
 
     // Commands that do not (directly) consume resources
 
     /// Return a renderer object. See `Renderer` documentation for info on usage.
-    pub fn renderer<'a>(&'a mut self) -> Renderer {
+    pub fn renderer<'a>(&'a mut self) -> Renderer<'a> {
         Renderer::new(self)
     }
 
+    // Surface handling
+
+    /// Give the surface (for example, a window) the context renders to. The viewport is
+    /// immediately set to cover the whole surface, and updated on `present` whenever the surface
+    /// size changes.
+    pub fn set_surface(&mut self, surface: Box<dyn Surface>) {
+        self.surface = Some(surface);
+        self.update_viewport();
+    }
+
+    /// Take the surface away from the context, if one was given.
+    pub fn take_surface(&mut self) -> Option<Box<dyn Surface>> {
+        self.surface.take()
+    }
+
+    /// Present the rendered frame on the surface, and update the viewport if the surface has
+    /// been resized. Panics if no surface has been given with `set_surface`.
+    pub fn present(&mut self) {
+        match self.surface {
+            Some(ref mut surface) => surface.present(),
+            None => panic!("Can't present, the context has no surface")
+        }
+        self.update_viewport();
+        self.delete_queued_objects();
+    }
+
+    /// Delete the OpenGL objects of the resources that have been dropped. Dropping the last
+    /// handle to a resource doesn't delete the object right away, as that could happen at any
+    /// point, for example while rendering. Instead the object is queued, and the queue is
+    /// emptied by this method, which is called by `present` and when the context is dropped.
+    /// Call it yourself at the end of a frame if you don't present through the context.
+    ///
+    /// The binding trackers forget the deleted objects, so that binding a new resource is never
+    /// skipped because of a stale binding.
+    pub fn delete_queued_objects(&mut self) {
+        let queue = mem::take(&mut self.shared_state.borrow_mut().deletion_queue);
+        for object in queue {
+            self.forget_binding(object);
+            delete_object(object);
+        }
+    }
+
+    /// Set the swap interval of the surface, see `Surface::set_swap_interval`. Panics if no
+    /// surface has been given with `set_surface`.
+    pub fn set_swap_interval(&mut self, interval: i32) {
+        match self.surface {
+            Some(ref mut surface) => surface.set_swap_interval(interval),
+            None => panic!("Can't set swap interval, the context has no surface")
+        }
+    }
+
+    /// Insert a fence into the command stream, to find out when the GPU has executed the
+    /// commands given so far. See `Fence`.
+    pub fn insert_fence(&mut self) -> Fence {
+        sync::new_fence(self.registration_handle(ResourceKind::Fence))
+    }
+
+    /// Create a query object of the target. See `Query` and `Renderer::with_query`.
+    pub fn new_query(&mut self, target: QueryTarget) -> Query {
+        query::new_query(target, self.registration_handle(ResourceKind::Query))
+    }
+
+    /// Count the live resources of the context and the memory they use. In debug builds, the
+    /// resources still alive when the context is dropped are reported on the standard error.
+    pub fn resource_report(&self) -> ResourceReport {
+        self.shared_state.borrow().resources
+    }
+
+    /// Describe the live resources of the context, with their labels, sizes, formats and the
+    /// resources they refer to, for inspection tools or for checking the state in tests. With the
+    /// `serde` feature, the snapshot can be serialized, for example to JSON.
+    pub fn export_debug_snapshot(&self) -> DebugSnapshot {
+        self.live_resources.snapshot(self.resource_report())
+    }
+
+    /// Give the resource a label, shown in `export_debug_snapshot` and, if the context supports
+    /// object labels, by graphics debuggers. See glObjectLabel.
+    pub fn set_label<T: SnapshotResource>(&mut self, resource: &Handle<T>, label: &str) {
+        self.check_owner(resource.access());
+        let (identifier, name) = resource.access().label_target();
+        debug::object_label(&self.info, identifier, name, label);
+        resource.access().registration().set_label(label);
+    }
+
+    /// Dimensions of the surface, if the context has one.
+    pub fn surface_dimensions(&self) -> Option<(u32, u32)> {
+        self.surface.as_ref().map(|surface| surface.dimensions())
+    }
+
+    /// Set how the binding trackers of the context handle binding resources that are already
+    /// bound. Skipping the redundant glBind* calls is the default; binding always, and possibly
+    /// logging the binds, helps to find out if a problem is caused by the skipping.
+    pub fn set_tracker_mode(&mut self, mode: TrackerMode) {
+        self.program_tracker.set_mode(mode);
+        self.vbo_tracker.set_mode(mode);
+        self.ubo_tracker.set_mode(mode);
+        self.indirect_tracker.set_mode(mode);
+        self.vao_tracker.set_mode(mode);
+        for tracker in self.texture_trackers.iter_mut() {
+            tracker.set_mode(mode);
+        }
+        self.draw_framebuffer_tracker.set_mode(mode);
+        self.read_framebuffer_tracker.set_mode(mode);
+        self.indexed_ranges.set_mode(mode);
+    }
+
+    /// The buffer ranges bound to the indexed binding points of the target through the library,
+    /// as far as the context knows, ordered by binding point. Unlike `debug_dump_bindings`, this
+    /// doesn't query OpenGL.
+    pub fn bound_buffer_ranges(&self, target: IndexedBufferTarget) -> Vec<IndexedBufferBinding> {
+        self.indexed_ranges.ranges(target)
+    }
+
+    /// Bind zero to all the binding points the context tracks: the program, the vertex array,
+    /// the vertex, uniform and draw indirect buffers, the indexed buffer ranges, the textures of
+    /// all the texture units and the framebuffer, binding the default framebuffer. The resources
+    /// used for rendering are forgotten, so they must be used again before drawing. Useful before
+    /// handing the context over to code that expects nothing to be bound.
+    pub fn unbind_all(&mut self) {
+        self.program_tracker.unbind();
+        self.vao_tracker.unbind();
+        self.indexed_ranges.unbind();
+        self.vbo_tracker.unbind();
+        self.ubo_tracker.unbind();
+        self.indirect_tracker.unbind();
+        for tracker in self.texture_trackers.iter_mut() {
+            tracker.unbind();
+        }
+        self.draw_framebuffer_tracker.unbind();
+        self.read_framebuffer_tracker.unbind();
+    }
+
+    /// Run code that calls OpenGL functions directly. Afterwards the binding trackers and the pixel
+    /// store, render option, clear value and attribute default caches are invalidated, as the
+    /// closure may have changed any of the state they track. The resources used for rendering are
+    /// bound again before the next draw call.
+    ///
+    /// The closure should not delete objects that are used through handles.
+    pub fn with_raw_gl<F, T>(&mut self, f: F) -> T where F: FnOnce(&RawGlScope) -> T {
+        let result = f(&raw::new_raw_gl_scope());
+        self.assume_state_clean();
+        result
+    }
+
+    /// Set all the state the context believes OpenGL to have, whether or not it is believed to be
+    /// set already: the resources bound for rendering (zero where there are none), the indexed
+    /// buffer ranges, the viewport (of the current render pass, if any, otherwise the one covering
+    /// the surface) and the cached pixel store parameters, render options, clear values and
+    /// attribute defaults. Nothing is bound to the vertex, uniform and draw indirect buffer binding
+    /// points after this.
+    ///
+    /// Use this before handing the context over to code that expects to find the state it set
+    /// earlier through this library, for example after a middleware library has run in between.
+    /// State the library hasn't set is left as it is.
+    pub fn flush_state(&mut self) {
+        self.program_tracker.reapply();
+        self.vao_tracker.reapply();
+        for tracker in self.texture_trackers.iter_mut() {
+            tracker.reapply();
+        }
+        self.draw_framebuffer_tracker.reapply();
+        self.read_framebuffer_tracker.reapply();
+        self.indexed_ranges.reapply();
+        self.vbo_tracker.unbind();
+        self.ubo_tracker.unbind();
+        self.indirect_tracker.unbind();
+        match self.pass_viewport {
+            Some(viewport) => set_viewport(viewport),
+            None if self.viewport_size != (0, 0) => set_viewport([0, 0, self.viewport_size.0 as i32, self.viewport_size.1 as i32]),
+            None => ()
+        }
+        self.pixel_store.reapply();
+        self.render_options.reapply();
+        self.clear_values.reapply();
+        self.attribute_defaults.reapply();
+    }
+
+    /// Forget the state the context believes OpenGL to have: the binding trackers and the pixel
+    /// store, render option, clear value and attribute default caches are invalidated, so that
+    /// everything is set again when next needed. The framebuffer and the viewport of the current
+    /// render pass are set again right away, and the other resources used for rendering before
+    /// the next draw call. `with_raw_gl` does this after running its closure.
+    ///
+    /// Use this after code outside this library has changed the OpenGL state, and nothing is
+    /// assumed about what it left behind.
+    pub fn assume_state_clean(&mut self) {
+        self.program_tracker.invalidate();
+        self.vbo_tracker.invalidate();
+        self.ubo_tracker.invalidate();
+        self.indirect_tracker.invalidate();
+        self.vao_tracker.invalidate();
+        for tracker in self.texture_trackers.iter_mut() {
+            tracker.invalidate();
+        }
+        self.draw_framebuffer_tracker.invalidate();
+        self.read_framebuffer_tracker.invalidate();
+        self.pixel_store.invalidate();
+        self.render_options.invalidate();
+        self.clear_values.invalidate();
+        self.attribute_defaults.invalidate();
+        self.indexed_ranges.invalidate();
+        // Make the next present set the viewport again
+        self.viewport_size = (0, 0);
+        if let Some(viewport) = self.pass_viewport {
+            set_viewport(viewport);
+        }
+        self.restore_framebuffer();
+    }
+
+    /// Check whether the context has been lost in a graphics reset, such as a driver restart or a
+    /// GPU hang. See glGetGraphicsResetStatus. Always `NoError` without
+    /// `ExtensionFeature::Robustness`, or if the context was not created with a reset notification
+    /// strategy of GL_LOSE_CONTEXT_ON_RESET (see `RobustnessInfo`).
+    ///
+    /// After a reset, the context and all its resources are unusable: `is_lost` returns true and
+    /// so does `Handle::is_lost` for the resources of this context. Create a new OpenGL context
+    /// and a new `Context`, and rebuild the resources.
+    pub fn graphics_reset_status(&mut self) -> GraphicsResetStatus {
+        if !self.info.supports(ExtensionFeature::Robustness) {
+            return GraphicsResetStatus::NoError;
+        }
+        let status = match unsafe { gl::GetGraphicsResetStatus() } {
+            gl::GUILTY_CONTEXT_RESET => GraphicsResetStatus::GuiltyContextReset,
+            gl::INNOCENT_CONTEXT_RESET => GraphicsResetStatus::InnocentContextReset,
+            gl::UNKNOWN_CONTEXT_RESET => GraphicsResetStatus::UnknownContextReset,
+            _ => GraphicsResetStatus::NoError
+        };
+        if status != GraphicsResetStatus::NoError {
+            let mut shared = self.shared_state.borrow_mut();
+            shared.context_lost = true;
+            shared.deletion_queue.clear();
+        }
+        status
+    }
+
+    /// Has a graphics reset been detected by `graphics_reset_status`?
+    pub fn is_lost(&self) -> bool {
+        self.shared_state.borrow().context_lost
+    }
+
     // Expose context info to user too!
 
     /// `ContextInfo` contains unchanging values related to the context, like
@@ -238,46 +1035,387 @@ impl Context {
         &self.info
     }
 
+    /// Set the default sRGB policy of texture uploads, used by the texture editors created from
+    /// now on. See `SrgbPolicy`.
+    pub fn set_srgb_policy(&mut self, policy: SrgbPolicy) {
+        self.srgb_policy = policy;
+    }
+
+    /// The default sRGB policy of texture uploads.
+    pub fn srgb_policy(&self) -> SrgbPolicy {
+        self.srgb_policy
+    }
+
+    /// The clip control state set with `Renderer::set_clip_control`, the OpenGL default if it
+    /// has not been set. Give it to the projection functions of the `matrix` module, so that
+    /// the matrices match the clip space of the context.
+    pub fn clip_control(&self) -> (ClipOrigin, ClipDepth) {
+        self.clip_control
+    }
+
+    /// The allocator of uniform buffer binding points, for assigning them by name up to
+    /// GL_MAX_UNIFORM_BUFFER_BINDINGS. See `ProgramEditor::bind_uniform_blocks` for binding the
+    /// uniform blocks of a program accordingly.
+    pub fn uniform_binding_points(&mut self) -> &mut BindingPointAllocator {
+        &mut self.uniform_binding_points
+    }
+
+    /// The allocator of shader storage buffer binding points, for assigning them by name up to
+    /// GL_MAX_SHADER_STORAGE_BUFFER_BINDINGS, none without `ExtensionFeature::ShaderStorageBuffer`.
+    /// See `ProgramEditor::bind_storage_blocks` for binding the storage blocks of a program
+    /// accordingly.
+    pub fn storage_binding_points(&mut self) -> &mut BindingPointAllocator {
+        &mut self.storage_binding_points
+    }
+
+    /// Query what OpenGL has bound to the binding points the context uses: the program, the vertex
+    /// array, the vertex and uniform buffers, the indexed uniform, storage, atomic counter and
+    /// transform feedback buffer bindings, the textures of each unit and the framebuffers. The
+    /// bindings are compared with what the binding trackers believe, and the differences are listed
+    /// in `BindingReport::divergences`. Useful for finding out what went wrong when OpenGL is
+    /// called directly without `with_raw_gl`.
+    ///
+    /// Causes a lot of glGet* calls, so this is for debugging only.
+    pub fn debug_dump_bindings(&self) -> BindingReport {
+        let uniform_bindings = self.info.uniform_buffer.max_bindings as u32;
+        let mut report = bindingreport::query_bindings(&self.info, self.texture_trackers.len() as u32, uniform_bindings);
+        let (program, vertex_array, array_buffer, uniform_buffer) =
+            (report.program, report.vertex_array, report.array_buffer, report.uniform_buffer);
+        report.compare("GL_CURRENT_PROGRAM", self.program_tracker.bound_name(), program);
+        report.compare("GL_VERTEX_ARRAY_BINDING", self.vao_tracker.bound_name(), vertex_array);
+        report.compare("GL_ARRAY_BUFFER_BINDING", self.vbo_tracker.bound_name(), array_buffer);
+        report.compare("GL_UNIFORM_BUFFER_BINDING", self.ubo_tracker.bound_name(), uniform_buffer);
+        for (unit, tracker) in self.texture_trackers.iter().enumerate() {
+            // Compare on the target of the texture believed to be bound. A texture bound only
+            // for editing isn't kept by the tracker, but the editing binds are undone afterwards.
+            let target = tracker.bound_for_rendering()
+                .filter(|texture| Some(texture.get_id()) == tracker.bound_name())
+                .map_or(TextureTarget::Texture2d, |texture| texture.target());
+            let actual = report.texture(unit as u32, target);
+            let binding = match target {
+                TextureTarget::Texture2d => "GL_TEXTURE_BINDING_2D",
+                TextureTarget::Buffer => "GL_TEXTURE_BINDING_BUFFER",
+                TextureTarget::Texture2dMultisample => "GL_TEXTURE_BINDING_2D_MULTISAMPLE"
+            };
+            report.compare(&format!("{} of unit {}", binding, unit), tracker.bound_name(), actual);
+        }
+        let targets = [IndexedBufferTarget::Uniform, IndexedBufferTarget::ShaderStorage,
+            IndexedBufferTarget::AtomicCounter, IndexedBufferTarget::TransformFeedback];
+        for &target in targets.iter() {
+            for believed in self.indexed_ranges.ranges(target) {
+                let actual = report.ranges(target).iter().find(|range| range.binding == believed.binding).cloned();
+                if actual != Some(believed) {
+                    // A different range of the same buffer shows up with the same names on both sides
+                    report.divergences.push(BindingDivergence {
+                        binding: format!("{} of binding {} (range {}..{})", target.binding_name(), believed.binding,
+                            believed.offset, believed.offset + believed.size),
+                        believed: believed.buffer,
+                        actual: actual.map_or(0, |range| range.buffer)
+                    });
+                }
+            }
+        }
+        let (draw_framebuffer, read_framebuffer) = (report.draw_framebuffer, report.read_framebuffer);
+        report.compare("GL_DRAW_FRAMEBUFFER_BINDING", self.draw_framebuffer_tracker.bound_name(), draw_framebuffer);
+        report.compare("GL_READ_FRAMEBUFFER_BINDING", self.read_framebuffer_tracker.bound_name(), read_framebuffer);
+        report
+    }
+
+    /// Insert a marker with the message into the command stream, shown by graphics debuggers
+    /// like RenderDoc in frame captures. Does nothing if debug markers are not available (see
+    /// `ContextInfo::supports_debug_groups`). See glDebugMessageInsert.
+    pub fn debug_marker(&mut self, message: &str) {
+        debug::insert_marker(&self.info, message);
+    }
+
+    /// Route the OpenGL errors caught by the error checks of the library, and the failures of
+    /// the validation of draw calls, to the handler instead of panicking, for example to log
+    /// them in a long-running editor. A draw call that fails validation is skipped. The handler
+    /// is removed when the context is dropped.
+    ///
+    /// The error checks don't know which context they are made for, so with several contexts on
+    /// the thread, the errors go to the handler of the context that set its handler last. Other
+    /// misuse of the library, like using a feature the context doesn't support, still panics.
+    pub fn set_error_handler<F>(&mut self, handler: F) where F: FnMut(&HtglError) + 'static {
+        util::set_error_handler(&self.error_handler, Some(Box::new(handler)));
+    }
+
+    /// Remove the error handler, so that errors panic again.
+    pub fn clear_error_handler(&mut self) {
+        util::set_error_handler(&self.error_handler, None);
+    }
+
     // Internal stuff
 
+    /// Panic if the resource belongs to another context, see `RegistrationHandle::check_context`.
+    fn check_owner(&self, resource: &dyn ContextResource) {
+        resource.registration().check_context(&self.shared_state);
+    }
+
+    fn update_viewport(&mut self) {
+        if let Some(size) = self.surface_dimensions() {
+            if size != self.viewport_size {
+                set_viewport([0, 0, size.0 as i32, size.1 as i32]);
+                self.viewport_size = size;
+            }
+        }
+    }
+
+    /// Bind the draw and read framebuffers of the current render pass again after binding
+    /// another render target for editing, or the default framebuffer if there is no render pass.
+    fn restore_framebuffer(&mut self) {
+        for tracker in [&mut self.draw_framebuffer_tracker, &mut self.read_framebuffer_tracker] {
+            if tracker.bound_for_rendering().is_some() {
+                tracker.restore_rendering_state();
+            }
+            else {
+                tracker.unbind();
+            }
+        }
+    }
+
+    /// Make the trackers forget the object, if it is bound.
+    fn forget_binding(&mut self, object: QueuedDeletion) {
+        match object {
+            QueuedDeletion::Buffer(id, tracker_id) => {
+                self.vbo_tracker.forget(tracker_id);
+                self.ubo_tracker.forget(tracker_id);
+                self.indirect_tracker.forget(tracker_id);
+                self.indexed_ranges.forget_buffer(id);
+            },
+            QueuedDeletion::VertexArray(_, tracker_id) => self.vao_tracker.forget(tracker_id),
+            QueuedDeletion::Program(_, tracker_id) => self.program_tracker.forget(tracker_id),
+            QueuedDeletion::Texture(_, tracker_id) => {
+                for tracker in self.texture_trackers.iter_mut() {
+                    tracker.forget(tracker_id);
+                }
+            },
+            QueuedDeletion::Framebuffer(_, tracker_id) => {
+                self.draw_framebuffer_tracker.forget(tracker_id);
+                self.read_framebuffer_tracker.forget(tracker_id);
+            },
+            QueuedDeletion::Shader(_) | QueuedDeletion::Sync(_) | QueuedDeletion::Query(_) => ()
+        }
+    }
+
+    /// Remember the resource for `export_debug_snapshot`.
+    fn track<T: SnapshotResource>(&mut self, handle: Handle<T>) -> Handle<T> {
+        self.live_resources.add(&handle);
+        handle
+    }
+
     /// Resources get a handle to the shared state
-    fn registration_handle(&self) -> RegistrationHandle {
-        RegistrationHandle::new(self.shared_state.clone())
+    fn registration_handle(&self, kind: ResourceKind) -> RegistrationHandle {
+        RegistrationHandle::new(self.shared_state.clone(), kind)
     }
 }
 
+#[cfg(not(feature = "es3"))]
+fn validate_version(version: &VersionInfo) -> Result<(), ContextCreationError> {
+    if version.major < 3 || (version.major == 3 && version.minor < 3) {
+        return Err(ContextCreationError::UnsupportedVersion(version.major, version.minor));
+    }
+    if !version.core_profile {
+        return Err(ContextCreationError::NotCoreProfile);
+    }
+    Ok(())
+}
+
+/// OpenGL ES has no profiles, so only the version is checked.
+#[cfg(feature = "es3")]
+fn validate_version(version: &VersionInfo) -> Result<(), ContextCreationError> {
+    if version.major < 3 {
+        return Err(ContextCreationError::UnsupportedVersion(version.major, version.minor));
+    }
+    Ok(())
+}
+
 impl Drop for Context {
     fn drop(&mut self) {
-        self.shared_state.borrow_mut().context_alive = false;
+        // Let go of the resources the context itself holds, so that only the ones held by the
+        // user are reported
+        self.program_cache.clear();
+        self.scratch_vertex_array = None;
+        self.unbind_all();
+        self.delete_queued_objects();
+        let mut shared = self.shared_state.borrow_mut();
+        shared.context_alive = false;
+        if cfg!(debug_assertions) && shared.resources.live_count() > 0 {
+            eprintln!("htgl: context dropped while resources are still alive: {:?}", shared.resources);
+        }
     }
 }
 
+/// The live resources of a context and the approximate memory they use, see
+/// `Context::resource_report`. Resources are counted from their creation until their last
+/// handle is dropped, adopted ones included.
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ResourceReport {
+    pub buffers: usize,
+    pub vertex_arrays: usize,
+    pub shaders: usize,
+    pub programs: usize,
+    pub textures: usize,
+    pub render_targets: usize,
+    pub fences: usize,
+    pub queries: usize,
+    /// The total size of the buffer data stores.
+    pub buffer_bytes: usize,
+    /// The total size of the texture images, computed from the internal formats and the sizes
+    /// of the uploaded mipmap levels. Adopted textures are not included, as their contents are
+    /// unknown.
+    pub texture_bytes: usize
+}
+
+impl ResourceReport {
+    /// How many resources there are in total.
+    pub fn live_count(&self) -> usize {
+        self.buffers + self.vertex_arrays + self.shaders + self.programs + self.textures + self.render_targets +
+            self.fences + self.queries
+    }
+
+    fn count_mut(&mut self, kind: ResourceKind) -> &mut usize {
+        match kind {
+            ResourceKind::Buffer => &mut self.buffers,
+            ResourceKind::VertexArray => &mut self.vertex_arrays,
+            ResourceKind::Shader => &mut self.shaders,
+            ResourceKind::Program => &mut self.programs,
+            ResourceKind::Texture => &mut self.textures,
+            ResourceKind::RenderTarget => &mut self.render_targets,
+            ResourceKind::Fence => &mut self.fences,
+            ResourceKind::Query => &mut self.queries
+        }
+    }
+
+    fn memory_mut(&mut self, kind: ResourceKind) -> Option<&mut usize> {
+        match kind {
+            ResourceKind::Buffer => Some(&mut self.buffer_bytes),
+            ResourceKind::Texture => Some(&mut self.texture_bytes),
+            _ => None
+        }
+    }
+}
+
+/// The kinds of resources counted in `ResourceReport`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ResourceKind {
+    Buffer,
+    VertexArray,
+    Shader,
+    Program,
+    Texture,
+    RenderTarget,
+    Fence,
+    Query
+}
+
+/// The result of glGetGraphicsResetStatus.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum GraphicsResetStatus {
+    /// GL_NO_ERROR, the context has not been reset.
+    NoError,
+    /// GL_GUILTY_CONTEXT_RESET, the reset was caused by this context.
+    GuiltyContextReset,
+    /// GL_INNOCENT_CONTEXT_RESET, the reset was caused by something else.
+    InnocentContextReset,
+    /// GL_UNKNOWN_CONTEXT_RESET, the cause of the reset is unknown.
+    UnknownContextReset
+}
+
+/// The ways creating a `Context` with `Context::from_loader` can fail. Only `LoadingFailed` is
+/// returned by `Context::from_loader_unvalidated`.
+#[derive(Debug)]
+pub enum ContextCreationError {
+    /// The OpenGL functions could not be loaded or the context version could not be queried.
+    /// Most likely there is no current context, or it is older than OpenGL 3.0.
+    LoadingFailed,
+    /// The context is older than OpenGL 3.3. The values are the major and minor version.
+    UnsupportedVersion(i32, i32),
+    /// The context is not a core profile context.
+    NotCoreProfile
+}
+
+impl fmt::Display for ContextCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContextCreationError::LoadingFailed =>
+                write!(f, "Failed to load OpenGL functions or to query the context version"),
+            ContextCreationError::UnsupportedVersion(major, minor) =>
+                write!(f, "OpenGL 3.3 or newer is required, but the context version is {}.{}", major, minor),
+            ContextCreationError::NotCoreProfile =>
+                write!(f, "A core profile context is required")
+        }
+    }
+}
+
+impl Error for ContextCreationError {}
+
 /// A trait with the purpose to expose only the editing functionality to other types in the
 /// library, without exposing all the internals of `Context`. Specifically it facilitates
 /// binding of resources *for editing*, something not exposed to outside users.
 pub trait ContextEditingSupport {
     fn bind_vbo_for_editing(&mut self, vbo: &BufferObject);
     fn bind_ubo_for_editing(&mut self, vbo: &BufferObject);
+    fn bind_indirect_buffer_for_editing(&mut self, buffer: &BufferObject);
     fn bind_vao_for_editing(&mut self, vao: &VertexArray);
     fn bind_program_for_editing(&mut self, program: &Program);
+    fn bind_texture_for_editing(&mut self, texture: &Texture);
+    fn bind_framebuffer_for_editing(&mut self, render_target: &RenderTarget);
+    /// Bind the framebuffer used for rendering again after a framebuffer has been bound without
+    /// the tracker.
+    fn reset_framebuffer_binding(&mut self);
+    fn set_pixel_store(&mut self, param: PixelStoreParam);
 }
 
 impl ContextEditingSupport for Context {
     fn bind_vbo_for_editing(&mut self, vbo: &BufferObject) {
+        self.check_owner(vbo);
         self.vbo_tracker.bind(&vbo);
     }
 
     fn bind_ubo_for_editing(&mut self, ubo: &BufferObject) {
+        self.check_owner(ubo);
         self.ubo_tracker.bind(ubo);
     }
 
+    fn bind_indirect_buffer_for_editing(&mut self, buffer: &BufferObject) {
+        self.check_owner(buffer);
+        self.indirect_tracker.bind(buffer);
+    }
+
     fn bind_vao_for_editing(&mut self, vao: &VertexArray) {
+        self.check_owner(vao);
         self.vao_tracker.bind_for_editing(vao);
     }
 
     fn bind_program_for_editing(&mut self, program: &Program) {
+        self.check_owner(program);
         self.program_tracker.bind_for_editing(program);
     }
+
+    fn bind_texture_for_editing(&mut self, texture: &Texture) {
+        self.check_owner(texture);
+        self.texture_trackers[0].bind_for_editing(texture);
+    }
+
+    fn bind_framebuffer_for_editing(&mut self, render_target: &RenderTarget) {
+        self.check_owner(render_target);
+        // Editing binds both, as some of the framebuffer state, like glReadBuffer, is set
+        // through the read binding
+        self.draw_framebuffer_tracker.bind_for_editing(render_target);
+        self.read_framebuffer_tracker.bind_for_editing(render_target);
+    }
+
+    fn reset_framebuffer_binding(&mut self) {
+        self.draw_framebuffer_tracker.invalidate();
+        self.read_framebuffer_tracker.invalidate();
+        self.restore_framebuffer();
+    }
+
+    fn set_pixel_store(&mut self, param: PixelStoreParam) {
+        self.pixel_store.set(param);
+    }
 }
 
 /// See `ContextEditingSupport`. This trait is to expose binding functions used when
@@ -285,52 +1423,369 @@ impl ContextEditingSupport for Context {
 pub trait ContextRenderingSupport {
     fn bind_vao_for_rendering(&mut self, vao: &VertexArrayHandle);
     fn bind_program_for_rendering(&mut self, program: &ProgramHandle);
+    fn bind_texture_for_rendering(&mut self, unit: u32, texture: &TextureHandle);
+    /// Bind the render target for rendering, or the default framebuffer if None.
+    fn bind_framebuffer_for_rendering(&mut self, render_target: Option<&Rc<RenderTarget>>);
+    fn bind_draw_framebuffer_for_rendering(&mut self, render_target: Option<&Rc<RenderTarget>>);
+    fn bind_read_framebuffer_for_rendering(&mut self, render_target: Option<&Rc<RenderTarget>>);
+    fn framebuffer_for_rendering(&self) -> Option<Rc<RenderTarget>>;
+    fn read_framebuffer_for_rendering(&self) -> Option<Rc<RenderTarget>>;
     fn prepare_for_rendering(&mut self);
+    /// Prepare for a draw call, after which the attribute defaults of the locations the vertex
+    /// array in use enables are undefined.
+    fn prepare_for_drawing(&mut self);
+    fn bind_uniform_buffer_range(&mut self, binding: u32, buffer: &BufferObject, offset: usize, size: usize);
+    /// Bind a range to an indexed binding point of a target other than uniform buffers, which
+    /// have their own checks.
+    fn bind_indexed_buffer_range(&mut self, target: IndexedBufferTarget, binding: u32, buffer: &BufferObject, offset: usize, size: usize);
+    fn bind_indirect_buffer_for_rendering(&mut self, buffer: &BufferObject);
+    fn unbind_vao_for_rendering(&mut self);
+    fn unbind_program_for_rendering(&mut self);
+    fn vao_for_rendering(&self) -> Option<&VertexArray>;
+    fn program_for_rendering(&self) -> Option<&Program>;
+    fn texture_for_rendering(&self, unit: u32) -> Option<&Texture>;
+    fn set_render_option(&mut self, option: RenderOption);
+    fn render_option(&self, option: RenderOption) -> Option<RenderOption>;
+    fn set_attribute_default(&mut self, location: u32, value: AttributeDefault);
+    fn clear_state(&self) -> &ClearState;
+    fn clear_state_mut(&mut self) -> &mut ClearState;
+    /// Clear with the given clear state, or the persistent one if None.
+    fn clear_buffers(&mut self, state: Option<&ClearState>);
+    fn set_clip_control(&mut self, origin: ClipOrigin, depth: ClipDepth);
+    /// Set the viewport of the render pass beginning or continuing, or forget it when the
+    /// outermost pass ends. The viewport itself is set only if given.
+    fn set_pass_viewport(&mut self, viewport: Option<[i32; 4]>);
+    fn pass_viewport(&self) -> Option<[i32; 4]>;
 }
 
 impl ContextRenderingSupport for Context {
     fn bind_vao_for_rendering(&mut self, vao: &VertexArrayHandle) {
+        self.check_owner(vao.access());
         self.vao_tracker.bind_for_rendering(vao.rc());
     }
 
     fn bind_program_for_rendering(&mut self, program: &ProgramHandle) {
+        self.check_owner(program.access());
         self.program_tracker.bind_for_rendering(program.rc());
     }
 
+    fn bind_texture_for_rendering(&mut self, unit: u32, texture: &TextureHandle) {
+        self.check_owner(texture.access());
+        let unit_count = self.texture_trackers.len();
+        match self.texture_trackers.get_mut(unit as usize) {
+            Some(tracker) => tracker.bind_for_rendering(texture.rc()),
+            None => panic!("Texture unit {} out of range, the context has {} units", unit, unit_count)
+        }
+    }
+
+    fn bind_framebuffer_for_rendering(&mut self, render_target: Option<&Rc<RenderTarget>>) {
+        self.bind_draw_framebuffer_for_rendering(render_target);
+        self.bind_read_framebuffer_for_rendering(render_target);
+    }
+
+    fn bind_draw_framebuffer_for_rendering(&mut self, render_target: Option<&Rc<RenderTarget>>) {
+        match render_target {
+            Some(render_target) => {
+                self.check_owner(&**render_target);
+                self.draw_framebuffer_tracker.bind_for_rendering(render_target)
+            },
+            None => self.draw_framebuffer_tracker.unbind()
+        }
+    }
+
+    fn bind_read_framebuffer_for_rendering(&mut self, render_target: Option<&Rc<RenderTarget>>) {
+        match render_target {
+            Some(render_target) => {
+                self.check_owner(&**render_target);
+                self.read_framebuffer_tracker.bind_for_rendering(render_target)
+            },
+            None => self.read_framebuffer_tracker.unbind()
+        }
+    }
+
+    fn framebuffer_for_rendering(&self) -> Option<Rc<RenderTarget>> {
+        self.draw_framebuffer_tracker.bound_for_rendering().cloned()
+    }
+
+    fn read_framebuffer_for_rendering(&self) -> Option<Rc<RenderTarget>> {
+        self.read_framebuffer_tracker.bound_for_rendering().cloned()
+    }
+
     fn prepare_for_rendering(&mut self) {
         self.vao_tracker.restore_rendering_state();
         self.program_tracker.restore_rendering_state();
+        // Only the first unit is used for editing
+        self.texture_trackers[0].restore_rendering_state();
+    }
+
+    fn prepare_for_drawing(&mut self) {
+        self.prepare_for_rendering();
+        if let Some(vao) = self.vao_tracker.bound_for_rendering() {
+            self.attribute_defaults.forget(vao.enabled_locations());
+        }
+    }
+
+    fn bind_uniform_buffer_range(&mut self, binding: u32, buffer: &BufferObject, offset: usize, size: usize) {
+        self.check_owner(buffer);
+        let max_bindings = self.info.uniform_buffer.max_bindings as u32;
+        if binding >= max_bindings {
+            panic!("Uniform buffer binding {} out of range, the context has {} bindings", binding, max_bindings);
+        }
+        if !self.info.uniform_buffer.is_offset_aligned(offset) {
+            panic!("Uniform buffer offset {} is not a multiple of the offset alignment {}, the next aligned offset is {}",
+                offset, self.info.uniform_buffer.offset_alignment, self.info.uniform_buffer.align_offset(offset));
+        }
+        if offset + size > buffer.size() {
+            panic!("Uniform buffer range {}..{} is out of the buffer of {} bytes", offset, offset + size, buffer.size());
+        }
+        if self.indexed_ranges.bind(IndexedBufferTarget::Uniform, binding, buffer.id, offset, size) {
+            // The generic binding point is changed too
+            self.ubo_tracker.invalidate();
+        }
+    }
+
+    fn bind_indexed_buffer_range(&mut self, target: IndexedBufferTarget, binding: u32, buffer: &BufferObject, offset: usize, size: usize) {
+        self.check_owner(buffer);
+        self.indexed_ranges.bind(target, binding, buffer.id, offset, size);
+    }
+
+    fn bind_indirect_buffer_for_rendering(&mut self, buffer: &BufferObject) {
+        self.check_owner(buffer);
+        self.indirect_tracker.bind(buffer);
+    }
+
+    fn unbind_vao_for_rendering(&mut self) {
+        self.vao_tracker.unbind();
+    }
+
+    fn unbind_program_for_rendering(&mut self) {
+        self.program_tracker.unbind();
+    }
+
+    fn vao_for_rendering(&self) -> Option<&VertexArray> {
+        self.vao_tracker.bound_for_rendering().map(|vao| &**vao)
+    }
+
+    fn program_for_rendering(&self) -> Option<&Program> {
+        self.program_tracker.bound_for_rendering().map(|program| &**program)
+    }
+
+    fn texture_for_rendering(&self, unit: u32) -> Option<&Texture> {
+        self.texture_trackers.get(unit as usize)
+            .and_then(|tracker| tracker.bound_for_rendering())
+            .map(|texture| &**texture)
+    }
+
+    fn set_render_option(&mut self, option: RenderOption) {
+        self.render_options.set(option);
+    }
+
+    fn render_option(&self, option: RenderOption) -> Option<RenderOption> {
+        self.render_options.get(option)
+    }
+
+    fn set_attribute_default(&mut self, location: u32, value: AttributeDefault) {
+        self.attribute_defaults.set(location, value);
+    }
+
+    fn clear_state(&self) -> &ClearState {
+        &self.clear_state
+    }
+
+    fn clear_state_mut(&mut self) -> &mut ClearState {
+        &mut self.clear_state
+    }
+
+    fn set_clip_control(&mut self, origin: ClipOrigin, depth: ClipDepth) {
+        options::set_clip_control(origin, depth);
+        self.clip_control = (origin, depth);
+    }
+
+    fn set_pass_viewport(&mut self, viewport: Option<[i32; 4]>) {
+        if let Some(viewport) = viewport {
+            set_viewport(viewport);
+        }
+        self.pass_viewport = viewport;
+    }
+
+    fn pass_viewport(&self) -> Option<[i32; 4]> {
+        self.pass_viewport
+    }
+
+    fn clear_buffers(&mut self, state: Option<&ClearState>) {
+        let state = state.unwrap_or(&self.clear_state);
+        // glClear obeys the depth and color masks, so the writes are enabled for the duration
+        let depth_write = self.render_options.get(RenderOption::DepthWrite(true));
+        if state.depth.is_some() && depth_write != Some(RenderOption::DepthWrite(true)) {
+            self.render_options.set(RenderOption::DepthWrite(true));
+        }
+        let color_write = self.render_options.get(RenderOption::ColorWrite(true));
+        let clears_colors = !state.colors.is_empty() || !state.integer_colors.is_empty();
+        if clears_colors && color_write != Some(RenderOption::ColorWrite(true)) {
+            self.render_options.set(RenderOption::ColorWrite(true));
+        }
+        self.clear_values.clear(state);
+        if state.depth.is_some() && depth_write == Some(RenderOption::DepthWrite(false)) {
+            self.render_options.set(RenderOption::DepthWrite(false));
+        }
+        if clears_colors && color_write == Some(RenderOption::ColorWrite(false)) {
+            self.render_options.set(RenderOption::ColorWrite(false));
+        }
     }
 }
 
+static NEXT_CONTEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
 /// Things that need to be shared between `Context` and the resources it spawns.
 /// This might be a bad idea, but allows the resource handles to live longer than the context,
 /// without causing freeing of GL resources after GL context has died. Alternative would have been
 /// to limit lifetimes of resource handles to strictly live within the lifetime of the context, but
 /// that would "infect" everything with a lifetime annotation...
 pub struct SharedContextState {
-    pub context_alive: bool
+    /// Numbers the contexts in creation order, for telling them apart in error messages.
+    pub context_id: usize,
+    pub context_alive: bool,
+    /// Set when a graphics reset has been detected. The objects of the context are gone then.
+    pub context_lost: bool,
+    /// Objects of the dropped resources, waiting to be deleted by `Context`.
+    pub deletion_queue: Vec<QueuedDeletion>,
+    /// Kept up to date by the registration handles of the resources.
+    pub resources: ResourceReport
 }
 
 impl SharedContextState {
     pub fn new() -> SharedContextState {
         SharedContextState {
-            context_alive: true
+            context_id: NEXT_CONTEXT_ID.fetch_add(1, Ordering::Relaxed),
+            context_alive: true,
+            context_lost: false,
+            deletion_queue: Vec::new(),
+            resources: ResourceReport::default()
         }
     }
 }
 
-/// Handle to the shared state, as used by the resources (and `Context`).
+/// An OpenGL object waiting to be deleted. The values are the object names, except for sync
+/// objects, which don't have names, and the tracker ids of the resources that can be bound.
+#[derive(Clone,Copy,Debug)]
+pub enum QueuedDeletion {
+    Buffer(u32, TrackerId),
+    VertexArray(u32, TrackerId),
+    Shader(u32),
+    Program(u32, TrackerId),
+    Texture(u32, TrackerId),
+    Framebuffer(u32, TrackerId),
+    Sync(GLsync),
+    Query(u32)
+}
+
+fn delete_object(object: QueuedDeletion) {
+    unsafe {
+        match object {
+            QueuedDeletion::Buffer(id, _) => gl::DeleteBuffers(1, &id),
+            QueuedDeletion::VertexArray(id, _) => gl::DeleteVertexArrays(1, &id),
+            QueuedDeletion::Shader(id) => gl::DeleteShader(id),
+            QueuedDeletion::Program(id, _) => gl::DeleteProgram(id),
+            QueuedDeletion::Texture(id, _) => gl::DeleteTextures(1, &id),
+            QueuedDeletion::Framebuffer(id, _) => gl::DeleteFramebuffers(1, &id),
+            QueuedDeletion::Sync(sync) => gl::DeleteSync(sync),
+            QueuedDeletion::Query(id) => gl::DeleteQueries(1, &id)
+        }
+    }
+    check_error!();
+}
+
+/// Set the viewport, given as (x, y, width, height). See glViewport.
+fn set_viewport(viewport: [i32; 4]) {
+    unsafe {
+        gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+    }
+    check_error!();
+}
+
+/// Implemented by the resources, to let handles find out about the state of the context.
+pub trait ContextResource {
+    fn registration(&self) -> &RegistrationHandle;
+}
+
+/// Handle to the shared state, as used by the resources (and `Context`). The resource is
+/// counted in the `ResourceReport` of the context for as long as the handle lives.
 pub struct RegistrationHandle {
-    context_shared: Rc<RefCell<SharedContextState>>
+    context_shared: Rc<RefCell<SharedContextState>>,
+    kind: ResourceKind,
+    /// The memory accounted to the resource with `set_memory`.
+    memory: Cell<usize>,
+    /// The label given with `Context::set_label`.
+    label: RefCell<Option<String>>
 }
 
 impl RegistrationHandle {
-    pub fn new(context_shared: Rc<RefCell<SharedContextState>>) -> RegistrationHandle {
-        RegistrationHandle { context_shared: context_shared }
+    pub fn new(context_shared: Rc<RefCell<SharedContextState>>, kind: ResourceKind) -> RegistrationHandle {
+        *context_shared.borrow_mut().resources.count_mut(kind) += 1;
+        RegistrationHandle {
+            context_shared,
+            kind,
+            memory: Cell::new(0),
+            label: RefCell::new(None)
+        }
+    }
+
+    /// The memory accounted to the resource, see `set_memory`.
+    pub fn memory(&self) -> usize {
+        self.memory.get()
+    }
+
+    pub fn set_label(&self, label: &str) {
+        *self.label.borrow_mut() = Some(label.to_string());
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.label.borrow().clone()
+    }
+
+    /// Record how much memory the resource uses now. Only buffers and textures are accounted.
+    pub fn set_memory(&self, bytes: usize) {
+        let mut shared = self.context_shared.borrow_mut();
+        if let Some(total) = shared.resources.memory_mut(self.kind) {
+            *total = *total - self.memory.get() + bytes;
+            self.memory.set(bytes);
+        }
     }
 
     pub fn context_alive(&self) -> bool {
         self.context_shared.borrow().context_alive
     }
+
+    /// Panic if the resource was not created by the context of the shared state. Using a
+    /// resource of another context, dead or alive, would refer to an unrelated object of the
+    /// same name or none at all.
+    pub fn check_context(&self, context_shared: &Rc<RefCell<SharedContextState>>) {
+        if !Rc::ptr_eq(&self.context_shared, context_shared) {
+            panic!("{:?} created on context #{} used with context #{}", self.kind,
+                self.context_shared.borrow().context_id, context_shared.borrow().context_id);
+        }
+    }
+
+    /// Has the context been lost in a graphics reset, taking the resource with it?
+    pub fn context_lost(&self) -> bool {
+        self.context_shared.borrow().context_lost
+    }
+
+    /// Queue the object to be deleted by the context, unless the context is already gone.
+    pub fn queue_deletion(&self, object: QueuedDeletion) {
+        let mut shared = self.context_shared.borrow_mut();
+        if shared.context_alive && !shared.context_lost {
+            shared.deletion_queue.push(object);
+        }
+    }
+}
+
+impl Drop for RegistrationHandle {
+    fn drop(&mut self) {
+        let mut shared = self.context_shared.borrow_mut();
+        *shared.resources.count_mut(self.kind) -= 1;
+        if let Some(total) = shared.resources.memory_mut(self.kind) {
+            *total -= self.memory.get();
+        }
+    }
 }
\ No newline at end of file
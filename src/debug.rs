@@ -0,0 +1,73 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Annotating the command stream for graphics debuggers, see `Renderer::debug_group` and
+//! `Context::debug_marker`.
+
+use gl::types::{GLenum,GLsizei};
+
+use super::info::{ContextInfo,ExtensionFeature};
+
+/// Are the debug group, marker and label functions available? See
+/// `ExtensionFeature::DebugOutput`.
+fn is_available(info: &ContextInfo) -> bool {
+    info.supports(ExtensionFeature::DebugOutput)
+}
+
+/// Begin a named group of commands. Does nothing if not available. See glPushDebugGroup.
+pub fn push_group(info: &ContextInfo, name: &str) {
+    if !is_available(info) {
+        return;
+    }
+    unsafe {
+        gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, name.len() as GLsizei, name.as_ptr() as *const i8);
+    }
+    check_error!();
+}
+
+/// End the group begun last. Does nothing if not available. See glPopDebugGroup.
+pub fn pop_group(info: &ContextInfo) {
+    if !is_available(info) {
+        return;
+    }
+    unsafe {
+        gl::PopDebugGroup();
+    }
+    check_error!();
+}
+
+/// Give the object a label that graphics debuggers show instead of its name. Does nothing if
+/// not available. See glObjectLabel.
+pub fn object_label(info: &ContextInfo, identifier: GLenum, name: u32, label: &str) {
+    if !is_available(info) {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(identifier, name, label.len() as GLsizei, label.as_ptr() as *const i8);
+    }
+    check_error!();
+}
+
+/// Insert a marker message into the command stream. Does nothing if not available. See
+/// glDebugMessageInsert.
+pub fn insert_marker(info: &ContextInfo, message: &str) {
+    if !is_available(info) {
+        return;
+    }
+    unsafe {
+        gl::DebugMessageInsert(gl::DEBUG_SOURCE_APPLICATION, gl::DEBUG_TYPE_MARKER, 0,
+            gl::DEBUG_SEVERITY_NOTIFICATION, message.len() as GLsizei, message.as_ptr() as *const i8);
+    }
+    check_error!();
+}
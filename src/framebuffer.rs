@@ -0,0 +1,357 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Framebuffer objects for rendering into textures, see `RenderTarget`. Rendering into a render
+//! target is done in a render pass, see `Renderer::pass`.
+
+use std::error::Error;
+use std::fmt;
+
+use gl::types::{GLenum,GLint};
+
+use super::TextureHandle;
+use super::context::{Context,ContextEditingSupport,RegistrationHandle,QueuedDeletion,ContextResource};
+use super::handle::HandleAccess;
+use super::info;
+use super::texture::{Texture,TextureFormat};
+use super::tracker::{Bind,TrackerId};
+
+/// A framebuffer object with textures attached: color textures to draw into, and optionally a
+/// depth (or depth-stencil) texture. The same mipmap level of each texture is attached, usually
+/// the base level, and it must have been allocated or uploaded through the library before
+/// creating the render target, of the same size in all the textures. See
+/// `Context::new_render_target` and `Context::new_render_target_level`.
+///
+/// Only two-dimensional textures can be created through the library, so there are no array
+/// layers or cube map faces to choose between; attaching a single layer with
+/// glFramebufferTextureLayer is left for when array and cube map textures are supported.
+///
+/// The render target keeps the textures alive. Don't sample a texture while rendering into it.
+pub struct RenderTarget {
+    pub id: u32,
+    tracker_id: TrackerId,
+    registration: RegistrationHandle,
+    color_textures: Vec<TextureHandle>,
+    depth_texture: Option<TextureHandle>,
+    level: u32,
+    dimensions: (u32, u32)
+}
+
+/// The ways creating a `RenderTarget` can fail.
+#[derive(Clone,Debug,PartialEq)]
+pub enum RenderTargetError {
+    /// The texture has no storage allocated through the library for the attached level. The
+    /// value is the index of the color texture, or None for the depth texture.
+    NoStorage(Option<usize>),
+    /// The texture is not of the same size as the first attachment. The value is as in
+    /// `NoStorage`.
+    SizeMismatch(Option<usize>),
+    /// A depth texture was given as a color texture, or a color texture as the depth texture.
+    /// The value is as in `NoStorage`.
+    WrongFormat(Option<usize>, TextureFormat),
+    /// More color textures than GL_MAX_COLOR_ATTACHMENTS were given.
+    TooManyColorTextures { count: usize, max: usize },
+    /// Neither color nor depth textures were given.
+    NoAttachments,
+    /// glCheckFramebufferStatus reported the framebuffer incomplete. The value is the status,
+    /// such as GL_FRAMEBUFFER_UNSUPPORTED.
+    Incomplete(GLenum)
+}
+
+impl fmt::Display for RenderTargetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenderTargetError::NoStorage(index) =>
+                write!(f, "The {} has no storage allocated for the attached level", attachment_name(index)),
+            RenderTargetError::SizeMismatch(index) =>
+                write!(f, "The {} is not of the same size as the other attachments", attachment_name(index)),
+            RenderTargetError::WrongFormat(index, format) =>
+                write!(f, "The {} can't be of the format {:?}", attachment_name(index), format),
+            RenderTargetError::TooManyColorTextures { count, max } =>
+                write!(f, "{} color textures given, but the context supports only {}", count, max),
+            RenderTargetError::NoAttachments =>
+                write!(f, "A render target needs at least one texture"),
+            RenderTargetError::Incomplete(status) =>
+                write!(f, "The framebuffer is incomplete, status 0x{:x}", status)
+        }
+    }
+}
+
+impl Error for RenderTargetError {}
+
+fn attachment_name(index: Option<usize>) -> String {
+    match index {
+        Some(index) => format!("color texture {}", index),
+        None => "depth texture".to_string()
+    }
+}
+
+impl RenderTarget {
+    /// Create the framebuffer object and attach the level of the textures, leaving the
+    /// framebuffer bound.
+    pub fn new(context: &mut Context,
+               tracker_id: TrackerId,
+               color_textures: &[TextureHandle],
+               depth_texture: Option<&TextureHandle>,
+               level: u32,
+               registration: RegistrationHandle) -> Result<RenderTarget, RenderTargetError> {
+        let max_color_textures = context.get_info().framebuffer.max_color_attachments as usize;
+        if color_textures.len() > max_color_textures {
+            return Err(RenderTargetError::TooManyColorTextures { count: color_textures.len(), max: max_color_textures });
+        }
+        let attachments = color_textures.iter().enumerate()
+            .map(|(index, texture)| (Some(index), texture))
+            .chain(depth_texture.map(|texture| (None, texture)));
+        let mut dimensions = None;
+        for (index, texture) in attachments {
+            let size = validate_attachment(index, texture.access(), level)?;
+            match dimensions {
+                None => dimensions = Some(size),
+                Some(dimensions) if dimensions != size => return Err(RenderTargetError::SizeMismatch(index)),
+                Some(_) => ()
+            }
+        }
+        let dimensions = match dimensions {
+            Some(dimensions) => dimensions,
+            None => return Err(RenderTargetError::NoAttachments)
+        };
+
+        let mut id: u32 = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+            check_error!();
+        }
+        let render_target = RenderTarget {
+            id,
+            tracker_id,
+            registration,
+            color_textures: color_textures.to_vec(),
+            depth_texture: depth_texture.cloned(),
+            level,
+            dimensions
+        };
+        context.bind_framebuffer_for_editing(&render_target);
+        for (index, texture) in color_textures.iter().enumerate() {
+            attach_texture(gl::COLOR_ATTACHMENT0 + index as GLenum, texture.access(), level);
+        }
+        if let Some(texture) = depth_texture {
+            let attachment = match texture.access().level_format(level) {
+                Some(TextureFormat::Depth24Stencil8) => gl::DEPTH_STENCIL_ATTACHMENT,
+                _ => gl::DEPTH_ATTACHMENT
+            };
+            attach_texture(attachment, texture.access(), level);
+        }
+        render_target.set_draw_buffers(None);
+        if render_target.color_textures.is_empty() {
+            unsafe {
+                gl::ReadBuffer(gl::NONE);
+            }
+            check_error!();
+        }
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        check_error!();
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(RenderTargetError::Incomplete(status));
+        }
+        Ok(render_target)
+    }
+
+    /// The size of the attached level of the textures.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// The mipmap level of the textures that is attached.
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// How many color textures are attached.
+    pub fn color_texture_count(&self) -> usize {
+        self.color_textures.len()
+    }
+
+    /// The attached color textures, in order.
+    pub fn color_textures(&self) -> &[TextureHandle] {
+        &self.color_textures
+    }
+
+    /// The attached depth texture, if any.
+    pub fn depth_texture(&self) -> Option<&TextureHandle> {
+        self.depth_texture.as_ref()
+    }
+
+    /// Set the color textures the fragment shader outputs are written to, the first output to
+    /// the first given color texture and so on. None draws into all the color textures in order.
+    /// The render target must be bound. See glDrawBuffers.
+    pub fn set_draw_buffers(&self, color_textures: Option<&[u32]>) {
+        let buffers: Vec<GLenum> = match color_textures {
+            Some(indices) => {
+                indices.iter().map(|&index| {
+                    if index as usize >= self.color_textures.len() {
+                        panic!("Color texture {} out of range, the render target has {}", index, self.color_textures.len());
+                    }
+                    gl::COLOR_ATTACHMENT0 + index
+                }).collect()
+            },
+            None => (0..self.color_textures.len() as GLenum).map(|index| gl::COLOR_ATTACHMENT0 + index).collect()
+        };
+        unsafe {
+            if buffers.is_empty() {
+                gl::DrawBuffers(1, &gl::NONE);
+            }
+            else {
+                gl::DrawBuffers(buffers.len() as GLint, buffers.as_ptr());
+            }
+        }
+        check_error!();
+    }
+
+    /// Tell OpenGL that the contents of all the attachments are no longer needed, so that they
+    /// don't have to be preserved, which saves bandwidth especially on tiled GPUs. The render
+    /// target must be bound, and glInvalidateFramebuffer must be supported (see
+    /// `ExtensionFeature::InvalidateFramebuffer`).
+    pub fn invalidate(&self) {
+        let mut attachments: Vec<GLenum> = (0..self.color_textures.len() as GLenum)
+            .map(|index| gl::COLOR_ATTACHMENT0 + index)
+            .collect();
+        if let Some(ref texture) = self.depth_texture {
+            attachments.push(match texture.access().level_format(self.level) {
+                Some(TextureFormat::Depth24Stencil8) => gl::DEPTH_STENCIL_ATTACHMENT,
+                _ => gl::DEPTH_ATTACHMENT
+            });
+        }
+        unsafe {
+            gl::InvalidateFramebuffer(gl::FRAMEBUFFER, attachments.len() as GLint, attachments.as_ptr());
+        }
+        check_error!();
+    }
+
+    /// Bind the framebuffer to GL_DRAW_FRAMEBUFFER or GL_READ_FRAMEBUFFER.
+    pub fn bind(&self, target: GLenum) {
+        unsafe {
+            gl::BindFramebuffer(target, self.id);
+        }
+        check_error!();
+    }
+}
+
+/// Check that the texture has storage of a format suitable for the attachment, returning its
+/// size.
+fn validate_attachment(index: Option<usize>, texture: &Texture, level: u32) -> Result<(u32, u32), RenderTargetError> {
+    let dimensions = match texture.level_dimensions(level) {
+        Some(dimensions) => dimensions,
+        None => return Err(RenderTargetError::NoStorage(index))
+    };
+    if let Some(format) = texture.level_format(level) {
+        if info::is_depth_format(format) != index.is_none() {
+            return Err(RenderTargetError::WrongFormat(index, format));
+        }
+    }
+    Ok(dimensions)
+}
+
+fn attach_texture(attachment: GLenum, texture: &Texture, level: u32) {
+    unsafe {
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, texture.gl_target(), texture.id, level as GLint);
+    }
+    check_error!();
+}
+
+impl fmt::Debug for RenderTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RenderTarget")
+            .field("id", &self.id)
+            .field("tracker_id", &self.tracker_id)
+            .field("dimensions", &self.dimensions)
+            .field("color_textures", &self.color_textures)
+            .field("depth_texture", &self.depth_texture)
+            .field("level", &self.level)
+            .finish()
+    }
+}
+
+impl ContextResource for RenderTarget {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        self.registration.queue_deletion(QueuedDeletion::Framebuffer(self.id, self.tracker_id));
+    }
+}
+
+/// Enable or disable the color buffer of the default framebuffer, which must be bound, for
+/// drawing and reading. The default framebuffer is assumed to be double buffered. See
+/// glDrawBuffer and glReadBuffer.
+#[cfg(not(feature = "es3"))]
+pub fn set_default_color_buffer(enabled: bool) {
+    let buffer = if enabled { gl::BACK } else { gl::NONE };
+    unsafe {
+        gl::DrawBuffer(buffer);
+        gl::ReadBuffer(buffer);
+    }
+    check_error!();
+}
+
+/// OpenGL ES has no glDrawBuffer, but glDrawBuffers accepts the back buffer for the default
+/// framebuffer.
+#[cfg(feature = "es3")]
+pub fn set_default_color_buffer(enabled: bool) {
+    let buffer = if enabled { gl::BACK } else { gl::NONE };
+    unsafe {
+        gl::DrawBuffers(1, &buffer);
+        gl::ReadBuffer(buffer);
+    }
+    check_error!();
+}
+
+/// Binds render targets to GL_DRAW_FRAMEBUFFER or GL_READ_FRAMEBUFFER for binding trackers.
+/// Unbinding binds the default framebuffer.
+pub struct FramebufferBinder {
+    target: GLenum
+}
+
+impl FramebufferBinder {
+    pub fn new_draw() -> FramebufferBinder {
+        FramebufferBinder { target: gl::DRAW_FRAMEBUFFER }
+    }
+
+    pub fn new_read() -> FramebufferBinder {
+        FramebufferBinder { target: gl::READ_FRAMEBUFFER }
+    }
+}
+
+impl Bind<RenderTarget> for FramebufferBinder {
+    fn bind(&self, render_target: &RenderTarget) {
+        render_target.bind(self.target);
+    }
+
+    fn unbind(&self) {
+        unsafe {
+            gl::BindFramebuffer(self.target, 0);
+        }
+        check_error!();
+    }
+
+    fn get_id(&self, render_target: &RenderTarget) -> TrackerId {
+        render_target.tracker_id
+    }
+
+    fn get_name(&self, render_target: &RenderTarget) -> u32 {
+        render_target.id
+    }
+}
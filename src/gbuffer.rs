@@ -0,0 +1,232 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! A render target for deferred shading, see `GBuffer`.
+
+use std::rc::Rc;
+
+use super::{Context,RenderTargetHandle,TextureHandle};
+use super::handle::HandleAccess;
+use super::framebuffer::{RenderTarget,RenderTargetError};
+use super::options::ClearState;
+use super::renderer::{Renderer,PassTarget,PassDesc};
+use super::targetpool::{TargetPool,TargetDesc,PooledTarget};
+use super::texture::{TextureFormat,TextureFilter};
+
+/// The formats of the textures of a `GBuffer`. The color textures are attached in the order
+/// albedo, normal, material, so the fragment shader of the geometry pass writes them to outputs
+/// 0, 1 and 2.
+#[derive(Clone,Debug,PartialEq)]
+pub struct GBufferLayout {
+    /// The surface color, and whatever the alpha channel is used for.
+    pub albedo: TextureFormat,
+    /// The surface normal, usually in view or world space.
+    pub normal: TextureFormat,
+    /// The material parameters like roughness and metalness, if they are needed.
+    pub material: Option<TextureFormat>,
+    /// The depth, for reconstructing the position in the lighting pass.
+    pub depth: TextureFormat
+}
+
+impl GBufferLayout {
+    /// sRGB encoded albedo, half float normals, eight bit material parameters and a depth
+    /// buffer with stencil for marking the lit areas.
+    pub fn standard() -> GBufferLayout {
+        GBufferLayout {
+            albedo: TextureFormat::Srgb8Alpha8,
+            normal: TextureFormat::Rgba16f,
+            material: Some(TextureFormat::Rgba8),
+            depth: TextureFormat::Depth24Stencil8
+        }
+    }
+
+    /// Eight bits per channel albedo, two channel normals to be encoded (for example with the
+    /// octahedral encoding) and no material texture, for when memory bandwidth is scarce.
+    pub fn compact() -> GBufferLayout {
+        GBufferLayout {
+            albedo: TextureFormat::Rgba8,
+            normal: TextureFormat::Rg16f,
+            material: None,
+            depth: TextureFormat::DepthComponent24
+        }
+    }
+
+    /// The formats of the color textures, in attachment order.
+    pub fn color_formats(&self) -> Vec<TextureFormat> {
+        let mut formats = vec![self.albedo, self.normal];
+        formats.extend(self.material);
+        formats
+    }
+
+    /// Does the depth format have a stencil buffer?
+    pub fn has_stencil(&self) -> bool {
+        self.depth == TextureFormat::Depth24Stencil8
+    }
+
+    /// The description of the render target of the layout in the size, for a `TargetPool`.
+    pub fn target_desc(&self, width: u32, height: u32) -> TargetDesc {
+        TargetDesc {
+            width,
+            height,
+            color_formats: self.color_formats(),
+            depth_format: Some(self.depth),
+            samples: 0,
+            filter: TextureFilter::Nearest
+        }
+    }
+}
+
+impl Default for GBufferLayout {
+    fn default() -> GBufferLayout {
+        GBufferLayout::standard()
+    }
+}
+
+/// The render target of the geometry pass of deferred shading: the albedo, normal and material
+/// textures and the depth texture, all of the same size. Draw the geometry into it with a
+/// render pass (the G-buffer is a `PassTarget`, see `pass_desc`), and read the textures in the
+/// lighting pass, for example through the units set by `use_textures`.
+///
+/// The render target is acquired from a `TargetPool`, see `GBufferLayout::target_desc`, so
+/// that the textures of the old size are handed out to others of the same description after
+/// a resize, and the G-buffer can be given back to the pool with `release` when it's not
+/// needed. The textures use nearest filtering and clamp to edges, as the lighting pass reads
+/// them texel by texel. Call `resize` when the window size changes.
+#[derive(Debug)]
+pub struct GBuffer {
+    layout: GBufferLayout,
+    width: u32,
+    height: u32,
+    target: PooledTarget
+}
+
+impl GBuffer {
+    /// Acquire the render target of the layout from the pool. Fails if the render target can't
+    /// be created, for example if a format is not renderable. See `TargetPool::acquire`.
+    pub fn new(context: &mut Context, pool: &mut TargetPool, width: u32, height: u32, layout: GBufferLayout) -> Result<GBuffer, RenderTargetError> {
+        let target = pool.acquire(context, &layout.target_desc(width, height))?;
+        Ok(GBuffer {
+            layout,
+            width,
+            height,
+            target
+        })
+    }
+
+    /// Release the render target to the pool and acquire one of the new size, if it differs
+    /// from the current one. The contents are lost. The texture handles change, so get them
+    /// again afterwards.
+    pub fn resize(&mut self, context: &mut Context, pool: &mut TargetPool, width: u32, height: u32) -> Result<(), RenderTargetError> {
+        if (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        let target = pool.acquire(context, &self.layout.target_desc(width, height))?;
+        pool.release(&self.target);
+        self.target = target;
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Give the render target back to the pool it was acquired from.
+    pub fn release(self, pool: &mut TargetPool) {
+        pool.release(&self.target);
+    }
+
+    /// Tell OpenGL the contents are not needed anymore, before drawing the geometry of the next
+    /// frame without clearing. See `Context::invalidate_render_target`.
+    pub fn invalidate(&self, context: &mut Context) {
+        context.invalidate_render_target(&self.target.render_target);
+    }
+
+    /// A pass description for the geometry pass, drawing into all the color textures and
+    /// clearing them to zero, the depth to one and the stencil, if the depth format has one, to
+    /// zero.
+    pub fn pass_desc(&self) -> PassDesc {
+        let draw_buffers: Vec<u32> = (0..self.target.color_textures.len() as u32).collect();
+        PassDesc::new()
+            .label("G-buffer")
+            .draw_buffers(&draw_buffers)
+            .clear(self.clear_state())
+    }
+
+    /// Clear values for all the color textures, the depth and the stencil if there is one.
+    pub fn clear_state(&self) -> ClearState {
+        ClearState {
+            colors: vec![[0.0; 4]; self.target.color_textures.len()],
+            integer_colors: Vec::new(),
+            depth: Some(1.0),
+            stencil: if self.layout.has_stencil() { Some(0) } else { None }
+        }
+    }
+
+    /// Use the textures for rendering from consecutive texture units starting from the first:
+    /// albedo, normal, the material if the layout has it, and the depth. Returns the unit
+    /// after the last one used.
+    pub fn use_textures(&self, renderer: &mut Renderer, first_unit: u32) -> u32 {
+        let mut unit = first_unit;
+        for texture in self.target.color_textures.iter().chain(self.target.depth_texture.as_ref()) {
+            renderer.use_texture(unit, texture);
+            unit += 1;
+        }
+        unit
+    }
+
+    pub fn layout(&self) -> &GBufferLayout {
+        &self.layout
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn render_target(&self) -> &RenderTargetHandle {
+        &self.target.render_target
+    }
+
+    pub fn albedo(&self) -> &TextureHandle {
+        &self.target.color_textures[0]
+    }
+
+    pub fn normal(&self) -> &TextureHandle {
+        &self.target.color_textures[1]
+    }
+
+    /// The material texture, if the layout has it.
+    pub fn material(&self) -> Option<&TextureHandle> {
+        self.target.color_textures.get(2)
+    }
+
+    pub fn depth(&self) -> &TextureHandle {
+        self.target.depth_texture.as_ref().expect("G-buffer targets have a depth texture")
+    }
+
+    /// The color textures in attachment order.
+    pub fn color_textures(&self) -> &[TextureHandle] {
+        &self.target.color_textures
+    }
+}
+
+impl PassTarget for GBuffer {
+    fn render_target(&self) -> Option<&Rc<RenderTarget>> {
+        Some(self.target.render_target.rc())
+    }
+
+    fn dimensions(&self) -> Option<(u32, u32)> {
+        Some((self.width, self.height))
+    }
+}
@@ -0,0 +1,12 @@
+// The indices of the instances that passed the culling of gpu_cull::GpuCuller, read from the
+// storage buffer it binds to binding point one. For vertex shaders of GLSL 4.30 or GLSL ES
+// 3.10, the latter only if the context has storage blocks in vertex shaders.
+
+layout(std430, binding = 1) readonly buffer HtglVisibleInstances {
+    uint htgl_visible_instances[];
+};
+
+// The index of the instance being drawn among all the instances, culled or not.
+uint htgl_culled_instance_index() {
+    return htgl_visible_instances[gl_InstanceID];
+}
@@ -0,0 +1,12 @@
+// Frustum culling tests. The planes are vec4(normal, distance) with the normals pointing inside,
+// so that dot(plane.xyz, point) + plane.w >= 0 for the points inside, as with the planes of
+// matrix::frustum_planes.
+
+bool htgl_sphere_in_frustum(vec4 sphere, vec4 planes[6]) {
+    for (int i = 0; i < 6; i++) {
+        if (dot(planes[i].xyz, sphere.xyz) + planes[i].w < -sphere.w) {
+            return false;
+        }
+    }
+    return true;
+}
@@ -0,0 +1,11 @@
+// Object ids for picking with Picker. Include in the fragment shader drawing into the picker
+// and call htgl_write_object_id in main. The id is set with Picker::set_object_id, zero meaning
+// no object.
+
+uniform uint htgl_object_id;
+
+layout(location = 0) out uint htgl_picked_id;
+
+void htgl_write_object_id() {
+    htgl_picked_id = htgl_object_id;
+}
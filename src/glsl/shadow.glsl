@@ -0,0 +1,34 @@
+// Shadow map sampling with percentage-closer filtering. The shadow map is a depth texture with
+// depth comparison enabled (see TextureEditor::set_depth_comparison), sampled through a
+// sampler2DShadow, so that each tap is already filtered by the hardware.
+// On OpenGL ES, declare a default precision for sampler2DShadow before including this.
+
+// The shadow coordinates of a position in the clip space of the light, in [0, 1], with the
+// depth offset by the bias to avoid shadow acne.
+vec3 htgl_shadow_coord(vec4 light_clip_position, float bias) {
+    vec3 coord = light_clip_position.xyz / light_clip_position.w * 0.5 + 0.5;
+    coord.z -= bias;
+    return coord;
+}
+
+// The fraction of light reaching the coordinate, averaged over (2 * radius + 1)^2 taps one texel
+// apart. Outside the shadow map everything is lit.
+float htgl_shadow_pcf(sampler2DShadow shadow_map, vec3 coord, int radius) {
+    if (coord.z > 1.0) {
+        return 1.0;
+    }
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float lit = 0.0;
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            lit += texture(shadow_map, vec3(coord.xy + vec2(x, y) * texel, coord.z));
+        }
+    }
+    float taps = float((2 * radius + 1) * (2 * radius + 1));
+    return lit / taps;
+}
+
+// htgl_shadow_pcf with the usual 3x3 taps.
+float htgl_shadow_pcf3x3(sampler2DShadow shadow_map, vec3 coord) {
+    return htgl_shadow_pcf(shadow_map, coord, 1);
+}
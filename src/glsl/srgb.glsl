@@ -0,0 +1,22 @@
+// Conversions between sRGB encoded and linear colors, for when the hardware conversion of the
+// sRGB texture and framebuffer formats can't be used. Alpha is linear and passed through.
+
+vec3 htgl_srgb_to_linear(vec3 srgb) {
+    vec3 low = srgb / 12.92;
+    vec3 high = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, vec3(lessThanEqual(srgb, vec3(0.04045))));
+}
+
+vec3 htgl_linear_to_srgb(vec3 linear) {
+    vec3 low = linear * 12.92;
+    vec3 high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(linear, vec3(0.0031308))));
+}
+
+vec4 htgl_srgb_to_linear(vec4 srgb) {
+    return vec4(htgl_srgb_to_linear(srgb.rgb), srgb.a);
+}
+
+vec4 htgl_linear_to_srgb(vec4 linear) {
+    return vec4(htgl_linear_to_srgb(linear.rgb), linear.a);
+}
@@ -0,0 +1,39 @@
+// Tonemapping operators, mapping linear HDR colors to [0, 1]. Apply the exposure before, and the
+// sRGB encoding after, if the framebuffer doesn't do it.
+
+// Reinhard: c / (1 + c).
+vec3 htgl_tonemap_reinhard(vec3 color) {
+    return color / (1.0 + color);
+}
+
+// Extended Reinhard, mapping the white point to one.
+vec3 htgl_tonemap_reinhard_extended(vec3 color, float white) {
+    return color * (1.0 + color / (white * white)) / (1.0 + color);
+}
+
+// The ACES filmic curve as fitted by Krzysztof Narkowicz.
+vec3 htgl_tonemap_aces(vec3 color) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+// The Uncharted 2 filmic curve by John Hable, with the white point 11.2.
+vec3 htgl_tonemap_uncharted2_partial(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 htgl_tonemap_uncharted2(vec3 color) {
+    const float exposure_bias = 2.0;
+    vec3 white_scale = 1.0 / htgl_tonemap_uncharted2_partial(vec3(11.2));
+    return htgl_tonemap_uncharted2_partial(color * exposure_bias) * white_scale;
+}
@@ -0,0 +1,179 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Frustum culling of instances on the GPU, see `GpuCuller`.
+//!
+//! A compute shader tests the bounding spheres of the instances against the view frustum and
+//! appends the indices of the visible ones to a storage buffer, counting them with an atomic
+//! counter that is the instance count of an indirect draw command. Drawing the mesh with that
+//! command then draws only the visible instances, without the CPU ever reading the count.
+//!
+//! Requires OpenGL 4.3 or OpenGL ES 3.1 with storage blocks in vertex shaders.
+
+use super::{Context,BufferHandle,ProgramHandle};
+use super::buffer::IndexType;
+use super::indirect::IndirectCommand;
+use super::matrix;
+use super::program::{SimpleUniformTypeFloat,SimpleUniformTypeU32};
+use super::renderer::{PrimitiveMode,MemoryBarrier,Renderer};
+use super::shader::ShaderType;
+use super::shaderlib::{ShaderSourceLibrary,COMPUTE_SHADER_HEADER};
+
+static COMPUTE_SHADER_SOURCE: &str = "
+layout(local_size_x = 64) in;
+#include <htgl/frustum.glsl>
+layout(std430, binding = 0) readonly buffer HtglCullBounds {
+    vec4 bounds[];
+};
+layout(std430, binding = 1) writeonly buffer HtglVisibleInstances {
+    uint visible_instances[];
+};
+layout(binding = 0, offset = 0) uniform atomic_uint visible_count;
+uniform vec4 planes[6];
+uniform uint instance_count;
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= instance_count) {
+        return;
+    }
+    if (htgl_sphere_in_frustum(bounds[index], planes)) {
+        visible_instances[atomicCounterIncrement(visible_count)] = index;
+    }
+}
+";
+
+/// The storage buffer binding point of the bounding spheres.
+pub const BOUNDS_BINDING: u32 = 0;
+/// The storage buffer binding point of the visible instance indices, as declared by
+/// `htgl/culled_instances.glsl`.
+pub const VISIBLE_INSTANCES_BINDING: u32 = 1;
+
+/// The byte offset of the instance count in the draw command, where the atomic counter is.
+const INSTANCE_COUNT_OFFSET: usize = 4;
+
+/// Culls the instances of a mesh against the view frustum with a compute shader, and draws the
+/// visible ones with an indirect draw. The vertex shader finds the index of the instance with
+/// `htgl_culled_instance_index()` of `htgl/culled_instances.glsl`, instead of `gl_InstanceID`:
+///
+///    #include <htgl/culled_instances.glsl>
+///    ...
+///    mat4 model = models[htgl_culled_instance_index()];
+///
+/// Each frame, or when the instances move, update the bounds with `set_bounds`, then `cull`
+/// with the view-projection matrix and `draw` with the program and the vertex array of the mesh
+/// in use.
+#[derive(Debug)]
+pub struct GpuCuller {
+    program: ProgramHandle,
+    planes_location: i32,
+    instance_count_location: i32,
+    max_instances: usize,
+    instance_count: usize,
+    bounds: BufferHandle,
+    visible_instances: BufferHandle,
+    command: BufferHandle,
+    mesh: IndirectCommand
+}
+
+impl GpuCuller {
+    /// Compile the culling program and create the buffers for up to `max_instances` instances.
+    /// Panics if the context doesn't support compute shaders.
+    pub fn new(context: &mut Context, max_instances: usize) -> GpuCuller {
+        let library = ShaderSourceLibrary::new();
+        let source = format!("{}{}", COMPUTE_SHADER_HEADER, COMPUTE_SHADER_SOURCE);
+        let shader = context.new_shader_from_library(ShaderType::ComputeShader, &source, &library)
+            .expect("The built-in frustum snippet is missing");
+        let program = context.new_program(&[shader]);
+        let (planes_location, instance_count_location) = {
+            let info = context.program_info(&program);
+            (info.get_uniform_location("planes"), info.get_uniform_location("instance_count"))
+        };
+        let bounds = context.new_buffer();
+        context.edit_vertex_buffer(&bounds).data(&vec![[0.0f32; 4]; max_instances.max(1)]);
+        let visible_instances = context.new_buffer();
+        context.edit_vertex_buffer(&visible_instances).data(&vec![0u32; max_instances.max(1)]);
+        let command = context.new_buffer();
+        context.edit_indirect_buffer(&command).data(&[IndirectCommand::default()]);
+        GpuCuller {
+            program,
+            planes_location,
+            instance_count_location,
+            max_instances,
+            instance_count: 0,
+            bounds,
+            visible_instances,
+            command,
+            mesh: IndirectCommand::default()
+        }
+    }
+
+    /// Set the bounding spheres of the instances in world space, `[x, y, z, radius]` each. The
+    /// number of spheres is the number of instances. Panics if there are more than
+    /// `max_instances`.
+    pub fn set_bounds(&mut self, context: &mut Context, spheres: &[[f32; 4]]) {
+        if spheres.len() > self.max_instances {
+            panic!("{} instances given, but the culler has room for {}", spheres.len(), self.max_instances);
+        }
+        context.edit_vertex_buffer(&self.bounds).sub_data(spheres, 0);
+        self.instance_count = spheres.len();
+    }
+
+    /// Set the index range of the mesh the instances are drawn with: the number of indices, the
+    /// first index and the base vertex, as in `IndirectCommand`.
+    pub fn set_mesh(&mut self, count: u32, first_index: u32, base_vertex: i32) {
+        self.mesh = IndirectCommand {
+            count,
+            instance_count: 0,
+            first_index,
+            base_vertex,
+            base_instance: 0
+        };
+    }
+
+    /// Cull the instances against the frustum of the view-projection matrix, resetting the
+    /// visible count and running the compute shader. The program in use is changed.
+    pub fn cull(&mut self, context: &mut Context, view_projection: &[f32; 16]) {
+        context.edit_indirect_buffer(&self.command).sub_data(&[self.mesh], 0);
+        {
+            let editor = context.edit_program(&self.program);
+            let planes = matrix::frustum_planes(view_projection);
+            let planes: Vec<f32> = planes.iter().flat_map(|plane| plane.iter().cloned()).collect();
+            editor.uniform_f32(self.planes_location, 6, SimpleUniformTypeFloat::Uniform4f, &planes);
+            editor.uniform_u32(self.instance_count_location, 1, SimpleUniformTypeU32::Uniform1u, &[self.instance_count as u32]);
+        }
+        let mut renderer = context.renderer();
+        renderer.use_storage_buffer_range(BOUNDS_BINDING, &self.bounds, 0, self.max_instances.max(1) * 16);
+        renderer.use_storage_buffer_range(VISIBLE_INSTANCES_BINDING, &self.visible_instances, 0, self.max_instances.max(1) * 4);
+        renderer.use_atomic_counter_buffer_range(0, &self.command, INSTANCE_COUNT_OFFSET, 4);
+        renderer.dispatch_for_elements(&self.program, self.instance_count as u32);
+        renderer.memory_barrier(&[MemoryBarrier::Command, MemoryBarrier::ShaderStorage]);
+    }
+
+    /// Draw the visible instances of the last `cull` with the program and the vertex array in
+    /// use, binding the visible instance indices for `htgl/culled_instances.glsl`.
+    pub fn draw(&self, renderer: &mut Renderer, primitive_mode: PrimitiveMode, index_type: IndexType) {
+        renderer.use_storage_buffer_range(VISIBLE_INSTANCES_BINDING, &self.visible_instances, 0, self.max_instances.max(1) * 4);
+        renderer.multi_draw_elements_indirect(primitive_mode, index_type, &self.command, 0, 1);
+    }
+
+    /// The largest number of instances.
+    pub fn max_instances(&self) -> usize {
+        self.max_instances
+    }
+
+    /// The draw command buffer, whose instance count is the number of visible instances after
+    /// `cull`.
+    pub fn command_buffer(&self) -> &BufferHandle {
+        &self.command
+    }
+}
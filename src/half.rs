@@ -0,0 +1,136 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Conversions between `f32` and the 16-bit floating point numbers read by
+//! `VertexAttributeType::HalfFloat` attributes. The half floats are `u16` values holding the
+//! IEEE 754 binary16 bits, so a converted slice can be given as is to `BufferEditor::data`.
+//!
+//! Values too large for a half float become infinities, too small ones become zeros or
+//! subnormals, and the rounding is to nearest even like in the GPU conversions.
+
+/// Convert a single `f32` to the bits of a half float.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // Infinities stay infinities and NaNs stay NaNs, with a quiet bit set to keep them NaN
+        let nan = if mantissa != 0 { 0x200 | (mantissa >> 13) as u16 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        return sign | round_shifted(mantissa, shift) as u16;
+    }
+    // A rounding carry out of the mantissa correctly increments the exponent, up to infinity
+    sign + (((half_exponent as u32) << 10) + round_shifted(mantissa, 13)) as u16
+}
+
+/// Convert the bits of a half float to an `f32`. The conversion is exact.
+pub fn f16_to_f32(value: u16) -> f32 {
+    let sign = ((value & 0x8000) as u32) << 16;
+    let exponent = ((value >> 10) & 0x1f) as u32;
+    let mantissa = (value & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            let magnitude = mantissa as f32 / 16_777_216.0;
+            sign | magnitude.to_bits()
+        },
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13)
+    };
+    f32::from_bits(bits)
+}
+
+/// Convert a slice of `f32` values to half floats, for example the positions or texture
+/// coordinates of a `HalfFloat` vertex buffer.
+pub fn f32_slice_to_f16(values: &[f32]) -> Vec<u16> {
+    values.iter().map(|&value| f32_to_f16(value)).collect()
+}
+
+/// Convert a slice of half floats to `f32` values, for example to inspect data read back from a
+/// buffer.
+pub fn f16_slice_to_f32(values: &[u16]) -> Vec<f32> {
+    values.iter().map(|&value| f16_to_f32(value)).collect()
+}
+
+/// Shift the mantissa right, rounding to nearest even.
+fn round_shifted(mantissa: u32, shift: u32) -> u32 {
+    let shifted = mantissa >> shift;
+    let remainder = mantissa & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if remainder > halfway || (remainder == halfway && shifted & 1 == 1) {
+        shifted + 1
+    }
+    else {
+        shifted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{f32_to_f16,f16_to_f32};
+
+    #[test]
+    fn known_values_convert_exactly() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f16_to_f32(0x3555), 1365.0 / 4096.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+    }
+
+    #[test]
+    fn rounding_is_to_nearest_even() {
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(3.0 * 2f32.powi(-25)), 0x0002);
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(1e-10), 0x0000);
+    }
+
+    #[test]
+    fn special_values_are_kept() {
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn every_half_float_survives_a_round_trip() {
+        for bits in 0..=u16::MAX {
+            let value = f16_to_f32(bits);
+            if value.is_nan() {
+                assert!(f16_to_f32(f32_to_f16(value)).is_nan());
+            }
+            else {
+                assert_eq!(f32_to_f16(value), bits, "{:#06x}", bits);
+            }
+        }
+    }
+}
@@ -15,10 +15,17 @@
 //! This module defines a handle structure, that is the user-facing representation of GL resources.
 
 use std::rc::Rc;
+use std::fmt;
+use std::hash::{Hash,Hasher};
+
+use super::context::ContextResource;
 
 /// `Handle` is essentially a wrapped `std::rc::Rc`, though you should not rely it always being that
 /// way. `Handle` does not implement `Deref`, as the contents are not meant to be used by user
 /// directly.
+///
+/// Handles compare equal and hash the same when they refer to the same resource, so they can be
+/// used as keys in maps, for example when grouping draws by program or texture.
 pub struct Handle<T> {
     resource: Rc<T>
 }
@@ -29,12 +36,49 @@ pub fn new_handle<T>(resource: T) -> Handle<T> {
     Handle { resource: Rc::new(resource) }
 }
 
+impl<T> Handle<T> {
+    /// An identifier of the resource, unique among the resources that are alive. Handles
+    /// referring to the same resource return the same value. The identifier can be reused after
+    /// the resource is dropped, so don't hold onto it longer than to a handle.
+    pub fn raw_id(&self) -> usize {
+        &*self.resource as *const T as usize
+    }
+}
+
+impl<T: ContextResource> Handle<T> {
+    /// Has the resource been lost in a graphics reset? See `Context::graphics_reset_status`.
+    /// A lost resource must be created again in a new context.
+    pub fn is_lost(&self) -> bool {
+        self.resource.registration().context_lost()
+    }
+}
+
 impl<T> Clone for Handle<T> {
     fn clone(&self) -> Handle<T> {
         Handle { resource: self.resource.clone() }
     }
 }
 
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Handle<T>) -> bool {
+        Rc::ptr_eq(&self.resource, &other.resource)
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw_id().hash(state);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Handle").field(&*self.resource).finish()
+    }
+}
+
 /// A "workaround" for the missing `Deref´ implementation: an nonpublic trait to access handle's
 /// target contents.
 pub trait HandleAccess<T> {
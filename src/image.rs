@@ -0,0 +1,279 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Image data in client memory, as given to the texture editor for uploading. The central piece
+//! is the `ImageData` trait, which describes the dimensions, format and contents of an image.
+//! `RawImage` is a simple owned implementation of it. With the `image` feature enabled, the
+//! `RgbaImage` type of the image crate implements it too.
+
+use gl::types::GLenum;
+
+/// Layout of the pixel components in client memory. See the format parameter of glTexImage2D.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PixelFormat {
+    /// GL_RED
+    Red,
+    /// GL_RG
+    Rg,
+    /// GL_RGB
+    Rgb,
+    /// GL_BGR
+    Bgr,
+    /// GL_RGBA
+    Rgba,
+    /// GL_BGRA
+    Bgra,
+    /// GL_RED_INTEGER
+    RedInteger,
+    /// GL_RG_INTEGER
+    RgInteger,
+    /// GL_RGB_INTEGER
+    RgbInteger,
+    /// GL_RGBA_INTEGER
+    RgbaInteger,
+    /// GL_DEPTH_COMPONENT
+    DepthComponent,
+    /// GL_DEPTH_STENCIL
+    DepthStencil
+}
+
+/// Data type of the pixel components in client memory. See the type parameter of glTexImage2D.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PixelType {
+    /// GL_UNSIGNED_BYTE
+    UnsignedByte,
+    /// GL_BYTE
+    Byte,
+    /// GL_UNSIGNED_SHORT
+    UnsignedShort,
+    /// GL_SHORT
+    Short,
+    /// GL_UNSIGNED_INT
+    UnsignedInt,
+    /// GL_INT
+    Int,
+    /// GL_HALF_FLOAT
+    HalfFloat,
+    /// GL_FLOAT
+    Float,
+    /// GL_UNSIGNED_INT_24_8, a packed type only usable with `PixelFormat::DepthStencil`.
+    UnsignedInt248
+}
+
+/// Block-compressed image formats. The S3TC formats are not part of the core OpenGL
+/// specification and require GL_EXT_texture_compression_s3tc.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum CompressedFormat {
+    /// GL_COMPRESSED_RED_RGTC1 (BC4)
+    RedRgtc1,
+    /// GL_COMPRESSED_SIGNED_RED_RGTC1 (BC4)
+    SignedRedRgtc1,
+    /// GL_COMPRESSED_RG_RGTC2 (BC5)
+    RgRgtc2,
+    /// GL_COMPRESSED_SIGNED_RG_RGTC2 (BC5)
+    SignedRgRgtc2,
+    /// GL_COMPRESSED_RGBA_BPTC_UNORM (BC7)
+    RgbaBptcUnorm,
+    /// GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM (BC7)
+    SrgbAlphaBptcUnorm,
+    /// GL_COMPRESSED_RGB_BPTC_SIGNED_FLOAT (BC6H)
+    RgbBptcSignedFloat,
+    /// GL_COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT (BC6H)
+    RgbBptcUnsignedFloat,
+    /// GL_COMPRESSED_RGB_S3TC_DXT1_EXT (BC1)
+    RgbS3tcDxt1,
+    /// GL_COMPRESSED_RGBA_S3TC_DXT1_EXT (BC1)
+    RgbaS3tcDxt1,
+    /// GL_COMPRESSED_RGBA_S3TC_DXT3_EXT (BC2)
+    RgbaS3tcDxt3,
+    /// GL_COMPRESSED_RGBA_S3TC_DXT5_EXT (BC3)
+    RgbaS3tcDxt5
+}
+
+/// The complete format of image data: either uncompressed pixels described by the pixel format
+/// and type, or block-compressed data.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ImageFormat {
+    Uncompressed(PixelFormat, PixelType),
+    Compressed(CompressedFormat)
+}
+
+impl ImageFormat {
+    /// How many bytes an image of the given dimensions takes in this format, when the rows are
+    /// tightly packed.
+    pub fn data_size(&self, width: u32, height: u32) -> usize {
+        match *self {
+            ImageFormat::Uncompressed(format, pixel_type) => {
+                width as usize * height as usize * pixel_size(format, pixel_type)
+            },
+            ImageFormat::Compressed(format) => {
+                let blocks_x = (width as usize).div_ceil(4);
+                let blocks_y = (height as usize).div_ceil(4);
+                blocks_x * blocks_y * block_size(format)
+            }
+        }
+    }
+}
+
+/// Image data that can be uploaded to a texture. The bytes are expected to be tightly packed,
+/// that is, rows are not padded to any alignment.
+pub trait ImageData {
+    /// Width and height of the image in pixels.
+    fn dimensions(&self) -> (u32, u32);
+    /// Format of the image data.
+    fn format(&self) -> ImageFormat;
+    /// The image contents.
+    fn bytes(&self) -> &[u8];
+}
+
+/// A simple owned image.
+pub struct RawImage {
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    data: Vec<u8>
+}
+
+impl RawImage {
+    /// Create an image from raw bytes. Panics if there are less bytes than the dimensions and
+    /// the format require.
+    pub fn new(width: u32, height: u32, format: ImageFormat, data: Vec<u8>) -> RawImage {
+        let expected_size = format.data_size(width, height);
+        if data.len() < expected_size {
+            panic!("Too small image data: {}x{} of {:?} takes {} bytes, but only got {}",
+                width, height, format, expected_size, data.len());
+        }
+        RawImage { width, height, format, data }
+    }
+}
+
+impl ImageData for RawImage {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data[..]
+    }
+}
+
+/// References to image data are image data too, so that for example the `&dyn ImageData` items
+/// of a mipmap chain can be given to the methods taking `&impl ImageData`.
+impl<T: ImageData + ?Sized> ImageData for &T {
+    fn dimensions(&self) -> (u32, u32) {
+        (**self).dimensions()
+    }
+
+    fn format(&self) -> ImageFormat {
+        (**self).format()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        (**self).bytes()
+    }
+}
+
+#[cfg(feature = "image")]
+impl ImageData for ::image::RgbaImage {
+    fn dimensions(&self) -> (u32, u32) {
+        ::image::RgbaImage::dimensions(self)
+    }
+
+    fn format(&self) -> ImageFormat {
+        ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+fn pixel_size(format: PixelFormat, pixel_type: PixelType) -> usize {
+    let components = match format {
+        PixelFormat::Red | PixelFormat::RedInteger | PixelFormat::DepthComponent => 1,
+        PixelFormat::Rg | PixelFormat::RgInteger => 2,
+        PixelFormat::Rgb | PixelFormat::Bgr | PixelFormat::RgbInteger => 3,
+        PixelFormat::Rgba | PixelFormat::Bgra | PixelFormat::RgbaInteger => 4,
+        PixelFormat::DepthStencil => 1
+    };
+    let component_size = match pixel_type {
+        PixelType::UnsignedByte | PixelType::Byte => 1,
+        PixelType::UnsignedShort | PixelType::Short | PixelType::HalfFloat => 2,
+        PixelType::UnsignedInt | PixelType::Int | PixelType::Float | PixelType::UnsignedInt248 => 4
+    };
+    components * component_size
+}
+
+fn block_size(format: CompressedFormat) -> usize {
+    match format {
+        CompressedFormat::RedRgtc1 |
+        CompressedFormat::SignedRedRgtc1 |
+        CompressedFormat::RgbS3tcDxt1 |
+        CompressedFormat::RgbaS3tcDxt1 => 8,
+        _ => 16
+    }
+}
+
+pub fn pixel_format_to_enum(format: PixelFormat) -> GLenum {
+    match format {
+        PixelFormat::Red => gl::RED,
+        PixelFormat::Rg => gl::RG,
+        PixelFormat::Rgb => gl::RGB,
+        PixelFormat::Bgr => gl::BGR,
+        PixelFormat::Rgba => gl::RGBA,
+        PixelFormat::Bgra => gl::BGRA,
+        PixelFormat::RedInteger => gl::RED_INTEGER,
+        PixelFormat::RgInteger => gl::RG_INTEGER,
+        PixelFormat::RgbInteger => gl::RGB_INTEGER,
+        PixelFormat::RgbaInteger => gl::RGBA_INTEGER,
+        PixelFormat::DepthComponent => gl::DEPTH_COMPONENT,
+        PixelFormat::DepthStencil => gl::DEPTH_STENCIL
+    }
+}
+
+pub fn pixel_type_to_enum(pixel_type: PixelType) -> GLenum {
+    match pixel_type {
+        PixelType::UnsignedByte => gl::UNSIGNED_BYTE,
+        PixelType::Byte => gl::BYTE,
+        PixelType::UnsignedShort => gl::UNSIGNED_SHORT,
+        PixelType::Short => gl::SHORT,
+        PixelType::UnsignedInt => gl::UNSIGNED_INT,
+        PixelType::Int => gl::INT,
+        PixelType::HalfFloat => gl::HALF_FLOAT,
+        PixelType::Float => gl::FLOAT,
+        PixelType::UnsignedInt248 => gl::UNSIGNED_INT_24_8
+    }
+}
+
+pub fn compressed_format_to_enum(format: CompressedFormat) -> GLenum {
+    match format {
+        CompressedFormat::RedRgtc1 => gl::COMPRESSED_RED_RGTC1,
+        CompressedFormat::SignedRedRgtc1 => gl::COMPRESSED_SIGNED_RED_RGTC1,
+        CompressedFormat::RgRgtc2 => gl::COMPRESSED_RG_RGTC2,
+        CompressedFormat::SignedRgRgtc2 => gl::COMPRESSED_SIGNED_RG_RGTC2,
+        CompressedFormat::RgbaBptcUnorm => gl::COMPRESSED_RGBA_BPTC_UNORM,
+        CompressedFormat::SrgbAlphaBptcUnorm => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+        CompressedFormat::RgbBptcSignedFloat => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+        CompressedFormat::RgbBptcUnsignedFloat => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+        // The S3TC enums are not in the core bindings, as they come from an extension.
+        CompressedFormat::RgbS3tcDxt1 => 0x83F0,
+        CompressedFormat::RgbaS3tcDxt1 => 0x83F1,
+        CompressedFormat::RgbaS3tcDxt3 => 0x83F2,
+        CompressedFormat::RgbaS3tcDxt5 => 0x83F3
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Turning mesh data parsed by asset crates (glTF, OBJ and the like) into `Mesh` objects. Enabled
+//! with the `import` feature. This module doesn't parse any files itself, it only takes care of
+//! the vertex layout and the uploads.
+//!
+//! The attributes are placed at fixed locations, see the `*_LOCATION` constants, so the vertex
+//! shaders used with the imported meshes should use matching layout qualifiers.
+
+use super::{Context,BufferHandle};
+use super::mesh::Mesh;
+use super::renderer::PrimitiveMode;
+use super::vertexarray::{VertexAttribute,VertexAttributeType};
+
+/// Attribute location of the vertex positions (vec3).
+pub const POSITION_LOCATION: u32 = 0;
+/// Attribute location of the vertex normals (vec3).
+pub const NORMAL_LOCATION: u32 = 1;
+/// Attribute location of the texture coordinates (vec2).
+pub const TEX_COORD_LOCATION: u32 = 2;
+
+/// The vertex data of a single primitive (in glTF terms), as given by an asset parser. All the
+/// given attribute slices must be of the same length. Triangle lists are assumed.
+pub struct PrimitiveData<'a> {
+    pub positions: &'a [[f32; 3]],
+    pub normals: Option<&'a [[f32; 3]]>,
+    pub tex_coords: Option<&'a [[f32; 2]]>,
+    /// Indices of the triangles, or None if the vertices form a plain triangle list.
+    pub indices: Option<&'a [u32]>
+}
+
+/// Upload the primitive data into new buffers and create a mesh of them. The vertex attributes
+/// are interleaved into a single vertex buffer. The indices are stored as u16 if the largest of
+/// them allows it, as u32 otherwise, see `BufferEditor::data_indices`.
+///
+/// Panics if the attribute slices are of different lengths.
+pub fn import_primitive(ctx: &mut Context, data: &PrimitiveData) -> Mesh {
+    let vertex_count = data.positions.len();
+    let mut components = 3;
+    if let Some(normals) = data.normals {
+        check_length("normals", normals.len(), vertex_count);
+        components += 3;
+    }
+    if let Some(tex_coords) = data.tex_coords {
+        check_length("texture coordinates", tex_coords.len(), vertex_count);
+        components += 2;
+    }
+
+    let mut vertices: Vec<f32> = Vec::with_capacity(vertex_count * components);
+    for index in 0..vertex_count {
+        vertices.extend(data.positions[index].iter().cloned());
+        if let Some(normals) = data.normals {
+            vertices.extend(normals[index].iter().cloned());
+        }
+        if let Some(tex_coords) = data.tex_coords {
+            vertices.extend(tex_coords[index].iter().cloned());
+        }
+    }
+    let vertex_buffer = ctx.new_buffer();
+    ctx.edit_vertex_buffer(&vertex_buffer).data(&vertices[..]);
+
+    let stride = (components * 4) as u32;
+    let mut attributes = vec![attribute(POSITION_LOCATION, 3, 0, stride, &vertex_buffer)];
+    let mut offset = 3 * 4;
+    if data.normals.is_some() {
+        attributes.push(attribute(NORMAL_LOCATION, 3, offset, stride, &vertex_buffer));
+        offset += 3 * 4;
+    }
+    if data.tex_coords.is_some() {
+        attributes.push(attribute(TEX_COORD_LOCATION, 2, offset, stride, &vertex_buffer));
+    }
+
+    match data.indices {
+        Some(indices) => {
+            let index_buffer = ctx.new_buffer();
+            let vertex_array = ctx.new_vertex_array(&attributes[..], Some(index_buffer));
+            let indices: Vec<usize> = indices.iter().map(|&index| index as usize).collect();
+            let index_type = ctx.edit_index_buffer(&vertex_array).unwrap().data_indices(&indices[..]);
+            Mesh {
+                vertex_array,
+                vertex_buffer,
+                primitive_mode: PrimitiveMode::Triangles,
+                index_type: Some(index_type),
+                element_count: indices.len() as u32,
+                base_vertex: 0
+            }
+        },
+        None => {
+            let vertex_array = ctx.new_vertex_array(&attributes[..], None);
+            Mesh {
+                vertex_array,
+                vertex_buffer,
+                primitive_mode: PrimitiveMode::Triangles,
+                index_type: None,
+                element_count: vertex_count as u32,
+                base_vertex: 0
+            }
+        }
+    }
+}
+
+fn attribute(location: u32, size: u8, offset: u32, stride: u32, vertex_buffer: &BufferHandle) -> VertexAttribute {
+    VertexAttribute {
+        index: location,
+        size,
+        attribute_type: VertexAttributeType::Float,
+        normalized: false,
+        stride,
+        offset,
+        divisor: 0,
+        vertex_buffer: vertex_buffer.clone()
+    }
+}
+
+fn check_length(name: &str, length: usize, vertex_count: usize) {
+    if length != vertex_count {
+        panic!("Mismatching primitive data: {} vertex positions, but {} {}", vertex_count, length, name);
+    }
+}
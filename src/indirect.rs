@@ -0,0 +1,124 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Draw commands stored in buffers, for drawing many meshes with one call. See
+//! `IndirectCommandBuffer` and `Renderer::multi_draw_elements_indirect`.
+
+use std::mem::size_of;
+
+use super::{Context,BufferHandle};
+use super::buffer::IndexType;
+use super::renderer::{PrimitiveMode,Renderer};
+
+/// The size of a DrawElementsIndirectCommand in bytes, twenty.
+pub const INDIRECT_COMMAND_SIZE: usize = size_of::<IndirectCommand>();
+
+/// An indexed draw command as read from a draw indirect buffer, laid out as the
+/// DrawElementsIndirectCommand structure of the OpenGL specification.
+#[repr(C)]
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct IndirectCommand {
+    /// The number of indices.
+    pub count: u32,
+    /// The number of instances, zero skipping the command.
+    pub instance_count: u32,
+    /// The index of the first index in the index buffer, in indices, not bytes.
+    pub first_index: u32,
+    /// Added to the indices before fetching the vertices.
+    pub base_vertex: i32,
+    /// The first instance, for instanced attributes. Must be zero unless the context supports
+    /// base instances (see `ContextInfo::supports_base_instance`), so always on OpenGL ES.
+    pub base_instance: u32
+}
+
+/// Collects indexed draw commands on the CPU and uploads them into a draw indirect buffer, for
+/// drawing them all with a single `Renderer::multi_draw_elements_indirect`:
+///
+///    commands.clear();
+///    for mesh in visible_meshes {
+///        commands.push(IndirectCommand {
+///            count: mesh.count,
+///            instance_count: 1,
+///            first_index: mesh.first,
+///            ..Default::default()
+///        });
+///    }
+///    commands.upload(&mut context);
+///    ...
+///    commands.draw(&mut renderer, PrimitiveMode::Triangles, IndexType::U32);
+///
+/// All the commands draw from the vertex array in use, so the meshes must share the vertex and
+/// index buffers.
+#[derive(Debug)]
+pub struct IndirectCommandBuffer {
+    commands: Vec<IndirectCommand>,
+    buffer: BufferHandle,
+    /// The number of commands in the buffer, as of the last upload.
+    uploaded_count: u32
+}
+
+impl IndirectCommandBuffer {
+    /// Create the buffer, empty.
+    pub fn new(context: &mut Context) -> IndirectCommandBuffer {
+        IndirectCommandBuffer {
+            commands: Vec::new(),
+            buffer: context.new_buffer(),
+            uploaded_count: 0
+        }
+    }
+
+    /// Add a command.
+    pub fn push(&mut self, command: IndirectCommand) {
+        self.commands.push(command);
+    }
+
+    /// Remove the commands. The buffer keeps the uploaded ones until the next upload.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// The commands added since the last clear.
+    pub fn commands(&self) -> &[IndirectCommand] {
+        &self.commands
+    }
+
+    /// Replace the contents of the buffer with the commands. Panics if a command has a base
+    /// instance but the context doesn't support them, see `IndirectCommand::base_instance`.
+    pub fn upload(&mut self, context: &mut Context) {
+        if !context.get_info().supports_base_instance() {
+            if let Some(index) = self.commands.iter().position(|command| command.base_instance != 0) {
+                panic!("Indirect command {} has the base instance {}, but the context doesn't support base instances",
+                    index, self.commands[index].base_instance);
+            }
+        }
+        context.edit_indirect_buffer(&self.buffer).data(&self.commands);
+        self.uploaded_count = self.commands.len() as u32;
+    }
+
+    /// The number of commands uploaded to the buffer.
+    pub fn command_count(&self) -> u32 {
+        self.uploaded_count
+    }
+
+    /// The draw indirect buffer.
+    pub fn buffer(&self) -> &BufferHandle {
+        &self.buffer
+    }
+
+    /// Draw all the uploaded commands. Does nothing if there are none.
+    pub fn draw(&self, renderer: &mut Renderer, primitive_mode: PrimitiveMode, index_type: IndexType) {
+        if self.uploaded_count > 0 {
+            renderer.multi_draw_elements_indirect(primitive_mode, index_type, &self.buffer, 0, self.uploaded_count);
+        }
+    }
+}
@@ -16,19 +16,466 @@
 //! more structured approach than a long list of glGet* results. See `ContextInfo`, it is the
 //! "root" of context info structures.
 
-use gl;
-use gl::types::{GLint,GLenum};
+use std::ffi::CStr;
+
+use gl::types::{GLint,GLenum,GLuint};
+#[cfg(feature = "serde")]
+use serde::{Serialize,Deserialize};
+
+use super::image::CompressedFormat;
+use super::texture::{self,TextureFormat};
 
 /// Currently `ContextInfo` doesn't contain much. The fields act as "categories". See field
 /// comments for further info.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct ContextInfo {
+    /// Version of the context.
+    pub version: VersionInfo,
+    /// Names of the extensions supported by the context. See GL_EXTENSIONS and glGetStringi.
+    pub extensions: Vec<String>,
     /// Information related to uniform buffers.
-    pub uniform_buffer: UniformBufferInfo
+    pub uniform_buffer: UniformBufferInfo,
+    /// Information related to textures.
+    pub texture: TextureInfo,
+    /// Attributes of the default framebuffer, that is, what the window provides.
+    pub default_framebuffer: DefaultFramebufferInfo,
+    /// Limits of framebuffer objects.
+    pub framebuffer: FramebufferInfo,
+    /// Limits of each shader stage.
+    pub shader_stages: ShaderStageInfo,
+    /// The robustness features the context was created with.
+    pub robustness: RobustnessInfo,
+    /// Limits introduced by extensions, see `ExtensionFeature`.
+    pub extension_limits: ExtensionLimits
+}
+
+/// Commonly needed features that are core only in recent versions, and otherwise come with
+/// extensions. See `ContextInfo::supports` and `ExtensionLimits`.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum ExtensionFeature {
+    /// Anisotropic texture filtering, see `TextureEditor::set_max_anisotropy`. Core in OpenGL
+    /// 4.6, otherwise GL_ARB_texture_filter_anisotropic or GL_EXT_texture_filter_anisotropic.
+    AnisotropicFiltering,
+    /// Debug output, messages, groups and object labels. Core in OpenGL 4.3 and OpenGL ES 3.2,
+    /// otherwise GL_KHR_debug on desktop OpenGL.
+    DebugOutput,
+    /// Immutable buffer storage (glBufferStorage). Core in OpenGL 4.4, otherwise
+    /// GL_ARB_buffer_storage or GL_EXT_buffer_storage on OpenGL ES.
+    BufferStorage,
+    /// Bindless textures, GL_ARB_bindless_texture or GL_NV_bindless_texture. Never core.
+    BindlessTexture,
+    /// glClipControl, see `ContextInfo::supports_clip_control`.
+    ClipControl,
+    /// glTexBufferRange, see `TextureEditor::set_buffer_range`. Core in OpenGL 4.3 and OpenGL
+    /// ES 3.2, otherwise GL_ARB_texture_buffer_range.
+    TextureBufferRange,
+    /// Named strings for resolving shader includes in the driver, GL_ARB_shading_language_include.
+    /// Never core, and not available on OpenGL ES. See `Context::register_named_strings`.
+    ShadingLanguageInclude,
+    /// Conservative occlusion queries, `QueryTarget::AnySamplesPassedConservative`. Core in
+    /// OpenGL 4.3 and OpenGL ES 3.0, otherwise GL_ARB_ES3_compatibility.
+    ConservativeOcclusionQuery,
+    /// Compute shaders, see `Renderer::dispatch_compute`. Core in OpenGL 4.3 and OpenGL ES 3.1,
+    /// otherwise GL_ARB_compute_shader.
+    ComputeShader,
+    /// Program interface queries, see `ProgramInfoAccessor::get_fragment_output_info`. Core in
+    /// OpenGL 4.3 and OpenGL ES 3.1, otherwise GL_ARB_program_interface_query.
+    ProgramInterfaceQuery,
+    /// glInvalidateFramebuffer, see `RenderTarget::invalidate`. Core in OpenGL 4.3 and OpenGL ES
+    /// 3.0, otherwise GL_ARB_invalidate_subdata.
+    InvalidateFramebuffer,
+    /// Graphics reset notification, see `Context::graphics_reset_status`. Core in OpenGL 4.5 and
+    /// OpenGL ES 3.2, otherwise GL_KHR_robustness on desktop OpenGL.
+    Robustness,
+    /// glMemoryBarrier, see `Renderer::memory_barrier`. Core in OpenGL 4.2 and OpenGL ES 3.1,
+    /// otherwise GL_ARB_shader_image_load_store.
+    MemoryBarrier,
+    /// Atomic counter buffers, see `Renderer::use_atomic_counter_buffer_range`. Core in OpenGL
+    /// 4.2 and OpenGL ES 3.1, otherwise GL_ARB_shader_atomic_counters.
+    AtomicCounterBuffer,
+    /// Shader storage buffers, see `Renderer::use_storage_buffer_range`. Core in OpenGL 4.3 and
+    /// OpenGL ES 3.1, otherwise GL_ARB_shader_storage_buffer_object.
+    ShaderStorageBuffer,
+    /// glDrawElementsIndirect, see `Renderer::multi_draw_elements_indirect`. Core in OpenGL 4.0
+    /// and OpenGL ES 3.1, otherwise GL_ARB_draw_indirect.
+    DrawIndirect,
+    /// glMultiDrawElementsIndirect, drawing all the indirect commands in one call. Core in OpenGL
+    /// 4.3, otherwise GL_ARB_multi_draw_indirect. OpenGL ES only has it as an EXT function,
+    /// which is not loaded.
+    MultiDrawIndirect,
+    /// Multisampled textures, see `Context::new_texture_2d_multisample`. Core in OpenGL 3.2 and
+    /// OpenGL ES 3.1.
+    MultisampleTexture
+}
+
+impl ContextInfo {
+    /// Tells if the context version is at least the given one.
+    pub fn version_at_least(&self, major: GLint, minor: GLint) -> bool {
+        self.version.major > major || (self.version.major == major && self.version.minor >= minor)
+    }
+
+    /// Tells if the named extension (for example "GL_ARB_clip_control") is supported.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Is the feature supported, either by the version of the context or by an extension?
+    pub fn supports(&self, feature: ExtensionFeature) -> bool {
+        feature_supported(feature, &self.version, &self.extensions)
+    }
+
+    /// Is glClipControl available? It is core in OpenGL 4.5, otherwise GL_ARB_clip_control is
+    /// required.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_clip_control(&self) -> bool {
+        self.version_at_least(4, 5) || self.has_extension("GL_ARB_clip_control")
+    }
+
+    /// Is glClipControl available? On OpenGL ES it only exists as glClipControlEXT, which is not
+    /// loaded, so never.
+    #[cfg(feature = "es3")]
+    pub fn supports_clip_control(&self) -> bool {
+        false
+    }
+
+    /// Can program binaries be retrieved and loaded? They are core in OpenGL 4.1, otherwise
+    /// GL_ARB_get_program_binary is required.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_program_binary(&self) -> bool {
+        self.version_at_least(4, 1) || self.has_extension("GL_ARB_get_program_binary")
+    }
+
+    /// Can program binaries be retrieved and loaded? They are core in OpenGL ES 3.0.
+    #[cfg(feature = "es3")]
+    pub fn supports_program_binary(&self) -> bool {
+        true
+    }
+
+    /// Can the base vertex be given to the indexed draw calls? glDrawElementsBaseVertex is core
+    /// in OpenGL 3.2, so always.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_base_vertex(&self) -> bool {
+        true
+    }
+
+    /// Can the base vertex be given to the indexed draw calls? glDrawElementsBaseVertex is core
+    /// in OpenGL ES 3.2.
+    #[cfg(feature = "es3")]
+    pub fn supports_base_vertex(&self) -> bool {
+        self.version_at_least(3, 2)
+    }
+
+    /// Can the base instance be given to the instanced draw calls? It is core in OpenGL 4.2,
+    /// otherwise GL_ARB_base_instance is required.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_base_instance(&self) -> bool {
+        self.version_at_least(4, 2) || self.has_extension("GL_ARB_base_instance")
+    }
+
+    /// Can the base instance be given to the instanced draw calls? Not on OpenGL ES.
+    #[cfg(feature = "es3")]
+    pub fn supports_base_instance(&self) -> bool {
+        false
+    }
+
+    /// Can textures be stored in the given compressed format? RGTC is core in OpenGL 3.0, BPTC
+    /// requires OpenGL 4.2 or GL_ARB_texture_compression_bptc and S3TC is only available through
+    /// GL_EXT_texture_compression_s3tc.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_compressed_format(&self, format: CompressedFormat) -> bool {
+        match format {
+            CompressedFormat::RedRgtc1 |
+            CompressedFormat::SignedRedRgtc1 |
+            CompressedFormat::RgRgtc2 |
+            CompressedFormat::SignedRgRgtc2 => true,
+            CompressedFormat::RgbaBptcUnorm |
+            CompressedFormat::SrgbAlphaBptcUnorm |
+            CompressedFormat::RgbBptcSignedFloat |
+            CompressedFormat::RgbBptcUnsignedFloat => {
+                self.version_at_least(4, 2) || self.has_extension("GL_ARB_texture_compression_bptc")
+            },
+            CompressedFormat::RgbS3tcDxt1 |
+            CompressedFormat::RgbaS3tcDxt1 |
+            CompressedFormat::RgbaS3tcDxt3 |
+            CompressedFormat::RgbaS3tcDxt5 => self.has_extension("GL_EXT_texture_compression_s3tc")
+        }
+    }
+
+    /// Can textures be stored in the given compressed format? On OpenGL ES all of the formats
+    /// require an extension: GL_EXT_texture_compression_rgtc, GL_EXT_texture_compression_bptc
+    /// or GL_EXT_texture_compression_s3tc.
+    #[cfg(feature = "es3")]
+    pub fn supports_compressed_format(&self, format: CompressedFormat) -> bool {
+        match format {
+            CompressedFormat::RedRgtc1 |
+            CompressedFormat::SignedRedRgtc1 |
+            CompressedFormat::RgRgtc2 |
+            CompressedFormat::SignedRgRgtc2 => self.has_extension("GL_EXT_texture_compression_rgtc"),
+            CompressedFormat::RgbaBptcUnorm |
+            CompressedFormat::SrgbAlphaBptcUnorm |
+            CompressedFormat::RgbBptcSignedFloat |
+            CompressedFormat::RgbBptcUnsignedFloat => self.has_extension("GL_EXT_texture_compression_bptc"),
+            CompressedFormat::RgbS3tcDxt1 |
+            CompressedFormat::RgbaS3tcDxt1 |
+            CompressedFormat::RgbaS3tcDxt3 |
+            CompressedFormat::RgbaS3tcDxt5 => self.has_extension("GL_EXT_texture_compression_s3tc")
+        }
+    }
+
+    /// Can buffers be filled with a value by glClearBufferData? It is core in OpenGL 4.3,
+    /// otherwise GL_ARB_clear_buffer_object is required.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_clear_buffer_data(&self) -> bool {
+        self.version_at_least(4, 3) || self.has_extension("GL_ARB_clear_buffer_object")
+    }
+
+    /// Can buffers be filled with a value by glClearBufferData? Not on OpenGL ES.
+    #[cfg(feature = "es3")]
+    pub fn supports_clear_buffer_data(&self) -> bool {
+        false
+    }
+
+    /// Can the command stream be annotated with debug groups and markers, see
+    /// `Renderer::debug_group`? They are core in OpenGL 4.3 and OpenGL ES 3.2, and come with
+    /// GL_KHR_debug on desktop OpenGL.
+    pub fn supports_debug_groups(&self) -> bool {
+        self.supports(ExtensionFeature::DebugOutput)
+    }
+
+    /// Can textures be filled with a value by glClearTexImage? It is core in OpenGL 4.4,
+    /// otherwise GL_ARB_clear_texture is required.
+    #[cfg(not(feature = "es3"))]
+    pub fn supports_clear_texture(&self) -> bool {
+        self.version_at_least(4, 4) || self.has_extension("GL_ARB_clear_texture")
+    }
+
+    /// Can textures be filled with a value by glClearTexImage? On OpenGL ES it only exists as
+    /// glClearTexImageEXT, which is not loaded, so never.
+    #[cfg(feature = "es3")]
+    pub fn supports_clear_texture(&self) -> bool {
+        false
+    }
+
+    /// What can be done with textures of the given internal format? The properties are queried
+    /// with glGetInternalformativ if OpenGL 4.3 or GL_ARB_internalformat_query2 is available,
+    /// otherwise they are derived from the formats the specification requires to be supported,
+    /// and the extensions that add to them.
+    pub fn texture_format_properties(&self, format: TextureFormat) -> TextureFormatProperties {
+        if self.supports_internalformat_query() {
+            query_texture_format_properties(format)
+        }
+        else {
+            self.required_texture_format_properties(format)
+        }
+    }
+
+    #[cfg(not(feature = "es3"))]
+    fn supports_internalformat_query(&self) -> bool {
+        self.version_at_least(4, 3) || self.has_extension("GL_ARB_internalformat_query2")
+    }
+
+    /// OpenGL ES only has glGetInternalformativ for the sample counts of renderbuffers.
+    #[cfg(feature = "es3")]
+    fn supports_internalformat_query(&self) -> bool {
+        false
+    }
+
+    /// The properties required by OpenGL 3.3. All the formats are supported, and all except the
+    /// integer ones filterable.
+    #[cfg(not(feature = "es3"))]
+    fn required_texture_format_properties(&self, format: TextureFormat) -> TextureFormatProperties {
+        let integer = is_integer_format(format);
+        let depth = is_depth_format(format);
+        let color_renderable = match format {
+            TextureFormat::Srgb8 | TextureFormat::Rgb16f | TextureFormat::Rgb32f |
+            TextureFormat::Rgb8ui | TextureFormat::Rgb8i | TextureFormat::Rgb16ui | TextureFormat::Rgb16i |
+            TextureFormat::Rgb32ui | TextureFormat::Rgb32i => false,
+            _ => !depth
+        };
+        TextureFormatProperties {
+            supported: true,
+            filterable: !integer,
+            color_renderable,
+            depth_renderable: depth
+        }
+    }
+
+    /// The properties required by OpenGL ES 3.0. Filtering 32-bit float textures requires
+    /// GL_OES_texture_float_linear and rendering to float textures GL_EXT_color_buffer_float.
+    /// Depth textures can't be filtered.
+    #[cfg(feature = "es3")]
+    fn required_texture_format_properties(&self, format: TextureFormat) -> TextureFormatProperties {
+        let integer = is_integer_format(format);
+        let depth = is_depth_format(format);
+        let float_linear = self.has_extension("GL_OES_texture_float_linear");
+        let color_buffer_float = self.has_extension("GL_EXT_color_buffer_float");
+        let filterable = match format {
+            TextureFormat::R32f | TextureFormat::Rg32f | TextureFormat::Rgb32f | TextureFormat::Rgba32f => float_linear,
+            _ => !integer && !depth
+        };
+        let color_renderable = match format {
+            TextureFormat::Srgb8 | TextureFormat::Rgb16f | TextureFormat::Rgb32f |
+            TextureFormat::Rgb8ui | TextureFormat::Rgb8i | TextureFormat::Rgb16ui | TextureFormat::Rgb16i |
+            TextureFormat::Rgb32ui | TextureFormat::Rgb32i => false,
+            TextureFormat::R16f | TextureFormat::Rg16f | TextureFormat::Rgba16f |
+            TextureFormat::R32f | TextureFormat::Rg32f | TextureFormat::Rgba32f => color_buffer_float,
+            _ => !depth
+        };
+        TextureFormatProperties {
+            supported: true,
+            filterable,
+            color_renderable,
+            depth_renderable: depth
+        }
+    }
+}
+
+/// What the context can do with textures of an internal format. See
+/// `ContextInfo::texture_format_properties`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct TextureFormatProperties {
+    /// Can textures be created in the format at all? GL_INTERNALFORMAT_SUPPORTED
+    pub supported: bool,
+    /// Can the textures be sampled with linear filtering? GL_FILTER
+    pub filterable: bool,
+    /// Can the textures be rendered to as color attachments? GL_COLOR_RENDERABLE
+    pub color_renderable: bool,
+    /// Can the textures be rendered to as depth attachments? GL_DEPTH_RENDERABLE
+    pub depth_renderable: bool
+}
+
+fn query_texture_format_properties(format: TextureFormat) -> TextureFormatProperties {
+    let internal_format = texture::texture_format_to_enum(format);
+    let query = |pname: GLenum| {
+        let mut value = 0;
+        unsafe {
+            gl::GetInternalformativ(gl::TEXTURE_2D, internal_format, pname, 1, &mut value);
+        }
+        check_error!();
+        value as GLenum
+    };
+    TextureFormatProperties {
+        supported: query(gl::INTERNALFORMAT_SUPPORTED) == gl::TRUE as GLenum,
+        // GL_CAVEAT_SUPPORT means it works but might be slow
+        filterable: query(gl::FILTER) != gl::NONE,
+        color_renderable: query(gl::COLOR_RENDERABLE) == gl::TRUE as GLenum,
+        depth_renderable: query(gl::DEPTH_RENDERABLE) == gl::TRUE as GLenum
+    }
+}
+
+pub fn is_integer_format(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::R8ui | TextureFormat::R16ui | TextureFormat::R32ui |
+        TextureFormat::Rg8ui | TextureFormat::Rg16ui | TextureFormat::Rg32ui |
+        TextureFormat::Rgb8ui | TextureFormat::Rgb16ui | TextureFormat::Rgb32ui |
+        TextureFormat::Rgba8ui | TextureFormat::Rgba16ui | TextureFormat::Rgba32ui => true,
+        _ => is_signed_integer_format(format)
+    }
+}
+
+pub fn is_signed_integer_format(format: TextureFormat) -> bool {
+    matches!(format,
+        TextureFormat::R8i | TextureFormat::R16i | TextureFormat::R32i |
+        TextureFormat::Rg8i | TextureFormat::Rg16i | TextureFormat::Rg32i |
+        TextureFormat::Rgb8i | TextureFormat::Rgb16i | TextureFormat::Rgb32i |
+        TextureFormat::Rgba8i | TextureFormat::Rgba16i | TextureFormat::Rgba32i)
+}
+
+pub fn is_depth_format(format: TextureFormat) -> bool {
+    matches!(format,
+        TextureFormat::DepthComponent16 | TextureFormat::DepthComponent24 |
+        TextureFormat::DepthComponent32f | TextureFormat::Depth24Stencil8)
+}
+
+/// Version of the OpenGL context.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct VersionInfo {
+    /// GL_MAJOR_VERSION
+    pub major: GLint,
+    /// GL_MINOR_VERSION
+    pub minor: GLint,
+    /// Is GL_CONTEXT_CORE_PROFILE_BIT set in GL_CONTEXT_PROFILE_MASK? Always false on OpenGL
+    /// ES, which has no profiles.
+    pub core_profile: bool,
+    /// GL_VENDOR, the company responsible for the implementation.
+    pub vendor: String,
+    /// GL_RENDERER, the name of the GPU or the renderer.
+    pub renderer: String,
+    /// GL_VERSION, the version string, usually with the driver version after the OpenGL one.
+    pub version_string: String
+}
+
+impl VersionInfo {
+    /// The vendor, renderer and version strings on their own lines, identifying the driver,
+    /// for example to tell if program binaries stored earlier are from the same driver.
+    pub fn driver_string(&self) -> String {
+        format!("{}\n{}\n{}", self.vendor, self.renderer, self.version_string)
+    }
+}
+
+/// Query the context version without panicking on errors, for validating a context before
+/// creating `ContextInfo` with `build_info`. Returns None if the version can't be queried, meaning
+/// that the functions are not loaded or the context is older than OpenGL 3.0.
+pub fn query_version() -> Option<VersionInfo> {
+    if !gl::GetIntegerv::is_loaded() || !gl::GetError::is_loaded() {
+        return None;
+    }
+    let mut major = 0;
+    let mut minor = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        if gl::GetError() != gl::NO_ERROR {
+            return None;
+        }
+    }
+    Some(VersionInfo {
+        major,
+        minor,
+        core_profile: query_core_profile(),
+        vendor: get_string(gl::VENDOR),
+        renderer: get_string(gl::RENDERER),
+        version_string: get_string(gl::VERSION)
+    })
+}
+
+/// Get a string of the implementation, or an empty string if glGetString is not loaded or
+/// fails.
+fn get_string(name: GLenum) -> String {
+    if !gl::GetString::is_loaded() {
+        return String::new();
+    }
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            gl::GetError();
+            return String::new();
+        }
+        CStr::from_ptr(string as *const _).to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(not(feature = "es3"))]
+fn query_core_profile() -> bool {
+    let mut profile_mask = 0;
+    unsafe {
+        gl::GetIntegerv(gl::CONTEXT_PROFILE_MASK, &mut profile_mask);
+        // The profile mask is only available from OpenGL 3.2 onwards
+        gl::GetError();
+    }
+    profile_mask as GLenum & gl::CONTEXT_CORE_PROFILE_BIT != 0
+}
+
+#[cfg(feature = "es3")]
+fn query_core_profile() -> bool {
+    false
 }
 
 /// Information related to uniform buffers.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct UniformBufferInfo {
     /// GL_MAX_UNIFORM_BUFFER_BINDINGS
     pub max_bindings: GLint,
@@ -44,17 +491,190 @@ pub struct UniformBufferInfo {
     pub offset_alignment: GLint
 }
 
+impl UniformBufferInfo {
+    /// Round the offset up to the next multiple of the offset alignment, so that a range
+    /// starting there can be bound with `Renderer::use_uniform_buffer_range`.
+    pub fn align_offset(&self, offset: usize) -> usize {
+        let alignment = self.offset_alignment.max(1) as usize;
+        offset.div_ceil(alignment) * alignment
+    }
+
+    /// Is the offset a multiple of the offset alignment?
+    pub fn is_offset_aligned(&self, offset: usize) -> bool {
+        offset.is_multiple_of(self.offset_alignment.max(1) as usize)
+    }
+}
+
+/// Limits of the features of `ExtensionFeature`. The limits of unsupported features are left
+/// at the values that mean "not available".
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct ExtensionLimits {
+    /// GL_MAX_TEXTURE_MAX_ANISOTROPY, 1.0 without anisotropic filtering.
+    pub max_anisotropy: f32,
+    /// GL_MAX_DEBUG_MESSAGE_LENGTH, zero without debug output.
+    pub max_debug_message_length: GLint,
+    /// GL_MAX_DEBUG_GROUP_STACK_DEPTH, zero without debug output.
+    pub max_debug_group_stack_depth: GLint,
+    /// GL_MAX_SHADER_STORAGE_BUFFER_BINDINGS, zero without shader storage buffers.
+    pub max_storage_buffer_bindings: GLint,
+    /// GL_MAX_COMPUTE_WORK_GROUP_COUNT along x, y and z, zeros without compute shaders.
+    pub max_compute_work_group_count: [GLint; 3]
+}
+
+/// Information related to textures.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct TextureInfo {
+    /// GL_MAX_TEXTURE_SIZE
+    pub max_size: GLint,
+    /// GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS
+    pub max_combined_units: GLint,
+    /// GL_MAX_TEXTURE_BUFFER_SIZE, the most texels a buffer texture can have. Zero on OpenGL ES
+    /// before 3.2, which has no buffer textures.
+    pub max_buffer_size: GLint,
+    /// GL_TEXTURE_BUFFER_OFFSET_ALIGNMENT, what the offsets of `TextureEditor::set_buffer_range`
+    /// must be multiples of. Zero without `ExtensionFeature::TextureBufferRange`.
+    pub buffer_offset_alignment: GLint,
+    /// GL_MAX_COLOR_TEXTURE_SAMPLES, the most samples of a multisampled color texture. Zero
+    /// without `ExtensionFeature::MultisampleTexture`.
+    pub max_color_samples: GLint,
+    /// GL_MAX_DEPTH_TEXTURE_SAMPLES, the most samples of a multisampled depth texture. Zero
+    /// without `ExtensionFeature::MultisampleTexture`.
+    pub max_depth_samples: GLint
+}
+
+/// Limits of the shader stages, for staying within them when generating shaders.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct ShaderStageInfo {
+    pub vertex: ShaderStageLimits,
+    /// All zero on OpenGL ES, which has no geometry shaders.
+    pub geometry: ShaderStageLimits,
+    pub fragment: ShaderStageLimits,
+    /// GL_MAX_VARYING_COMPONENTS, the components passed from the vertex (or geometry) shader to
+    /// the fragment shader.
+    pub max_varying_components: GLint,
+    /// GL_MAX_TRANSFORM_FEEDBACK_SEPARATE_ATTRIBS, the number of indexed transform feedback
+    /// buffer binding points.
+    pub max_transform_feedback_buffers: GLint
+}
+
+/// Limits of a single shader stage. The names are for the vertex stage, the other stages have
+/// their own.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct ShaderStageLimits {
+    /// GL_MAX_VERTEX_UNIFORM_COMPONENTS, the components of the uniforms outside blocks.
+    pub uniform_components: GLint,
+    /// GL_MAX_GEOMETRY_INPUT_COMPONENTS and GL_MAX_FRAGMENT_INPUT_COMPONENTS. For the vertex
+    /// stage, GL_MAX_VERTEX_ATTRIBS times four.
+    pub input_components: GLint,
+    /// GL_MAX_VERTEX_OUTPUT_COMPONENTS. For the fragment stage, GL_MAX_DRAW_BUFFERS times four.
+    pub output_components: GLint,
+    /// GL_MAX_VERTEX_TEXTURE_IMAGE_UNITS, and GL_MAX_TEXTURE_IMAGE_UNITS for the fragment stage.
+    pub texture_units: GLint
+}
+
+/// Information related to framebuffer objects.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct FramebufferInfo {
+    /// GL_MAX_COLOR_ATTACHMENTS
+    pub max_color_attachments: GLint,
+    /// GL_MAX_DRAW_BUFFERS
+    pub max_draw_buffers: GLint
+}
+
+/// Robustness features of the context, as requested when creating it. See GL_ARB_robustness,
+/// which is core in OpenGL 4.5. Both are false if robustness is not supported.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct RobustnessInfo {
+    /// Is GL_CONTEXT_FLAG_ROBUST_ACCESS_BIT set in GL_CONTEXT_FLAGS? Out of bounds buffer
+    /// accesses can't crash the application then.
+    pub robust_access: bool,
+    /// Is GL_RESET_NOTIFICATION_STRATEGY GL_LOSE_CONTEXT_ON_RESET? Graphics resets are only
+    /// reported by `Context::graphics_reset_status` if so.
+    pub lose_context_on_reset: bool
+}
+
+/// Attributes of the default framebuffer. The bit counts are zero for buffers the default
+/// framebuffer doesn't have.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
+pub struct DefaultFramebufferInfo {
+    /// GL_SAMPLES, zero if the default framebuffer is not multisampled.
+    pub samples: GLint,
+    /// GL_DOUBLEBUFFER
+    pub double_buffered: bool,
+    /// GL_FRAMEBUFFER_ATTACHMENT_RED_SIZE of the color buffer
+    pub red_bits: GLint,
+    /// GL_FRAMEBUFFER_ATTACHMENT_GREEN_SIZE of the color buffer
+    pub green_bits: GLint,
+    /// GL_FRAMEBUFFER_ATTACHMENT_BLUE_SIZE of the color buffer
+    pub blue_bits: GLint,
+    /// GL_FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE of the color buffer
+    pub alpha_bits: GLint,
+    /// GL_FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE of the depth buffer
+    pub depth_bits: GLint,
+    /// GL_FRAMEBUFFER_ATTACHMENT_STENCIL_SIZE of the stencil buffer
+    pub stencil_bits: GLint,
+    /// Is GL_FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING of the color buffer GL_SRGB, meaning the
+    /// default framebuffer is sRGB capable?
+    pub srgb: bool
+}
+
 /// Constructor for the context info. Causes a lof of glGet* calls!
 pub fn build_info() -> ContextInfo {
+    let version = VersionInfo {
+        major: get_integer(gl::MAJOR_VERSION),
+        minor: get_integer(gl::MINOR_VERSION),
+        core_profile: query_core_profile(),
+        vendor: get_string(gl::VENDOR),
+        renderer: get_string(gl::RENDERER),
+        version_string: get_string(gl::VERSION)
+    };
+    let extensions = get_extensions();
+    let robustness = build_robustness_info(&version, &extensions);
+    let extension_limits = build_extension_limits(&version, &extensions);
+    let texture = build_texture_info(&version, &extensions);
     ContextInfo {
+        version,
+        extensions,
         uniform_buffer: UniformBufferInfo {
             max_bindings: get_integer(gl::MAX_UNIFORM_BUFFER_BINDINGS),
             max_vertex_blocks: get_integer(gl::MAX_VERTEX_UNIFORM_BLOCKS),
-            max_geometry_blocks: get_integer(gl::MAX_GEOMETRY_UNIFORM_BLOCKS),
+            max_geometry_blocks: get_max_geometry_blocks(),
             max_fragment_blocks: get_integer(gl::MAX_FRAGMENT_UNIFORM_BLOCKS),
             max_block_size: get_integer(gl::MAX_UNIFORM_BLOCK_SIZE),
             offset_alignment: get_integer(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT)
-        }
+        },
+        texture,
+        default_framebuffer: build_default_framebuffer_info(),
+        framebuffer: FramebufferInfo {
+            max_color_attachments: get_integer(gl::MAX_COLOR_ATTACHMENTS),
+            max_draw_buffers: get_integer(gl::MAX_DRAW_BUFFERS)
+        },
+        shader_stages: ShaderStageInfo {
+            vertex: ShaderStageLimits {
+                uniform_components: get_integer(gl::MAX_VERTEX_UNIFORM_COMPONENTS),
+                input_components: get_integer(gl::MAX_VERTEX_ATTRIBS) * 4,
+                output_components: get_integer(gl::MAX_VERTEX_OUTPUT_COMPONENTS),
+                texture_units: get_integer(gl::MAX_VERTEX_TEXTURE_IMAGE_UNITS)
+            },
+            geometry: get_geometry_limits(),
+            fragment: ShaderStageLimits {
+                uniform_components: get_integer(gl::MAX_FRAGMENT_UNIFORM_COMPONENTS),
+                input_components: get_integer(gl::MAX_FRAGMENT_INPUT_COMPONENTS),
+                output_components: get_integer(gl::MAX_DRAW_BUFFERS) * 4,
+                texture_units: get_integer(gl::MAX_TEXTURE_IMAGE_UNITS)
+            },
+            max_varying_components: get_integer(gl::MAX_VARYING_COMPONENTS),
+            max_transform_feedback_buffers: get_integer(gl::MAX_TRANSFORM_FEEDBACK_SEPARATE_ATTRIBS)
+        },
+        robustness,
+        extension_limits
     }
 }
 
@@ -65,4 +685,244 @@ fn get_integer(property: GLenum) -> GLint {
         check_error!();
         value
     }
-}
\ No newline at end of file
+}
+
+fn get_indexed_integer(property: GLenum, index: GLuint) -> GLint {
+    unsafe {
+        let mut value = 0;
+        gl::GetIntegeri_v(property, index, &mut value);
+        check_error!();
+        value
+    }
+}
+
+#[cfg(not(feature = "es3"))]
+fn get_max_geometry_blocks() -> GLint {
+    get_integer(gl::MAX_GEOMETRY_UNIFORM_BLOCKS)
+}
+
+/// OpenGL ES 3.0 has no geometry shaders.
+#[cfg(feature = "es3")]
+fn get_max_geometry_blocks() -> GLint {
+    0
+}
+
+#[cfg(not(feature = "es3"))]
+fn get_geometry_limits() -> ShaderStageLimits {
+    ShaderStageLimits {
+        uniform_components: get_integer(gl::MAX_GEOMETRY_UNIFORM_COMPONENTS),
+        input_components: get_integer(gl::MAX_GEOMETRY_INPUT_COMPONENTS),
+        output_components: get_integer(gl::MAX_GEOMETRY_OUTPUT_COMPONENTS),
+        texture_units: get_integer(gl::MAX_GEOMETRY_TEXTURE_IMAGE_UNITS)
+    }
+}
+
+/// OpenGL ES 3.0 has no geometry shaders.
+#[cfg(feature = "es3")]
+fn get_geometry_limits() -> ShaderStageLimits {
+    ShaderStageLimits { uniform_components: 0, input_components: 0, output_components: 0, texture_units: 0 }
+}
+
+fn build_texture_info(version: &VersionInfo, extensions: &[String]) -> TextureInfo {
+    let range = feature_supported(ExtensionFeature::TextureBufferRange, version, extensions);
+    let multisample = feature_supported(ExtensionFeature::MultisampleTexture, version, extensions);
+    TextureInfo {
+        max_size: get_integer(gl::MAX_TEXTURE_SIZE),
+        max_combined_units: get_integer(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+        max_buffer_size: get_max_texture_buffer_size(version),
+        buffer_offset_alignment: if range { get_integer(gl::TEXTURE_BUFFER_OFFSET_ALIGNMENT) } else { 0 },
+        max_color_samples: if multisample { get_integer(gl::MAX_COLOR_TEXTURE_SAMPLES) } else { 0 },
+        max_depth_samples: if multisample { get_integer(gl::MAX_DEPTH_TEXTURE_SAMPLES) } else { 0 }
+    }
+}
+
+/// Buffer textures are core since OpenGL 3.1.
+#[cfg(not(feature = "es3"))]
+fn get_max_texture_buffer_size(_: &VersionInfo) -> GLint {
+    get_integer(gl::MAX_TEXTURE_BUFFER_SIZE)
+}
+
+/// Buffer textures are core in OpenGL ES 3.2.
+#[cfg(feature = "es3")]
+fn get_max_texture_buffer_size(version: &VersionInfo) -> GLint {
+    if version.major > 3 || (version.major == 3 && version.minor >= 2) {
+        get_integer(gl::MAX_TEXTURE_BUFFER_SIZE)
+    }
+    else {
+        0
+    }
+}
+
+fn get_extensions() -> Vec<String> {
+    let count = get_integer(gl::NUM_EXTENSIONS);
+    (0..count as GLuint).map(|index| {
+        unsafe {
+            let name = gl::GetStringi(gl::EXTENSIONS, index);
+            check_error!();
+            CStr::from_ptr(name as *const _).to_string_lossy().into_owned()
+        }
+    }).collect()
+}
+
+/// Query the attributes of the default framebuffer. Assumes that the default framebuffer is
+/// bound, as it is when the context is created.
+fn build_default_framebuffer_info() -> DefaultFramebufferInfo {
+    let (double_buffered, color) = default_color_buffer();
+    DefaultFramebufferInfo {
+        samples: get_integer(gl::SAMPLES),
+        double_buffered,
+        red_bits: get_attachment_parameter(color, gl::FRAMEBUFFER_ATTACHMENT_RED_SIZE),
+        green_bits: get_attachment_parameter(color, gl::FRAMEBUFFER_ATTACHMENT_GREEN_SIZE),
+        blue_bits: get_attachment_parameter(color, gl::FRAMEBUFFER_ATTACHMENT_BLUE_SIZE),
+        alpha_bits: get_attachment_parameter(color, gl::FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE),
+        depth_bits: get_attachment_parameter(gl::DEPTH, gl::FRAMEBUFFER_ATTACHMENT_DEPTH_SIZE),
+        stencil_bits: get_attachment_parameter(gl::STENCIL, gl::FRAMEBUFFER_ATTACHMENT_STENCIL_SIZE),
+        srgb: get_attachment_parameter(color, gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING) as GLenum == gl::SRGB
+    }
+}
+
+#[cfg(not(feature = "es3"))]
+fn build_robustness_info(version: &VersionInfo, extensions: &[String]) -> RobustnessInfo {
+    let supported = version.major > 4 || (version.major == 4 && version.minor >= 5) ||
+        extensions.iter().any(|extension| extension == "GL_ARB_robustness");
+    if !supported {
+        return RobustnessInfo { robust_access: false, lose_context_on_reset: false };
+    }
+    RobustnessInfo {
+        robust_access: get_integer(gl::CONTEXT_FLAGS) as GLenum & gl::CONTEXT_FLAG_ROBUST_ACCESS_BIT != 0,
+        lose_context_on_reset: get_integer(gl::RESET_NOTIFICATION_STRATEGY) as GLenum == gl::LOSE_CONTEXT_ON_RESET
+    }
+}
+
+/// OpenGL ES 3.0 only has robustness through GL_EXT_robustness, which is not supported.
+#[cfg(feature = "es3")]
+fn build_robustness_info(_: &VersionInfo, _: &[String]) -> RobustnessInfo {
+    RobustnessInfo { robust_access: false, lose_context_on_reset: false }
+}
+
+/// GL_TEXTURE_MAX_ANISOTROPY, which the bindings don't have.
+pub const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+/// GL_MAX_TEXTURE_MAX_ANISOTROPY
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+#[cfg(not(feature = "es3"))]
+fn feature_supported(feature: ExtensionFeature, version: &VersionInfo, extensions: &[String]) -> bool {
+    let at_least = |major, minor| version.major > major || (version.major == major && version.minor >= minor);
+    let has = |name: &str| extensions.iter().any(|extension| extension == name);
+    match feature {
+        ExtensionFeature::AnisotropicFiltering => {
+            at_least(4, 6) || has("GL_ARB_texture_filter_anisotropic") || has("GL_EXT_texture_filter_anisotropic")
+        },
+        ExtensionFeature::DebugOutput => at_least(4, 3) || has("GL_KHR_debug"),
+        ExtensionFeature::BufferStorage => at_least(4, 4) || has("GL_ARB_buffer_storage"),
+        ExtensionFeature::BindlessTexture => has("GL_ARB_bindless_texture") || has("GL_NV_bindless_texture"),
+        ExtensionFeature::ClipControl => at_least(4, 5) || has("GL_ARB_clip_control"),
+        ExtensionFeature::TextureBufferRange => at_least(4, 3) || has("GL_ARB_texture_buffer_range"),
+        ExtensionFeature::ShadingLanguageInclude => has("GL_ARB_shading_language_include"),
+        ExtensionFeature::ConservativeOcclusionQuery => at_least(4, 3) || has("GL_ARB_ES3_compatibility"),
+        ExtensionFeature::ComputeShader => at_least(4, 3) || has("GL_ARB_compute_shader"),
+        ExtensionFeature::ProgramInterfaceQuery => at_least(4, 3) || has("GL_ARB_program_interface_query"),
+        ExtensionFeature::InvalidateFramebuffer => at_least(4, 3) || has("GL_ARB_invalidate_subdata"),
+        ExtensionFeature::Robustness => at_least(4, 5) || has("GL_KHR_robustness"),
+        ExtensionFeature::MemoryBarrier => at_least(4, 2) || has("GL_ARB_shader_image_load_store"),
+        ExtensionFeature::AtomicCounterBuffer => at_least(4, 2) || has("GL_ARB_shader_atomic_counters"),
+        ExtensionFeature::ShaderStorageBuffer => at_least(4, 3) || has("GL_ARB_shader_storage_buffer_object"),
+        ExtensionFeature::DrawIndirect => at_least(4, 0) || has("GL_ARB_draw_indirect"),
+        ExtensionFeature::MultiDrawIndirect => at_least(4, 3) || has("GL_ARB_multi_draw_indirect"),
+        ExtensionFeature::MultisampleTexture => true
+    }
+}
+
+/// OpenGL ES has glClipControl only as glClipControlEXT, which is not loaded. The same goes
+/// for the other functions of extensions, such as those of GL_KHR_debug, which are suffixed
+/// with KHR on OpenGL ES.
+#[cfg(feature = "es3")]
+fn feature_supported(feature: ExtensionFeature, version: &VersionInfo, extensions: &[String]) -> bool {
+    let at_least = |major, minor| version.major > major || (version.major == major && version.minor >= minor);
+    let has = |name: &str| extensions.iter().any(|extension| extension == name);
+    match feature {
+        ExtensionFeature::AnisotropicFiltering => has("GL_EXT_texture_filter_anisotropic"),
+        ExtensionFeature::DebugOutput => at_least(3, 2),
+        ExtensionFeature::BufferStorage => has("GL_EXT_buffer_storage"),
+        ExtensionFeature::BindlessTexture => has("GL_NV_bindless_texture"),
+        ExtensionFeature::ClipControl => false,
+        ExtensionFeature::TextureBufferRange => at_least(3, 2),
+        ExtensionFeature::ShadingLanguageInclude => false,
+        ExtensionFeature::ConservativeOcclusionQuery => true,
+        ExtensionFeature::ComputeShader => at_least(3, 1),
+        ExtensionFeature::ProgramInterfaceQuery => at_least(3, 1),
+        ExtensionFeature::InvalidateFramebuffer => true,
+        ExtensionFeature::Robustness => at_least(3, 2),
+        ExtensionFeature::MemoryBarrier => at_least(3, 1),
+        ExtensionFeature::AtomicCounterBuffer => at_least(3, 1),
+        ExtensionFeature::ShaderStorageBuffer => at_least(3, 1),
+        ExtensionFeature::DrawIndirect => at_least(3, 1),
+        ExtensionFeature::MultiDrawIndirect => false,
+        ExtensionFeature::MultisampleTexture => at_least(3, 1)
+    }
+}
+
+fn build_extension_limits(version: &VersionInfo, extensions: &[String]) -> ExtensionLimits {
+    let max_anisotropy = if feature_supported(ExtensionFeature::AnisotropicFiltering, version, extensions) {
+        unsafe {
+            let mut value = 1.0;
+            gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut value);
+            check_error!();
+            value
+        }
+    }
+    else {
+        1.0
+    };
+    let debug_output = feature_supported(ExtensionFeature::DebugOutput, version, extensions);
+    let storage_buffers = feature_supported(ExtensionFeature::ShaderStorageBuffer, version, extensions);
+    let max_compute_work_group_count = if feature_supported(ExtensionFeature::ComputeShader, version, extensions) {
+        [0, 1, 2].map(|index| get_indexed_integer(gl::MAX_COMPUTE_WORK_GROUP_COUNT, index))
+    }
+    else {
+        [0; 3]
+    };
+    ExtensionLimits {
+        max_anisotropy,
+        max_debug_message_length: if debug_output { get_integer(gl::MAX_DEBUG_MESSAGE_LENGTH) } else { 0 },
+        max_debug_group_stack_depth: if debug_output { get_integer(gl::MAX_DEBUG_GROUP_STACK_DEPTH) } else { 0 },
+        max_storage_buffer_bindings: if storage_buffers { get_integer(gl::MAX_SHADER_STORAGE_BUFFER_BINDINGS) } else { 0 },
+        max_compute_work_group_count
+    }
+}
+
+/// Is the default framebuffer double buffered, and the name of its color buffer.
+#[cfg(not(feature = "es3"))]
+fn default_color_buffer() -> (bool, GLenum) {
+    let double_buffered = unsafe {
+        let mut value = 0;
+        gl::GetBooleanv(gl::DOUBLEBUFFER, &mut value);
+        check_error!();
+        value == gl::TRUE
+    };
+    (double_buffered, if double_buffered { gl::BACK_LEFT } else { gl::FRONT_LEFT })
+}
+
+/// OpenGL ES can't be queried for GL_DOUBLEBUFFER, but the default framebuffer of an ES
+/// context has a single color buffer called GL_BACK.
+#[cfg(feature = "es3")]
+fn default_color_buffer() -> (bool, GLenum) {
+    (true, gl::BACK)
+}
+
+/// Query a parameter of a default framebuffer attachment, or zero if the attachment doesn't
+/// exist. (Querying other parameters than the object type of a missing attachment is an error.)
+fn get_attachment_parameter(attachment: GLenum, property: GLenum) -> GLint {
+    unsafe {
+        let mut object_type = 0;
+        gl::GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER, attachment, gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE, &mut object_type);
+        check_error!();
+        if object_type as GLenum == gl::NONE {
+            return 0;
+        }
+        let mut value = 0;
+        gl::GetFramebufferAttachmentParameteriv(gl::FRAMEBUFFER, attachment, property, &mut value);
+        check_error!();
+        value
+    }
+}
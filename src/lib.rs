@@ -18,7 +18,9 @@
 //! that uses Rust's capabilities to make it easier to build correctly working programs. The
 //! preferred way is to ensure validity statically, but runtime checks are used where necessary.
 //! That said, OpenGL error checking is meant to be enabled only in debug builds, as the basic
-//! error checking with glGetError may cause slowdowns.
+//! error checking with glGetError may cause slowdowns. The error checks and the validation of
+//! draw calls are compiled out of release builds, unless the `strict-validation` feature is
+//! enabled.
 //!
 //! The second purpose is to make OpenGL more convenient to use. Non-purposes or non-goals include
 //! hiding OpenGL - while there are some new abstractions, the underlying API is not meant to be
@@ -62,7 +64,8 @@
 //! instead of a more expensive call to the driver every time. Time will tell if this is a good
 //! idea.
 
-extern crate gl;
+#[cfg(target_arch = "wasm32")]
+compile_error!("htgl doesn't support wasm32 and WebGL2, see the non-goals in README.md");
 
 pub use gl::load_with;
 pub use renderer::Renderer;
@@ -71,27 +74,66 @@ pub use program::{ProgramEditor,
     ProgramInfoAccessor,
     ShaderAttributeInfo,
     ShaderAttribute,
+    ShaderAttributeIter,
+    FragmentOutputInfo,
+    FragmentOutput,
+    TransformFeedbackInfo,
+    TransformFeedbackVarying,
     UniformInfo,
     Uniform,
+    UniformType,
     InterfaceBlock,
     BlockUniform,
+    LayoutMismatch,
     SimpleUniformTypeFloat,
     SimpleUniformTypeI32,
     SimpleUniformTypeMatrix,
     SimpleUniformTypeU32};
 pub use shader::ShaderInfoAccessor;
-pub use buffer::BufferEditor;
-pub use context::Context;
-pub use vertexarray::VertexAttributeType;
-pub use options::RenderOption;
-pub use renderer::PrimitiveMode;
+pub use buffer::{BufferEditor,IndexType};
+pub use context::{Context,ContextCreationError,GraphicsResetStatus,ResourceReport};
+pub use info::TextureFormatProperties;
+pub use info::ExtensionFeature;
+pub use info::ExtensionLimits;
+pub use vertexarray::{VertexAttributeType,VertexAttributeError,VertexFormat,VertexLayout,VertexLayoutError,VertexInterleaver,InterleaveError,AttributeDefault};
+pub use texture::{TextureEditor,TextureBufferError,TextureFormat,TextureFilter,TextureWrap,TextureClearValue,MipmapPolicy,SrgbPolicy,max_levels_for};
+#[cfg(feature = "loaders")]
+pub use texture::loaders;
+pub use image::{ImageData,ImageFormat,PixelFormat,PixelType,CompressedFormat,RawImage};
+pub use options::{RenderOption,ClearState,IntegerClearValue,BlendFactor,BlendEquation,BlendPreset,DepthFunction,ClipOrigin,ClipDepth,PixelStoreParam,Hint,PointSpriteOrigin};
+pub use renderer::{PrimitiveMode,MemoryBarrier,DrawParams,PassTarget,DefaultTarget,DefaultFramebuffer,PassDesc,RenderPass};
+pub use framebuffer::RenderTargetError;
+pub use mesh::{Mesh,INSTANCE_ATTRIBUTE_LOCATION};
+pub use surface::Surface;
+pub use raw::{RawGlObject,RawGlScope,Ownership};
+pub use command::CommandList;
+pub use color::{LinearRgba,SrgbRgba};
+pub use atlas::{TextureAtlas,AtlasRegion};
+pub use sync::Fence;
+pub use query::{Query,QueryTarget,ConditionalRenderMode};
+pub use occlusion::OcclusionCuller;
+pub use indirect::{IndirectCommand,IndirectCommandBuffer};
+pub use tracker::{TrackerMode,IndexedBufferTarget};
+pub use perframe::PerFrameUbo;
+pub use shadowed::ShadowedUbo;
+pub use targetpool::{TargetPool,TargetDesc,PooledTarget};
+pub use gbuffer::{GBuffer,GBufferLayout};
+pub use picking::{Picker,PICKING_ID_UNIFORM};
+pub use tonemap::{TonemapPass,TonemapOperator};
+pub use skinning::{BoneMatrixBuffer,BoneStorage,BONES_SNIPPET_NAME};
+pub use binding::BindingPointAllocator;
+pub use bindingreport::{BindingReport,BindingDivergence,IndexedBufferBinding};
+pub use capture::CaptureHelper;
+pub use snapshot::{DebugSnapshot,BufferSnapshot,VertexArraySnapshot,ShaderSnapshot,ProgramSnapshot,TextureSnapshot,RenderTargetSnapshot};
+pub use util::HtglError;
+pub use shaderlib::{ShaderSourceLibrary,IncludeError};
+pub use variants::ProgramVariants;
+pub use pod::Pod;
 
-use vertexarray::VertexArray;
-use program::Program;
 use handle::Handle;
 
 macro_rules! check_error(
-    () => (::util::check_error(file!(), line!()));
+    () => (if $crate::util::VALIDATION { $crate::util::check_error(file!(), line!()) });
 );
 
 mod handle;
@@ -105,6 +147,49 @@ mod options;
 mod renderer;
 mod context;
 mod info;
+mod texture;
+mod framebuffer;
+mod image;
+mod mesh;
+mod surface;
+mod raw;
+mod command;
+mod color;
+mod atlas;
+mod sync;
+mod query;
+mod occlusion;
+mod indirect;
+mod perframe;
+mod shadowed;
+mod targetpool;
+mod gbuffer;
+mod picking;
+mod tonemap;
+mod skinning;
+mod binding;
+mod bindingreport;
+mod capture;
+mod snapshot;
+mod shaderlib;
+mod variants;
+mod pod;
+mod namedstring;
+mod debug;
+pub mod matrix;
+pub mod half;
+pub mod packed;
+pub mod semantic;
+#[cfg(feature = "import")]
+pub mod import;
+#[cfg(feature = "text")]
+pub mod text;
+#[cfg(feature = "staging")]
+pub mod staging;
+#[cfg(feature = "gpu_cull")]
+pub mod gpu_cull;
+#[cfg(all(feature = "adaptive_resolution", not(feature = "es3")))]
+pub mod adaptive_resolution;
 
 /// Handle to a buffer object (vertex, index, uniform and so on).
 pub type BufferHandle = Handle<buffer::BufferObject>;
@@ -115,3 +200,7 @@ pub type VertexArrayHandle = Handle<vertexarray::VertexArray>;
 pub type ShaderHandle = Handle<shader::Shader>;
 /// Handle to a shader program.
 pub type ProgramHandle = Handle<program::Program>;
+/// Handle to a texture object.
+pub type TextureHandle = Handle<texture::Texture>;
+/// Handle to a render target, a framebuffer object with textures attached.
+pub type RenderTargetHandle = Handle<framebuffer::RenderTarget>;
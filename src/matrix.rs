@@ -0,0 +1,226 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Projection and view matrices, so that getting something on the screen doesn't require a math
+//! crate. The matrices are column major `[f32; 16]` arrays, ready to be given to
+//! `Renderer::uniform_matrix` without transposing.
+//!
+//! The projections take the clip control state of the context (see `Context::clip_control`), so
+//! that they map the depth range to the clip space depth range in use, and flip the y axis when
+//! the clip origin is the upper left corner.
+
+use super::options::{ClipOrigin,ClipDepth};
+
+/// The identity matrix.
+pub fn identity() -> [f32; 16] {
+    [
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0
+    ]
+}
+
+/// The product `a * b`, which transforms by `b` first and then by `a`.
+pub fn multiply(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut product = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            product[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+    product
+}
+
+/// A translation matrix.
+pub fn translation(x: f32, y: f32, z: f32) -> [f32; 16] {
+    let mut matrix = identity();
+    matrix[12] = x;
+    matrix[13] = y;
+    matrix[14] = z;
+    matrix
+}
+
+/// A perspective projection, the vertical field of view given in radians. The near and far
+/// distances must be positive. As with gluPerspective, the camera looks towards negative z.
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32, clip_control: (ClipOrigin, ClipDepth)) -> [f32; 16] {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    let (origin, depth) = clip_control;
+    let (depth_scale, depth_offset) = match depth {
+        ClipDepth::NegativeOneToOne => ((far + near) / (near - far), 2.0 * far * near / (near - far)),
+        ClipDepth::ZeroToOne => (far / (near - far), far * near / (near - far))
+    };
+    [
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, f * y_sign(origin), 0.0, 0.0,
+        0.0, 0.0, depth_scale, -1.0,
+        0.0, 0.0, depth_offset, 0.0
+    ]
+}
+
+/// A perspective projection for the reversed-Z depth mapping set up by
+/// `Renderer::setup_reversed_z`: the near plane maps to one and the far plane to zero. Requires
+/// the zero-to-one clip space depth range, so only the clip origin is given.
+pub fn perspective_reversed_z(fov_y: f32, aspect: f32, near: f32, far: f32, origin: ClipOrigin) -> [f32; 16] {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    [
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, f * y_sign(origin), 0.0, 0.0,
+        0.0, 0.0, near / (far - near), -1.0,
+        0.0, 0.0, far * near / (far - near), 0.0
+    ]
+}
+
+/// An orthographic projection of the box, as with glOrtho. The near and far planes are at
+/// negative z, that is, `-near` and `-far`.
+pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32, clip_control: (ClipOrigin, ClipDepth)) -> [f32; 16] {
+    let (origin, depth) = clip_control;
+    let (depth_scale, depth_offset) = match depth {
+        ClipDepth::NegativeOneToOne => (-2.0 / (far - near), -(far + near) / (far - near)),
+        ClipDepth::ZeroToOne => (-1.0 / (far - near), -near / (far - near))
+    };
+    let y_sign = y_sign(origin);
+    [
+        2.0 / (right - left), 0.0, 0.0, 0.0,
+        0.0, y_sign * 2.0 / (top - bottom), 0.0, 0.0,
+        0.0, 0.0, depth_scale, 0.0,
+        -(right + left) / (right - left), y_sign * -(top + bottom) / (top - bottom), depth_offset, 1.0
+    ]
+}
+
+/// An orthographic projection for drawing in pixels: x and y from zero to the width and height,
+/// with the origin at the lower left corner like window coordinates. z from -1 to 1 is visible.
+pub fn pixel_orthographic(width: u32, height: u32, clip_control: (ClipOrigin, ClipDepth)) -> [f32; 16] {
+    orthographic(0.0, width as f32, 0.0, height as f32, -1.0, 1.0, clip_control)
+}
+
+/// A view matrix of a camera at `eye` looking at `target`, as with gluLookAt. `up` must not be
+/// parallel to the viewing direction.
+pub fn look_at(eye: [f32; 3], target: [f32; 3], up: [f32; 3]) -> [f32; 16] {
+    let forward = normalize(sub(target, eye));
+    let side = normalize(cross(forward, up));
+    let up = cross(side, forward);
+    [
+        side[0], up[0], -forward[0], 0.0,
+        side[1], up[1], -forward[1], 0.0,
+        side[2], up[2], -forward[2], 0.0,
+        -dot(side, eye), -dot(up, eye), dot(forward, eye), 1.0
+    ]
+}
+
+/// With the upper left clip origin, window y grows downwards, so y is flipped to keep the image
+/// upright.
+fn y_sign(origin: ClipOrigin) -> f32 {
+    match origin {
+        ClipOrigin::LowerLeft => 1.0,
+        ClipOrigin::UpperLeft => -1.0
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+/// The six planes of the view frustum of the projection (or view-projection) matrix, as
+/// `[a, b, c, d]` with the normal `[a, b, c]` of unit length pointing inside, so that the points
+/// inside have `a * x + b * y + c * z + d >= 0`. The order is left, right, bottom, top, near
+/// and far. With the zero-to-one clip depth the near plane is placed where the minus one depth
+/// would be, which only makes culling against it more conservative.
+pub fn frustum_planes(matrix: &[f32; 16]) -> [[f32; 4]; 6] {
+    let row = |index: usize| [matrix[index], matrix[4 + index], matrix[8 + index], matrix[12 + index]];
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+    let plane = |a: [f32; 4], b: [f32; 4], sign: f32| {
+        let plane = [a[0] + sign * b[0], a[1] + sign * b[1], a[2] + sign * b[2], a[3] + sign * b[3]];
+        let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+        [plane[0] / length, plane[1] / length, plane[2] / length, plane[3] / length]
+    };
+    [plane(w, x, 1.0), plane(w, x, -1.0), plane(w, y, 1.0), plane(w, y, -1.0), plane(w, z, 1.0), plane(w, z, -1.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GL_CLIP: (ClipOrigin, ClipDepth) = (ClipOrigin::LowerLeft, ClipDepth::NegativeOneToOne);
+    const D3D_CLIP: (ClipOrigin, ClipDepth) = (ClipOrigin::UpperLeft, ClipDepth::ZeroToOne);
+
+    /// Transform the point and divide by w.
+    fn project(matrix: &[f32; 16], point: [f32; 3]) -> [f32; 3] {
+        let transformed: Vec<f32> = (0..4)
+            .map(|row| matrix[row] * point[0] + matrix[4 + row] * point[1] + matrix[8 + row] * point[2] + matrix[12 + row])
+            .collect();
+        [transformed[0] / transformed[3], transformed[1] / transformed[3], transformed[2] / transformed[3]]
+    }
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_the_depth_range() {
+        let fov_y = std::f32::consts::FRAC_PI_2;
+        let gl = perspective(fov_y, 1.0, 1.0, 10.0, GL_CLIP);
+        assert_close(project(&gl, [1.0, 1.0, -1.0]), [1.0, 1.0, -1.0]);
+        assert_close(project(&gl, [0.0, 0.0, -10.0]), [0.0, 0.0, 1.0]);
+        let d3d = perspective(fov_y, 1.0, 1.0, 10.0, D3D_CLIP);
+        assert_close(project(&d3d, [1.0, 1.0, -1.0]), [1.0, -1.0, 0.0]);
+        assert_close(project(&d3d, [0.0, 0.0, -10.0]), [0.0, 0.0, 1.0]);
+        let reversed = perspective_reversed_z(fov_y, 1.0, 1.0, 10.0, ClipOrigin::LowerLeft);
+        assert_close(project(&reversed, [0.0, 0.0, -1.0]), [0.0, 0.0, 1.0]);
+        assert_close(project(&reversed, [0.0, 0.0, -10.0]), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn pixel_orthographic_covers_the_window() {
+        let gl = pixel_orthographic(800, 600, GL_CLIP);
+        assert_close(project(&gl, [0.0, 0.0, 0.0]), [-1.0, -1.0, 0.0]);
+        assert_close(project(&gl, [800.0, 600.0, 0.0]), [1.0, 1.0, 0.0]);
+        let d3d = pixel_orthographic(800, 600, D3D_CLIP);
+        assert_close(project(&d3d, [0.0, 0.0, 0.0]), [-1.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn look_at_moves_the_target_in_front_of_the_camera() {
+        let view = look_at([1.0, 2.0, 3.0], [1.0, 2.0, -2.0], [0.0, 1.0, 0.0]);
+        assert_close(project(&view, [1.0, 2.0, 3.0]), [0.0, 0.0, 0.0]);
+        assert_close(project(&view, [1.0, 2.0, -2.0]), [0.0, 0.0, -5.0]);
+        let moved = multiply(&translation(0.0, 0.0, -1.0), &view);
+        assert_close(project(&moved, [1.0, 3.0, 3.0]), [0.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn frustum_planes_point_inside() {
+        let planes = frustum_planes(&orthographic(-1.0, 1.0, -1.0, 1.0, 1.0, 3.0, GL_CLIP));
+        assert_eq!(planes[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(planes[3], [0.0, -1.0, 0.0, 1.0]);
+        for plane in planes.iter() {
+            let distance = plane[0] * 0.0 + plane[1] * 0.0 + plane[2] * -2.0 + plane[3];
+            assert!(distance > 0.0);
+        }
+    }
+}
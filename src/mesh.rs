@@ -0,0 +1,75 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A mesh bundles a vertex array with the information needed to draw it. See `Mesh`.
+
+use super::{BufferHandle,VertexArrayHandle};
+use super::buffer::IndexType;
+use super::renderer::{Renderer,PrimitiveMode,DrawParams};
+use super::vertexarray::VertexFormat;
+
+/// The first attribute location used for the per-instance attributes by `Mesh::draw_instanced`.
+/// The attributes of the mesh itself should be below it.
+pub const INSTANCE_ATTRIBUTE_LOCATION: u32 = 8;
+
+/// A vertex array together with its vertex buffer and the parameters of the draw call: the
+/// primitive mode, the element count and the index type. A mesh without an index type is drawn
+/// with glDrawArrays, otherwise with glDrawElements.
+#[derive(Clone,Debug)]
+pub struct Mesh {
+    /// The vertex array describing the mesh's vertex attributes, and the index buffer if any.
+    pub vertex_array: VertexArrayHandle,
+    /// The buffer containing the vertex data.
+    pub vertex_buffer: BufferHandle,
+    /// How the vertices form primitives.
+    pub primitive_mode: PrimitiveMode,
+    /// Type of the indices, or None if the mesh is not indexed.
+    pub index_type: Option<IndexType>,
+    /// The number of vertices (or indices, for indexed meshes) to draw.
+    pub element_count: u32,
+    /// The first vertex of the mesh in the vertex buffer, for meshes sharing their buffers with
+    /// others. Added to the indices of indexed meshes, see glDrawElementsBaseVertex.
+    pub base_vertex: i32
+}
+
+impl Mesh {
+    /// Bind the vertex array of the mesh and draw all of it. The program must have been chosen
+    /// beforehand with `Renderer::use_program`.
+    pub fn draw(&self, renderer: &mut Renderer) {
+        renderer.use_vertex_array(&self.vertex_array);
+        renderer.draw(&self.draw_params());
+    }
+
+    /// Draw `instance_count` instances of the mesh, reading the per-instance attributes from the
+    /// instance buffer, one `I` per instance. The attributes of `I` are bound to the locations
+    /// starting from `INSTANCE_ATTRIBUTE_LOCATION`, with a divisor of one:
+    ///
+    ///    mesh.draw_instanced::<InstanceData>(&mut renderer, &instance_buffer, 100);
+    ///
+    /// See `Renderer::use_vertex_array_instanced`.
+    pub fn draw_instanced<I: VertexFormat>(&self, renderer: &mut Renderer, instance_buffer: &BufferHandle, instance_count: u32) {
+        renderer.use_vertex_array_instanced::<I>(&self.vertex_array, instance_buffer);
+        renderer.draw(&self.draw_params().instances(instance_count));
+    }
+
+    fn draw_params(&self) -> DrawParams {
+        match self.index_type {
+            None => DrawParams::arrays(self.primitive_mode, self.base_vertex as u32, self.element_count),
+            Some(index_type) => {
+                DrawParams::elements(self.primitive_mode, index_type, 0, self.element_count)
+                    .base_vertex(self.base_vertex)
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! The functions of GL_ARB_shading_language_include, which the OpenGL bindings don't have, so
+//! they are loaded here with the loader given to `Context::from_loader`.
+
+use std::mem;
+use std::os::raw::c_void;
+
+use gl::types::{GLchar,GLenum,GLint,GLsizei,GLuint};
+
+/// GL_SHADER_INCLUDE_ARB
+const SHADER_INCLUDE: GLenum = 0x8DAE;
+
+type NamedStringFn = extern "system" fn(GLenum, GLint, *const GLchar, GLint, *const GLchar);
+type DeleteNamedStringFn = extern "system" fn(GLint, *const GLchar);
+type CompileShaderIncludeFn = extern "system" fn(GLuint, GLsizei, *const *const GLchar, *const GLint);
+
+/// The loaded glNamedStringARB, glDeleteNamedStringARB and glCompileShaderIncludeARB.
+#[derive(Clone,Copy)]
+pub struct NamedStringFunctions {
+    named_string: NamedStringFn,
+    delete_named_string: DeleteNamedStringFn,
+    compile_shader_include: CompileShaderIncludeFn
+}
+
+impl NamedStringFunctions {
+    /// Load the functions, None if any of them is missing.
+    pub fn load<F>(loader: &mut F) -> Option<NamedStringFunctions> where F: FnMut(&str) -> *const c_void {
+        let named_string = loader("glNamedStringARB");
+        let delete_named_string = loader("glDeleteNamedStringARB");
+        let compile_shader_include = loader("glCompileShaderIncludeARB");
+        if named_string.is_null() || delete_named_string.is_null() || compile_shader_include.is_null() {
+            return None;
+        }
+        unsafe {
+            Some(NamedStringFunctions {
+                named_string: mem::transmute::<*const c_void, NamedStringFn>(named_string),
+                delete_named_string: mem::transmute::<*const c_void, DeleteNamedStringFn>(delete_named_string),
+                compile_shader_include: mem::transmute::<*const c_void, CompileShaderIncludeFn>(compile_shader_include)
+            })
+        }
+    }
+
+    /// Set the named string. The name must start with a slash. See glNamedStringARB.
+    pub fn set(&self, name: &str, source: &str) {
+        (self.named_string)(SHADER_INCLUDE, name.len() as GLint, name.as_ptr() as *const GLchar,
+            source.len() as GLint, source.as_ptr() as *const GLchar);
+        check_error!();
+    }
+
+    /// Delete the named string. See glDeleteNamedStringARB.
+    pub fn delete(&self, name: &str) {
+        (self.delete_named_string)(name.len() as GLint, name.as_ptr() as *const GLchar);
+        check_error!();
+    }
+
+    /// Compile the shader, its source already set, resolving relative include names against
+    /// the search paths. See glCompileShaderIncludeARB.
+    pub fn compile(&self, shader: u32, paths: &[&str]) {
+        let pointers: Vec<*const GLchar> = paths.iter().map(|path| path.as_ptr() as *const GLchar).collect();
+        let lengths: Vec<GLint> = paths.iter().map(|path| path.len() as GLint).collect();
+        (self.compile_shader_include)(shader, paths.len() as GLsizei, pointers.as_ptr(), lengths.as_ptr());
+        check_error!();
+    }
+}
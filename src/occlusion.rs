@@ -0,0 +1,225 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Occlusion culling with queries, see `OcclusionCuller`.
+
+use super::{Context,ExtensionFeature,ProgramHandle,VertexArrayHandle};
+use super::options::RenderOption;
+use super::program::{SimpleUniformTypeFloat,SimpleUniformTypeMatrix};
+use super::query::{Query,QueryTarget,ConditionalRenderMode};
+use super::renderer::{PrimitiveMode,Renderer};
+use super::shader::ShaderType;
+use super::shaderlib::SHADER_HEADER;
+
+/// The twelve triangles of a box, made from the vertex ids without any vertex data. The bits
+/// of a corner index select the maximum instead of the minimum of x, y and z.
+static VERTEX_SHADER_SOURCE: &str = "
+uniform mat4 view_projection;
+uniform vec3 box_min;
+uniform vec3 box_max;
+const int corners[36] = int[36](
+    0, 2, 6, 0, 6, 4,
+    1, 5, 7, 1, 7, 3,
+    0, 4, 5, 0, 5, 1,
+    2, 3, 7, 2, 7, 6,
+    0, 1, 3, 0, 3, 2,
+    4, 6, 7, 4, 7, 5);
+void main() {
+    int corner = corners[gl_VertexID];
+    vec3 position = mix(box_min, box_max, vec3(float(corner & 1), float((corner >> 1) & 1), float((corner >> 2) & 1)));
+    gl_Position = view_projection * vec4(position, 1.0);
+}
+";
+
+static FRAGMENT_SHADER_SOURCE: &str = "
+out vec4 frag_color;
+void main() {
+    frag_color = vec4(1.0);
+}
+";
+
+/// The options `OcclusionCuller::test` changes, restored afterwards.
+const TEST_OPTIONS: [RenderOption; 4] = [
+    RenderOption::ColorWrite(false),
+    RenderOption::DepthWrite(false),
+    RenderOption::DepthTest(true),
+    RenderOption::CullingEnabled(false)
+];
+
+/// The OpenGL defaults of `TEST_OPTIONS`, restored when the options were not known before the
+/// test.
+const DEFAULT_OPTIONS: [RenderOption; 4] = [
+    RenderOption::ColorWrite(true),
+    RenderOption::DepthWrite(true),
+    RenderOption::DepthTest(false),
+    RenderOption::CullingEnabled(false)
+];
+
+/// The query of an object and what is known of its visibility.
+#[derive(Debug)]
+struct OcclusionSlot {
+    query: Query,
+    /// Has the query been run without its result being read yet?
+    pending: bool,
+    /// Has the query been run at all?
+    issued: bool,
+    visible: bool
+}
+
+/// Occlusion culling with a query per object. Each frame, after drawing the occluders (or the
+/// depth pre-pass), the bounding boxes of the objects are tested against the depth buffer with
+/// `test`, without writing colors or depth. The results arrive with a delay, so an object is
+/// drawn based on the test of an earlier frame, or guarded with `draw_if_visible`, which lets
+/// the GPU skip the draws with conditional rendering as soon as the result is there:
+///
+///    let mut culler = OcclusionCuller::new(&mut context, meshes.len());
+///    ...
+///    culler.test(&mut renderer, &view_projection, &bounds);
+///    for (index, mesh) in meshes.iter().enumerate() {
+///        culler.draw_if_visible(&mut renderer, index, |renderer| mesh.draw(renderer));
+///    }
+///
+/// The objects are identified by their index in the bounds given to `test`, and there is a
+/// query for each, GL_ANY_SAMPLES_PASSED_CONSERVATIVE. A query still
+/// waiting for its result is not run again, so that testing never stalls; the object keeps its
+/// earlier visibility until the result arrives. Objects not tested yet are visible.
+#[derive(Debug)]
+pub struct OcclusionCuller {
+    program: ProgramHandle,
+    vertex_array: VertexArrayHandle,
+    view_projection_location: i32,
+    box_min_location: i32,
+    box_max_location: i32,
+    slots: Vec<OcclusionSlot>
+}
+
+impl OcclusionCuller {
+    /// Compile the bounding box program and create the queries for the given number of objects.
+    pub fn new(context: &mut Context, object_count: usize) -> OcclusionCuller {
+        let vertex_source = format!("{}{}", SHADER_HEADER, VERTEX_SHADER_SOURCE);
+        let fragment_source = format!("{}{}", SHADER_HEADER, FRAGMENT_SHADER_SOURCE);
+        let shaders = [
+            context.new_shader(ShaderType::VertexShader, &vertex_source),
+            context.new_shader(ShaderType::FragmentShader, &fragment_source)
+        ];
+        let program = context.new_program(&shaders);
+        let (view_projection_location, box_min_location, box_max_location) = {
+            let info = context.program_info(&program);
+            (info.get_uniform_location("view_projection"),
+             info.get_uniform_location("box_min"),
+             info.get_uniform_location("box_max"))
+        };
+        let mut culler = OcclusionCuller {
+            program,
+            vertex_array: context.new_vertex_array(&[], None),
+            view_projection_location,
+            box_min_location,
+            box_max_location,
+            slots: Vec::new()
+        };
+        culler.set_object_count(context, object_count);
+        culler
+    }
+
+    /// Change the number of objects, creating or dropping queries. The objects that remain keep
+    /// their visibility. The queries are conservative where
+    /// `ExtensionFeature::ConservativeOcclusionQuery` is supported.
+    pub fn set_object_count(&mut self, context: &mut Context, object_count: usize) {
+        self.slots.truncate(object_count);
+        let target = if context.get_info().supports(ExtensionFeature::ConservativeOcclusionQuery) {
+            QueryTarget::AnySamplesPassedConservative
+        }
+        else {
+            QueryTarget::AnySamplesPassed
+        };
+        while self.slots.len() < object_count {
+            let query = context.new_query(target);
+            self.slots.push(OcclusionSlot { query, pending: false, issued: false, visible: true });
+        }
+    }
+
+    /// The number of objects.
+    pub fn object_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Test the bounding boxes of the objects against the current depth buffer, each box given
+    /// as its minimum and maximum corner in world space. First reads the results that have
+    /// arrived since the last test. The color and depth writes are disabled, depth testing
+    /// enabled and culling disabled for the duration. The options are restored afterwards, to
+    /// the OpenGL defaults if they were not known. The program and the vertex array in use are changed. Panics if there
+    /// are more boxes than objects.
+    pub fn test(&mut self, renderer: &mut Renderer, view_projection: &[f32; 16], bounds: &[([f32; 3], [f32; 3])]) {
+        if bounds.len() > self.slots.len() {
+            panic!("{} bounding boxes given, but the occlusion culler has {} objects", bounds.len(), self.slots.len());
+        }
+        self.collect_results();
+        let previous_options: Vec<_> = TEST_OPTIONS.iter().zip(DEFAULT_OPTIONS.iter())
+            .map(|(&option, &default)| renderer.render_option(option).unwrap_or(default))
+            .collect();
+        for &option in TEST_OPTIONS.iter() {
+            renderer.set_option(option);
+        }
+        renderer.use_program(&self.program);
+        renderer.use_vertex_array(&self.vertex_array);
+        renderer.uniform_matrix(self.view_projection_location, 1, SimpleUniformTypeMatrix::Matrix4f, false, view_projection);
+        for (slot, &(min, max)) in self.slots.iter_mut().zip(bounds.iter()) {
+            if slot.pending {
+                continue;
+            }
+            renderer.uniform_f32(self.box_min_location, 1, SimpleUniformTypeFloat::Uniform3f, &min);
+            renderer.uniform_f32(self.box_max_location, 1, SimpleUniformTypeFloat::Uniform3f, &max);
+            renderer.with_query(&slot.query, |renderer| renderer.draw_arrays(PrimitiveMode::Triangles, 0, 36));
+            slot.pending = true;
+            slot.issued = true;
+        }
+        for &option in previous_options.iter() {
+            renderer.set_option(option);
+        }
+    }
+
+    /// Read the results of the queries that have arrived, without blocking. Done by `test`, but
+    /// can be called to update `is_visible` in between.
+    pub fn collect_results(&mut self) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.pending) {
+            if let Some(result) = slot.query.try_result() {
+                slot.visible = result != 0;
+                slot.pending = false;
+            }
+        }
+    }
+
+    /// Was the object visible in the latest test with a result? True for objects that have
+    /// not been tested yet.
+    pub fn is_visible(&self, object: usize) -> bool {
+        self.slots.get(object).is_none_or(|slot| slot.visible)
+    }
+
+    /// Run the drawing commands of the object, guarded by conditional rendering on its latest
+    /// query, so that the GPU skips the draws if the bounding box was found hidden. If the
+    /// result is not there yet, the object is drawn. See `Renderer::conditional_render`.
+    pub fn draw_if_visible<'a, F>(&self, renderer: &mut Renderer<'a>, object: usize, commands: F)
+            where F: FnOnce(&mut Renderer<'a>) {
+        match self.slots.get(object) {
+            Some(slot) if slot.issued => {
+                if slot.pending {
+                    renderer.conditional_render(&slot.query, ConditionalRenderMode::NoWait, commands);
+                }
+                else if slot.visible {
+                    commands(renderer);
+                }
+            },
+            _ => commands(renderer)
+        }
+    }
+}
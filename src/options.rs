@@ -15,24 +15,670 @@
 //! This module defined basic options or states that can be set in the GL context.
 //! Examples are clear color and back face culling. See `RenderOption`.
 
-use gl;
-use gl::types::GLenum;
+use std::mem;
+
+use gl::types::{GLenum,GLint,GLsizei,GLbitfield};
 
 /// Rendering options.
+#[derive(Clone,Copy,Debug,PartialEq)]
 pub enum RenderOption {
     /// glClearColor
     ClearColor(f32, f32, f32, f32),
+    /// glClearDepth
+    ClearDepth(f64),
     /// GL_DEPTH_TEST
     DepthTest(bool),
+    /// glDepthFunc
+    DepthFunction(DepthFunction),
     /// GL_CULL_FACE
-    CullingEnabled(bool)
+    CullingEnabled(bool),
+    /// GL_BLEND
+    Blending(bool),
+    /// glBlendFunc, the source and destination factors.
+    BlendFunction(BlendFactor, BlendFactor),
+    /// glBlendEquation
+    BlendEquation(BlendEquation),
+    /// glBlendColor, the constant color of the ConstantColor and ConstantAlpha blend factors.
+    BlendColor(f32, f32, f32, f32),
+    /// glDepthMask, whether depth values are written. Clearing through the renderer writes depth
+    /// values even if this is disabled.
+    DepthWrite(bool),
+    /// glColorMask with the same value for all the components, whether colors are written.
+    /// Clearing through the renderer writes colors even if this is disabled.
+    ColorWrite(bool),
+    /// GL_SCISSOR_TEST
+    ScissorTest(bool),
+    /// glScissor, the x and y of the lower left corner, the width and the height.
+    ScissorBox(i32, i32, u32, u32),
+    /// GL_PRIMITIVE_RESTART and glPrimitiveRestartIndex, the restart index or None to disable
+    /// restarting. OpenGL ES only has GL_PRIMITIVE_RESTART_FIXED_INDEX, which restarts at the
+    /// largest value of the index type, so there the index must be 0xFF, 0xFFFF or 0xFFFFFFFF,
+    /// matching the index type of the draws.
+    PrimitiveRestart(Option<u32>),
+    /// GL_LINE_SMOOTH. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    LineSmooth(bool),
+    /// GL_LINE_SMOOTH_HINT. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    LineSmoothHint(Hint),
+    /// GL_POLYGON_SMOOTH. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    PolygonSmooth(bool),
+    /// GL_POLYGON_SMOOTH_HINT. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    PolygonSmoothHint(Hint),
+    /// GL_POINT_SPRITE_COORD_ORIGIN, where gl_PointCoord has its origin. Not available on OpenGL
+    /// ES, where the origin is always the upper left corner.
+    #[cfg(not(feature = "es3"))]
+    PointSpriteCoordOrigin(PointSpriteOrigin),
+    /// GL_POINT_FADE_THRESHOLD_SIZE, the size below which multisampled points fade out instead
+    /// of getting smaller. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    PointFadeThreshold(f32)
+}
+
+/// Implementation quality hints. See glHint.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Hint {
+    /// GL_FASTEST
+    Fastest,
+    /// GL_NICEST
+    Nicest,
+    /// GL_DONT_CARE, the default.
+    DontCare
+}
+
+/// The origin of the point sprite coordinates. See GL_POINT_SPRITE_COORD_ORIGIN.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PointSpriteOrigin {
+    /// GL_LOWER_LEFT
+    LowerLeft,
+    /// GL_UPPER_LEFT, the OpenGL default.
+    UpperLeft
+}
+
+/// Remembers the render options set through the renderer, so that setting an option to the value
+/// it already has doesn't cause a call to OpenGL. As with `PixelStoreCache`, options that
+/// haven't been set yet are unknown and will always be set.
+pub struct RenderOptionCache {
+    /// The last value of each kind of option set.
+    options: Vec<RenderOption>
+}
+
+impl RenderOptionCache {
+    pub fn new() -> RenderOptionCache {
+        RenderOptionCache { options: Vec::new() }
+    }
+
+    /// Set a render option, unless it is known to be already set to the same value.
+    pub fn set(&mut self, option: RenderOption) {
+        let kind = mem::discriminant(&option);
+        match self.options.iter().position(|cached| mem::discriminant(cached) == kind) {
+            Some(index) => {
+                if self.options[index] == option {
+                    return;
+                }
+                self.options[index] = option;
+            },
+            None => self.options.push(option)
+        }
+        set_option(option);
+    }
+
+    /// The cached value of the same kind of option as the given one. None if the option hasn't
+    /// been set yet, or the cache has been invalidated since.
+    pub fn get(&self, option: RenderOption) -> Option<RenderOption> {
+        let kind = mem::discriminant(&option);
+        self.options.iter().find(|cached| mem::discriminant(*cached) == kind).cloned()
+    }
+
+    /// Set all the cached options again, so that OpenGL has the values the cache believes it has.
+    pub fn reapply(&self) {
+        for &option in self.options.iter() {
+            set_option(option);
+        }
+    }
+
+    /// Forget the cached values, so that the next `set` of each option calls OpenGL.
+    pub fn invalidate(&mut self) {
+        self.options.clear();
+    }
+}
+
+/// The values the buffers of the current surface are cleared to. Set it as the persistent
+/// clear state of the renderer with `Renderer::set_clear_state`, or clear with it once with
+/// `Renderer::clear_with`. The default clears the color to black and the depth to one, and
+/// leaves the stencil buffer alone.
+#[derive(Clone,Debug,PartialEq)]
+pub struct ClearState {
+    /// The clear color of each draw buffer, indexed by draw buffer. Color buffers are not cleared
+    /// if this is empty. With more than one color, each draw buffer is cleared separately with
+    /// glClearBufferfv.
+    pub colors: Vec<[f32; 4]>,
+    /// The draw buffers of integer formats and their clear values, as integer color buffers
+    /// can't be cleared with the float colors. Each is cleared with glClearBufferuiv or
+    /// glClearBufferiv.
+    pub integer_colors: Vec<(u32, IntegerClearValue)>,
+    /// The depth value, or None to not clear the depth buffer.
+    pub depth: Option<f64>,
+    /// The stencil value, or None to not clear the stencil buffer.
+    pub stencil: Option<i32>
+}
+
+impl Default for ClearState {
+    fn default() -> ClearState {
+        ClearState {
+            colors: vec![[0.0, 0.0, 0.0, 0.0]],
+            integer_colors: Vec::new(),
+            depth: Some(1.0),
+            stencil: None
+        }
+    }
+}
+
+/// The clear value of an integer color buffer, see `ClearState::integer_colors`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum IntegerClearValue {
+    /// For unsigned integer formats, like `TextureFormat::R32ui`.
+    Unsigned([u32; 4]),
+    /// For signed integer formats, like `TextureFormat::R32i`.
+    Signed([i32; 4])
+}
+
+/// Remembers the clear color, depth and stencil values set through this library, so that
+/// clearing with the same values again doesn't set them again. Like with `PixelStoreCache`,
+/// values that haven't been set yet are unknown.
+pub struct ClearValueCache {
+    color: Option<[f32; 4]>,
+    depth: Option<f64>,
+    stencil: Option<i32>
+}
+
+impl ClearValueCache {
+    pub fn new() -> ClearValueCache {
+        ClearValueCache { color: None, depth: None, stencil: None }
+    }
+
+    /// Clear the buffers of the current surface as described by the clear state.
+    pub fn clear(&mut self, state: &ClearState) {
+        let mut mask: GLbitfield = 0;
+        if state.colors.len() == 1 {
+            let color = state.colors[0];
+            if self.color != Some(color) {
+                unsafe {
+                    gl::ClearColor(color[0], color[1], color[2], color[3]);
+                }
+                self.color = Some(color);
+            }
+            mask |= gl::COLOR_BUFFER_BIT;
+        }
+        else {
+            for (draw_buffer, color) in state.colors.iter().enumerate() {
+                unsafe {
+                    gl::ClearBufferfv(gl::COLOR, draw_buffer as GLint, color.as_ptr());
+                }
+            }
+        }
+        for &(draw_buffer, value) in state.integer_colors.iter() {
+            unsafe {
+                match value {
+                    IntegerClearValue::Unsigned(value) => gl::ClearBufferuiv(gl::COLOR, draw_buffer as GLint, value.as_ptr()),
+                    IntegerClearValue::Signed(value) => gl::ClearBufferiv(gl::COLOR, draw_buffer as GLint, value.as_ptr())
+                }
+            }
+        }
+        if let Some(depth) = state.depth {
+            if self.depth != Some(depth) {
+                set_clear_depth(depth);
+                self.depth = Some(depth);
+            }
+            mask |= gl::DEPTH_BUFFER_BIT;
+        }
+        if let Some(stencil) = state.stencil {
+            if self.stencil != Some(stencil) {
+                unsafe {
+                    gl::ClearStencil(stencil);
+                }
+                self.stencil = Some(stencil);
+            }
+            mask |= gl::STENCIL_BUFFER_BIT;
+        }
+        if mask != 0 {
+            unsafe {
+                gl::Clear(mask);
+            }
+        }
+        check_error!();
+    }
+
+    /// Set the cached values again, so that OpenGL has the values the cache believes it has.
+    pub fn reapply(&self) {
+        if let Some(color) = self.color {
+            unsafe {
+                gl::ClearColor(color[0], color[1], color[2], color[3]);
+            }
+        }
+        if let Some(depth) = self.depth {
+            set_clear_depth(depth);
+        }
+        if let Some(stencil) = self.stencil {
+            unsafe {
+                gl::ClearStencil(stencil);
+            }
+        }
+        check_error!();
+    }
+
+    /// Forget the cached values, so that the next `clear` sets all of them.
+    pub fn invalidate(&mut self) {
+        self.color = None;
+        self.depth = None;
+        self.stencil = None;
+    }
+}
+
+/// Comparison functions for the depth test. See glDepthFunc.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum DepthFunction {
+    /// GL_NEVER
+    Never,
+    /// GL_LESS
+    Less,
+    /// GL_EQUAL
+    Equal,
+    /// GL_LEQUAL
+    LessOrEqual,
+    /// GL_GREATER
+    Greater,
+    /// GL_NOTEQUAL
+    NotEqual,
+    /// GL_GEQUAL
+    GreaterOrEqual,
+    /// GL_ALWAYS
+    Always
+}
+
+/// How the source and destination colors, multiplied by their factors, are combined when
+/// blending. See glBlendEquation.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum BlendEquation {
+    /// GL_FUNC_ADD, the default.
+    Add,
+    /// GL_FUNC_SUBTRACT, source minus destination.
+    Subtract,
+    /// GL_FUNC_REVERSE_SUBTRACT, destination minus source.
+    ReverseSubtract,
+    /// GL_MIN, ignoring the factors.
+    Min,
+    /// GL_MAX, ignoring the factors.
+    Max
+}
+
+/// Common combinations of the blending and depth options, set with `Renderer::set_blend_preset`
+/// or for the duration of a pass with `PassDesc::blend_preset`. All the presets test depth;
+/// the blended ones don't write it, so that transparent surfaces don't hide what is drawn
+/// behind them later.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum BlendPreset {
+    /// No blending, depth written. For opaque geometry.
+    Opaque,
+    /// `source * source alpha + destination * (1 - source alpha)`. For colors with straight,
+    /// non-premultiplied alpha; draw back to front.
+    AlphaBlend,
+    /// `source + destination`. For light-like effects such as fire and glows, which can be
+    /// drawn in any order.
+    Additive,
+    /// `source + destination * (1 - source alpha)`. For colors with premultiplied alpha, which
+    /// filter correctly and can also be additive where alpha is zero; draw back to front.
+    Premultiplied
+}
+
+impl BlendPreset {
+    /// The render options of the preset: blending, the blend equation and function, the depth
+    /// test and depth writes.
+    pub fn options(self) -> [RenderOption; 5] {
+        let (blending, source, destination) = match self {
+            BlendPreset::Opaque => (false, BlendFactor::One, BlendFactor::Zero),
+            BlendPreset::AlphaBlend => (true, BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+            BlendPreset::Additive => (true, BlendFactor::One, BlendFactor::One),
+            BlendPreset::Premultiplied => (true, BlendFactor::One, BlendFactor::OneMinusSrcAlpha)
+        };
+        [
+            RenderOption::Blending(blending),
+            RenderOption::BlendEquation(BlendEquation::Add),
+            RenderOption::BlendFunction(source, destination),
+            RenderOption::DepthTest(true),
+            RenderOption::DepthWrite(!blending)
+        ]
+    }
+}
+
+/// Factors for combining the source and destination colors when blending. See glBlendFunc.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum BlendFactor {
+    /// GL_ZERO
+    Zero,
+    /// GL_ONE
+    One,
+    /// GL_SRC_COLOR
+    SrcColor,
+    /// GL_ONE_MINUS_SRC_COLOR
+    OneMinusSrcColor,
+    /// GL_DST_COLOR
+    DstColor,
+    /// GL_ONE_MINUS_DST_COLOR
+    OneMinusDstColor,
+    /// GL_SRC_ALPHA
+    SrcAlpha,
+    /// GL_ONE_MINUS_SRC_ALPHA
+    OneMinusSrcAlpha,
+    /// GL_DST_ALPHA
+    DstAlpha,
+    /// GL_ONE_MINUS_DST_ALPHA
+    OneMinusDstAlpha,
+    /// GL_CONSTANT_COLOR
+    ConstantColor,
+    /// GL_ONE_MINUS_CONSTANT_COLOR
+    OneMinusConstantColor,
+    /// GL_CONSTANT_ALPHA
+    ConstantAlpha,
+    /// GL_ONE_MINUS_CONSTANT_ALPHA
+    OneMinusConstantAlpha,
+    /// GL_SRC_ALPHA_SATURATE
+    SrcAlphaSaturate
+}
+
+/// The origin of the window coordinates. See glClipControl.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ClipOrigin {
+    /// GL_LOWER_LEFT, the OpenGL default.
+    LowerLeft,
+    /// GL_UPPER_LEFT
+    UpperLeft
+}
+
+/// The clip space depth range. See glClipControl.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ClipDepth {
+    /// GL_NEGATIVE_ONE_TO_ONE, the OpenGL default.
+    NegativeOneToOne,
+    /// GL_ZERO_TO_ONE
+    ZeroToOne
+}
+
+/// Pixel storage modes, affecting how pixel data is read from client memory when uploading
+/// (unpacking) and written to client memory when reading back (packing). See glPixelStore.
+///
+/// For example, tightly packed RGB8 image data requires `UnpackAlignment(1)`, as the default
+/// alignment of 4 bytes would otherwise skew the rows of the image.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PixelStoreParam {
+    /// GL_UNPACK_ALIGNMENT, must be 1, 2, 4 or 8.
+    UnpackAlignment(u8),
+    /// GL_UNPACK_ROW_LENGTH
+    UnpackRowLength(u32),
+    /// GL_UNPACK_IMAGE_HEIGHT
+    UnpackImageHeight(u32),
+    /// GL_UNPACK_SKIP_PIXELS
+    UnpackSkipPixels(u32),
+    /// GL_UNPACK_SKIP_ROWS
+    UnpackSkipRows(u32),
+    /// GL_UNPACK_SKIP_IMAGES
+    UnpackSkipImages(u32),
+    /// GL_UNPACK_SWAP_BYTES, for data that is not in the native byte order. Not available on
+    /// OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    UnpackSwapBytes(bool),
+    /// GL_PACK_ALIGNMENT, must be 1, 2, 4 or 8.
+    PackAlignment(u8),
+    /// GL_PACK_ROW_LENGTH
+    PackRowLength(u32),
+    /// GL_PACK_IMAGE_HEIGHT. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    PackImageHeight(u32),
+    /// GL_PACK_SKIP_PIXELS
+    PackSkipPixels(u32),
+    /// GL_PACK_SKIP_ROWS
+    PackSkipRows(u32),
+    /// GL_PACK_SKIP_IMAGES. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    PackSkipImages(u32),
+    /// GL_PACK_SWAP_BYTES. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    PackSwapBytes(bool)
+}
+
+/// Remembers the pixel store parameters set through this library, so that setting a parameter to
+/// the value it already has doesn't cause a call to OpenGL. Parameters that haven't been set yet
+/// are considered unknown and will always be set.
+pub struct PixelStoreCache {
+    values: Vec<(GLenum, GLint)>
+}
+
+impl PixelStoreCache {
+    pub fn new() -> PixelStoreCache {
+        PixelStoreCache { values: Vec::new() }
+    }
+
+    /// Set a pixel store parameter, unless it is known to be already set to the same value.
+    pub fn set(&mut self, param: PixelStoreParam) {
+        let (pname, value) = pixel_store_param_to_gl(param);
+        match self.values.iter().position(|&(cached_pname, _)| cached_pname == pname) {
+            Some(index) => {
+                if self.values[index].1 == value {
+                    return;
+                }
+                self.values[index].1 = value;
+            },
+            None => self.values.push((pname, value))
+        }
+        unsafe {
+            gl::PixelStorei(pname, value);
+        }
+        check_error!();
+    }
+
+    /// Set the cached parameters again, so that OpenGL has the values the cache believes it has.
+    pub fn reapply(&self) {
+        for &(pname, value) in self.values.iter() {
+            unsafe {
+                gl::PixelStorei(pname, value);
+            }
+        }
+        check_error!();
+    }
+
+    /// Forget the cached values, so that the next `set` of each parameter calls glPixelStorei.
+    pub fn invalidate(&mut self) {
+        self.values.clear();
+    }
+}
+
+fn pixel_store_param_to_gl(param: PixelStoreParam) -> (GLenum, GLint) {
+    match param {
+        PixelStoreParam::UnpackAlignment(alignment) => (gl::UNPACK_ALIGNMENT, validate_alignment(alignment)),
+        PixelStoreParam::UnpackRowLength(value) => (gl::UNPACK_ROW_LENGTH, value as GLint),
+        PixelStoreParam::UnpackImageHeight(value) => (gl::UNPACK_IMAGE_HEIGHT, value as GLint),
+        PixelStoreParam::UnpackSkipPixels(value) => (gl::UNPACK_SKIP_PIXELS, value as GLint),
+        PixelStoreParam::UnpackSkipRows(value) => (gl::UNPACK_SKIP_ROWS, value as GLint),
+        PixelStoreParam::UnpackSkipImages(value) => (gl::UNPACK_SKIP_IMAGES, value as GLint),
+        #[cfg(not(feature = "es3"))]
+        PixelStoreParam::UnpackSwapBytes(swap) => (gl::UNPACK_SWAP_BYTES, swap as GLint),
+        PixelStoreParam::PackAlignment(alignment) => (gl::PACK_ALIGNMENT, validate_alignment(alignment)),
+        PixelStoreParam::PackRowLength(value) => (gl::PACK_ROW_LENGTH, value as GLint),
+        #[cfg(not(feature = "es3"))]
+        PixelStoreParam::PackImageHeight(value) => (gl::PACK_IMAGE_HEIGHT, value as GLint),
+        PixelStoreParam::PackSkipPixels(value) => (gl::PACK_SKIP_PIXELS, value as GLint),
+        PixelStoreParam::PackSkipRows(value) => (gl::PACK_SKIP_ROWS, value as GLint),
+        #[cfg(not(feature = "es3"))]
+        PixelStoreParam::PackSkipImages(value) => (gl::PACK_SKIP_IMAGES, value as GLint),
+        #[cfg(not(feature = "es3"))]
+        PixelStoreParam::PackSwapBytes(swap) => (gl::PACK_SWAP_BYTES, swap as GLint)
+    }
+}
+
+fn validate_alignment(alignment: u8) -> GLint {
+    match alignment {
+        1 | 2 | 4 | 8 => alignment as GLint,
+        _ => panic!("Invalid pixel store alignment {}, must be 1, 2, 4 or 8", alignment)
+    }
 }
 
 pub fn set_option(option: RenderOption) {
     match option {
         RenderOption::ClearColor(r, g, b, a) => unsafe { gl::ClearColor(r, g, b, a) },
+        RenderOption::ClearDepth(depth) => set_clear_depth(depth),
         RenderOption::DepthTest(enable) => set_capability(gl::DEPTH_TEST, enable),
-        RenderOption::CullingEnabled(enable) => set_capability(gl::CULL_FACE, enable)
+        RenderOption::DepthFunction(function) => unsafe { gl::DepthFunc(depth_function_to_enum(function)) },
+        RenderOption::CullingEnabled(enable) => set_capability(gl::CULL_FACE, enable),
+        RenderOption::Blending(enable) => set_capability(gl::BLEND, enable),
+        RenderOption::BlendFunction(source, destination) => unsafe {
+            gl::BlendFunc(blend_factor_to_enum(source), blend_factor_to_enum(destination))
+        },
+        RenderOption::BlendEquation(equation) => unsafe { gl::BlendEquation(blend_equation_to_enum(equation)) },
+        RenderOption::BlendColor(r, g, b, a) => unsafe { gl::BlendColor(r, g, b, a) },
+        RenderOption::DepthWrite(enable) => unsafe { gl::DepthMask(if enable { gl::TRUE } else { gl::FALSE }) },
+        RenderOption::ColorWrite(enable) => unsafe {
+            let enable = if enable { gl::TRUE } else { gl::FALSE };
+            gl::ColorMask(enable, enable, enable, enable)
+        },
+        RenderOption::ScissorTest(enable) => set_capability(gl::SCISSOR_TEST, enable),
+        RenderOption::ScissorBox(x, y, width, height) => unsafe {
+            gl::Scissor(x, y, width as GLsizei, height as GLsizei)
+        },
+        RenderOption::PrimitiveRestart(restart_index) => set_primitive_restart(restart_index),
+        #[cfg(not(feature = "es3"))]
+        RenderOption::LineSmooth(enable) => set_capability(gl::LINE_SMOOTH, enable),
+        #[cfg(not(feature = "es3"))]
+        RenderOption::LineSmoothHint(hint) => unsafe { gl::Hint(gl::LINE_SMOOTH_HINT, hint_to_enum(hint)) },
+        #[cfg(not(feature = "es3"))]
+        RenderOption::PolygonSmooth(enable) => set_capability(gl::POLYGON_SMOOTH, enable),
+        #[cfg(not(feature = "es3"))]
+        RenderOption::PolygonSmoothHint(hint) => unsafe { gl::Hint(gl::POLYGON_SMOOTH_HINT, hint_to_enum(hint)) },
+        #[cfg(not(feature = "es3"))]
+        RenderOption::PointSpriteCoordOrigin(origin) => {
+            let origin = match origin {
+                PointSpriteOrigin::LowerLeft => gl::LOWER_LEFT,
+                PointSpriteOrigin::UpperLeft => gl::UPPER_LEFT
+            };
+            unsafe { gl::PointParameteri(gl::POINT_SPRITE_COORD_ORIGIN, origin as GLint) }
+        },
+        #[cfg(not(feature = "es3"))]
+        RenderOption::PointFadeThreshold(size) => unsafe { gl::PointParameterf(gl::POINT_FADE_THRESHOLD_SIZE, size) }
+    }
+    check_error!();
+}
+
+fn blend_equation_to_enum(equation: BlendEquation) -> GLenum {
+    match equation {
+        BlendEquation::Add => gl::FUNC_ADD,
+        BlendEquation::Subtract => gl::FUNC_SUBTRACT,
+        BlendEquation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+        BlendEquation::Min => gl::MIN,
+        BlendEquation::Max => gl::MAX
+    }
+}
+
+#[cfg(not(feature = "es3"))]
+fn hint_to_enum(hint: Hint) -> GLenum {
+    match hint {
+        Hint::Fastest => gl::FASTEST,
+        Hint::Nicest => gl::NICEST,
+        Hint::DontCare => gl::DONT_CARE
+    }
+}
+
+#[cfg(not(feature = "es3"))]
+fn set_clear_depth(depth: f64) {
+    unsafe {
+        gl::ClearDepth(depth);
+    }
+}
+
+/// OpenGL ES only has the single precision variant.
+#[cfg(feature = "es3")]
+fn set_clear_depth(depth: f64) {
+    unsafe {
+        gl::ClearDepthf(depth as f32);
+    }
+}
+
+/// Set the clip control state. The caller is responsible for checking that glClipControl is
+/// actually available.
+pub fn set_clip_control(origin: ClipOrigin, depth: ClipDepth) {
+    let origin = match origin {
+        ClipOrigin::LowerLeft => gl::LOWER_LEFT,
+        ClipOrigin::UpperLeft => gl::UPPER_LEFT
+    };
+    let depth = match depth {
+        ClipDepth::NegativeOneToOne => gl::NEGATIVE_ONE_TO_ONE,
+        ClipDepth::ZeroToOne => gl::ZERO_TO_ONE
+    };
+    unsafe {
+        gl::ClipControl(origin, depth);
+    }
+    check_error!();
+}
+
+/// Enable primitive restart with the given restart index, or disable it.
+#[cfg(not(feature = "es3"))]
+fn set_primitive_restart(restart_index: Option<u32>) {
+    unsafe {
+        match restart_index {
+            Some(index) => {
+                gl::Enable(gl::PRIMITIVE_RESTART);
+                gl::PrimitiveRestartIndex(index);
+            },
+            None => gl::Disable(gl::PRIMITIVE_RESTART)
+        }
+    }
+}
+
+/// OpenGL ES restarts primitives only at the largest value of the index type, so the index
+/// must be one of those.
+#[cfg(feature = "es3")]
+fn set_primitive_restart(restart_index: Option<u32>) {
+    match restart_index {
+        Some(index) => {
+            if index != u8::MAX as u32 && index != u16::MAX as u32 && index != u32::MAX {
+                panic!("OpenGL ES only restarts primitives at the largest index value, not at {:#x}", index);
+            }
+            set_capability(gl::PRIMITIVE_RESTART_FIXED_INDEX, true);
+        },
+        None => set_capability(gl::PRIMITIVE_RESTART_FIXED_INDEX, false)
+    }
+}
+
+pub fn depth_function_to_enum(function: DepthFunction) -> GLenum {
+    match function {
+        DepthFunction::Never => gl::NEVER,
+        DepthFunction::Less => gl::LESS,
+        DepthFunction::Equal => gl::EQUAL,
+        DepthFunction::LessOrEqual => gl::LEQUAL,
+        DepthFunction::Greater => gl::GREATER,
+        DepthFunction::NotEqual => gl::NOTEQUAL,
+        DepthFunction::GreaterOrEqual => gl::GEQUAL,
+        DepthFunction::Always => gl::ALWAYS
+    }
+}
+
+fn blend_factor_to_enum(factor: BlendFactor) -> GLenum {
+    match factor {
+        BlendFactor::Zero => gl::ZERO,
+        BlendFactor::One => gl::ONE,
+        BlendFactor::SrcColor => gl::SRC_COLOR,
+        BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+        BlendFactor::DstColor => gl::DST_COLOR,
+        BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+        BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+        BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+        BlendFactor::DstAlpha => gl::DST_ALPHA,
+        BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+        BlendFactor::ConstantColor => gl::CONSTANT_COLOR,
+        BlendFactor::OneMinusConstantColor => gl::ONE_MINUS_CONSTANT_COLOR,
+        BlendFactor::ConstantAlpha => gl::CONSTANT_ALPHA,
+        BlendFactor::OneMinusConstantAlpha => gl::ONE_MINUS_CONSTANT_ALPHA,
+        BlendFactor::SrcAlphaSaturate => gl::SRC_ALPHA_SATURATE
     }
 }
 
@@ -0,0 +1,146 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Packing of four component vectors into the single 32-bit values read by
+//! `VertexAttributeType::Int2101010Rev` and `VertexAttributeType::UnsignedInt2101010Rev`
+//! attributes. The x, y and z components take ten bits each starting from the least significant
+//! bits, and w takes the two most significant bits.
+//!
+//! The signed values are meant for normalized attributes, so that normals and tangents take four
+//! bytes instead of twelve or sixteen. They use the signed normalization rules of OpenGL 4.2 and
+//! OpenGL ES 3.0, where -1.0 and 1.0 are both exactly representable and the two bit w component
+//! has only the values -1.0, 0.0 and 1.0.
+
+/// Pack four components in range [-1.0, 1.0] into an `Int2101010Rev` value. The components are
+/// clamped and rounded to the nearest representable value.
+pub fn pack_snorm_2101010(x: f32, y: f32, z: f32, w: f32) -> u32 {
+    pack_snorm(x, 10) | (pack_snorm(y, 10) << 10) | (pack_snorm(z, 10) << 20) | (pack_snorm(w, 2) << 30)
+}
+
+/// Unpack an `Int2101010Rev` value into the four components the GPU reads from a normalized
+/// attribute.
+pub fn unpack_snorm_2101010(value: u32) -> [f32; 4] {
+    [
+        unpack_snorm(value, 10),
+        unpack_snorm(value >> 10, 10),
+        unpack_snorm(value >> 20, 10),
+        unpack_snorm(value >> 30, 2)
+    ]
+}
+
+/// Pack four components in range [0.0, 1.0] into an `UnsignedInt2101010Rev` value, for example
+/// a color with a coarse alpha. The components are clamped and rounded to the nearest
+/// representable value.
+pub fn pack_unorm_2101010(x: f32, y: f32, z: f32, w: f32) -> u32 {
+    pack_unorm(x, 10) | (pack_unorm(y, 10) << 10) | (pack_unorm(z, 10) << 20) | (pack_unorm(w, 2) << 30)
+}
+
+/// Unpack an `UnsignedInt2101010Rev` value into the four components the GPU reads from a
+/// normalized attribute.
+pub fn unpack_unorm_2101010(value: u32) -> [f32; 4] {
+    [
+        unpack_unorm(value, 10),
+        unpack_unorm(value >> 10, 10),
+        unpack_unorm(value >> 20, 10),
+        unpack_unorm(value >> 30, 2)
+    ]
+}
+
+/// Pack a normal, with w as zero. The normal is normalized first, a zero vector stays zero.
+pub fn pack_normal(normal: [f32; 3]) -> u32 {
+    let [x, y, z] = normalize(normal);
+    pack_snorm_2101010(x, y, z, 0.0)
+}
+
+/// Pack a tangent and the handedness of the bitangent, as in the four component tangents of
+/// glTF. The tangent is normalized first and w is packed as -1.0 if it's negative and 1.0
+/// otherwise.
+pub fn pack_tangent(tangent: [f32; 4]) -> u32 {
+    let [x, y, z] = normalize([tangent[0], tangent[1], tangent[2]]);
+    let handedness = if tangent[3] < 0.0 { -1.0 } else { 1.0 };
+    pack_snorm_2101010(x, y, z, handedness)
+}
+
+/// Pack a slice of normals, see `pack_normal`.
+pub fn pack_normals(normals: &[[f32; 3]]) -> Vec<u32> {
+    normals.iter().map(|&normal| pack_normal(normal)).collect()
+}
+
+/// Pack a slice of tangents, see `pack_tangent`.
+pub fn pack_tangents(tangents: &[[f32; 4]]) -> Vec<u32> {
+    tangents.iter().map(|&tangent| pack_tangent(tangent)).collect()
+}
+
+fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt();
+    if length > 0.0 {
+        [vector[0] / length, vector[1] / length, vector[2] / length]
+    }
+    else {
+        [0.0; 3]
+    }
+}
+
+fn pack_snorm(value: f32, bits: u32) -> u32 {
+    let max = ((1 << (bits - 1)) - 1) as f32;
+    let value = if value.is_nan() { 0.0 } else { value.clamp(-1.0, 1.0) };
+    ((value * max).round() as i32 as u32) & ((1 << bits) - 1)
+}
+
+fn unpack_snorm(value: u32, bits: u32) -> f32 {
+    let max = ((1 << (bits - 1)) - 1) as f32;
+    // Move the sign bit of the field to the sign bit of the i32 and shift back to extend it
+    let shift = 32 - bits;
+    let signed = ((value << shift) as i32) >> shift;
+    (signed as f32 / max).max(-1.0)
+}
+
+fn pack_unorm(value: f32, bits: u32) -> u32 {
+    let max = ((1 << bits) - 1) as f32;
+    let value = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+    (value * max).round() as u32
+}
+
+fn unpack_unorm(value: u32, bits: u32) -> f32 {
+    let max = (1 << bits) - 1;
+    (value & max) as f32 / max as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snorm_components_go_to_their_bits() {
+        assert_eq!(pack_snorm_2101010(1.0, 0.0, 0.0, 0.0), 0x0000_01ff);
+        assert_eq!(pack_snorm_2101010(0.0, -1.0, 0.0, 0.0), 0x0008_0400);
+        assert_eq!(pack_snorm_2101010(0.0, 0.0, 0.0, -1.0), 0xc000_0000);
+        assert_eq!(unpack_snorm_2101010(0x0000_0200), [-1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(unpack_snorm_2101010(pack_snorm_2101010(2.0, -2.0, f32::NAN, 1.0)), [1.0, -1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn unorm_components_go_to_their_bits() {
+        assert_eq!(pack_unorm_2101010(1.0, 0.0, 0.0, 0.0), 0x0000_03ff);
+        assert_eq!(pack_unorm_2101010(0.0, 0.0, 1.0, 1.0), 0xfff0_0000);
+        assert_eq!(unpack_unorm_2101010(pack_unorm_2101010(0.5, -1.0, 2.0, 1.0 / 3.0)), [512.0 / 1023.0, 0.0, 1.0, 1.0 / 3.0]);
+    }
+
+    #[test]
+    fn normals_and_tangents_are_normalized() {
+        assert_eq!(pack_normal([0.0, 0.0, 5.0]), pack_snorm_2101010(0.0, 0.0, 1.0, 0.0));
+        assert_eq!(pack_normal([0.0; 3]), 0);
+        assert_eq!(pack_tangent([3.0, 0.0, 0.0, -0.5]), pack_snorm_2101010(1.0, 0.0, 0.0, -1.0));
+        assert_eq!(pack_tangent([0.0, 2.0, 0.0, 0.0]), pack_snorm_2101010(0.0, 1.0, 0.0, 1.0));
+    }
+}
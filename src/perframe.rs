@@ -0,0 +1,99 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Uniform buffers that are rewritten every frame, see `PerFrameUbo`.
+
+use std::mem::size_of_val;
+
+use super::{Context,BufferHandle};
+use super::renderer::Renderer;
+use super::sync::Fence;
+
+/// A uniform buffer for block data that changes every frame, for example the camera matrices.
+///
+/// Writing a uniform buffer the GPU is still reading for the previous frame either stalls or
+/// corrupts that frame. To avoid this, the buffer is split into one region per frame in
+/// flight, and each frame writes and binds its own region. When a frame ends, a fence is
+/// inserted for its region, and the region isn't written again before the GPU has passed the
+/// fence.
+///
+/// Each frame, `write` the data, bind it with `bind`, draw, and call `end_frame`.
+#[derive(Debug)]
+pub struct PerFrameUbo {
+    buffer: BufferHandle,
+    block_size: usize,
+    region_size: usize,
+    fences: Vec<Option<Fence>>,
+    current: usize
+}
+
+impl PerFrameUbo {
+    /// Create the buffer for blocks of the given size in bytes, with as many regions as there
+    /// are frames in flight. The regions are aligned according to the uniform buffer offset
+    /// alignment of the context.
+    pub fn new(context: &mut Context, block_size: usize, frames_in_flight: usize) -> PerFrameUbo {
+        if frames_in_flight == 0 {
+            panic!("A per-frame uniform buffer needs at least one frame in flight");
+        }
+        let region_size = context.get_info().uniform_buffer.align_offset(block_size);
+        let buffer = context.new_buffer();
+        context.edit_uniform_buffer(&buffer).data(&vec![0u8; region_size * frames_in_flight]);
+        PerFrameUbo {
+            buffer,
+            block_size,
+            region_size,
+            fences: (0..frames_in_flight).map(|_| None).collect(),
+            current: 0
+        }
+    }
+
+    /// The buffer containing all the regions.
+    pub fn buffer(&self) -> &BufferHandle {
+        &self.buffer
+    }
+
+    /// The offset and size of the region of the current frame, for binding the region yourself.
+    pub fn binding_range(&self) -> (usize, usize) {
+        (self.current * self.region_size, self.block_size)
+    }
+
+    /// Write the block data of the current frame. If the GPU may still be reading the region
+    /// for an earlier frame, waits until it's done. Panics if the data is larger than the block
+    /// size.
+    pub fn write<D>(&mut self, context: &mut Context, data: &[D]) {
+        let data_size = size_of_val(data);
+        if data_size > self.block_size {
+            panic!("Writing {} bytes to a per-frame uniform buffer of {} byte blocks", data_size, self.block_size);
+        }
+        if let Some(fence) = self.fences[self.current].take() {
+            fence.wait();
+        }
+        let (offset, _) = self.binding_range();
+        context.edit_uniform_buffer(&self.buffer).sub_data_aligned(data, offset);
+    }
+
+    /// Bind the region of the current frame to the uniform buffer binding point. See
+    /// `Renderer::use_uniform_buffer_range`.
+    pub fn bind(&self, renderer: &mut Renderer, binding: u32) {
+        let (offset, size) = self.binding_range();
+        renderer.use_uniform_buffer_range(binding, &self.buffer, offset, size);
+    }
+
+    /// End the frame: fence the region of the current frame, as the GPU may still be reading
+    /// it, and move on to the next region.
+    pub fn end_frame(&mut self, context: &mut Context) {
+        self.fences[self.current] = Some(context.insert_fence());
+        self.current = (self.current + 1) % self.fences.len();
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Picking objects by the pixel under the cursor, see `Picker`.
+
+use std::rc::Rc;
+use std::slice;
+
+use super::context::ContextEditingSupport;
+use super::{Context,BufferHandle,ProgramHandle,RenderTargetHandle,TextureHandle};
+use super::handle::HandleAccess;
+use super::framebuffer::{RenderTarget,RenderTargetError};
+use super::options::{ClearState,IntegerClearValue,PixelStoreParam};
+use super::program::SimpleUniformTypeU32;
+use super::renderer::{Renderer,RenderPass,PassTarget,PassDesc};
+use super::sync::Fence;
+use super::targetpool;
+use super::texture::{TextureFormat,TextureFilter};
+
+/// The name of the object id uniform of the `htgl/picking.glsl` built-in source.
+pub const PICKING_ID_UNIFORM: &str = "htgl_object_id";
+
+/// Finds out which object is drawn at a pixel, for selecting objects with the mouse in editors
+/// and such. The objects are drawn into an R32UI texture with their ids instead of colors, with
+/// a depth texture so that the nearest object wins, and the id under the pixel is read back
+/// through a pixel pack buffer.
+///
+/// Draw the objects in the pass begun by `pass`, with a fragment shader that includes
+/// `htgl/picking.glsl` and calls `htgl_write_object_id`, setting the id of each object with
+/// `set_object_id`. Ids start from one, zero is the background.
+///
+/// `pick` reads the id right away, waiting for the GPU to finish drawing. To avoid the stall,
+/// `request_pick` after drawing and get the id with `pick_result` a frame later.
+#[derive(Debug)]
+pub struct Picker {
+    width: u32,
+    height: u32,
+    render_target: RenderTargetHandle,
+    id_texture: TextureHandle,
+    depth_texture: TextureHandle,
+    buffer: BufferHandle,
+    pending: Option<Fence>
+}
+
+impl Picker {
+    /// Create the textures and the render target of the size, usually that of the window.
+    pub fn new(context: &mut Context, width: u32, height: u32) -> Result<Picker, RenderTargetError> {
+        let id_texture = targetpool::new_target_texture(context, TextureFormat::R32ui, width, height, TextureFilter::Nearest);
+        let depth_texture = targetpool::new_target_texture(context, TextureFormat::DepthComponent24, width, height, TextureFilter::Nearest);
+        let render_target = context.new_render_target(slice::from_ref(&id_texture), Some(&depth_texture))?;
+        let buffer = context.new_buffer();
+        buffer.access().allocate_for_reading(4);
+        Ok(Picker {
+            width,
+            height,
+            render_target,
+            id_texture,
+            depth_texture,
+            buffer,
+            pending: None
+        })
+    }
+
+    /// Recreate the textures in the new size, if it differs from the current one. A pending
+    /// pick is dropped.
+    pub fn resize(&mut self, context: &mut Context, width: u32, height: u32) -> Result<(), RenderTargetError> {
+        if (width, height) != (self.width, self.height) {
+            *self = Picker::new(context, width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Begin a render pass drawing into the picker, with the ids cleared to zero and the depth
+    /// to one.
+    pub fn pass<'r, 'a>(&self, renderer: &'r mut Renderer<'a>) -> RenderPass<'r, 'a> {
+        let clear = ClearState {
+            colors: Vec::new(),
+            integer_colors: vec![(0, IntegerClearValue::Unsigned([0; 4]))],
+            depth: Some(1.0),
+            stencil: None
+        };
+        renderer.pass(self, &PassDesc::new().label("Picking").clear(clear))
+    }
+
+    /// The location of the object id uniform in the program, -1 if the program has none.
+    pub fn id_location(context: &Context, program: &ProgramHandle) -> i32 {
+        context.program_info(program).get_uniform_location(PICKING_ID_UNIFORM)
+    }
+
+    /// Set the id of the objects drawn next, to the uniform at the location (see
+    /// `id_location`) of the program in use.
+    pub fn set_object_id(renderer: &mut Renderer, location: i32, id: u32) {
+        renderer.uniform_u32(location, 1, SimpleUniformTypeU32::Uniform1u, &[id]);
+    }
+
+    /// The id of the object drawn at the pixel, counting from the lower left corner, or None
+    /// if nothing was. Waits for the GPU to finish drawing. Replaces a pending `request_pick`.
+    pub fn pick(&mut self, context: &mut Context, x: u32, y: u32) -> Option<u32> {
+        self.request_pick(context, x, y);
+        if let Some(fence) = self.pending.take() {
+            fence.wait();
+        }
+        self.read_id()
+    }
+
+    /// Start reading the id at the pixel asynchronously, replacing the pending request if any.
+    /// Panics if the pixel is outside the picker.
+    pub fn request_pick(&mut self, context: &mut Context, x: u32, y: u32) {
+        if x >= self.width || y >= self.height {
+            panic!("Picking pixel ({}, {}) outside the picker of {}x{}", x, y, self.width, self.height);
+        }
+        context.renderer().use_read_framebuffer(self);
+        context.set_pixel_store(PixelStoreParam::PackAlignment(4));
+        self.buffer.access().read_pixels(x as i32, y as i32, 1, 1, gl::RED_INTEGER, gl::UNSIGNED_INT);
+        self.pending = Some(context.insert_fence());
+    }
+
+    /// The result of the last `request_pick` if the GPU is done with it: Some(None) if nothing
+    /// was drawn at the pixel. None if the result is not ready yet, or there is no pending
+    /// request.
+    pub fn pick_result(&mut self) -> Option<Option<u32>> {
+        if !self.pending.as_ref().is_some_and(|fence| fence.is_signaled()) {
+            return None;
+        }
+        self.pending = None;
+        Some(self.read_id())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn render_target(&self) -> &RenderTargetHandle {
+        &self.render_target
+    }
+
+    /// The R32UI texture of the ids.
+    pub fn id_texture(&self) -> &TextureHandle {
+        &self.id_texture
+    }
+
+    pub fn depth_texture(&self) -> &TextureHandle {
+        &self.depth_texture
+    }
+
+    fn read_id(&self) -> Option<u32> {
+        let bytes = self.buffer.access().read_back();
+        match u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) {
+            0 => None,
+            id => Some(id)
+        }
+    }
+}
+
+impl PassTarget for Picker {
+    fn render_target(&self) -> Option<&Rc<RenderTarget>> {
+        Some(self.render_target.rc())
+    }
+
+    fn dimensions(&self) -> Option<(u32, u32)> {
+        Some((self.width, self.height))
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Viewing plain data as bytes for uploading, see `Pod`.
+
+use std::mem;
+use std::slice;
+
+/// Plain old data: values that can be viewed as their bytes in memory, for uploading them into
+/// buffers. Implemented for the numeric types and arrays of them.
+///
+/// Implement it for a vertex or a uniform block struct to upload values of it, for example with
+/// `VertexInterleaver::stream` or `ShadowedUbo::write`:
+///
+///    #[repr(C)]
+///    #[derive(Clone,Copy)]
+///    struct Vertex {
+///        position: [f32; 3],
+///        color: [u8; 4]
+///    }
+///
+///    unsafe impl Pod for Vertex {}
+///
+/// # Safety
+///
+/// Every byte of a value of the type must be initialized: the type must be `#[repr(C)]` (or
+/// `#[repr(transparent)]`), its fields all `Pod`, and it must have no padding between the
+/// fields or after the last one. Reading the padding bytes of a value is undefined behavior.
+pub unsafe trait Pod: Copy + 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// View the values as their bytes in memory.
+pub fn as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    // Pod types have no padding, so all the bytes are initialized
+    unsafe { slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::as_bytes;
+
+    #[test]
+    fn bytes_in_memory_order() {
+        let values: [u16; 2] = [0x0102, 0x0304];
+        let expected: Vec<u8> = values.iter().flat_map(|value| value.to_ne_bytes()).collect();
+        assert_eq!(as_bytes(&values[..]), &expected[..]);
+    }
+
+    #[test]
+    fn arrays() {
+        let values = [[1.0f32, 2.0], [3.0, 4.0]];
+        assert_eq!(as_bytes(&values[..]).len(), 16);
+        assert_eq!(&as_bytes(&values[..])[4..8], &2.0f32.to_ne_bytes()[..]);
+    }
+}
@@ -12,17 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::iter::repeat;
+use std::fmt;
 
-use gl;
+#[cfg(feature = "serde")]
+use serde::{Serialize,Deserialize};
 
-use super::super::util::slice_to_string;
+use super::super::util::{slice_to_string,StableHasher};
 use super::Program;
 
 /// See the `type` argument of glGetActiveAttrib (the sixth one) for the set of values this enum's
 /// variants correspond to. Notice the UnrecognizedType that handles the cases this library
 /// doesn't know of yet.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub enum ShaderAttributeType {
     Float,
     FloatVec2,
@@ -50,6 +52,7 @@ pub enum ShaderAttributeType {
 
 /// Contains information on shader program's (vertex) attributes.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct ShaderAttributeInfo {
     /// List of attributes.
     pub attributes: Vec<ShaderAttribute>
@@ -67,10 +70,28 @@ impl ShaderAttributeInfo {
         }
         None
     }
+
+    /// A digest of the names, types, array sizes and locations of the attributes. The value
+    /// stays the same between runs and doesn't depend on the order of the attributes, so a
+    /// different value after reloading a shader means the vertex arrays may need remapping.
+    /// See `UniformInfo::interface_hash`.
+    pub fn interface_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        let mut attributes: Vec<&ShaderAttribute> = self.attributes.iter().collect();
+        attributes.sort_by(|a, b| a.name.cmp(&b.name));
+        for attribute in attributes {
+            hasher.feed(attribute.name.as_bytes());
+            hasher.feed_u32(attribute_type_to_u32(&attribute.attribute_type));
+            hasher.feed_i32(attribute.size);
+            hasher.feed_i32(attribute.location);
+        }
+        hasher.finish()
+    }
 }
 
 /// Describes an (active) attribute of a shader program.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct ShaderAttribute {
     /// Name of the attribute
     pub name: String,
@@ -85,27 +106,97 @@ pub struct ShaderAttribute {
 /// Read all the attributes and build a ShaderAttributeInfo structure from them - makes lots of GL
 /// calls, so don't call repeatedly!
 pub fn make_attribute_info_vec(program: &Program) -> ShaderAttributeInfo {
-    let attr_count = program.get_value(gl::ACTIVE_ATTRIBUTES);
+    ShaderAttributeInfo { attributes: new_shader_attribute_iter(program).collect() }
+}
+
+/// Find a single attribute by name. The names of the attributes are compared without building
+/// the other attributes.
+pub fn find_attribute(program: &Program, name: &str) -> Option<ShaderAttribute> {
+    let mut iter = new_shader_attribute_iter(program);
+    while iter.index < iter.count {
+        let index = iter.index;
+        iter.index += 1;
+        let (length, size, gl_type) = iter.query(index);
+        if &iter.name_buffer[..length] == name.as_bytes() {
+            return Some(iter.attribute(length, size, gl_type));
+        }
+    }
+    None
+}
+
+/// Iterates over the attributes of a program, querying them one at a time, see
+/// `ProgramInfoAccessor::attributes`. Stopping early saves querying the rest.
+pub struct ShaderAttributeIter<'a> {
+    program: &'a Program,
+    index: u32,
+    count: u32,
+    /// Reused for each name, long enough for the longest.
+    name_buffer: Vec<u8>
+}
+
+/// Constructor not visible to library users.
+pub fn new_shader_attribute_iter(program: &Program) -> ShaderAttributeIter<'_> {
     let max_length = program.get_value(gl::ACTIVE_ATTRIBUTE_MAX_LENGTH);
-    let mut name_vec: Vec<u8> = repeat(0u8).take(max_length as usize).collect();
-    ShaderAttributeInfo { attributes: (0..attr_count as usize).map(|i| {
+    ShaderAttributeIter {
+        program,
+        index: 0,
+        count: program.get_value(gl::ACTIVE_ATTRIBUTES) as u32,
+        name_buffer: vec![0u8; max_length as usize]
+    }
+}
+
+impl<'a> ShaderAttributeIter<'a> {
+    /// Read the attribute of the index, its name into the name buffer. Returns the length of the
+    /// name, the size and the type. See glGetActiveAttrib.
+    fn query(&mut self, index: u32) -> (usize, i32, u32) {
         let mut actual_length = 0;
         let mut size = 0;
         let mut gl_type = 0;
-        let name_vec_ptr = name_vec.as_mut_ptr() as *mut i8;
+        let name_ptr = self.name_buffer.as_mut_ptr() as *mut i8;
         unsafe {
-            gl::GetActiveAttrib(program.id, i as u32, name_vec.len() as i32, &mut actual_length, &mut size, &mut gl_type, name_vec_ptr);
+            gl::GetActiveAttrib(self.program.id, index, self.name_buffer.len() as i32, &mut actual_length, &mut size, &mut gl_type, name_ptr);
         }
-        let name = slice_to_string(&name_vec[0..actual_length as usize]);
-        let attribute_type = attribute_type_from_u32(gl_type);
-        let location = program.get_attribute_location(&name[..]);
+        (actual_length as usize, size, gl_type)
+    }
+
+    fn attribute(&self, length: usize, size: i32, gl_type: u32) -> ShaderAttribute {
+        let name = slice_to_string(&self.name_buffer[0..length]);
+        let location = self.program.get_attribute_location(&name[..]);
         ShaderAttribute {
             name: name,
             location: location,
-            attribute_type: attribute_type,
+            attribute_type: attribute_type_from_u32(gl_type),
             size: size
         }
-    }).collect()}
+    }
+}
+
+impl<'a> Iterator for ShaderAttributeIter<'a> {
+    type Item = ShaderAttribute;
+
+    fn next(&mut self) -> Option<ShaderAttribute> {
+        if self.index >= self.count {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        let (length, size, gl_type) = self.query(index);
+        Some(self.attribute(length, size, gl_type))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> fmt::Debug for ShaderAttributeIter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShaderAttributeIter")
+            .field("index", &self.index)
+            .field("count", &self.count)
+            .finish()
+    }
 }
 
 fn attribute_type_from_u32(gl_type: u32) -> ShaderAttributeType {
@@ -133,4 +224,32 @@ fn attribute_type_from_u32(gl_type: u32) -> ShaderAttributeType {
         gl::UNSIGNED_INT_VEC4 => ShaderAttributeType::UnsignedIntVec4,
         _ => ShaderAttributeType::UnrecognizedType(gl_type)
     }
+}
+
+/// The OpenGL enum of the type, the inverse of `attribute_type_from_u32`.
+fn attribute_type_to_u32(attribute_type: &ShaderAttributeType) -> u32 {
+    match *attribute_type {
+        ShaderAttributeType::Float => gl::FLOAT,
+        ShaderAttributeType::FloatVec2 => gl::FLOAT_VEC2,
+        ShaderAttributeType::FloatVec3 => gl::FLOAT_VEC3,
+        ShaderAttributeType::FloatVec4 => gl::FLOAT_VEC4,
+        ShaderAttributeType::FloatMat2 => gl::FLOAT_MAT2,
+        ShaderAttributeType::FloatMat3 => gl::FLOAT_MAT3,
+        ShaderAttributeType::FloatMat4 => gl::FLOAT_MAT4,
+        ShaderAttributeType::FloatMat2x3 => gl::FLOAT_MAT2x3,
+        ShaderAttributeType::FloatMat2x4 => gl::FLOAT_MAT2x4,
+        ShaderAttributeType::FloatMat3x2 => gl::FLOAT_MAT3x2,
+        ShaderAttributeType::FloatMat3x4 => gl::FLOAT_MAT3x4,
+        ShaderAttributeType::FloatMat4x2 => gl::FLOAT_MAT4x2,
+        ShaderAttributeType::FloatMat4x3 => gl::FLOAT_MAT4x3,
+        ShaderAttributeType::Int => gl::INT,
+        ShaderAttributeType::IntVec2 => gl::INT_VEC2,
+        ShaderAttributeType::IntVec3 => gl::INT_VEC3,
+        ShaderAttributeType::IntVec4 => gl::INT_VEC4,
+        ShaderAttributeType::UnsignedInt => gl::UNSIGNED_INT,
+        ShaderAttributeType::UnsignedIntVec2 => gl::UNSIGNED_INT_VEC2,
+        ShaderAttributeType::UnsignedIntVec3 => gl::UNSIGNED_INT_VEC3,
+        ShaderAttributeType::UnsignedIntVec4 => gl::UNSIGNED_INT_VEC4,
+        ShaderAttributeType::UnrecognizedType(gl_type) => gl_type
+    }
 }
\ No newline at end of file
@@ -0,0 +1,292 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Matching Rust structs with the memory layout of uniform blocks, see
+//! `InterfaceBlock::generate_rust_struct` and `InterfaceBlock::validate_layout`.
+
+use std::fmt::Write;
+
+use super::uniform::{InterfaceBlock,BlockUniform,UniformType};
+
+/// A difference between the layout of a uniform block and a Rust struct, found by
+/// `InterfaceBlock::validate_layout`.
+#[derive(Clone,Debug,PartialEq)]
+pub enum LayoutMismatch {
+    /// The size of the struct is not the data size of the block.
+    Size { expected: usize, actual: usize },
+    /// The field is not at the offset of the corresponding uniform.
+    Offset { name: String, expected: usize, actual: usize },
+    /// The block has no uniform of the name of the field.
+    UnknownField(String)
+}
+
+impl InterfaceBlock {
+    /// Generate the source of a `#[repr(C)]` Rust struct matching the layout of the block, using
+    /// the offsets and strides reported by OpenGL. Gaps between the uniforms are filled with
+    /// padding fields. The field names are the uniform names made into identifiers, for example
+    /// `light.color` becomes `light_color`.
+    ///
+    /// Vectors become arrays (`[f32; 3]`) and matrices arrays of columns, each column padded to
+    /// the matrix stride, or arrays of rows padded likewise for `row_major` matrices. If the
+    /// array stride of an array uniform is larger than its elements, the elements are flattened
+    /// to arrays of the base type filling the whole stride.
+    pub fn generate_rust_struct(&self, struct_name: &str) -> String {
+        let mut source = String::new();
+        writeln!(source, "#[repr(C)]").unwrap();
+        writeln!(source, "#[derive(Clone,Copy,Debug)]").unwrap();
+        writeln!(source, "pub struct {} {{", struct_name).unwrap();
+        let mut cursor = 0;
+        let mut padding_count = 0;
+        let mut fields = Vec::new();
+        for uniform in self.sorted_uniforms() {
+            let offset = uniform.offset as usize;
+            if offset > cursor {
+                fields.push(format!("    _padding{}: [u8; {}]", padding_count, offset - cursor));
+                padding_count += 1;
+            }
+            let (field_type, size) = rust_type(uniform);
+            fields.push(format!("    pub {}: {}", field_name(&uniform.name), field_type));
+            cursor = offset + size;
+        }
+        let data_size = self.data_size as usize;
+        if data_size > cursor {
+            fields.push(format!("    _padding{}: [u8; {}]", padding_count, data_size - cursor));
+        }
+        writeln!(source, "{}", fields.join(",\n")).unwrap();
+        writeln!(source, "}}").unwrap();
+        source
+    }
+
+    /// Check that a Rust struct matches the layout of the block. The struct size must equal the
+    /// data size of the block, and each of the given fields must be at the offset of the
+    /// uniform of the same name. The names may be given as the uniform names or as the field
+    /// names of `generate_rust_struct`. Use `std::mem::size_of` and `std::mem::offset_of` to get
+    /// the values:
+    ///
+    ///    block.validate_layout(size_of::<Lights>(), &[("light_color", offset_of!(Lights, light_color))])
+    ///
+    /// Returns all the mismatches found.
+    pub fn validate_layout(&self, struct_size: usize, field_offsets: &[(&str, usize)]) -> Result<(), Vec<LayoutMismatch>> {
+        let mut mismatches = Vec::new();
+        if struct_size != self.data_size as usize {
+            mismatches.push(LayoutMismatch::Size { expected: self.data_size as usize, actual: struct_size });
+        }
+        for &(name, offset) in field_offsets.iter() {
+            let uniform = self.uniforms.iter().find(|uniform| {
+                uniform.name == name || field_name(&uniform.name) == name
+            });
+            match uniform {
+                Some(uniform) if uniform.offset as usize != offset => {
+                    mismatches.push(LayoutMismatch::Offset {
+                        name: name.to_string(),
+                        expected: uniform.offset as usize,
+                        actual: offset
+                    });
+                },
+                Some(_) => (),
+                None => mismatches.push(LayoutMismatch::UnknownField(name.to_string()))
+            }
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(mismatches)
+        }
+    }
+
+    fn sorted_uniforms(&self) -> Vec<&BlockUniform> {
+        let mut uniforms: Vec<&BlockUniform> = self.uniforms.iter().collect();
+        uniforms.sort_by_key(|uniform| uniform.offset);
+        uniforms
+    }
+}
+
+impl BlockUniform {
+    /// Lay out tightly packed values of the uniform as they are in the block: each array element
+    /// at the array stride, and each column of a matrix at the matrix stride. A matrix is given
+    /// column by column, for example `[f32; 16]` for a `mat4`, also when the block stores it
+    /// `row_major`; then each value is placed in its row, the rows at the matrix stride. Gives
+    /// the byte offset in the block of each piece of the bytes, or None if the bytes are not
+    /// whole elements or there are more elements than the uniform has.
+    pub fn scatter<'a>(&self, bytes: &'a [u8]) -> Option<Vec<(usize, &'a [u8])>> {
+        let offset = self.offset as usize;
+        let (_, rows, columns) = match type_shape(self.uniform_type) {
+            Some(shape) => shape,
+            None => return Some(vec![(offset, bytes)])
+        };
+        let column_size = rows * 4;
+        let element_size = column_size * columns;
+        if !bytes.len().is_multiple_of(element_size) || bytes.len() / element_size > self.size.max(1) as usize {
+            return None;
+        }
+        let array_stride = if self.array_stride > 0 { self.array_stride as usize } else { element_size };
+        let matrix_stride = if self.matrix_stride > 0 { self.matrix_stride as usize } else { column_size };
+        let mut pieces = Vec::with_capacity(bytes.len() / column_size);
+        for (index, element) in bytes.chunks(element_size).enumerate() {
+            let element_offset = offset + index * array_stride;
+            if columns == 1 {
+                pieces.push((element_offset, element));
+            }
+            else if self.is_row_major {
+                for (column, column_bytes) in element.chunks(column_size).enumerate() {
+                    for (row, value) in column_bytes.chunks(4).enumerate() {
+                        pieces.push((element_offset + row * matrix_stride + column * 4, value));
+                    }
+                }
+            }
+            else {
+                for (column, column_bytes) in element.chunks(column_size).enumerate() {
+                    pieces.push((element_offset + column * matrix_stride, column_bytes));
+                }
+            }
+        }
+        Some(pieces)
+    }
+}
+
+/// Turn a uniform name into a Rust identifier: `lights[0].color` becomes `lights_0_color`. A
+/// trailing `[0]` of an array uniform is dropped.
+fn field_name(uniform_name: &str) -> String {
+    let name = uniform_name.strip_suffix("[0]").unwrap_or(uniform_name);
+    let mut field = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            field.push(c);
+        }
+        else if !field.is_empty() && !field.ends_with('_') {
+            field.push('_');
+        }
+    }
+    field.trim_end_matches('_').to_string()
+}
+
+/// The Rust type of the uniform, and how many bytes it takes.
+fn rust_type(uniform: &BlockUniform) -> (String, usize) {
+    let (base, rows, columns) = match type_shape(uniform.uniform_type) {
+        Some(shape) => shape,
+        // Samplers and such can't be in blocks, but keep the layout intact anyway
+        None => return ("[u8; 4]".to_string(), 4)
+    };
+    let (element_type, element_size) = if columns > 1 {
+        // Each column, or each row of a row major matrix, is padded to the matrix stride
+        let vector_count = if uniform.is_row_major { rows } else { columns };
+        let vector_floats = uniform.matrix_stride as usize / 4;
+        (format!("[[{}; {}]; {}]", base, vector_floats, vector_count), uniform.matrix_stride as usize * vector_count)
+    }
+    else if rows > 1 {
+        (format!("[{}; {}]", base, rows), rows * 4)
+    }
+    else {
+        (base.to_string(), 4)
+    };
+    let array_stride = uniform.array_stride as usize;
+    if uniform.size <= 1 && array_stride == 0 {
+        return (element_type, element_size);
+    }
+    let count = uniform.size.max(1) as usize;
+    if array_stride > element_size {
+        (format!("[[{}; {}]; {}]", base, array_stride / 4, count), array_stride * count)
+    }
+    else {
+        (format!("[{}; {}]", element_type, count), element_size * count)
+    }
+}
+
+/// The base type, row count and column count of the uniform type. Vectors have one column,
+/// scalars one row too. Booleans are four bytes in blocks, so they are given as u32.
+pub fn type_shape(uniform_type: UniformType) -> Option<(&'static str, usize, usize)> {
+    let shape = match uniform_type {
+        UniformType::Float => ("f32", 1, 1),
+        UniformType::FloatVec2 => ("f32", 2, 1),
+        UniformType::FloatVec3 => ("f32", 3, 1),
+        UniformType::FloatVec4 => ("f32", 4, 1),
+        UniformType::Int => ("i32", 1, 1),
+        UniformType::IntVec2 => ("i32", 2, 1),
+        UniformType::IntVec3 => ("i32", 3, 1),
+        UniformType::IntVec4 => ("i32", 4, 1),
+        UniformType::UnsignedInt | UniformType::Bool => ("u32", 1, 1),
+        UniformType::UnsignedIntVec2 | UniformType::BoolVec2 => ("u32", 2, 1),
+        UniformType::UnsignedIntVec3 | UniformType::BoolVec3 => ("u32", 3, 1),
+        UniformType::UnsignedIntVec4 | UniformType::BoolVec4 => ("u32", 4, 1),
+        UniformType::FloatMat2 => ("f32", 2, 2),
+        UniformType::FloatMat3 => ("f32", 3, 3),
+        UniformType::FloatMat4 => ("f32", 4, 4),
+        UniformType::FloatMat2x3 => ("f32", 3, 2),
+        UniformType::FloatMat2x4 => ("f32", 4, 2),
+        UniformType::FloatMat3x2 => ("f32", 2, 3),
+        UniformType::FloatMat3x4 => ("f32", 4, 3),
+        UniformType::FloatMat4x2 => ("f32", 2, 4),
+        UniformType::FloatMat4x3 => ("f32", 3, 4),
+        _ => return None
+    };
+    Some(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rust_type;
+    use super::super::uniform::{BlockUniform,UniformType};
+
+    fn uniform(uniform_type: UniformType, size: i32, offset: i32, array_stride: i32, matrix_stride: i32) -> BlockUniform {
+        BlockUniform {
+            name: "value".to_string(),
+            uniform_type,
+            size,
+            offset,
+            array_stride,
+            matrix_stride,
+            is_row_major: false
+        }
+    }
+
+    #[test]
+    fn scatter_array_at_stride() {
+        // vec3[2] in std140, each element padded to 16 bytes
+        let uniform = uniform(UniformType::FloatVec3, 2, 16, 16, 0);
+        let bytes = [0u8; 24];
+        let pieces = uniform.scatter(&bytes[..]).unwrap();
+        assert_eq!(pieces.iter().map(|&(offset, piece)| (offset, piece.len())).collect::<Vec<_>>(), vec![(16, 12), (32, 12)]);
+    }
+
+    #[test]
+    fn scatter_matrix_columns_at_matrix_stride() {
+        // mat3 in std140, each column padded to 16 bytes
+        let uniform = uniform(UniformType::FloatMat3, 1, 0, 0, 16);
+        let bytes: Vec<u8> = (0..36).collect();
+        let pieces = uniform.scatter(&bytes[..]).unwrap();
+        assert_eq!(pieces.iter().map(|&(offset, _)| offset).collect::<Vec<_>>(), vec![0, 16, 32]);
+        assert_eq!(pieces[1].1, &bytes[12..24]);
+    }
+
+    #[test]
+    fn scatter_row_major_matrix_into_rows() {
+        // row_major mat2x3 (two columns, three rows) in std140, each row padded to 16 bytes
+        let uniform = BlockUniform { is_row_major: true, ..uniform(UniformType::FloatMat2x3, 1, 0, 0, 16) };
+        let bytes: Vec<u8> = (0..24).collect();
+        let pieces = uniform.scatter(&bytes[..]).unwrap();
+        assert_eq!(pieces.iter().map(|&(offset, _)| offset).collect::<Vec<_>>(), vec![0, 16, 32, 4, 20, 36]);
+        // The second column, first row
+        assert_eq!(pieces[3].1, &bytes[12..16]);
+        assert_eq!(rust_type(&uniform), ("[[f32; 4]; 3]".to_string(), 48));
+    }
+
+    #[test]
+    fn scatter_rejects_partial_and_extra_elements() {
+        let uniform = uniform(UniformType::FloatVec4, 2, 0, 16, 0);
+        assert!(uniform.scatter(&[0u8; 8][..]).is_none());
+        assert!(uniform.scatter(&[0u8; 48][..]).is_none());
+        assert!(uniform.scatter(&[0u8; 32][..]).is_some());
+    }
+}
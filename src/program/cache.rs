@@ -0,0 +1,215 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The program cache of the context, see `Context::get_or_create_program`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use super::super::ProgramHandle;
+use super::super::util::StableHasher;
+
+/// Identifies a program in the cache: the shader sources and the defines injected into them.
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub struct ProgramKey {
+    vertex_source: String,
+    fragment_source: String,
+    defines: Vec<(String, String)>
+}
+
+impl ProgramKey {
+    pub fn new(vertex_source: &str, fragment_source: &str, defines: &[(&str, &str)]) -> ProgramKey {
+        ProgramKey {
+            vertex_source: vertex_source.to_string(),
+            fragment_source: fragment_source.to_string(),
+            defines: defines.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect()
+        }
+    }
+
+    /// A hash of the key that stays the same between runs, unlike the one of `HashMap`. Used to
+    /// name the program binary files.
+    fn stable_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.feed(self.vertex_source.as_bytes());
+        hasher.feed(self.fragment_source.as_bytes());
+        for (name, value) in self.defines.iter() {
+            hasher.feed(name.as_bytes());
+            hasher.feed(value.as_bytes());
+        }
+        hasher.finish()
+    }
+
+    /// The key and the driver string as bytes, stored in the program binary files to tell if a
+    /// file belongs to the program and to the driver, since two keys may have the same hash, and
+    /// binaries of another driver may be misread instead of rejected.
+    fn identity(&self, driver: &str) -> Vec<u8> {
+        let mut identity = Vec::new();
+        let mut push = |bytes: &[u8]| {
+            identity.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            identity.extend_from_slice(bytes);
+        };
+        push(driver.as_bytes());
+        push(self.vertex_source.as_bytes());
+        push(self.fragment_source.as_bytes());
+        for (name, value) in self.defines.iter() {
+            push(name.as_bytes());
+            push(value.as_bytes());
+        }
+        identity
+    }
+}
+
+/// The programs created through the cache, and the directory the program binaries are stored
+/// in, if any.
+#[derive(Debug)]
+pub struct ProgramCache {
+    programs: HashMap<ProgramKey, ProgramHandle>,
+    binary_directory: Option<PathBuf>
+}
+
+impl ProgramCache {
+    pub fn new() -> ProgramCache {
+        ProgramCache { programs: HashMap::new(), binary_directory: None }
+    }
+
+    pub fn get(&self, key: &ProgramKey) -> Option<ProgramHandle> {
+        self.programs.get(key).cloned()
+    }
+
+    pub fn insert(&mut self, key: ProgramKey, program: ProgramHandle) {
+        self.programs.insert(key, program);
+    }
+
+    pub fn clear(&mut self) {
+        self.programs.clear();
+    }
+
+    pub fn set_binary_directory(&mut self, directory: Option<PathBuf>) {
+        self.binary_directory = directory;
+    }
+
+    pub fn has_binary_directory(&self) -> bool {
+        self.binary_directory.is_some()
+    }
+
+    /// Read the stored binary of the program: the binary format and the binary itself. None if
+    /// there is no binary, or if it was stored for another program or by another driver, see
+    /// `VersionInfo::driver_string`.
+    pub fn load_binary(&self, key: &ProgramKey, driver: &str) -> Option<(u32, Vec<u8>)> {
+        let path = self.binary_path(key)?;
+        let bytes = fs::read(path).ok()?;
+        decode_binary_file(&bytes, &key.identity(driver))
+    }
+
+    /// Store the binary of the program, along with the key and the driver string. Failing to
+    /// write the file is not an error, the program is just compiled again the next time.
+    pub fn store_binary(&self, key: &ProgramKey, driver: &str, format: u32, binary: &[u8]) {
+        if let Some(path) = self.binary_path(key) {
+            let _ = fs::write(path, encode_binary_file(&key.identity(driver), format, binary));
+        }
+    }
+
+    fn binary_path(&self, key: &ProgramKey) -> Option<PathBuf> {
+        self.binary_directory.as_ref().map(|directory| directory.join(format!("{:016x}.bin", key.stable_hash())))
+    }
+}
+
+/// The contents of a program binary file: the length of the identity, the identity, the binary
+/// format and the binary.
+fn encode_binary_file(identity: &[u8], format: u32, binary: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + identity.len() + binary.len());
+    bytes.extend_from_slice(&(identity.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(identity);
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(binary);
+    bytes
+}
+
+/// The binary format and the binary of a program binary file, if the file is of the identity.
+fn decode_binary_file(bytes: &[u8], identity: &[u8]) -> Option<(u32, Vec<u8>)> {
+    let read_u32 = |offset: usize| bytes.get(offset..offset + 4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+    let identity_length = read_u32(0)? as usize;
+    if identity_length != identity.len() || bytes.get(4..4 + identity_length)? != identity {
+        return None;
+    }
+    let format = read_u32(4 + identity_length)?;
+    let binary = &bytes[8 + identity_length..];
+    if binary.is_empty() {
+        return None;
+    }
+    Some((format, binary.to_vec()))
+}
+
+/// Add `#define` lines for the defines to the shader source. They are placed after the
+/// `#version` line if there is one, as nothing but comments may precede it.
+pub fn inject_defines(source: &str, defines: &[(&str, &str)]) -> String {
+    if defines.is_empty() {
+        return source.to_string();
+    }
+    let mut define_lines = String::new();
+    for &(name, value) in defines.iter() {
+        define_lines.push_str(&format!("#define {} {}\n", name, value));
+    }
+    inject_after_version(source, &define_lines)
+}
+
+/// Add the lines to the shader source, after the `#version` line if there is one. The lines
+/// must end with a newline.
+pub fn inject_after_version(source: &str, lines: &str) -> String {
+    let version_start = source.find("#version")
+        .filter(|&start| source[..start].trim().is_empty());
+    match version_start {
+        Some(start) => {
+            let line_end = source[start..].find('\n').map_or(source.len(), |end| start + end + 1);
+            let mut result = source[..line_end].to_string();
+            if !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result.push_str(lines);
+            result.push_str(&source[line_end..]);
+            result
+        },
+        None => lines.to_string() + source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProgramKey,encode_binary_file,decode_binary_file,inject_defines};
+
+    #[test]
+    fn defines_go_after_the_version_line() {
+        let defines = [("LIGHTS", "4"), ("SHADOWS", "1")];
+        assert_eq!(inject_defines("#version 330\nvoid main() {}", &defines),
+            "#version 330\n#define LIGHTS 4\n#define SHADOWS 1\nvoid main() {}");
+        assert_eq!(inject_defines("\n  #version 300 es", &defines[..1]), "\n  #version 300 es\n#define LIGHTS 4\n");
+        assert_eq!(inject_defines("void main() {}", &defines[..1]), "#define LIGHTS 4\nvoid main() {}");
+        assert_eq!(inject_defines("// #version 330\n", &defines[..1]), "#define LIGHTS 4\n// #version 330\n");
+        assert_eq!(inject_defines("#version 330\n", &[]), "#version 330\n");
+    }
+
+    #[test]
+    fn binary_files_are_read_only_for_their_key_and_driver() {
+        let key = ProgramKey::new("vertex", "fragment", &[("LIGHTS", "4")]);
+        let bytes = encode_binary_file(&key.identity("driver 1"), 0x1234, &[1, 2, 3]);
+        assert_eq!(decode_binary_file(&bytes, &key.identity("driver 1")), Some((0x1234, vec![1, 2, 3])));
+        assert_eq!(decode_binary_file(&bytes, &key.identity("driver 2")), None);
+        let other = ProgramKey::new("vertex", "fragment", &[("LIGHTS", "8")]);
+        assert_eq!(decode_binary_file(&bytes, &other.identity("driver 1")), None);
+        assert_eq!(decode_binary_file(&bytes[..bytes.len() - 3], &key.identity("driver 1")), None);
+        assert_eq!(decode_binary_file(&bytes[..6], &key.identity("driver 1")), None);
+    }
+}
@@ -15,25 +15,43 @@
 //! The program module is complex, because it contains also the means to manipulate and query
 //! program uniforms and attributes.
 
-use std::iter::repeat;
+use std::cell::{Cell,RefCell};
+use std::collections::HashMap;
 use std::ffi::CString;
+use std::fmt;
+use std::rc::Rc;
 
-use gl;
-use gl::types::GLenum;
+use gl::types::{GLenum,GLint,GLsizei,GLvoid};
 
 use super::util::vec_to_string;
 use super::tracker::Bind;
 use super::handle::HandleAccess;
-use super::context::{Context,RegistrationHandle,ContextEditingSupport};
+use super::context::{Context,RegistrationHandle,ContextEditingSupport,QueuedDeletion,ContextResource};
 use super::ShaderHandle;
 use super::tracker::TrackerId;
+use super::raw::Ownership;
+use super::shader;
+use super::semantic::AttributeSemantic;
+use super::info::{ContextInfo,ExtensionFeature};
 
 pub use self::uniform::{SimpleUniformTypeFloat,SimpleUniformTypeI32,SimpleUniformTypeMatrix,SimpleUniformTypeU32};
-pub use self::uniform::{UniformInfo,Uniform,InterfaceBlock,BlockUniform};
-pub use self::attribute::{ShaderAttributeInfo,ShaderAttribute};
+pub use self::uniform::{UniformInfo,Uniform,UniformType,InterfaceBlock,BlockUniform};
+pub use self::attribute::{ShaderAttributeInfo,ShaderAttribute,ShaderAttributeIter};
+pub use self::output::{FragmentOutputInfo,FragmentOutput};
+pub use self::varying::{TransformFeedbackInfo,TransformFeedbackVarying};
+pub use self::blocklayout::LayoutMismatch;
+pub use self::uniform::{uniform_f32,uniform_matrix,uniform_u32,uniform_i32,sampler_target,texture_target_name};
+pub use self::uniform::{validate_uniform_f32,validate_uniform_matrix,validate_uniform_u32,validate_uniform_i32};
 
 mod uniform;
 mod attribute;
+mod output;
+mod varying;
+mod blocklayout;
+pub mod cache;
+
+/// The name, location, type and array size of a sampler uniform.
+type SamplerUniform = (String, i32, UniformType, i32);
 
 /// A shader program, formed by linking together `Shader` objects.
 pub struct Program {
@@ -41,25 +59,130 @@ pub struct Program {
     tracker_id: TrackerId,
     registration: RegistrationHandle,
     /// The program keeps the shaders alive even though OpenGL should take care of it. Not sure
-    /// at all if really necessary.
-    shaders: Vec<ShaderHandle>
+    /// at all if really necessary. The shaders can be replaced with `ProgramEditor::replace_shaders`.
+    shaders: RefCell<Vec<ShaderHandle>>,
+    ownership: Ownership,
+    /// The name, location, type and array size of the sampler uniforms, gathered on first use by
+    /// `sampler_units`.
+    samplers: RefCell<Option<Vec<SamplerUniform>>>,
+    /// Whether a compute shader is attached, found out on first use by `is_compute`.
+    compute: Cell<Option<bool>>,
+    /// The locations of the active attributes by name, gathered on first use by
+    /// `attribute_locations`.
+    attribute_locations: RefCell<Option<Rc<HashMap<String, u32>>>>
 }
 
 impl Program {
     /// Create a program, attach shaders to it and link the program.
     pub fn new(tracker_id: TrackerId, shaders: &[ShaderHandle], registration: RegistrationHandle) -> Program {
+        Program::new_linked(tracker_id, shaders, registration, false)
+    }
+
+    /// Like `new`, but hint that the binary of the program is going to be retrieved with
+    /// `get_binary`.
+    pub fn new_retrievable(tracker_id: TrackerId, shaders: &[ShaderHandle], registration: RegistrationHandle) -> Program {
+        Program::new_linked(tracker_id, shaders, registration, true)
+    }
+
+    fn new_linked(tracker_id: TrackerId, shaders: &[ShaderHandle], registration: RegistrationHandle, retrievable: bool) -> Program {
         let id = unsafe { gl::CreateProgram() };
         check_error!();
         let program = Program {
             id: id,
             tracker_id: tracker_id,
             registration: registration,
-            shaders: shaders.to_vec()
+            shaders: RefCell::new(shaders.to_vec()),
+            ownership: Ownership::Owned,
+            samplers: RefCell::new(None),
+            compute: Cell::new(None),
+            attribute_locations: RefCell::new(None)
         };
+        if retrievable {
+            unsafe {
+                gl::ProgramParameteri(id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+            }
+            check_error!();
+        }
         program.link();
         program
     }
 
+    /// Create a program from a binary retrieved earlier with `get_binary`. Returns None if the
+    /// binary format is not one of GL_PROGRAM_BINARY_FORMATS, or if the binary is not accepted,
+    /// which happens for example after a driver update. See glProgramBinary.
+    pub fn from_binary(tracker_id: TrackerId, format: u32, binary: &[u8], registration: RegistrationHandle) -> Option<Program> {
+        if !program_binary_formats().contains(&format) {
+            return None;
+        }
+        let id = unsafe { gl::CreateProgram() };
+        check_error!();
+        let program = Program {
+            id,
+            tracker_id,
+            registration,
+            shaders: RefCell::new(Vec::new()),
+            ownership: Ownership::Owned,
+            samplers: RefCell::new(None),
+            compute: Cell::new(None),
+            attribute_locations: RefCell::new(None)
+        };
+        // A supported format is not an error even if the binary is rejected, that is reported
+        // with the link status
+        unsafe {
+            gl::ProgramBinary(id, format, binary.as_ptr() as *const GLvoid, binary.len() as GLsizei);
+        }
+        check_error!();
+        if program.get_link_status() {
+            Some(program)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Get the binary format and the binary of the linked program, or None if the
+    /// implementation doesn't provide one. See glGetProgramBinary.
+    pub fn get_binary(&self) -> Option<(u32, Vec<u8>)> {
+        let length = self.get_value(gl::PROGRAM_BINARY_LENGTH);
+        if length <= 0 {
+            return None;
+        }
+        let mut binary = vec![0u8; length as usize];
+        let mut actual_length = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(self.id, length, &mut actual_length, &mut format, binary.as_mut_ptr() as *mut GLvoid);
+            check_error!();
+        }
+        binary.truncate(actual_length as usize);
+        Some((format, binary))
+    }
+
+    /// Wrap an existing, already linked program object.
+    pub fn adopt(gl_id: u32, tracker_id: TrackerId, registration: RegistrationHandle, ownership: Ownership) -> Program {
+        Program {
+            id: gl_id,
+            tracker_id,
+            registration,
+            shaders: RefCell::new(Vec::new()),
+            ownership,
+            samplers: RefCell::new(None),
+            compute: Cell::new(None),
+            attribute_locations: RefCell::new(None)
+        }
+    }
+
+    /// Identify the program. The returned value is the actual OpenGL object name.
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    /// The shaders the program keeps alive. Adopted programs and programs loaded from binaries
+    /// have none.
+    pub fn get_shaders(&self) -> Vec<ShaderHandle> {
+        self.shaders.borrow().clone()
+    }
+
     /// See glGetAttribLocation.
     pub fn get_attribute_location(&self, name: &str) -> i32 {
         let c_name = CString::new(name).unwrap();
@@ -90,7 +213,8 @@ impl Program {
         }
     }
 
-    /// See glGetFragDataIndex.
+    /// See glGetFragDataIndex. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
     pub fn get_frag_data_index(&self, name: &str) -> i32 {
         let c_name = CString::new(name).unwrap();
         unsafe {
@@ -100,8 +224,112 @@ impl Program {
         }
     }
 
+    /// The local size of the compute shader of the program, as declared with
+    /// `layout(local_size_x = ...)`. None if the program has no compute shader. See
+    /// GL_COMPUTE_WORK_GROUP_SIZE.
+    pub fn compute_work_group_size(&self) -> Option<[u32; 3]> {
+        if !self.is_compute() {
+            return None;
+        }
+        let mut size = [0 as GLint; 3];
+        unsafe {
+            gl::GetProgramiv(self.id, gl::COMPUTE_WORK_GROUP_SIZE, size.as_mut_ptr());
+        }
+        check_error!();
+        Some([size[0] as u32, size[1] as u32, size[2] as u32])
+    }
+
+    /// Tells if a compute shader is attached to the program. Programs loaded from binaries have
+    /// no attached shaders, so they are never compute programs; the program cache only creates
+    /// programs of vertex and fragment shaders anyway. See GL_ATTACHED_SHADERS and
+    /// GL_SHADER_TYPE.
+    pub fn is_compute(&self) -> bool {
+        if let Some(compute) = self.compute.get() {
+            return compute;
+        }
+        let compute = self.attached_shader_ids().into_iter().any(|shader_id| {
+            let mut shader_type = 0;
+            unsafe {
+                gl::GetShaderiv(shader_id, gl::SHADER_TYPE, &mut shader_type);
+            }
+            check_error!();
+            shader_type as GLenum == gl::COMPUTE_SHADER
+        });
+        self.compute.set(Some(compute));
+        compute
+    }
+
+    /// The OpenGL names of the shaders attached to the program. Asks OpenGL instead of trusting
+    /// our list, adopted programs have shaders we don't know of. See glGetAttachedShaders.
+    fn attached_shader_ids(&self) -> Vec<u32> {
+        let attached_count = self.get_value(gl::ATTACHED_SHADERS);
+        let mut attached = vec![0u32; attached_count as usize];
+        let mut actual_count = 0;
+        unsafe {
+            gl::GetAttachedShaders(self.id, attached_count, &mut actual_count, attached.as_mut_ptr());
+            check_error!();
+        }
+        attached.truncate(actual_count as usize);
+        attached
+    }
+
+    /// Detach all the shaders attached to the program, attach the given ones instead and link
+    /// the program again. The program keeps its OpenGL object name.
+    fn relink(&self, shaders: &[ShaderHandle]) {
+        for shader_id in self.attached_shader_ids() {
+            unsafe {
+                gl::DetachShader(self.id, shader_id);
+            }
+            check_error!();
+        }
+        *self.shaders.borrow_mut() = shaders.to_vec();
+        *self.samplers.borrow_mut() = None;
+        self.compute.set(None);
+        *self.attribute_locations.borrow_mut() = None;
+        self.link();
+    }
+
+    /// The locations of the active attributes of the program by name, leaving out the built-in
+    /// attributes without a location. Queried on first use and kept until the program is
+    /// linked again, for matching vertex arrays to the program on every use.
+    pub fn attribute_locations(&self) -> Rc<HashMap<String, u32>> {
+        let mut locations = self.attribute_locations.borrow_mut();
+        locations.get_or_insert_with(|| {
+            Rc::new(attribute::make_attribute_info_vec(self).attributes.into_iter()
+                .filter(|attribute| attribute.location >= 0)
+                .map(|attribute| (attribute.name, attribute.location as u32))
+                .collect())
+        }).clone()
+    }
+
+    /// The sampler uniforms of the program with the texture units they are currently set to, as
+    /// (name, type, unit). Each element of a sampler array is listed separately. See
+    /// glGetUniformiv.
+    pub fn sampler_units(&self) -> Vec<(String, UniformType, u32)> {
+        let mut samplers = self.samplers.borrow_mut();
+        if samplers.is_none() {
+            let uniform_info = uniform::make_uniform_info(self);
+            *samplers = Some(uniform_info.globals.into_iter()
+                .filter(|uniform| sampler_target(uniform.uniform_type).is_some())
+                .map(|uniform| (uniform.name, uniform.location, uniform.uniform_type, uniform.size))
+                .collect());
+        }
+        let mut units = Vec::new();
+        for &(ref name, location, uniform_type, size) in samplers.as_ref().unwrap().iter() {
+            for element in 0..size.max(1) {
+                let mut unit = 0;
+                unsafe {
+                    gl::GetUniformiv(self.id, location + element, &mut unit);
+                }
+                check_error!();
+                units.push((name.clone(), uniform_type, unit as u32));
+            }
+        }
+        units
+    }
+
     fn link(&self) {
-        for ref shader in self.shaders.iter() {
+        for shader in self.shaders.borrow().iter() {
             unsafe {
                 gl::AttachShader(self.id, shader.access().get_id());
             }
@@ -116,7 +344,7 @@ impl Program {
     fn get_info_log(&self) -> String {
         let info_length = self.get_value(gl::INFO_LOG_LENGTH);
         let mut actual_info_length = 0;
-        let mut info_vec: Vec<u8> = repeat(0u8).take(info_length as usize).collect();
+        let mut info_vec: Vec<u8> = vec![0u8; info_length as usize];
         unsafe {
             let info_vec_ptr = info_vec.as_mut_ptr() as *mut i8;
             gl::GetProgramInfoLog(self.id, info_length, &mut actual_info_length, info_vec_ptr);
@@ -126,7 +354,7 @@ impl Program {
         vec_to_string(info_vec)
     }
 
-    fn get_link_status(&self) -> bool {
+    pub fn get_link_status(&self) -> bool {
         let link_status = self.get_value(gl::LINK_STATUS);
         link_status == (gl::TRUE as i32)
     }
@@ -147,13 +375,27 @@ impl Program {
     }
 }
 
+impl fmt::Debug for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Program")
+            .field("id", &self.id)
+            .field("tracker_id", &self.tracker_id)
+            .field("shaders", &self.shaders)
+            .field("ownership", &self.ownership)
+            .finish()
+    }
+}
+
+impl ContextResource for Program {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
 impl Drop for Program {
     fn drop(&mut self) {
-        if self.registration.context_alive() {
-            unsafe {
-                gl::DeleteProgram(self.id);
-            }
-            check_error!();
+        if self.ownership == Ownership::Owned {
+            self.registration.queue_deletion(QueuedDeletion::Program(self.id, self.tracker_id));
         }
     }
 }
@@ -171,14 +413,32 @@ impl Bind<Program> for ProgramBinder {
         program.bind();
     }
 
+    fn unbind(&self) {
+        unsafe {
+            gl::UseProgram(0);
+        }
+        check_error!();
+    }
+
     fn get_id(&self, program: &Program) -> TrackerId {
         program.tracker_id
     }
+
+    fn get_name(&self, program: &Program) -> u32 {
+        program.id
+    }
 }
 
 /// Program info accessor provides info on a program.
 pub struct ProgramInfoAccessor<'a> {
-    program: &'a Program
+    program: &'a Program,
+    info: &'a ContextInfo
+}
+
+impl<'a> fmt::Debug for ProgramInfoAccessor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgramInfoAccessor").field("program", self.program).finish()
+    }
 }
 
 impl<'a> ProgramInfoAccessor<'a> {
@@ -204,12 +464,72 @@ impl<'a> ProgramInfoAccessor<'a> {
         attribute::make_attribute_info_vec(self.program)
     }
 
+    /// Returns information on a single global uniform, querying only that uniform instead of
+    /// building the whole `UniformInfo`. None if there is no such active uniform outside blocks.
+    /// See glGetUniformIndices.
+    pub fn find_uniform(&self, name: &str) -> Option<Uniform> {
+        uniform::find_uniform(self.program, name)
+    }
+
+    /// Returns information on a single uniform block and its uniforms, querying only that block.
+    /// None if there is no such active block. See glGetUniformBlockIndex.
+    pub fn find_uniform_block(&self, name: &str) -> Option<InterfaceBlock> {
+        uniform::find_block(self.program, name)
+    }
+
+    /// Returns information on a single attribute. The attributes are still gone through to find
+    /// it, but only the matching one is built.
+    pub fn find_attribute(&self, name: &str) -> Option<ShaderAttribute> {
+        attribute::find_attribute(self.program, name)
+    }
+
+    /// Iterate over the attributes of the program, querying each only when it is reached.
+    pub fn attributes(&self) -> ShaderAttributeIter<'a> {
+        attribute::new_shader_attribute_iter(self.program)
+    }
+
+    /// Returns information on all the active fragment shader outputs in one go, for example for
+    /// checking that a program matches the color textures of a render target. None if program
+    /// interface queries are not supported (see `ExtensionFeature::ProgramInterfaceQuery`); use
+    /// `get_frag_data_location` instead then. See `FragmentOutputInfo`.
+    pub fn get_fragment_output_info(&self) -> Option<FragmentOutputInfo> {
+        if !self.info.supports(ExtensionFeature::ProgramInterfaceQuery) {
+            return None;
+        }
+        Some(output::make_fragment_output_info(self.program))
+    }
+
+    /// The names of the shader storage blocks of the program, in block index order. None if
+    /// shader storage buffers or program interface queries are not supported, see
+    /// `ExtensionFeature::ShaderStorageBuffer`.
+    pub fn get_storage_block_names(&self) -> Option<Vec<String>> {
+        if !self.info.supports(ExtensionFeature::ShaderStorageBuffer) || !self.info.supports(ExtensionFeature::ProgramInterfaceQuery) {
+            return None;
+        }
+        Some(output::make_storage_block_names(self.program))
+    }
+
+    /// Returns information on the varyings the program captures with transform feedback, and
+    /// the layout of the capture buffers. The library doesn't set up transform feedback itself
+    /// yet, so the varyings come from adopted programs. The offsets are worked out assuming the
+    /// varyings are packed in capture order. See `TransformFeedbackInfo`.
+    pub fn get_transform_feedback_info(&self) -> TransformFeedbackInfo {
+        varying::make_transform_feedback_info(self.program)
+    }
+
     /// See glGetFragDataLocation.
     pub fn get_frag_data_location(&self, name: &str) -> i32 {
         self.program.get_frag_data_location(name)
     }
 
-    /// See glGetFragDataIndex.
+    /// The local work group size of the compute shader, or None if the program is not a
+    /// compute program. See GL_COMPUTE_WORK_GROUP_SIZE.
+    pub fn get_compute_work_group_size(&self) -> Option<[u32; 3]> {
+        self.program.compute_work_group_size()
+    }
+
+    /// See glGetFragDataIndex. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
     pub fn get_frag_data_index(&self, name: &str) -> i32 {
         self.program.get_frag_data_index(name)
     }
@@ -223,23 +543,59 @@ impl<'a> ProgramInfoAccessor<'a> {
     pub fn get_info_log(&self) -> String {
         self.program.get_info_log()
     }
+
+    /// The shaders the program was linked from. Empty for programs created from a binary or
+    /// adopted from raw OpenGL objects.
+    pub fn attached_shaders(&self) -> Vec<ShaderHandle> {
+        self.program.shaders.borrow().clone()
+    }
+
+    /// The semantic annotations of the attributes of the attached vertex shaders. See
+    /// `ShaderInfoAccessor::get_attribute_semantics`.
+    pub fn get_attribute_semantics(&self) -> Vec<AttributeSemantic> {
+        self.program.shaders.borrow().iter()
+            .flat_map(|shader| shader::new_shader_info_accessor(shader.access()).get_attribute_semantics().to_vec())
+            .collect()
+    }
+}
+
+/// The binary formats glProgramBinary accepts. See GL_PROGRAM_BINARY_FORMATS.
+fn program_binary_formats() -> Vec<u32> {
+    let mut count = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut count);
+    }
+    check_error!();
+    let mut formats = vec![0 as GLint; count.max(0) as usize];
+    if !formats.is_empty() {
+        unsafe {
+            gl::GetIntegerv(gl::PROGRAM_BINARY_FORMATS, formats.as_mut_ptr());
+        }
+        check_error!();
+    }
+    formats.into_iter().map(|format| format as u32).collect()
 }
 
 /// Constructor not visible to library users.
-pub fn new_program_info_accessor(program: &Program) -> ProgramInfoAccessor {
-    ProgramInfoAccessor { program: program }
+pub fn new_program_info_accessor<'a>(program: &'a Program, info: &'a ContextInfo) -> ProgramInfoAccessor<'a> {
+    ProgramInfoAccessor { program, info }
 }
 
 /// Program editor allows settings uniform values.
 pub struct ProgramEditor<'a> {
-    /// Borrow context even though it's not used to prevent other actions on it while editing program.
-    #[allow(dead_code)]
+    /// Borrow context to prevent other actions on it while editing program.
     context: &'a mut Context,
     /// Borrow program too for the same reason as the context.
     #[allow(dead_code)]
     program: &'a Program
 }
 
+impl<'a> fmt::Debug for ProgramEditor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgramEditor").field("program", self.program).finish()
+    }
+}
+
 impl<'a> ProgramEditor<'a> {
     /// Specify a uniform value (or multiple values of single uniform) of type f32.
     /// You must specify exactly the right amount of values, for example if count is 1 and
@@ -272,10 +628,81 @@ impl<'a> ProgramEditor<'a> {
         uniform::uniform_i32(location, count, uniform_type, values)
     }
 
+    /// Make the uniform block of the given index read its values from the uniform buffer bound
+    /// to the binding point, see `Renderer::use_uniform_buffer_range`. The block index is
+    /// `InterfaceBlock::index`. See glUniformBlockBinding.
+    pub fn uniform_block_binding(&self, block_index: u32, binding: u32) {
+        unsafe {
+            gl::UniformBlockBinding(self.program.id, block_index, binding);
+        }
+        check_error!();
+    }
+
+    /// Bind each uniform block of the program to the binding point assigned to its name by
+    /// `Context::uniform_binding_points`, assigning new ones as needed. Returns the names of the
+    /// blocks and their binding points. Panics if the binding points run out.
+    pub fn bind_uniform_blocks(&mut self) -> Vec<(String, u32)> {
+        let blocks = self.program_info().get_uniform_info().blocks;
+        let mut bindings = Vec::with_capacity(blocks.len());
+        for block in blocks.into_iter() {
+            let binding = match self.context.uniform_binding_points().alloc(&block.name) {
+                Some(binding) => binding,
+                None => panic!("No uniform buffer binding points left for the block {}", block.name)
+            };
+            self.uniform_block_binding(block.index, binding);
+            bindings.push((block.name, binding));
+        }
+        bindings
+    }
+
+    /// Make the shader storage block of the given index read and write the buffer bound to the
+    /// binding point, see `Renderer::use_storage_buffer_range`. OpenGL ES has no
+    /// glShaderStorageBlockBinding, the bindings must be given in the shaders there. See
+    /// glShaderStorageBlockBinding.
+    #[cfg(not(feature = "es3"))]
+    pub fn storage_block_binding(&self, block_index: u32, binding: u32) {
+        unsafe {
+            gl::ShaderStorageBlockBinding(self.program.id, block_index, binding);
+        }
+        check_error!();
+    }
+
+    /// Bind each shader storage block of the program to the binding point assigned to its name
+    /// by `Context::storage_binding_points`, assigning new ones as needed. Returns the names of
+    /// the blocks and their binding points, none if storage buffers are not supported. Panics if
+    /// the binding points run out.
+    #[cfg(not(feature = "es3"))]
+    pub fn bind_storage_blocks(&mut self) -> Vec<(String, u32)> {
+        let names = self.program_info().get_storage_block_names().unwrap_or_default();
+        let mut bindings = Vec::with_capacity(names.len());
+        for (index, name) in names.into_iter().enumerate() {
+            let binding = match self.context.storage_binding_points().alloc(&name) {
+                Some(binding) => binding,
+                None => panic!("No shader storage buffer binding points left for the block {}", name)
+            };
+            self.storage_block_binding(index as u32, binding);
+            bindings.push((name, binding));
+        }
+        bindings
+    }
+
+    /// Detach the shaders of the program, attach the given ones and link the program again, for
+    /// example to reload changed shader sources. The program keeps its OpenGL object name and
+    /// all the handles to it stay valid. See glDetachShader and glLinkProgram.
+    ///
+    /// Check the result with `get_link_status` of `program_info`: if linking fails, the program
+    /// can't be used until it is linked successfully again. Even on success, the uniform values
+    /// are reset to their defaults, and the uniform and attribute locations may have changed, so
+    /// query them again. Note that `Context::get_or_create_program` still finds the program with
+    /// the sources it was created from.
+    pub fn replace_shaders(&self, shaders: &[ShaderHandle]) {
+        self.program.relink(shaders);
+    }
+
     /// Allow accessing program info even during editing the said program. Just a convenience
     /// method not different from the one in `Context`.
-    pub fn program_info(&self) -> ProgramInfoAccessor {
-        new_program_info_accessor(self.program)
+    pub fn program_info(&self) -> ProgramInfoAccessor<'_> {
+        new_program_info_accessor(self.program, self.context.get_info())
     }
 }
 
@@ -0,0 +1,136 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use gl::types::{GLenum,GLint};
+
+use super::super::util::slice_to_string;
+use super::Program;
+use super::uniform::{self,UniformType};
+
+/// The active outputs of a program's fragment shader, see
+/// `ProgramInfoAccessor::get_fragment_output_info`.
+#[derive(Debug)]
+pub struct FragmentOutputInfo {
+    /// List of outputs.
+    pub outputs: Vec<FragmentOutput>
+}
+
+impl FragmentOutputInfo {
+    /// Find an output by name.
+    pub fn get_output(&self, name: &str) -> Option<&FragmentOutput> {
+        self.outputs.iter().find(|output| output.name == name)
+    }
+
+    /// The output written to the given draw buffer, if any. Only index zero outputs are
+    /// considered, the others are for dual source blending.
+    pub fn output_for_draw_buffer(&self, draw_buffer: u32) -> Option<&FragmentOutput> {
+        self.outputs.iter().find(|output| {
+            output.index == 0 && output.location >= 0 &&
+                draw_buffer >= output.location as u32 && draw_buffer < (output.location + output.array_size.max(1)) as u32
+        })
+    }
+}
+
+/// Describes an active output of a fragment shader.
+#[derive(Debug)]
+pub struct FragmentOutput {
+    /// Name of the output
+    pub name: String,
+    /// The location, that is, the draw buffer the output is written to. -1 for built-in outputs
+    /// such as gl_FragDepth.
+    pub location: i32,
+    /// The index for dual source blending, zero unless given in the shader. Always zero on
+    /// OpenGL ES.
+    pub index: i32,
+    /// Data type of the output. The same types are used as for uniforms.
+    pub output_type: UniformType,
+    /// Number of array elements, one for non-arrays. An array takes consecutive locations.
+    pub array_size: i32
+}
+
+/// Read the fragment shader outputs with program interface queries, which must be supported,
+/// see `ExtensionFeature::ProgramInterfaceQuery`.
+pub fn make_fragment_output_info(program: &Program) -> FragmentOutputInfo {
+    let mut count = 0;
+    let mut max_length = 0;
+    unsafe {
+        gl::GetProgramInterfaceiv(program.id, gl::PROGRAM_OUTPUT, gl::ACTIVE_RESOURCES, &mut count);
+        gl::GetProgramInterfaceiv(program.id, gl::PROGRAM_OUTPUT, gl::MAX_NAME_LENGTH, &mut max_length);
+    }
+    check_error!();
+    let mut name_vec: Vec<u8> = vec![0u8; max_length.max(1) as usize];
+    let outputs = (0..count as u32).map(|index| {
+        let mut actual_length = 0;
+        unsafe {
+            gl::GetProgramResourceName(program.id, gl::PROGRAM_OUTPUT, index, name_vec.len() as GLint,
+                &mut actual_length, name_vec.as_mut_ptr() as *mut i8);
+        }
+        check_error!();
+        let values = output_properties(program, index);
+        FragmentOutput {
+            name: slice_to_string(&name_vec[0..actual_length as usize]),
+            location: values[0],
+            output_type: uniform::uniform_type_from_u32(values[1] as u32),
+            array_size: values[2],
+            index: values[3]
+        }
+    }).collect();
+    FragmentOutputInfo { outputs }
+}
+
+/// Read the names of the shader storage blocks with program interface queries, which must be
+/// supported, as must storage buffers.
+pub fn make_storage_block_names(program: &Program) -> Vec<String> {
+    let mut count = 0;
+    let mut max_length = 0;
+    unsafe {
+        gl::GetProgramInterfaceiv(program.id, gl::SHADER_STORAGE_BLOCK, gl::ACTIVE_RESOURCES, &mut count);
+        gl::GetProgramInterfaceiv(program.id, gl::SHADER_STORAGE_BLOCK, gl::MAX_NAME_LENGTH, &mut max_length);
+    }
+    check_error!();
+    let mut name_vec: Vec<u8> = vec![0u8; max_length.max(1) as usize];
+    (0..count as u32).map(|index| {
+        let mut actual_length = 0;
+        unsafe {
+            gl::GetProgramResourceName(program.id, gl::SHADER_STORAGE_BLOCK, index, name_vec.len() as GLint,
+                &mut actual_length, name_vec.as_mut_ptr() as *mut i8);
+        }
+        check_error!();
+        slice_to_string(&name_vec[0..actual_length as usize])
+    }).collect()
+}
+
+/// The location, type, array size and index of the output.
+#[cfg(not(feature = "es3"))]
+fn output_properties(program: &Program, index: u32) -> [GLint; 4] {
+    let properties: [GLenum; 4] = [gl::LOCATION, gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION_INDEX];
+    query_properties(program, index, &properties)
+}
+
+/// The location, type, array size and index of the output. OpenGL ES has no GL_LOCATION_INDEX.
+#[cfg(feature = "es3")]
+fn output_properties(program: &Program, index: u32) -> [GLint; 4] {
+    let properties: [GLenum; 3] = [gl::LOCATION, gl::TYPE, gl::ARRAY_SIZE];
+    query_properties(program, index, &properties)
+}
+
+fn query_properties(program: &Program, index: u32, properties: &[GLenum]) -> [GLint; 4] {
+    let mut values = [0; 4];
+    unsafe {
+        gl::GetProgramResourceiv(program.id, gl::PROGRAM_OUTPUT, index, properties.len() as GLint, properties.as_ptr(),
+            values.len() as GLint, &mut 0, values.as_mut_ptr());
+    }
+    check_error!();
+    values
+}
@@ -19,14 +19,14 @@
 //! `info` module and the uniform block offset alignment and the uniform block maximum size
 //! values.)
 
-use std::iter::repeat;
-use std::ptr::null_mut;
 use std::fmt::Debug;
 use std::ffi::CString;
 
-use gl;
 use gl::types::GLenum;
+#[cfg(feature = "serde")]
+use serde::{Serialize,Deserialize};
 
+use super::super::util::{slice_to_string,StableHasher};
 use super::Program;
 
 /// A helper enum to be used when setting a uniform's value directly (not through a uniform
@@ -78,6 +78,7 @@ pub enum SimpleUniformTypeU32 {
 /// handles the types that are not recognized by this library. See glGetActiveUniformsiv for
 /// the official list of values.
 #[derive(Clone,Copy,Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub enum UniformType {
     Float,
     FloatVec2,
@@ -143,6 +144,49 @@ pub enum UniformType {
     UnrecognizedType(u32)
 }
 
+/// The texture target a sampler of the type reads from, or None if the type is not a sampler.
+pub fn sampler_target(uniform_type: UniformType) -> Option<GLenum> {
+    let target = match uniform_type {
+        UniformType::Sampler1d | UniformType::Sampler1dShadow | UniformType::IntSampler1d |
+        UniformType::UnsignedIntSampler1d => gl::TEXTURE_1D,
+        UniformType::Sampler2d | UniformType::Sampler2dShadow | UniformType::IntSampler2d |
+        UniformType::UnsignedIntSampler2d => gl::TEXTURE_2D,
+        UniformType::Sampler3d | UniformType::IntSampler3d | UniformType::UnsignedIntSampler3d => gl::TEXTURE_3D,
+        UniformType::SamplerCube | UniformType::SamplerCubeShadow | UniformType::IntSamplerCube |
+        UniformType::UnsignedIntSamplerCube => gl::TEXTURE_CUBE_MAP,
+        UniformType::Sampler1dArray | UniformType::Sampler1dArrayShadow | UniformType::IntSampler1dArray |
+        UniformType::UnsignedIntSampler1dArray => gl::TEXTURE_1D_ARRAY,
+        UniformType::Sampler2dArray | UniformType::Sampler2dArrayShadow | UniformType::IntSampler2dArray |
+        UniformType::UnsignedIntSampler2dArray => gl::TEXTURE_2D_ARRAY,
+        UniformType::Sampler2dMultisample | UniformType::IntSampler2dMultisample |
+        UniformType::UnsignedIntSampler2dMultisample => gl::TEXTURE_2D_MULTISAMPLE,
+        UniformType::Sampler2dMultisampleArray | UniformType::IntSampler2dMultisampleArray |
+        UniformType::UnsignedIntSampler2dMultisampleArray => gl::TEXTURE_2D_MULTISAMPLE_ARRAY,
+        UniformType::SamplerBuffer | UniformType::IntSamplerBuffer | UniformType::UnsignedIntSamplerBuffer => gl::TEXTURE_BUFFER,
+        UniformType::Sampler2dRect | UniformType::Sampler2dRectShadow | UniformType::IntSampler2dRect |
+        UniformType::UnsignedIntSampler2dRect => gl::TEXTURE_RECTANGLE,
+        _ => return None
+    };
+    Some(target)
+}
+
+/// A readable name of a texture target, for error messages.
+pub fn texture_target_name(target: GLenum) -> &'static str {
+    match target {
+        gl::TEXTURE_1D => "1D",
+        gl::TEXTURE_2D => "2D",
+        gl::TEXTURE_3D => "3D",
+        gl::TEXTURE_CUBE_MAP => "cube map",
+        gl::TEXTURE_1D_ARRAY => "1D array",
+        gl::TEXTURE_2D_ARRAY => "2D array",
+        gl::TEXTURE_2D_MULTISAMPLE => "2D multisample",
+        gl::TEXTURE_2D_MULTISAMPLE_ARRAY => "2D multisample array",
+        gl::TEXTURE_BUFFER => "buffer",
+        gl::TEXTURE_RECTANGLE => "rectangle",
+        _ => "unknown"
+    }
+}
+
 /// Helper struct containing all the information a GL uniform has. This is only an intermediary
 /// to use when gathering the uniform information, before passing it to user in a nicer form.
 struct GlUniform {
@@ -153,6 +197,7 @@ struct GlUniform {
     offset: i32,
     array_stride: i32,
     matrix_stride: i32,
+    is_row_major: bool
 }
 
 impl GlUniform {
@@ -164,13 +209,15 @@ impl GlUniform {
             block_index: 0,
             offset: 0,
             array_stride: 0,
-            matrix_stride: 0
+            matrix_stride: 0,
+            is_row_major: false
         }
     }
 }
 
 /// Top-level result structure for program's uniform introspection info.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct UniformInfo {
     /// Global uniforms, not in interface blocks.
     pub globals: Vec<Uniform>,
@@ -207,10 +254,46 @@ impl UniformInfo {
         }
         None
     }
+
+    /// A digest of the uniforms and the blocks: their names, types, array sizes and locations,
+    /// and the sizes, indices and layouts of the blocks. The value stays the same between runs,
+    /// and doesn't depend on the order the uniforms are listed in, so when a reloaded shader
+    /// gives a different value, its interface has changed and the locations, buffer layouts and
+    /// such derived from it must be looked up again.
+    pub fn interface_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        let mut globals: Vec<&Uniform> = self.globals.iter().collect();
+        globals.sort_by(|a, b| a.name.cmp(&b.name));
+        for uniform in globals {
+            hasher.feed(uniform.name.as_bytes());
+            hasher.feed_u32(uniform_type_to_u32(uniform.uniform_type));
+            hasher.feed_i32(uniform.size);
+            hasher.feed_i32(uniform.location);
+        }
+        let mut blocks: Vec<&InterfaceBlock> = self.blocks.iter().collect();
+        blocks.sort_by(|a, b| a.name.cmp(&b.name));
+        for block in blocks {
+            hasher.feed(block.name.as_bytes());
+            hasher.feed_i32(block.index as i32);
+            hasher.feed_i32(block.data_size);
+            let mut uniforms: Vec<&BlockUniform> = block.uniforms.iter().collect();
+            uniforms.sort_by(|a, b| a.name.cmp(&b.name));
+            for uniform in uniforms {
+                hasher.feed(uniform.name.as_bytes());
+                hasher.feed_u32(uniform_type_to_u32(uniform.uniform_type));
+                hasher.feed_i32(uniform.size);
+                hasher.feed_i32(uniform.offset);
+                hasher.feed_i32(uniform.array_stride);
+                hasher.feed_i32(uniform.matrix_stride);
+            }
+        }
+        hasher.finish()
+    }
 }
 
 /// A uniform not in a block. A "global" uniform.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct Uniform {
     /// Name of the uniform.
     pub name: String,
@@ -236,6 +319,7 @@ impl Uniform {
 
 /// Description of an interface block.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct InterfaceBlock {
     /// Name of the block.
     pub name: String,
@@ -260,8 +344,8 @@ impl InterfaceBlock {
 }
 
 /// A uniform contained within a block.
-/// TODO: Missing info whether a matrix uniform is row major.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct BlockUniform {
     /// Name of the uniform.
     pub name: String,
@@ -276,6 +360,10 @@ pub struct BlockUniform {
     pub array_stride: i32,
     /// Distance between rows/cols of a matrix uniform. See GL_UNIFORM_MATRIX_STRIDE.
     pub matrix_stride: i32,
+    /// Is the matrix uniform stored row by row, declared `row_major`, with each row at the
+    /// matrix stride? False for column major matrices and other types. See
+    /// GL_UNIFORM_IS_ROW_MAJOR.
+    pub is_row_major: bool
 }
 
 impl BlockUniform {
@@ -286,7 +374,8 @@ impl BlockUniform {
             size: gl_uniform.size,
             offset: gl_uniform.offset,
             array_stride: gl_uniform.array_stride,
-            matrix_stride: gl_uniform.matrix_stride
+            matrix_stride: gl_uniform.matrix_stride,
+            is_row_major: gl_uniform.is_row_major
         }
     }
 }
@@ -312,39 +401,89 @@ pub fn make_uniform_info(program: &Program) -> UniformInfo {
     }
 }
 
+/// Find a single global uniform by name, querying only that uniform. None if the program has no
+/// such active uniform, or if it is in a block.
+pub fn find_uniform(program: &Program, name: &str) -> Option<Uniform> {
+    let index = get_uniform_index(program.id, name)?;
+    let mut gl_uniforms = [GlUniform::new(name.to_string())];
+    fill_gl_uniforms(program.id, &[index], &mut gl_uniforms, &mut [0]);
+    let [gl_uniform] = gl_uniforms;
+    if gl_uniform.block_index >= 0 {
+        return None;
+    }
+    let location = program.get_uniform_location(name);
+    Some(Uniform::new(gl_uniform, location))
+}
+
+/// Find a single uniform block by name, querying only that block and its uniforms. None if the
+/// program has no such active block.
+pub fn find_block(program: &Program, name: &str) -> Option<InterfaceBlock> {
+    let index = get_uniform_block_index(program.id, name);
+    if index == gl::INVALID_INDEX {
+        return None;
+    }
+    let data_size = get_block_info(program.id, index, gl::UNIFORM_BLOCK_DATA_SIZE);
+    let uniform_count = get_block_info(program.id, index, gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS) as usize;
+    let mut uniforms = Vec::with_capacity(uniform_count);
+    if uniform_count > 0 {
+        let mut intvalues = vec![0; uniform_count];
+        unsafe {
+            gl::GetActiveUniformBlockiv(program.id, index, gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES, intvalues.as_mut_ptr());
+            check_error!();
+        }
+        // In the same order as in make_uniform_info
+        let mut indices: Vec<u32> = intvalues.iter().map(|&index| index as u32).collect();
+        indices.sort();
+        let mut name_buffer = vec![0u8; program.get_value(gl::ACTIVE_UNIFORM_MAX_LENGTH) as usize];
+        let mut gl_uniforms: Vec<GlUniform> = indices.iter()
+            .map(|&index| GlUniform::new(uniform_name(program.id, index, &mut name_buffer)))
+            .collect();
+        fill_gl_uniforms(program.id, &indices, &mut gl_uniforms, &mut intvalues);
+        uniforms.extend(gl_uniforms.into_iter().map(BlockUniform::new));
+    }
+    Some(InterfaceBlock {
+        name: name.to_string(),
+        index,
+        data_size,
+        uniforms
+    })
+}
+
 /// Builds a vector of GlUniform that represents global uniforms of a program.
 fn make_gl_uniform_info_vec(program: &Program) -> Vec<GlUniform> {
     let count = program.get_value(gl::ACTIVE_UNIFORMS) as usize;
     if count == 0 {
         return Vec::new();
     }
-    //let mut info_vec = Vec::with_capacity(count);
-    let mut intvalues = repeat(0).take(count).collect();
-    let indices = (0..count as u32).collect();
-    fill_uniform_info_vec(program.id, &indices, gl::UNIFORM_NAME_LENGTH, &mut intvalues);
-    //for (index, expected_len) in intvalues.iter().enumerate() {
-    //    info_vec.push(GlUniform::new(uniform_name(program.id, index as u32, *expected_len as u32)));
-    //}
-    let mut info_vec: Vec<GlUniform> = intvalues.iter().enumerate()
-        .map(|(index, expected_len)| GlUniform::new(uniform_name(program.id, index as u32, *expected_len as u32)))
+    let indices: Vec<u32> = (0..count as u32).collect();
+    let mut intvalues = vec![0; count];
+    // One buffer fits all the names, so it is reused for each
+    let mut name_buffer = vec![0u8; program.get_value(gl::ACTIVE_UNIFORM_MAX_LENGTH) as usize];
+    let mut info_vec: Vec<GlUniform> = indices.iter()
+        .map(|&index| GlUniform::new(uniform_name(program.id, index, &mut name_buffer)))
         .collect();
-    {
-        let mut fill_info = |property, info_fn: &mut Fn(&mut GlUniform, i32)| {
-            fill_uniform_info_vec(program.id, &indices, property, &mut intvalues);
-            for (info, value) in info_vec.iter_mut().zip(intvalues.iter()) {
-                info_fn(info, *value);
-            }
-        };
-        fill_info(gl::UNIFORM_SIZE, &mut|info, value| info.size = value);
-        fill_info(gl::UNIFORM_TYPE, &mut|info, value| info.uniform_type = value);
-        fill_info(gl::UNIFORM_OFFSET, &mut|info, value| info.offset = value);
-        fill_info(gl::UNIFORM_BLOCK_INDEX, &mut|info, value| info.block_index = value);
-        fill_info(gl::UNIFORM_ARRAY_STRIDE, &mut|info, value| info.array_stride = value);
-        fill_info(gl::UNIFORM_MATRIX_STRIDE, &mut|info, value| info.matrix_stride = value);
-    }
+    fill_gl_uniforms(program.id, &indices, &mut info_vec, &mut intvalues);
     info_vec
 }
 
+/// Query the properties of the uniforms of the indices, one property for all of them at a time.
+/// The value slice is scratch space of the same length as the indices.
+fn fill_gl_uniforms(program_id: u32, indices: &[u32], info_vec: &mut [GlUniform], intvalues: &mut [i32]) {
+    let mut fill_info = |property, info_fn: &mut dyn Fn(&mut GlUniform, i32)| {
+        fill_uniform_info_vec(program_id, indices, property, intvalues);
+        for (info, value) in info_vec.iter_mut().zip(intvalues.iter()) {
+            info_fn(info, *value);
+        }
+    };
+    fill_info(gl::UNIFORM_SIZE, &mut|info, value| info.size = value);
+    fill_info(gl::UNIFORM_TYPE, &mut|info, value| info.uniform_type = value);
+    fill_info(gl::UNIFORM_OFFSET, &mut|info, value| info.offset = value);
+    fill_info(gl::UNIFORM_BLOCK_INDEX, &mut|info, value| info.block_index = value);
+    fill_info(gl::UNIFORM_ARRAY_STRIDE, &mut|info, value| info.array_stride = value);
+    fill_info(gl::UNIFORM_MATRIX_STRIDE, &mut|info, value| info.matrix_stride = value);
+    fill_info(gl::UNIFORM_IS_ROW_MAJOR, &mut|info, value| info.is_row_major = value != 0);
+}
+
 /// Builds a vector of GlUniform that represents uniform blocks of a program.
 fn make_uniform_block_info_vec(program: &Program) -> Vec<InterfaceBlock> {
     let count = program.get_value(gl::ACTIVE_UNIFORM_BLOCKS);
@@ -352,11 +491,10 @@ fn make_uniform_block_info_vec(program: &Program) -> Vec<InterfaceBlock> {
         return Vec::new();
     }
     let mut info_vec = Vec::with_capacity(count as usize);
+    let mut name_buffer = vec![0u8; program.get_value(gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH) as usize];
     for index in 0..count as u32 {
-        let expected_len = get_block_info(program.id, index, gl::UNIFORM_BLOCK_NAME_LENGTH) as u32;
         let data_size = get_block_info(program.id, index, gl::UNIFORM_BLOCK_DATA_SIZE);
-        let name = block_name(program.id, index, expected_len);
-        let index = get_uniform_block_index(program.id, &name[..]);
+        let name = block_name(program.id, index, &mut name_buffer);
         info_vec.push(InterfaceBlock {
             index: index,
             name: name,
@@ -368,7 +506,7 @@ fn make_uniform_block_info_vec(program: &Program) -> Vec<InterfaceBlock> {
 }
 
 /// Gets a single uniform-describing attribute for multiple uniforms as identified by the indices.
-fn fill_uniform_info_vec(program_id: u32, indices: &Vec<u32>, property: GLenum, intvalues: &mut Vec<i32>) {
+fn fill_uniform_info_vec(program_id: u32, indices: &[u32], property: GLenum, intvalues: &mut [i32]) {
     unsafe {
         gl::GetActiveUniformsiv(program_id, indices.len() as i32, indices.as_ptr(), property, intvalues.as_mut_ptr());
         check_error!();
@@ -385,28 +523,54 @@ fn get_block_info(program_id: u32, block_index: u32, property: GLenum) -> i32 {
     }
 }
 
-/// Finds out what a uniform's name is.
-fn uniform_name(program_id: u32, index: u32, expected_len: u32) -> String {
-    let mut name_vec: Vec<u8> = repeat(0u8).take(expected_len as usize).collect();
+/// Finds out what a uniform's name is. The name is read into the buffer, which must be long
+/// enough for the longest name (see GL_ACTIVE_UNIFORM_MAX_LENGTH).
+#[cfg(not(feature = "es3"))]
+fn uniform_name(program_id: u32, index: u32, buffer: &mut [u8]) -> String {
+    let mut length = 0;
+    unsafe {
+        let name_ptr = buffer.as_mut_ptr() as *mut i8;
+        gl::GetActiveUniformName(program_id, index, buffer.len() as i32, &mut length, name_ptr);
+        check_error!();
+    }
+    slice_to_string(&buffer[..length as usize])
+}
+
+/// Finds out what a uniform's name is. OpenGL ES has no glGetActiveUniformName, but
+/// glGetActiveUniform returns the name too.
+#[cfg(feature = "es3")]
+fn uniform_name(program_id: u32, index: u32, buffer: &mut [u8]) -> String {
+    let mut length = 0;
     unsafe {
-        let name_ptr = name_vec[..].as_mut_ptr() as *mut i8;
-        gl::GetActiveUniformName(program_id, index, name_vec.len() as i32, null_mut(), name_ptr);
+        let name_ptr = buffer.as_mut_ptr() as *mut i8;
+        let mut size = 0;
+        let mut uniform_type = 0;
+        gl::GetActiveUniform(program_id, index, buffer.len() as i32, &mut length, &mut size, &mut uniform_type, name_ptr);
         check_error!();
     }
-    name_vec.pop(); // Remove the null byte from end
-    String::from_utf8(name_vec).unwrap()
+    slice_to_string(&buffer[..length as usize])
 }
 
-/// Finds out what an interface block's name is.
-fn block_name(program_id: u32, index: u32, expected_len: u32) -> String {
-    let mut name_vec: Vec<u8> = repeat(0u8).take(expected_len as usize).collect();
+/// Finds out what an interface block's name is, reading it into the buffer like `uniform_name`.
+fn block_name(program_id: u32, index: u32, buffer: &mut [u8]) -> String {
+    let mut length = 0;
     unsafe {
-        let name_ptr = name_vec[..].as_mut_ptr() as *mut i8;
-        gl::GetActiveUniformBlockName(program_id, index, name_vec.len() as i32, null_mut(), name_ptr);
+        let name_ptr = buffer.as_mut_ptr() as *mut i8;
+        gl::GetActiveUniformBlockName(program_id, index, buffer.len() as i32, &mut length, name_ptr);
         check_error!();
     }
-    name_vec.pop(); // Remove the null byte from end
-    String::from_utf8(name_vec).unwrap()
+    slice_to_string(&buffer[..length as usize])
+}
+
+/// The index of the active uniform of the name, if there is one. See glGetUniformIndices.
+fn get_uniform_index(program_id: u32, name: &str) -> Option<u32> {
+    let c_name = CString::new(name).unwrap();
+    let mut index = gl::INVALID_INDEX;
+    unsafe {
+        gl::GetUniformIndices(program_id, 1, &c_name.as_ptr(), &mut index);
+        check_error!();
+    }
+    if index == gl::INVALID_INDEX { None } else { Some(index) }
 }
 
 fn get_uniform_block_index(program_id: u32, name: &str) -> u32 {
@@ -485,7 +649,7 @@ pub fn uniform_i32(location: i32, count: usize, uniform_type: SimpleUniformTypeI
 }
 
 /// Check that there's enough values in the slice to set `count` uniforms of given type.
-fn validate_uniform_f32(count: usize, uniform_type: SimpleUniformTypeFloat, values: &[f32]) {
+pub fn validate_uniform_f32(count: usize, uniform_type: SimpleUniformTypeFloat, values: &[f32]) {
     let element_count = match uniform_type {
         SimpleUniformTypeFloat::Uniform1f => 1,
         SimpleUniformTypeFloat::Uniform2f => 2,
@@ -496,7 +660,7 @@ fn validate_uniform_f32(count: usize, uniform_type: SimpleUniformTypeFloat, valu
 }
 
 /// Check that there's enough values in the slice to set `count` uniforms of given type.
-fn validate_uniform_matrix(count: usize, uniform_type: SimpleUniformTypeMatrix, values: &[f32]) {
+pub fn validate_uniform_matrix(count: usize, uniform_type: SimpleUniformTypeMatrix, values: &[f32]) {
     let element_count = match uniform_type {
         SimpleUniformTypeMatrix::Matrix2f => 2 * 2,
         SimpleUniformTypeMatrix::Matrix3f => 3 * 3,
@@ -512,7 +676,7 @@ fn validate_uniform_matrix(count: usize, uniform_type: SimpleUniformTypeMatrix,
 }
 
 /// Check that there's enough values in the slice to set `count` uniforms of given type.
-fn validate_uniform_u32(count: usize, uniform_type: SimpleUniformTypeU32, values: &[u32]) {
+pub fn validate_uniform_u32(count: usize, uniform_type: SimpleUniformTypeU32, values: &[u32]) {
     let element_count = match uniform_type {
         SimpleUniformTypeU32::Uniform1u => 1,
         SimpleUniformTypeU32::Uniform2u => 2,
@@ -523,7 +687,7 @@ fn validate_uniform_u32(count: usize, uniform_type: SimpleUniformTypeU32, values
 }
 
 /// Check that there's enough values in the slice to set `count` uniforms of given type.
-fn validate_uniform_i32(count: usize, uniform_type: SimpleUniformTypeI32, values: &[i32]) {
+pub fn validate_uniform_i32(count: usize, uniform_type: SimpleUniformTypeI32, values: &[i32]) {
     let element_count = match uniform_type {
         SimpleUniformTypeI32::Uniform1i => 1,
         SimpleUniformTypeI32::Uniform2i => 2,
@@ -542,7 +706,7 @@ fn validate_uniform<T, U: Debug>(count: usize, uniform_type: U, element_count: u
     }
 }
 
-fn uniform_type_from_u32(gl_type: u32) -> UniformType {
+pub fn uniform_type_from_u32(gl_type: u32) -> UniformType {
     match gl_type {
         gl::FLOAT => UniformType::Float,
         gl::FLOAT_VEC2 => UniformType::FloatVec2,
@@ -607,4 +771,72 @@ fn uniform_type_from_u32(gl_type: u32) -> UniformType {
         gl::UNSIGNED_INT_SAMPLER_2D_RECT => UniformType::UnsignedIntSampler2dRect,
         _ => UniformType::UnrecognizedType(gl_type)
     }
+}
+
+/// The OpenGL enum of the type, the inverse of `uniform_type_from_u32`.
+fn uniform_type_to_u32(uniform_type: UniformType) -> u32 {
+    match uniform_type {
+        UniformType::Float => gl::FLOAT,
+        UniformType::FloatVec2 => gl::FLOAT_VEC2,
+        UniformType::FloatVec3 => gl::FLOAT_VEC3,
+        UniformType::FloatVec4 => gl::FLOAT_VEC4,
+        UniformType::Int => gl::INT,
+        UniformType::IntVec2 => gl::INT_VEC2,
+        UniformType::IntVec3 => gl::INT_VEC3,
+        UniformType::IntVec4 => gl::INT_VEC4,
+        UniformType::UnsignedInt => gl::UNSIGNED_INT,
+        UniformType::UnsignedIntVec2 => gl::UNSIGNED_INT_VEC2,
+        UniformType::UnsignedIntVec3 => gl::UNSIGNED_INT_VEC3,
+        UniformType::UnsignedIntVec4 => gl::UNSIGNED_INT_VEC4,
+        UniformType::Bool => gl::BOOL,
+        UniformType::BoolVec2 => gl::BOOL_VEC2,
+        UniformType::BoolVec3 => gl::BOOL_VEC3,
+        UniformType::BoolVec4 => gl::BOOL_VEC4,
+        UniformType::FloatMat2 => gl::FLOAT_MAT2,
+        UniformType::FloatMat3 => gl::FLOAT_MAT3,
+        UniformType::FloatMat4 => gl::FLOAT_MAT4,
+        UniformType::FloatMat2x3 => gl::FLOAT_MAT2x3,
+        UniformType::FloatMat2x4 => gl::FLOAT_MAT2x4,
+        UniformType::FloatMat3x2 => gl::FLOAT_MAT3x2,
+        UniformType::FloatMat3x4 => gl::FLOAT_MAT3x4,
+        UniformType::FloatMat4x2 => gl::FLOAT_MAT4x2,
+        UniformType::FloatMat4x3 => gl::FLOAT_MAT4x3,
+        UniformType::Sampler1d => gl::SAMPLER_1D,
+        UniformType::Sampler2d => gl::SAMPLER_2D,
+        UniformType::Sampler3d => gl::SAMPLER_3D,
+        UniformType::SamplerCube => gl::SAMPLER_CUBE,
+        UniformType::Sampler1dShadow => gl::SAMPLER_1D_SHADOW,
+        UniformType::Sampler2dShadow => gl::SAMPLER_2D_SHADOW,
+        UniformType::Sampler1dArray => gl::SAMPLER_1D_ARRAY,
+        UniformType::Sampler2dArray => gl::SAMPLER_2D_ARRAY,
+        UniformType::Sampler1dArrayShadow => gl::SAMPLER_1D_ARRAY_SHADOW,
+        UniformType::Sampler2dArrayShadow => gl::SAMPLER_2D_ARRAY_SHADOW,
+        UniformType::Sampler2dMultisample => gl::SAMPLER_2D_MULTISAMPLE,
+        UniformType::Sampler2dMultisampleArray => gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+        UniformType::SamplerCubeShadow => gl::SAMPLER_CUBE_SHADOW,
+        UniformType::SamplerBuffer => gl::SAMPLER_BUFFER,
+        UniformType::Sampler2dRect => gl::SAMPLER_2D_RECT,
+        UniformType::Sampler2dRectShadow => gl::SAMPLER_2D_RECT_SHADOW,
+        UniformType::IntSampler1d => gl::INT_SAMPLER_1D,
+        UniformType::IntSampler2d => gl::INT_SAMPLER_2D,
+        UniformType::IntSampler3d => gl::INT_SAMPLER_3D,
+        UniformType::IntSamplerCube => gl::INT_SAMPLER_CUBE,
+        UniformType::IntSampler1dArray => gl::INT_SAMPLER_1D_ARRAY,
+        UniformType::IntSampler2dArray => gl::INT_SAMPLER_2D_ARRAY,
+        UniformType::IntSampler2dMultisample => gl::INT_SAMPLER_2D_MULTISAMPLE,
+        UniformType::IntSampler2dMultisampleArray => gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+        UniformType::IntSamplerBuffer => gl::INT_SAMPLER_BUFFER,
+        UniformType::IntSampler2dRect => gl::INT_SAMPLER_2D_RECT,
+        UniformType::UnsignedIntSampler1d => gl::UNSIGNED_INT_SAMPLER_1D,
+        UniformType::UnsignedIntSampler2d => gl::UNSIGNED_INT_SAMPLER_2D,
+        UniformType::UnsignedIntSampler3d => gl::UNSIGNED_INT_SAMPLER_3D,
+        UniformType::UnsignedIntSamplerCube => gl::UNSIGNED_INT_SAMPLER_CUBE,
+        UniformType::UnsignedIntSampler1dArray => gl::UNSIGNED_INT_SAMPLER_1D_ARRAY,
+        UniformType::UnsignedIntSampler2dArray => gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+        UniformType::UnsignedIntSampler2dMultisample => gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE,
+        UniformType::UnsignedIntSampler2dMultisampleArray => gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+        UniformType::UnsignedIntSamplerBuffer => gl::UNSIGNED_INT_SAMPLER_BUFFER,
+        UniformType::UnsignedIntSampler2dRect => gl::UNSIGNED_INT_SAMPLER_2D_RECT,
+        UniformType::UnrecognizedType(gl_type) => gl_type
+    }
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::super::util::slice_to_string;
+use super::Program;
+use super::blocklayout;
+use super::uniform::{self,UniformType};
+
+/// The varyings a program captures with transform feedback, see
+/// `ProgramInfoAccessor::get_transform_feedback_info`.
+#[derive(Debug)]
+pub struct TransformFeedbackInfo {
+    /// Are the varyings interleaved into the capture buffers (GL_INTERLEAVED_ATTRIBS), or each
+    /// written to its own buffer (GL_SEPARATE_ATTRIBS)?
+    pub interleaved: bool,
+    /// List of the captured varyings, in capture order.
+    pub varyings: Vec<TransformFeedbackVarying>,
+    /// The bytes written per vertex to each capture buffer, indexed by buffer.
+    pub buffer_strides: Vec<usize>
+}
+
+impl TransformFeedbackInfo {
+    /// Find a varying by name.
+    pub fn get_varying(&self, name: &str) -> Option<&TransformFeedbackVarying> {
+        self.varyings.iter().find(|varying| varying.name == name)
+    }
+}
+
+/// Describes a varying captured with transform feedback.
+#[derive(Debug)]
+pub struct TransformFeedbackVarying {
+    /// Name of the varying
+    pub name: String,
+    /// Data type of the varying. The same types are used as for uniforms.
+    pub varying_type: UniformType,
+    /// Number of array elements, one for non-arrays.
+    pub size: i32,
+    /// The capture buffer the varying is written to.
+    pub buffer_index: u32,
+    /// Byte offset of the varying within a captured vertex of its buffer.
+    pub offset: usize
+}
+
+/// Read the transform feedback varyings with glGetTransformFeedbackVarying. The buffers and
+/// offsets are worked out from the buffer mode and the varying sizes, honoring the
+/// gl_NextBuffer and gl_SkipComponents markers of interleaved capture.
+pub fn make_transform_feedback_info(program: &Program) -> TransformFeedbackInfo {
+    let interleaved = program.get_value(gl::TRANSFORM_FEEDBACK_BUFFER_MODE) as u32 == gl::INTERLEAVED_ATTRIBS;
+    let count = program.get_value(gl::TRANSFORM_FEEDBACK_VARYINGS);
+    let max_length = program.get_value(gl::TRANSFORM_FEEDBACK_VARYING_MAX_LENGTH);
+    let mut name_vec: Vec<u8> = vec![0u8; max_length.max(1) as usize];
+    let mut varyings = Vec::new();
+    let mut buffer_strides = Vec::new();
+    let mut buffer_index = 0;
+    let mut offset = 0;
+    for index in 0..count as u32 {
+        let mut actual_length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        unsafe {
+            gl::GetTransformFeedbackVarying(program.id, index, name_vec.len() as i32, &mut actual_length, &mut size,
+                &mut gl_type, name_vec.as_mut_ptr() as *mut i8);
+        }
+        check_error!();
+        let name = slice_to_string(&name_vec[0..actual_length as usize]);
+        if name == "gl_NextBuffer" {
+            buffer_strides.push(offset);
+            buffer_index += 1;
+            offset = 0;
+            continue;
+        }
+        if name.starts_with("gl_SkipComponents") {
+            offset += size as usize * 4;
+            continue;
+        }
+        let varying_type = uniform::uniform_type_from_u32(gl_type);
+        let varying_size = match blocklayout::type_shape(varying_type) {
+            Some((_, rows, columns)) => rows * columns * 4 * size as usize,
+            None => 0
+        };
+        varyings.push(TransformFeedbackVarying {
+            name,
+            varying_type,
+            size,
+            buffer_index,
+            offset
+        });
+        if interleaved {
+            offset += varying_size;
+        }
+        else {
+            buffer_strides.push(varying_size);
+            buffer_index += 1;
+        }
+    }
+    if interleaved && (offset > 0 || !buffer_strides.is_empty()) {
+        buffer_strides.push(offset);
+    }
+    TransformFeedbackInfo { interleaved, varyings, buffer_strides }
+}
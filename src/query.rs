@@ -0,0 +1,163 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Query objects, for finding out things about the commands the GPU has executed, see `Query`.
+
+use std::fmt;
+
+use gl::types::{GLenum,GLuint};
+#[cfg(not(feature = "es3"))]
+use gl::types::GLuint64;
+
+use super::context::{RegistrationHandle,QueuedDeletion,ContextResource};
+
+/// What a query measures. See glBeginQuery.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum QueryTarget {
+    /// GL_ANY_SAMPLES_PASSED, one if any samples passed the depth and stencil tests, else zero.
+    AnySamplesPassed,
+    /// GL_ANY_SAMPLES_PASSED_CONSERVATIVE, like `AnySamplesPassed`, but may report samples
+    /// passed when none did, which can be faster. Core in OpenGL 4.3 and OpenGL ES 3.0.
+    AnySamplesPassedConservative,
+    /// GL_SAMPLES_PASSED, the number of samples that passed. Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
+    SamplesPassed,
+    /// GL_TIME_ELAPSED, the time the GPU took to execute the commands in nanoseconds. Core in
+    /// OpenGL 3.3, not available on OpenGL ES. Read long times with `result_u64`.
+    #[cfg(not(feature = "es3"))]
+    TimeElapsed
+}
+
+/// What conditional rendering does when the result of the query is not available yet, see
+/// `Renderer::conditional_render`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum ConditionalRenderMode {
+    /// GL_QUERY_WAIT, wait for the result on the GPU.
+    Wait,
+    /// GL_QUERY_NO_WAIT, render as if the query had passed.
+    NoWait
+}
+
+/// A query object created with `Context::new_query`. The query is run around drawing commands
+/// with `Renderer::with_query`, and the result becomes available some time after the GPU has
+/// executed them. Don't run the query again before reading the result, or the result is lost.
+pub struct Query {
+    pub id: u32,
+    target: QueryTarget,
+    registration: RegistrationHandle
+}
+
+impl Query {
+    /// What the query measures.
+    pub fn target(&self) -> QueryTarget {
+        self.target
+    }
+
+    /// Is the result of the last run available? Doesn't block. A query that has never been run
+    /// has no result. See GL_QUERY_RESULT_AVAILABLE.
+    pub fn is_available(&self) -> bool {
+        let mut available: GLuint = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        check_error!();
+        available != 0
+    }
+
+    /// The result of the last run, or None if it is not available yet. Doesn't block.
+    pub fn try_result(&self) -> Option<u32> {
+        if self.is_available() {
+            Some(self.result())
+        }
+        else {
+            None
+        }
+    }
+
+    /// The result of the last run, blocking until the GPU has got that far. See GL_QUERY_RESULT.
+    pub fn result(&self) -> u32 {
+        let mut result: GLuint = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.id, gl::QUERY_RESULT, &mut result);
+        }
+        check_error!();
+        result
+    }
+
+    /// The result of the last run as a 64-bit value, or None if it is not available yet.
+    /// Doesn't block.
+    #[cfg(not(feature = "es3"))]
+    pub fn try_result_u64(&self) -> Option<u64> {
+        if self.is_available() {
+            Some(self.result_u64())
+        }
+        else {
+            None
+        }
+    }
+
+    /// The result of the last run as a 64-bit value, blocking like `result`. See
+    /// glGetQueryObjectui64v.
+    #[cfg(not(feature = "es3"))]
+    pub fn result_u64(&self) -> u64 {
+        let mut result: GLuint64 = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(self.id, gl::QUERY_RESULT, &mut result);
+        }
+        check_error!();
+        result
+    }
+
+    /// The OpenGL target of the query, such as GL_ANY_SAMPLES_PASSED.
+    pub fn gl_target(&self) -> GLenum {
+        match self.target {
+            QueryTarget::AnySamplesPassed => gl::ANY_SAMPLES_PASSED,
+            QueryTarget::AnySamplesPassedConservative => gl::ANY_SAMPLES_PASSED_CONSERVATIVE,
+            #[cfg(not(feature = "es3"))]
+            QueryTarget::SamplesPassed => gl::SAMPLES_PASSED,
+            #[cfg(not(feature = "es3"))]
+            QueryTarget::TimeElapsed => gl::TIME_ELAPSED
+        }
+    }
+}
+
+impl fmt::Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Query")
+            .field("id", &self.id)
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+impl ContextResource for Query {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.registration.queue_deletion(QueuedDeletion::Query(self.id));
+    }
+}
+
+/// Constructor not visible to library users.
+pub fn new_query(target: QueryTarget, registration: RegistrationHandle) -> Query {
+    let mut id: u32 = 0;
+    unsafe {
+        gl::GenQueries(1, &mut id);
+    }
+    check_error!();
+    Query { id, target, registration }
+}
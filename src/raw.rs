@@ -0,0 +1,87 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An escape hatch to the raw OpenGL object names, for interoperating with other code that uses
+//! OpenGL directly. Objects created elsewhere can be brought under this library with the
+//! `adopt_raw_*` methods of `Context`.
+
+use super::{BufferHandle,VertexArrayHandle,ShaderHandle,ProgramHandle,TextureHandle};
+use super::handle::HandleAccess;
+
+/// Access to the OpenGL name of the object behind a handle. Only implemented by the library
+/// itself.
+///
+/// # Safety
+///
+/// The name gives a way around everything this library keeps track of. Implementations must
+/// return the name of a live object of the kind the handle stands for, owned or adopted by the
+/// context. Whoever uses the name must not delete the object or change the size or format of
+/// its storage, and must only bind it inside `Context::with_raw_gl`: otherwise the context is
+/// left in a state that doesn't match what the library thinks it is.
+pub unsafe trait RawGlObject {
+    /// The OpenGL object name, as returned by glGen* or glCreate*.
+    fn raw_gl_id(&self) -> u32;
+}
+
+/// A token given to the closure of `Context::with_raw_gl`, marking the code where calling
+/// OpenGL functions directly is fine. The token can't be created or kept outside the closure.
+pub struct RawGlScope {
+    _private: ()
+}
+
+/// Create the token. Not a method to keep it out of the public API.
+pub fn new_raw_gl_scope() -> RawGlScope {
+    RawGlScope { _private: () }
+}
+
+/// Who is responsible for deleting an adopted object.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Ownership {
+    /// The object is deleted when the last handle to it is dropped, like any object created
+    /// through `Context`.
+    Owned,
+    /// The object is never deleted by this library. Whoever created it must delete it, after the
+    /// last handle has been dropped.
+    Borrowed
+}
+
+unsafe impl RawGlObject for BufferHandle {
+    fn raw_gl_id(&self) -> u32 {
+        self.access().id
+    }
+}
+
+unsafe impl RawGlObject for VertexArrayHandle {
+    fn raw_gl_id(&self) -> u32 {
+        self.access().id
+    }
+}
+
+unsafe impl RawGlObject for ShaderHandle {
+    fn raw_gl_id(&self) -> u32 {
+        self.access().get_id()
+    }
+}
+
+unsafe impl RawGlObject for ProgramHandle {
+    fn raw_gl_id(&self) -> u32 {
+        self.access().get_id()
+    }
+}
+
+unsafe impl RawGlObject for TextureHandle {
+    fn raw_gl_id(&self) -> u32 {
+        self.access().id
+    }
+}
@@ -14,22 +14,318 @@
 
 //! This module contains the actual drawing functionality. See `Renderer` for further information.
 
-use gl;
-use gl::types::{GLint,GLsizei,GLvoid,GLenum};
+use std::ops::{Deref,DerefMut};
+use std::rc::Rc;
 
-use super::{VertexArrayHandle,ProgramHandle};
-use super::context::{Context,ContextRenderingSupport};
-use super::options::{self,RenderOption};
+use gl::types::{GLbitfield,GLint,GLsizei,GLvoid,GLenum};
+
+use super::{BufferHandle,VertexArrayHandle,ProgramHandle,TextureHandle,RenderTargetHandle};
+use super::handle::HandleAccess;
+use super::buffer::IndexType;
+use super::program::{self,SimpleUniformTypeFloat,SimpleUniformTypeMatrix,SimpleUniformTypeU32,SimpleUniformTypeI32};
+use super::context::{Context,ContextEditingSupport,ContextRenderingSupport};
+use super::options::{RenderOption,ClearState,DepthFunction,ClipOrigin,ClipDepth,PixelStoreParam,BlendPreset,BlendEquation,BlendFactor};
+use super::framebuffer::{self,RenderTarget};
+use super::surface::Surface;
+use super::debug;
+use super::info::ExtensionFeature;
+use super::query::{Query,ConditionalRenderMode};
+use super::indirect::INDIRECT_COMMAND_SIZE;
+use super::tracker::IndexedBufferTarget;
+use super::util::{self,VALIDATION};
+use super::vertexarray::{VertexFormat,AttributeDefault};
 
 /// Supported primitive drawing modes
+#[derive(Clone,Copy,Debug,PartialEq)]
 pub enum PrimitiveMode {
+    /// GL_POINTS
+    Points,
+    /// GL_LINES
+    Lines,
+    /// GL_LINE_STRIP
+    LineStrip,
+    /// GL_LINE_LOOP
+    LineLoop,
     /// GL_TRIANGLES
-    Triangles
+    Triangles,
+    /// GL_TRIANGLE_STRIP
+    TriangleStrip,
+    /// GL_TRIANGLE_FAN
+    TriangleFan
+}
+
+/// The kinds of reads that must see the writes of earlier shaders through storage buffers,
+/// atomic counters and images, see `Renderer::memory_barrier`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum MemoryBarrier {
+    /// GL_VERTEX_ATTRIB_ARRAY_BARRIER_BIT, vertex data read from buffers.
+    VertexAttribArray,
+    /// GL_ELEMENT_ARRAY_BARRIER_BIT, indices read from buffers.
+    ElementArray,
+    /// GL_UNIFORM_BARRIER_BIT, uniform blocks.
+    Uniform,
+    /// GL_COMMAND_BARRIER_BIT, indirect draw commands.
+    Command,
+    /// GL_BUFFER_UPDATE_BARRIER_BIT, buffer reads and writes through OpenGL calls.
+    BufferUpdate,
+    /// GL_SHADER_STORAGE_BARRIER_BIT, storage buffers.
+    ShaderStorage,
+    /// GL_ATOMIC_COUNTER_BARRIER_BIT, atomic counters.
+    AtomicCounter,
+    /// GL_ALL_BARRIER_BITS
+    All
+}
+
+/// A complete description of a draw call, for `Renderer::draw`. Start with `arrays` or
+/// `elements` and add instancing and the rest with the builder methods:
+///
+///    let params = DrawParams::elements(PrimitiveMode::Triangles, IndexType::U16, 0, 36)
+///        .instances(100);
+///    renderer.draw(&params);
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct DrawParams {
+    primitive_mode: PrimitiveMode,
+    /// The first vertex for unindexed draws, the byte offset of the first index otherwise.
+    first: u32,
+    count: u32,
+    /// None for unindexed draws.
+    index_type: Option<IndexType>,
+    instance_count: u32,
+    base_vertex: i32,
+    base_instance: u32,
+    primitive_restart: bool
+}
+
+impl DrawParams {
+    /// Draw `count` unindexed vertices starting from the vertex `first`. See glDrawArrays.
+    pub fn arrays(primitive_mode: PrimitiveMode, first: u32, count: u32) -> DrawParams {
+        DrawParams {
+            primitive_mode,
+            first,
+            count,
+            index_type: None,
+            instance_count: 1,
+            base_vertex: 0,
+            base_instance: 0,
+            primitive_restart: false
+        }
+    }
+
+    /// Draw `count` indexed vertices, the first index being at the byte offset `start` of the
+    /// index buffer. See glDrawElements.
+    pub fn elements(primitive_mode: PrimitiveMode, index_type: IndexType, start: u32, count: u32) -> DrawParams {
+        DrawParams { index_type: Some(index_type), ..DrawParams::arrays(primitive_mode, start, count) }
+    }
+
+    /// Draw this many instances. See glDrawArraysInstanced and glDrawElementsInstanced.
+    pub fn instances(self, instance_count: u32) -> DrawParams {
+        DrawParams { instance_count, ..self }
+    }
+
+    /// Add the value to the indices before fetching the vertices. Only for indexed draws. See
+    /// glDrawElementsBaseVertex.
+    pub fn base_vertex(self, base_vertex: i32) -> DrawParams {
+        DrawParams { base_vertex, ..self }
+    }
+
+    /// Offset the instance index used for fetching the instanced attributes. See
+    /// glDrawArraysInstancedBaseInstance.
+    pub fn base_instance(self, base_instance: u32) -> DrawParams {
+        DrawParams { base_instance, ..self }
+    }
+
+    /// Enable primitive restart for the draw, the restart index being the largest value of the
+    /// index type. Only for indexed draws. The earlier primitive restart option is restored
+    /// after the draw. See `Renderer::draw_elements_restart_u16`.
+    pub fn primitive_restart(self, enable: bool) -> DrawParams {
+        DrawParams { primitive_restart: enable, ..self }
+    }
+}
+
+/// Something a render pass draws into: a render target, or the default framebuffer with
+/// `DefaultTarget` or `DefaultFramebuffer`. See `Renderer::pass`.
+pub trait PassTarget {
+    /// The render target to bind, or None for the default framebuffer.
+    fn render_target(&self) -> Option<&Rc<RenderTarget>>;
+
+    /// The size of the target in pixels, which the viewport covers by default. None if not
+    /// known, in which case the size of the surface of the context is used.
+    fn dimensions(&self) -> Option<(u32, u32)> {
+        self.render_target().map(|render_target| render_target.dimensions())
+    }
+}
+
+/// The default framebuffer, that is, the surface the context presents, as a render pass target.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct DefaultTarget;
+
+impl PassTarget for DefaultTarget {
+    fn render_target(&self) -> Option<&Rc<RenderTarget>> {
+        None
+    }
+}
+
+/// The default framebuffer of the given drawable size as a render pass target. Unlike
+/// `DefaultTarget`, this doesn't rely on the surface given to the context, so it works without
+/// one. With several windows, each with its own context, make one per window of the size of its
+/// drawable area, and code drawing into render targets can draw into the windows the same way.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct DefaultFramebuffer {
+    pub width: u32,
+    pub height: u32
+}
+
+impl DefaultFramebuffer {
+    /// The default framebuffer of the given size in pixels.
+    pub fn new(width: u32, height: u32) -> DefaultFramebuffer {
+        DefaultFramebuffer { width, height }
+    }
+
+    /// The default framebuffer of the size of the drawable area of the surface.
+    pub fn of_surface(surface: &dyn Surface) -> DefaultFramebuffer {
+        let (width, height) = surface.dimensions();
+        DefaultFramebuffer::new(width, height)
+    }
+}
+
+impl PassTarget for DefaultFramebuffer {
+    fn render_target(&self) -> Option<&Rc<RenderTarget>> {
+        None
+    }
+
+    fn dimensions(&self) -> Option<(u32, u32)> {
+        Some((self.width, self.height))
+    }
+}
+
+impl PassTarget for RenderTargetHandle {
+    fn render_target(&self) -> Option<&Rc<RenderTarget>> {
+        Some(self.rc())
+    }
+}
+
+/// What is set up when a render pass begins, see `Renderer::pass`. By default the viewport
+/// covers the whole target, nothing is cleared, and all the color textures of a render target
+/// are drawn into. Set the rest with the builder methods:
+///
+///    renderer.pass(&target, &PassDesc::new().clear(ClearState::default()).draw_buffers(&[0, 2]));
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct PassDesc {
+    label: Option<String>,
+    viewport: Option<(i32, i32, u32, u32)>,
+    clear: Option<ClearState>,
+    draw_buffers: Option<Vec<u32>>,
+    blend_preset: Option<BlendPreset>
+}
+
+impl PassDesc {
+    /// The defaults: whole target, no clearing, all color textures.
+    pub fn new() -> PassDesc {
+        PassDesc::default()
+    }
+
+    /// Name the pass for graphics debuggers: the commands of the pass are put in a debug group
+    /// of the name, see `Renderer::debug_group`.
+    pub fn label(self, name: &str) -> PassDesc {
+        PassDesc { label: Some(name.to_string()), ..self }
+    }
+
+    /// Set the viewport to the given rectangle instead of the whole target. See glViewport.
+    pub fn viewport(self, x: i32, y: i32, width: u32, height: u32) -> PassDesc {
+        PassDesc { viewport: Some((x, y, width, height)), ..self }
+    }
+
+    /// Clear the target with the clear state when the pass begins, after setting the viewport
+    /// and the draw buffers.
+    pub fn clear(self, state: ClearState) -> PassDesc {
+        PassDesc { clear: Some(state), ..self }
+    }
+
+    /// Draw into the given color textures of the render target, by their index: the first
+    /// fragment shader output goes to the first given texture and so on. Only for render
+    /// targets. See glDrawBuffers.
+    pub fn draw_buffers(self, color_textures: &[u32]) -> PassDesc {
+        PassDesc { draw_buffers: Some(color_textures.to_vec()), ..self }
+    }
+
+    /// Draw with the blending and depth options of the preset. The options are set back to
+    /// what they were when the pass ends, as far as the option cache of the context knows them,
+    /// and to the OpenGL defaults otherwise.
+    pub fn blend_preset(self, preset: BlendPreset) -> PassDesc {
+        PassDesc { blend_preset: Some(preset), ..self }
+    }
+}
+
+/// The OpenGL defaults of the options of `BlendPreset::options`, in the same order, restored when
+/// a pass with a blend preset ends and the options were not known before it.
+const BLEND_PRESET_DEFAULTS: [RenderOption; 5] = [
+    RenderOption::Blending(false),
+    RenderOption::BlendEquation(BlendEquation::Add),
+    RenderOption::BlendFunction(BlendFactor::One, BlendFactor::Zero),
+    RenderOption::DepthTest(false),
+    RenderOption::DepthWrite(true)
+];
+
+/// A scope of drawing into one target, returned by `Renderer::pass`. The pass dereferences to
+/// the renderer, so everything is drawn with the usual methods. When the pass is dropped, the
+/// previous target and viewport are restored.
+///
+/// As the pass borrows the renderer mutably, the target can't change in the middle of the pass
+/// other than by beginning a nested pass, which ends before the outer one continues.
+pub struct RenderPass<'r, 'a: 'r> {
+    renderer: &'r mut Renderer<'a>,
+    labeled: bool,
+    previous_target: Option<Rc<RenderTarget>>,
+    previous_read_target: Option<Rc<RenderTarget>>,
+    previous_viewport: [GLint; 4],
+    /// The viewport of the enclosing pass, None if this is the outermost one.
+    previous_pass_viewport: Option<[GLint; 4]>,
+    /// The values to restore of the options changed by the blend preset of the pass.
+    previous_options: Vec<RenderOption>
+}
+
+impl<'r, 'a> Deref for RenderPass<'r, 'a> {
+    type Target = Renderer<'a>;
+
+    fn deref(&self) -> &Renderer<'a> {
+        self.renderer
+    }
+}
+
+impl<'r, 'a> DerefMut for RenderPass<'r, 'a> {
+    fn deref_mut(&mut self) -> &mut Renderer<'a> {
+        self.renderer
+    }
+}
+
+impl<'r, 'a> Drop for RenderPass<'r, 'a> {
+    fn drop(&mut self) {
+        let context = &mut *self.renderer.context;
+        context.bind_draw_framebuffer_for_rendering(self.previous_target.as_ref());
+        context.bind_read_framebuffer_for_rendering(self.previous_read_target.as_ref());
+        match self.previous_pass_viewport {
+            Some(viewport) => context.set_pass_viewport(Some(viewport)),
+            None => {
+                context.set_pass_viewport(None);
+                let [x, y, width, height] = self.previous_viewport;
+                set_viewport(x, y, width, height);
+            }
+        }
+        for &option in self.previous_options.iter() {
+            context.set_render_option(option);
+        }
+        if self.labeled {
+            debug::pop_group(context.get_info());
+        }
+    }
 }
 
 /// The renderer handles the actual drawing calls. It borrows the context mutably, so doing other
 /// things while it is active/alive, is not possible. This is to keep the library's state tracking
 /// simpler (and hopefully more correct).
+///
+/// Drawing goes to the default framebuffer, unless it happens in a render pass drawing into a
+/// render target, see `pass`.
 pub struct Renderer<'a> {
     context: &'a mut Context
 }
@@ -40,20 +336,359 @@ impl<'a> Renderer<'a> {
         Renderer { context: context }
     }
 
+    /// Begin a render pass drawing into the target: bind the target, set the draw buffers and
+    /// the viewport, and clear, as described by the pass description. Draw through the returned
+    /// pass; dropping it ends the pass, restoring the target and the viewport of before:
+    ///
+    ///    {
+    ///        let mut pass = renderer.pass(&shadow_map, &PassDesc::new().clear(depth_only));
+    ///        pass.draw_arrays(PrimitiveMode::Triangles, 0, 36);
+    ///    }
+    ///    let desc = PassDesc::new().clear(ClearState::default());
+    ///    let mut pass = renderer.pass(&DefaultTarget, &desc);
+    ///
+    /// The default viewport of a render target covers its textures, and that of the default
+    /// framebuffer the size given with `DefaultFramebuffer`, or the surface of the context with
+    /// `DefaultTarget`. Without a surface, the viewport of `DefaultTarget` is left as it is. Panics
+    /// if draw buffers are given for the default framebuffer.
+    pub fn pass<'r, T: PassTarget + ?Sized>(&'r mut self, target: &T, desc: &PassDesc) -> RenderPass<'r, 'a> {
+        let previous_target = self.context.framebuffer_for_rendering();
+        let previous_read_target = self.context.read_framebuffer_for_rendering();
+        let previous_pass_viewport = self.context.pass_viewport();
+        let mut previous_viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+        }
+        check_error!();
+        if let Some(ref label) = desc.label {
+            debug::push_group(self.context.get_info(), label);
+        }
+        let target_size = target.dimensions();
+        let target = target.render_target();
+        self.context.bind_framebuffer_for_rendering(target);
+        let target_size = match target {
+            Some(render_target) => {
+                render_target.set_draw_buffers(desc.draw_buffers.as_ref().map(|buffers| &buffers[..]));
+                target_size
+            },
+            None => {
+                if desc.draw_buffers.is_some() {
+                    panic!("Draw buffers can only be given for passes drawing into a render target");
+                }
+                target_size.or_else(|| self.context.surface_dimensions())
+            }
+        };
+        let viewport = match (desc.viewport, target_size) {
+            (Some((x, y, width, height)), _) => [x, y, width as GLint, height as GLint],
+            (None, Some((width, height))) => [0, 0, width as GLint, height as GLint],
+            (None, None) => previous_viewport
+        };
+        self.context.set_pass_viewport(Some(viewport));
+        if let Some(ref state) = desc.clear {
+            self.clear_with(state);
+        }
+        let mut previous_options = Vec::new();
+        if let Some(preset) = desc.blend_preset {
+            let options = preset.options();
+            previous_options.extend(options.iter().zip(BLEND_PRESET_DEFAULTS.iter())
+                .map(|(&option, &default)| self.context.render_option(option).unwrap_or(default)));
+            self.set_blend_preset(preset);
+        }
+        RenderPass {
+            renderer: self,
+            labeled: desc.label.is_some(),
+            previous_target,
+            previous_read_target,
+            previous_viewport,
+            previous_pass_viewport,
+            previous_options
+        }
+    }
+
+    /// Disable or enable writing colors into the framebuffer being drawn into, at the
+    /// framebuffer level rather than by masking: the draw buffer (and the read buffer) of the
+    /// default framebuffer is set to none, or no color texture of a render target is drawn
+    /// into. Depth and stencil are written as usual, which is what a depth pre-pass or a shadow
+    /// map pass wants. Some drivers still pay for color writes masked off with `ColorMask`.
+    ///
+    /// Enabling draws into the back buffer of the default framebuffer again, or into all the
+    /// color textures of a render target. The draw buffers of a render target are set anew when
+    /// a pass into it begins, but the setting of the default framebuffer stays until enabled
+    /// again. See glDrawBuffer and glDrawBuffers.
+    pub fn set_color_buffers_enabled(&mut self, enabled: bool) {
+        match self.context.framebuffer_for_rendering() {
+            Some(render_target) => {
+                let none: &[u32] = &[];
+                render_target.set_draw_buffers(if enabled { None } else { Some(none) });
+            },
+            None => framebuffer::set_default_color_buffer(enabled)
+        }
+    }
+
+    /// Run the closure with the commands it gives in a debug group of the name, shown by
+    /// graphics debuggers like RenderDoc and Nsight in frame captures. Without debug group
+    /// support (see `ContextInfo::supports_debug_groups`) the closure is just run. Render passes
+    /// can be named with `PassDesc::label`. See glPushDebugGroup and glPopDebugGroup.
+    pub fn debug_group<F, R>(&mut self, name: &str, commands: F) -> R where F: FnOnce(&mut Renderer<'a>) -> R {
+        debug::push_group(self.context.get_info(), name);
+        let result = commands(self);
+        debug::pop_group(self.context.get_info());
+        result
+    }
+
+    /// Run the query around the drawing commands of the closure. See glBeginQuery and
+    /// glEndQuery.
+    pub fn with_query<F, R>(&mut self, query: &Query, commands: F) -> R where F: FnOnce(&mut Renderer<'a>) -> R {
+        unsafe {
+            gl::BeginQuery(query.gl_target(), query.id);
+        }
+        check_error!();
+        let result = commands(self);
+        unsafe {
+            gl::EndQuery(query.gl_target());
+        }
+        check_error!();
+        result
+    }
+
+    /// Draw the commands of the closure only if the last run of the occlusion query found
+    /// samples that passed, deciding on the GPU. The closure is always run; the draws just
+    /// don't happen. See glBeginConditionalRender.
+    #[cfg(not(feature = "es3"))]
+    pub fn conditional_render<F>(&mut self, query: &Query, mode: ConditionalRenderMode, commands: F)
+            where F: FnOnce(&mut Renderer<'a>) {
+        let mode = match mode {
+            ConditionalRenderMode::Wait => gl::QUERY_WAIT,
+            ConditionalRenderMode::NoWait => gl::QUERY_NO_WAIT
+        };
+        unsafe {
+            gl::BeginConditionalRender(query.id, mode);
+        }
+        check_error!();
+        commands(self);
+        unsafe {
+            gl::EndConditionalRender();
+        }
+        check_error!();
+    }
+
+    /// Run the closure only if the last run of the occlusion query found samples that passed.
+    /// OpenGL ES has no conditional rendering, so the result is read on the CPU instead:
+    /// `Wait` blocks until it is available, and with `NoWait` the closure is run if it's not.
+    #[cfg(feature = "es3")]
+    pub fn conditional_render<F>(&mut self, query: &Query, mode: ConditionalRenderMode, commands: F)
+            where F: FnOnce(&mut Renderer<'a>) {
+        let passed = match mode {
+            ConditionalRenderMode::Wait => query.result() != 0,
+            ConditionalRenderMode::NoWait => query.try_result().is_none_or(|result| result != 0)
+        };
+        if passed {
+            commands(self);
+        }
+    }
+
+    /// Bind the target as the draw framebuffer only, leaving the read framebuffer as it is. A
+    /// render pass binds its target as both, and restores both when it ends. Unlike `pass`, the
+    /// viewport and the draw buffers are not touched.
+    pub fn use_draw_framebuffer<T: PassTarget + ?Sized>(&mut self, target: &T) {
+        self.context.bind_draw_framebuffer_for_rendering(target.render_target());
+    }
+
+    /// Bind the target as the read framebuffer only, the source of glReadPixels and blits,
+    /// leaving the draw framebuffer as it is. The binding lasts until the next render pass
+    /// begins or ends.
+    pub fn use_read_framebuffer<T: PassTarget + ?Sized>(&mut self, target: &T) {
+        self.context.bind_read_framebuffer_for_rendering(target.render_target());
+    }
+
     /// Bind a vertex array for drawing
     pub fn use_vertex_array(&mut self, vao: &VertexArrayHandle) {
         self.context.bind_vao_for_rendering(vao);
     }
 
+    /// Bind a vertex array for drawing with the program, remapping the attribute locations by
+    /// their semantic names if the program expects them elsewhere. The remapped variants are
+    /// cached, see `Context::vertex_array_for_program`. The program is not put in use.
+    pub fn use_vertex_array_with(&mut self, vao: &VertexArrayHandle, program: &ProgramHandle) {
+        let vao = self.context.vertex_array_for_program(vao, program);
+        self.context.bind_vao_for_rendering(&vao);
+    }
+
+    /// Bind a vertex array for drawing, together with per-instance attributes of the type `I`
+    /// read from the instance buffer. See `Context::vertex_array_with_instances`.
+    pub fn use_vertex_array_instanced<I: VertexFormat>(&mut self, vao: &VertexArrayHandle, instance_buffer: &BufferHandle) {
+        let vao = self.context.vertex_array_with_instances::<I>(vao, instance_buffer);
+        self.context.bind_vao_for_rendering(&vao);
+    }
+
+    /// Set the value the attribute location has when the vertex array in use doesn't enable it,
+    /// so that meshes without some attribute, like vertex colors, can be drawn with the same
+    /// program as the ones that have it. The values are cached, so setting a default to its
+    /// current value doesn't cause a redundant OpenGL call. See `AttributeDefault`.
+    pub fn set_attribute_default(&mut self, location: u32, value: AttributeDefault) {
+        self.context.set_attribute_default(location, value);
+    }
+
     /// Use a program to define the programmable part of rendering (so, most of it)
     pub fn use_program(&mut self, program: &ProgramHandle) {
         self.context.bind_program_for_rendering(program);
     }
 
+    /// Stop using the vertex array in use, binding zero in its place. Drawing requires using a
+    /// vertex array again.
+    pub fn clear_vertex_array(&mut self) {
+        self.context.unbind_vao_for_rendering();
+    }
+
+    /// Stop using the program in use, binding zero in its place. Drawing requires using a
+    /// program again.
+    pub fn clear_program(&mut self) {
+        self.context.unbind_program_for_rendering();
+    }
+
+    /// Bind a texture to a texture unit for drawing. Panics if the unit is out of range.
+    pub fn use_texture(&mut self, unit: u32, texture: &TextureHandle) {
+        self.context.bind_texture_for_rendering(unit, texture);
+    }
+
+    /// Bind a texture to a texture unit and set the sampler uniform at the location to read from
+    /// that unit. The program must be in use. In debug builds, panics if the location is not a
+    /// sampler uniform, or if the sampler reads a different kind of texture, for example a cube
+    /// map sampler given a 2D texture.
+    pub fn use_texture_for_sampler(&mut self, location: i32, unit: u32, texture: &TextureHandle) {
+        if VALIDATION {
+            self.validate_sampler_location(location, texture.access().gl_target());
+        }
+        self.use_texture(unit, texture);
+        self.uniform_i32(location, 1, SimpleUniformTypeI32::Uniform1i, &[unit as i32]);
+    }
+
+    /// Bind a range of a uniform buffer to the binding point, for the uniform blocks set to read
+    /// from it with `ProgramEditor::uniform_block_binding`. Panics if the binding point is out of
+    /// range, the offset is not a multiple of the offset alignment of the context, or the range
+    /// doesn't fit in the buffer. See glBindBufferRange.
+    pub fn use_uniform_buffer_range(&mut self, binding: u32, buffer: &BufferHandle, offset: usize, size: usize) {
+        self.context.bind_uniform_buffer_range(binding, buffer.access(), offset, size);
+    }
+
+    /// Bind the range of the buffer to the indexed shader storage buffer binding point, for the
+    /// storage blocks of the programs to read and write. The offset must be a multiple of
+    /// GL_SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT. Panics if storage buffers are not supported
+    /// (before OpenGL 4.3 or OpenGL ES 3.1) or the range is out of the buffer. See
+    /// glBindBufferRange.
+    pub fn use_storage_buffer_range(&mut self, binding: u32, buffer: &BufferHandle, offset: usize, size: usize) {
+        self.bind_indexed_buffer_range(IndexedBufferTarget::ShaderStorage, binding, buffer, offset, size);
+    }
+
+    /// Bind the range of the buffer to the indexed atomic counter buffer binding point, where
+    /// the `atomic_uint` uniforms of the programs are stored. The offset must be a multiple of
+    /// four. Panics if atomic counters are not supported (before OpenGL 4.2 or OpenGL ES 3.1)
+    /// or the range is out of the buffer. See glBindBufferRange.
+    pub fn use_atomic_counter_buffer_range(&mut self, binding: u32, buffer: &BufferHandle, offset: usize, size: usize) {
+        if !offset.is_multiple_of(4) {
+            panic!("Atomic counter buffer offset {} is not a multiple of four", offset);
+        }
+        self.bind_indexed_buffer_range(IndexedBufferTarget::AtomicCounter, binding, buffer, offset, size);
+    }
+
+    /// Bind the range of the buffer to the indexed transform feedback buffer binding point, for
+    /// capturing the varyings of the program (see `TransformFeedbackInfo`). The offset
+    /// and the size must be multiples of four. Panics if they are not, if the binding point is
+    /// not below GL_MAX_TRANSFORM_FEEDBACK_SEPARATE_ATTRIBS, or the range is out of the buffer.
+    /// See glBindBufferRange.
+    pub fn use_transform_feedback_buffer_range(&mut self, binding: u32, buffer: &BufferHandle, offset: usize, size: usize) {
+        let max_buffers = self.context.get_info().shader_stages.max_transform_feedback_buffers;
+        if binding >= max_buffers as u32 {
+            panic!("Transform feedback buffer binding point {} is out of range, there are {}", binding, max_buffers);
+        }
+        if !offset.is_multiple_of(4) || !size.is_multiple_of(4) {
+            panic!("Transform feedback buffer range {}..{} is not aligned to four bytes", offset, offset + size);
+        }
+        let buffer_size = buffer.access().size();
+        if buffer_size > 0 && offset + size > buffer_size {
+            panic!("Transform feedback buffer range {}..{} is out of the buffer of {} bytes", offset, offset + size, buffer_size);
+        }
+        self.context.bind_indexed_buffer_range(IndexedBufferTarget::TransformFeedback, binding, buffer.access(), offset, size);
+    }
+
+    /// Make the writes of the shaders run so far through storage buffers, atomic counters and
+    /// images visible to the given kinds of later reads, for example `Command` before drawing
+    /// with indirect commands written by a compute shader. Panics if the context doesn't have
+    /// glMemoryBarrier (before OpenGL 4.2 or OpenGL ES 3.1). See glMemoryBarrier.
+    pub fn memory_barrier(&mut self, barriers: &[MemoryBarrier]) {
+        if !self.context.get_info().supports(ExtensionFeature::MemoryBarrier) {
+            panic!("Memory barriers are not supported by the context");
+        }
+        let bits = barriers.iter().fold(0, |bits, &barrier| bits | memory_barrier_bit(barrier));
+        unsafe {
+            gl::MemoryBarrier(bits);
+        }
+        check_error!();
+    }
+
+    /// Like `use_uniform_buffer_range`, but bind to the binding point assigned to the name by
+    /// `Context::uniform_binding_points`, assigning one if needed. Panics if the binding points
+    /// run out.
+    pub fn use_uniform_buffer_range_named(&mut self, name: &str, buffer: &BufferHandle, offset: usize, size: usize) {
+        let binding = match self.context.uniform_binding_points().alloc(name) {
+            Some(binding) => binding,
+            None => panic!("No uniform buffer binding points left for {}", name)
+        };
+        self.use_uniform_buffer_range(binding, buffer, offset, size);
+    }
+
+    /// Like `use_storage_buffer_range`, but bind to the binding point assigned to the name by
+    /// `Context::storage_binding_points`, assigning one if needed. Panics if the binding points
+    /// run out.
+    pub fn use_storage_buffer_range_named(&mut self, name: &str, buffer: &BufferHandle, offset: usize, size: usize) {
+        let binding = match self.context.storage_binding_points().alloc(name) {
+            Some(binding) => binding,
+            None => panic!("No shader storage buffer binding points left for {}", name)
+        };
+        self.use_storage_buffer_range(binding, buffer, offset, size);
+    }
+
+    /// Set a uniform of the program in use. Panics if no program is in use. See
+    /// `ProgramEditor::uniform_f32` for the details.
+    pub fn uniform_f32(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeFloat, values: &[f32]) {
+        self.prepare_uniform();
+        program::uniform_f32(location, count, uniform_type, values);
+    }
+
+    /// Set a matrix uniform of the program in use. See `uniform_f32`.
+    pub fn uniform_matrix(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeMatrix, transpose: bool, values: &[f32]) {
+        self.prepare_uniform();
+        program::uniform_matrix(location, count, uniform_type, transpose, values);
+    }
+
+    /// Set a u32 uniform of the program in use. See `uniform_f32`.
+    pub fn uniform_u32(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeU32, values: &[u32]) {
+        self.prepare_uniform();
+        program::uniform_u32(location, count, uniform_type, values);
+    }
+
+    /// Set an i32 uniform of the program in use. See `uniform_f32`.
+    pub fn uniform_i32(&mut self, location: i32, count: usize, uniform_type: SimpleUniformTypeI32, values: &[i32]) {
+        self.prepare_uniform();
+        program::uniform_i32(location, count, uniform_type, values);
+    }
+
     /// Draws unindexed vertices. See glDrawArrays.
+    ///
+    /// In debug builds, the draw fails validation if the vertex buffers of the vertex array in
+    /// use don't have enough data for the vertex range, or if the count doesn't make whole
+    /// primitives of the primitive mode, for example if drawing triangles with a count not
+    /// divisible by three. It also fails if a sampler uniform of the program reads a texture
+    /// unit with the wrong kind of texture. A failed draw panics, or is skipped and reported to
+    /// the error handler if one is set, see `Context::set_error_handler`.
+    #[track_caller]
     pub fn draw_arrays(&mut self, primitive_mode: PrimitiveMode, first: u32, count: u32) {
+        if VALIDATION && !(validate_primitive_count(primitive_mode, count) && self.validate_vertex_range(first, count) &&
+                           self.validate_instance_range(0, 1) && self.validate_samplers()) {
+            return;
+        }
         let primitive_mode = gl_primitive_mode(primitive_mode);
-        self.context.prepare_for_rendering();
+        self.context.prepare_for_drawing();
         unsafe {
             gl::DrawArrays(primitive_mode, first as GLint, count as GLsizei);
         }
@@ -61,25 +696,214 @@ impl<'a> Renderer<'a> {
     }
 
     /// Draws indexed vertices, with u8 indices. See glDrawElements.
+    ///
+    /// The start is the byte offset of the first index in the index buffer. In debug builds,
+    /// the draw fails validation as in `draw_arrays` if the index buffer of the vertex array in
+    /// use is too small for the index range, or if the count doesn't make whole primitives.
+    #[track_caller]
     pub fn draw_elements_u8(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        if VALIDATION && !validate_primitive_count(primitive_mode, count) {
+            return;
+        }
         let primitive_mode = gl_primitive_mode(primitive_mode);
-        self.draw_elements(primitive_mode, count, gl::UNSIGNED_BYTE, start);
+        self.draw_elements(primitive_mode, count, IndexType::U8, start);
     }
 
-    /// Draws indexed vertices, with u16 indices. See glDrawElements.
+    /// Draws indexed vertices, with u16 indices. See glDrawElements and `draw_elements_u8`.
+    #[track_caller]
     pub fn draw_elements_u16(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        if VALIDATION && !validate_primitive_count(primitive_mode, count) {
+            return;
+        }
         let primitive_mode = gl_primitive_mode(primitive_mode);
-        self.draw_elements(primitive_mode, count, gl::UNSIGNED_SHORT, start);
+        self.draw_elements(primitive_mode, count, IndexType::U16, start);
     }
 
-    /// Draws indexed vertices, with u32 indices. See glDrawElements.
+    /// Draws indexed vertices, with u32 indices. See glDrawElements and `draw_elements_u8`.
+    #[track_caller]
     pub fn draw_elements_u32(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        if VALIDATION && !validate_primitive_count(primitive_mode, count) {
+            return;
+        }
         let primitive_mode = gl_primitive_mode(primitive_mode);
-        self.draw_elements(primitive_mode, count, gl::UNSIGNED_INT, start);
+        self.draw_elements(primitive_mode, count, IndexType::U32, start);
     }
 
-    fn draw_elements(&mut self, primitive_mode: GLenum, count: u32, index_type: GLenum, start: u32) {
-        self.context.prepare_for_rendering();
+    /// Draws indexed vertices, with the index type recorded in the index buffer by
+    /// `BufferEditor::data_indices`. See glDrawElements and `draw_elements_u8`. Panics if the
+    /// vertex array in use has no index buffer, or if its index type is not known.
+    #[track_caller]
+    pub fn draw_indexed(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        let index_type = match self.context.vao_for_rendering() {
+            Some(vao) => match vao.index_buffer() {
+                Some(index_buffer) => match index_buffer.index_type() {
+                    Some(index_type) => index_type,
+                    None => panic!("The index type of the index buffer is not known, write the indices with BufferEditor::data_indices")
+                },
+                None => panic!("Drawing indexed vertices, but the vertex array in use has no index buffer")
+            },
+            None => panic!("Drawing indexed vertices, but no vertex array is in use")
+        };
+        if VALIDATION && !validate_primitive_count(primitive_mode, count) {
+            return;
+        }
+        let primitive_mode = gl_primitive_mode(primitive_mode);
+        self.draw_elements(primitive_mode, count, index_type, start);
+    }
+
+    /// Draws indexed vertices with u16 indices, with primitive restart enabled for the duration
+    /// of the call: the index 0xFFFF ends the current strip and starts a new one. Meant for
+    /// indices written with `BufferEditor::data_strips_u16`. Afterwards the primitive restart
+    /// option has its earlier value again, or restarting is disabled if the value wasn't known.
+    /// See GL_PRIMITIVE_RESTART.
+    #[track_caller]
+    pub fn draw_elements_restart_u16(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        let primitive_mode = gl_primitive_mode(primitive_mode);
+        let previous = restart_to_restore(self.context.render_option(RenderOption::PrimitiveRestart(None)));
+        self.context.set_render_option(RenderOption::PrimitiveRestart(Some(u16::MAX as u32)));
+        self.draw_elements(primitive_mode, count, IndexType::U16, start);
+        self.context.set_render_option(previous);
+    }
+
+    /// Like `draw_elements_restart_u16`, but with u32 indices and the restart index 0xFFFFFFFF.
+    #[track_caller]
+    pub fn draw_elements_restart_u32(&mut self, primitive_mode: PrimitiveMode, count: u32, start: u32) {
+        let primitive_mode = gl_primitive_mode(primitive_mode);
+        let previous = restart_to_restore(self.context.render_option(RenderOption::PrimitiveRestart(None)));
+        self.context.set_render_option(RenderOption::PrimitiveRestart(Some(u32::MAX)));
+        self.draw_elements(primitive_mode, count, IndexType::U32, start);
+        self.context.set_render_option(previous);
+    }
+
+    /// Make the draw call described by the parameters, choosing the matching glDraw* function.
+    ///
+    /// Panics if the parameters don't make sense together, for example if a base vertex is given
+    /// for an unindexed draw, or if the context doesn't support them. In debug builds, the vertex
+    /// and index ranges and the count are validated as in `draw_arrays` and `draw_elements_u8`.
+    /// The count is not validated with primitive restart, as the restart indices split it.
+    #[track_caller]
+    pub fn draw(&mut self, params: &DrawParams) {
+        self.validate_draw_params(params);
+        if VALIDATION && !params.primitive_restart && !validate_primitive_count(params.primitive_mode, params.count) {
+            return;
+        }
+        if VALIDATION && !self.validate_instance_range(params.base_instance, params.instance_count) {
+            return;
+        }
+        let primitive_mode = gl_primitive_mode(params.primitive_mode);
+        let count = params.count as GLsizei;
+        let instance_count = params.instance_count as GLsizei;
+        match params.index_type {
+            None => {
+                if VALIDATION && !(self.validate_vertex_range(params.first, params.count) && self.validate_samplers()) {
+                    return;
+                }
+                self.context.prepare_for_drawing();
+                let first = params.first as GLint;
+                unsafe {
+                    if params.base_instance != 0 {
+                        gl::DrawArraysInstancedBaseInstance(primitive_mode, first, count, instance_count, params.base_instance);
+                    }
+                    else if params.instance_count != 1 {
+                        gl::DrawArraysInstanced(primitive_mode, first, count, instance_count);
+                    }
+                    else {
+                        gl::DrawArrays(primitive_mode, first, count);
+                    }
+                }
+                check_error!();
+            },
+            Some(index_type) => {
+                if VALIDATION && !(self.validate_index_range(params.count, index_type, params.first, params.base_vertex) && self.validate_samplers()) {
+                    return;
+                }
+                let previous_restart = if params.primitive_restart {
+                    let previous = restart_to_restore(self.context.render_option(RenderOption::PrimitiveRestart(None)));
+                    self.context.set_render_option(RenderOption::PrimitiveRestart(Some(restart_index(index_type))));
+                    Some(previous)
+                }
+                else {
+                    None
+                };
+                self.context.prepare_for_drawing();
+                let gl_index_type = gl_index_type(index_type);
+                let start = params.first as usize as *const GLvoid;
+                unsafe {
+                    if params.base_instance != 0 {
+                        gl::DrawElementsInstancedBaseVertexBaseInstance(primitive_mode, count, gl_index_type, start,
+                            instance_count, params.base_vertex, params.base_instance);
+                    }
+                    else if params.base_vertex != 0 {
+                        gl::DrawElementsInstancedBaseVertex(primitive_mode, count, gl_index_type, start,
+                            instance_count, params.base_vertex);
+                    }
+                    else if params.instance_count != 1 {
+                        gl::DrawElementsInstanced(primitive_mode, count, gl_index_type, start, instance_count);
+                    }
+                    else {
+                        gl::DrawElements(primitive_mode, count, gl_index_type, start);
+                    }
+                }
+                check_error!();
+                if let Some(previous) = previous_restart {
+                    self.context.set_render_option(previous);
+                }
+            }
+        }
+    }
+
+    /// Draw with the commands in the draw indirect buffer, `draw_count` DrawElementsIndirectCommand
+    /// structures starting at `offset` bytes, see `IndirectCommand`. The indices come from the
+    /// index buffer of the vertex array in use. Falls back to a glDrawElementsIndirect call per
+    /// command without glMultiDrawElementsIndirect (before OpenGL 4.3), and panics if neither is
+    /// available (OpenGL ES before 3.1). Also panics if the offset is not a multiple of four or
+    /// the commands don't fit in the buffer.
+    ///
+    /// The commands are not read back, so their base instances are not checked here: they must
+    /// be zero unless `ContextInfo::supports_base_instance`, which `IndirectCommandBuffer::upload`
+    /// checks for the commands it uploads. See glMultiDrawElementsIndirect.
+    pub fn multi_draw_elements_indirect(&mut self, primitive_mode: PrimitiveMode, index_type: IndexType,
+                                        buffer: &BufferHandle, offset: usize, draw_count: u32) {
+        if !self.context.get_info().supports(ExtensionFeature::DrawIndirect) {
+            panic!("Indirect drawing is not supported by the context");
+        }
+        if !offset.is_multiple_of(4) {
+            panic!("Draw indirect buffer offset {} is not a multiple of four", offset);
+        }
+        let end = offset + draw_count as usize * INDIRECT_COMMAND_SIZE;
+        if end > buffer.access().size() {
+            panic!("{} indirect draw commands at {} don't fit in the buffer of {} bytes", draw_count, offset, buffer.access().size());
+        }
+        if VALIDATION && !self.validate_samplers() {
+            return;
+        }
+        self.context.bind_indirect_buffer_for_rendering(buffer.access());
+        self.context.prepare_for_drawing();
+        let primitive_mode = gl_primitive_mode(primitive_mode);
+        let index_type = gl_index_type(index_type);
+        let multi_draw = self.context.get_info().supports(ExtensionFeature::MultiDrawIndirect);
+        unsafe {
+            if multi_draw {
+                gl::MultiDrawElementsIndirect(primitive_mode, index_type, offset as *const GLvoid, draw_count as GLsizei, 0);
+            }
+            else {
+                for index in 0..draw_count as usize {
+                    let command_offset = offset + index * INDIRECT_COMMAND_SIZE;
+                    gl::DrawElementsIndirect(primitive_mode, index_type, command_offset as *const GLvoid);
+                }
+            }
+        }
+        check_error!();
+    }
+
+    #[track_caller]
+    fn draw_elements(&mut self, primitive_mode: GLenum, count: u32, index_type: IndexType, start: u32) {
+        if VALIDATION && !(self.validate_index_range(count, index_type, start, 0) && self.validate_instance_range(0, 1) &&
+                           self.validate_samplers()) {
+            return;
+        }
+        let index_type = gl_index_type(index_type);
+        self.context.prepare_for_drawing();
         unsafe {
             let start = start as *const GLvoid;
             gl::DrawElements(primitive_mode, count as GLint, index_type, start);
@@ -87,23 +911,463 @@ impl<'a> Renderer<'a> {
         }
     }
 
-    /// Clear the current surface.
-    pub fn clear(&mut self) {
+    /// Run the compute program in use with the given number of work groups in each dimension.
+    /// Panics if no program is in use, if the program in use is not a compute program, or if
+    /// compute shaders are not supported (see `ExtensionFeature::ComputeShader`). See
+    /// glDispatchCompute.
+    pub fn dispatch_compute(&mut self, groups_x: u32, groups_y: u32, groups_z: u32) {
+        if !self.context.get_info().supports(ExtensionFeature::ComputeShader) {
+            panic!("Compute shaders are not supported by the context");
+        }
+        match self.context.program_for_rendering() {
+            Some(program) if !program.is_compute() => panic!("Can't dispatch compute work, the program in use has no compute shader"),
+            Some(_) => (),
+            None => panic!("Can't dispatch compute work, no program is in use")
+        }
+        self.context.prepare_for_rendering();
         unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl::DispatchCompute(groups_x, groups_y, groups_z);
         }
         check_error!();
     }
 
+    /// Use the compute program and run it for the given number of elements, one invocation per
+    /// element. The number of work groups is derived from the local size of the program, and
+    /// the groups are laid out along x. Returns the number of work groups, whose invocations
+    /// cover at least the elements; the shader should skip the invocations whose index, for
+    /// example `gl_GlobalInvocationID.x` of a one-dimensional local size, is past the elements.
+    ///
+    /// Panics if compute shaders are not supported or the program is not a compute program. A
+    /// local size without invocations is a validation failure, as is, in debug builds, a group
+    /// count past GL_MAX_COMPUTE_WORK_GROUP_COUNT; then nothing is dispatched and zero returned.
+    #[track_caller]
+    pub fn dispatch_for_elements(&mut self, program: &ProgramHandle, total_elements: u32) -> u32 {
+        if !self.context.get_info().supports(ExtensionFeature::ComputeShader) {
+            panic!("Compute shaders are not supported by the context");
+        }
+        let local_size = match program.access().compute_work_group_size() {
+            Some(local_size) => local_size,
+            None => panic!("Can't dispatch compute work, the program has no compute shader")
+        };
+        let invocations = local_size[0] * local_size[1] * local_size[2];
+        if invocations == 0 {
+            util::validation_failed(format!("Can't dispatch compute work, the local size {:?} has no invocations", local_size));
+            return 0;
+        }
+        let groups = total_elements.div_ceil(invocations);
+        let max_groups = self.context.get_info().extension_limits.max_compute_work_group_count[0] as u32;
+        if VALIDATION && groups > max_groups {
+            util::validation_failed(format!("Dispatching {} elements takes {} work groups of {} invocations, but at most {} are supported",
+                total_elements, groups, invocations, max_groups));
+            return 0;
+        }
+        self.use_program(program);
+        if groups > 0 {
+            self.dispatch_compute(groups, 1, 1);
+        }
+        groups
+    }
+
+    /// Clear the current surface with the persistent clear state. See `set_clear_state`.
+    pub fn clear(&mut self) {
+        self.context.clear_buffers(None);
+    }
+
+    /// Clear the current surface with the given clear state, without changing the persistent
+    /// one. Useful for clearing the draw buffers of a framebuffer to different values.
+    pub fn clear_with(&mut self, state: &ClearState) {
+        self.context.clear_buffers(Some(state));
+    }
+
+    /// Clear only a rectangle of the current surface, given in window coordinates like the
+    /// viewport, with the given clear state. The scissor test is enabled for the clear, and the
+    /// scissor state is restored afterwards to what it was set to through `set_option`. If the
+    /// scissor state is not known, that is, it has never been set, the test is left disabled and
+    /// the box reset to cover the whole render target or surface being drawn into.
+    pub fn clear_region(&mut self, x: i32, y: i32, width: u32, height: u32, state: &ClearState) {
+        let previous_test = self.context.render_option(RenderOption::ScissorTest(false));
+        let previous_box = self.context.render_option(RenderOption::ScissorBox(0, 0, 0, 0));
+        let previous_box = previous_box.unwrap_or_else(|| self.full_scissor_box());
+        self.context.set_render_option(RenderOption::ScissorBox(x, y, width, height));
+        self.context.set_render_option(RenderOption::ScissorTest(true));
+        self.context.clear_buffers(Some(state));
+        self.context.set_render_option(previous_box);
+        self.context.set_render_option(previous_test.unwrap_or(RenderOption::ScissorTest(false)));
+    }
+
+    /// A scissor box covering the render target being drawn into, or the surface of the context
+    /// for the default framebuffer. Without a surface, the scissor box OpenGL has now.
+    fn full_scissor_box(&self) -> RenderOption {
+        let size = match self.context.framebuffer_for_rendering() {
+            Some(render_target) => Some(render_target.dimensions()),
+            None => self.context.surface_dimensions()
+        };
+        match size {
+            Some((width, height)) => RenderOption::ScissorBox(0, 0, width, height),
+            None => {
+                let mut scissor_box = [0; 4];
+                unsafe {
+                    gl::GetIntegerv(gl::SCISSOR_BOX, scissor_box.as_mut_ptr());
+                }
+                check_error!();
+                RenderOption::ScissorBox(scissor_box[0], scissor_box[1], scissor_box[2] as u32, scissor_box[3] as u32)
+            }
+        }
+    }
+
+    /// Set the persistent clear state, used by `clear` from now on. The values are cached, so
+    /// clearing with unchanged values doesn't set them again.
+    pub fn set_clear_state(&mut self, state: ClearState) {
+        *self.context.clear_state_mut() = state;
+    }
+
+    /// The persistent clear state.
+    pub fn clear_state(&self) -> &ClearState {
+        self.context.clear_state()
+    }
+
     /// Set a rendering option, for example culling or clear color. See `RenderOption` for possible
-    /// options.
+    /// options. The clear color and depth options change the persistent clear state: the clear
+    /// color becomes the color of the first draw buffer. The other options are cached, so setting
+    /// an option to the value it already has doesn't cause a redundant OpenGL call.
     pub fn set_option(&mut self, option: RenderOption) {
-        options::set_option(option);
+        match option {
+            RenderOption::ClearColor(r, g, b, a) => {
+                let state = self.context.clear_state_mut();
+                if state.colors.is_empty() {
+                    state.colors.push([r, g, b, a]);
+                }
+                else {
+                    state.colors[0] = [r, g, b, a];
+                }
+            },
+            RenderOption::ClearDepth(depth) => self.context.clear_state_mut().depth = Some(depth),
+            _ => self.context.set_render_option(option)
+        }
+    }
+
+    /// The current value of the same kind of option as the given one, or None if it is not
+    /// known because it hasn't been set through the library since the state was last
+    /// invalidated. The clear color and depth are not options in this sense, see `clear_state`.
+    pub fn render_option(&self, option: RenderOption) -> Option<RenderOption> {
+        self.context.render_option(option)
+    }
+
+    /// Set all the state the context believes OpenGL to have, see `Context::flush_state`.
+    pub fn flush_state(&mut self) {
+        self.context.flush_state();
+    }
+
+    /// Forget the state the context believes OpenGL to have, see `Context::assume_state_clean`.
+    pub fn assume_state_clean(&mut self) {
+        self.context.assume_state_clean();
+    }
+
+    /// Set the constant color of the ConstantColor and ConstantAlpha blend factors, the same as
+    /// `RenderOption::BlendColor`. Cached like the other render options.
+    pub fn set_blend_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.set_option(RenderOption::BlendColor(r, g, b, a));
+    }
+
+    /// Set the blending and depth options of the preset. See `BlendPreset`.
+    pub fn set_blend_preset(&mut self, preset: BlendPreset) {
+        for &option in preset.options().iter() {
+            self.set_option(option);
+        }
+    }
+
+    /// Set a pixel store parameter, affecting how pixel data is laid out in client memory. See
+    /// `PixelStoreParam`. The values are cached, so setting a parameter to its current value
+    /// doesn't cause a redundant OpenGL call.
+    pub fn set_pixel_store(&mut self, param: PixelStoreParam) {
+        self.context.set_pixel_store(param);
+    }
+
+    /// Set the clip space origin and depth range. See glClipControl.
+    ///
+    /// Panics if the context doesn't support clip control, that is, the context is older than
+    /// OpenGL 4.5 and GL_ARB_clip_control is not available. See
+    /// `ContextInfo::supports_clip_control`.
+    pub fn set_clip_control(&mut self, origin: ClipOrigin, depth: ClipDepth) {
+        if !self.context.get_info().supports_clip_control() {
+            panic!("glClipControl is not supported by the context (requires OpenGL 4.5 or GL_ARB_clip_control)");
+        }
+        self.context.set_clip_control(origin, depth);
+    }
+
+    /// Set up the "reversed-Z" depth mapping: the clip space depth range is set to zero-to-one,
+    /// the depth test function to GL_GREATER and the clear depth to zero, so that the far plane
+    /// maps to zero and the near plane to one. Combined with a floating point depth buffer, this
+    /// distributes the depth precision much more evenly than the default mapping. Remember that
+    /// the projection matrix must also be built for the reversed range, for example with
+    /// `matrix::perspective_reversed_z`.
+    ///
+    /// Panics if clip control is not supported, see `set_clip_control`.
+    pub fn setup_reversed_z(&mut self) {
+        self.set_clip_control(ClipOrigin::LowerLeft, ClipDepth::ZeroToOne);
+        self.set_option(RenderOption::DepthFunction(DepthFunction::Greater));
+        self.set_option(RenderOption::ClearDepth(0.0));
+    }
+}
+
+impl<'a> Renderer<'a> {
+    /// Bind a range to a storage or atomic counter buffer binding point, after checking that
+    /// the context supports the feature and that the range fits in the buffer.
+    fn bind_indexed_buffer_range(&mut self, target: IndexedBufferTarget, binding: u32, buffer: &BufferHandle, offset: usize, size: usize) {
+        let (feature, kind) = match target {
+            IndexedBufferTarget::ShaderStorage => (ExtensionFeature::ShaderStorageBuffer, "Shader storage"),
+            _ => (ExtensionFeature::AtomicCounterBuffer, "Atomic counter")
+        };
+        if !self.context.get_info().supports(feature) {
+            panic!("{} buffers are not supported by the context", kind);
+        }
+        let buffer_size = buffer.access().size();
+        if buffer_size > 0 && offset + size > buffer_size {
+            panic!("{} buffer range {}..{} is out of the buffer of {} bytes", kind, offset, offset + size, buffer_size);
+        }
+        self.context.bind_indexed_buffer_range(target, binding, buffer.access(), offset, size);
+    }
+
+    /// Make sure the program in use is actually bound, as uniforms are set to the bound program.
+    fn prepare_uniform(&mut self) {
+        if self.context.program_for_rendering().is_none() {
+            panic!("Can't set a uniform value, no program is in use");
+        }
+        self.context.prepare_for_rendering();
+    }
+
+    /// Check that the sampler uniforms of the program in use read from texture units with
+    /// textures of the matching target bound. Units with no texture bound for rendering are not
+    /// checked, they may be bound outside the renderer.
+    #[track_caller]
+    fn validate_samplers(&self) -> bool {
+        let program = match self.context.program_for_rendering() {
+            Some(program) => program,
+            None => return true
+        };
+        for (name, uniform_type, unit) in program.sampler_units() {
+            let sampler_target = program::sampler_target(uniform_type).unwrap();
+            if let Some(texture) = self.context.texture_for_rendering(unit) {
+                if texture.gl_target() != sampler_target {
+                    util::validation_failed(format!("Sampler uniform {} of type {:?} reads texture unit {}, but a {} texture is bound there instead of a {} texture",
+                        name, uniform_type, unit, program::texture_target_name(texture.gl_target()),
+                        program::texture_target_name(sampler_target)));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Check that the location is a sampler uniform of the program in use, reading textures of
+    /// the given target.
+    fn validate_sampler_location(&self, location: i32, texture_target: GLenum) {
+        let program = match self.context.program_for_rendering() {
+            Some(program) => program,
+            None => panic!("Can't set a sampler uniform, no program is in use")
+        };
+        let uniform_info = program::new_program_info_accessor(program, self.context.get_info()).get_uniform_info();
+        let uniform = uniform_info.globals.iter()
+            .find(|uniform| location >= uniform.location && location < uniform.location + uniform.size.max(1));
+        let uniform = match uniform {
+            Some(uniform) => uniform,
+            None => panic!("No uniform at location {} in the program in use", location)
+        };
+        match program::sampler_target(uniform.uniform_type) {
+            Some(target) if target == texture_target => (),
+            Some(target) => panic!("Sampler uniform {} of type {:?} can't read a {} texture, it needs a {} texture",
+                uniform.name, uniform.uniform_type, program::texture_target_name(texture_target),
+                program::texture_target_name(target)),
+            None => panic!("Uniform {} of type {:?} is not a sampler", uniform.name, uniform.uniform_type)
+        }
+    }
+
+    /// Check that the draw parameters are consistent and supported by the context.
+    fn validate_draw_params(&self, params: &DrawParams) {
+        let info = self.context.get_info();
+        if params.index_type.is_none() {
+            if params.base_vertex != 0 {
+                panic!("A base vertex can only be given for indexed draws");
+            }
+            if params.primitive_restart {
+                panic!("Primitive restart can only be enabled for indexed draws");
+            }
+        }
+        if params.base_vertex != 0 && !info.supports_base_vertex() {
+            panic!("Drawing with a base vertex is not supported by the context");
+        }
+        if params.base_instance != 0 && !info.supports_base_instance() {
+            panic!("Drawing with a base instance is not supported by the context (requires OpenGL 4.2 or GL_ARB_base_instance)");
+        }
+    }
+
+    /// Check that the vertex buffers of the vertex array in use contain the vertices from
+    /// `first` to `first + count`.
+    #[track_caller]
+    fn validate_vertex_range(&self, first: u32, count: u32) -> bool {
+        if let Some(vao) = self.context.vao_for_rendering() {
+            if let Some(limit) = vao.vertex_count_limit() {
+                if first as u64 + count as u64 > limit as u64 {
+                    util::validation_failed(format!("Drawing vertices {}..{}, but the vertex buffers only contain {} vertices",
+                        first, first as u64 + count as u64, limit));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Check that the per-instance attributes of the vertex array in use have data for the
+    /// instances from `base_instance` on.
+    #[track_caller]
+    fn validate_instance_range(&self, base_instance: u32, instance_count: u32) -> bool {
+        if let Some(vao) = self.context.vao_for_rendering() {
+            if let Some(limit) = vao.instance_count_limit(base_instance) {
+                if instance_count as u64 > limit {
+                    util::validation_failed(format!("Drawing {} instances from instance {}, but the instance buffers only have data for {}",
+                        instance_count, base_instance, limit));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Check that the index buffer of the vertex array in use contains `count` indices starting
+    /// from the byte offset `start`, and that the base vertex doesn't point past the vertex
+    /// buffers. The indices are not read back, so a negative base vertex can't be checked.
+    #[track_caller]
+    fn validate_index_range(&self, count: u32, index_type: IndexType, start: u32, base_vertex: i32) -> bool {
+        if let Some(vao) = self.context.vao_for_rendering() {
+            match vao.index_buffer() {
+                Some(index_buffer) => {
+                    let end = start as usize + count as usize * index_type.size();
+                    if end > index_buffer.size() {
+                        util::validation_failed(format!("Drawing {} indices of type {:?} from offset {} needs {} bytes, but the index buffer has only {}",
+                            count, index_type, start, end, index_buffer.size()));
+                        return false;
+                    }
+                },
+                None => {
+                    util::validation_failed("Drawing indexed vertices, but the vertex array in use has no index buffer".to_string());
+                    return false;
+                }
+            }
+            if let Some(limit) = vao.vertex_count_limit() {
+                if count > 0 && base_vertex > 0 && base_vertex as u32 >= limit {
+                    util::validation_failed(format!("Drawing with base vertex {}, but the vertex buffers only contain {} vertices",
+                        base_vertex, limit));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Check that the vertex count makes whole primitives of the primitive mode: lines take two
+/// vertices each and triangles three, and strips, loops and fans need at least one whole
+/// primitive. Drawing nothing is fine. The failure is reported at the caller of the draw method.
+#[track_caller]
+pub fn validate_primitive_count(primitive_mode: PrimitiveMode, count: u32) -> bool {
+    if count == 0 {
+        return true;
+    }
+    let valid = match primitive_mode {
+        PrimitiveMode::Points => true,
+        PrimitiveMode::Lines => count.is_multiple_of(2),
+        PrimitiveMode::LineStrip | PrimitiveMode::LineLoop => count >= 2,
+        PrimitiveMode::Triangles => count.is_multiple_of(3),
+        PrimitiveMode::TriangleStrip | PrimitiveMode::TriangleFan => count >= 3
+    };
+    if !valid {
+        let requirement = match primitive_mode {
+            PrimitiveMode::Lines => "a multiple of 2",
+            PrimitiveMode::Triangles => "a multiple of 3",
+            PrimitiveMode::LineStrip | PrimitiveMode::LineLoop => "at least 2",
+            _ => "at least 3"
+        };
+        util::validation_failed(format!("Drawing {} vertices as {:?}, but the count must be {}", count, primitive_mode, requirement));
+    }
+    valid
+}
+
+fn set_viewport(x: GLint, y: GLint, width: GLint, height: GLint) {
+    unsafe {
+        gl::Viewport(x, y, width, height);
+    }
+    check_error!();
+}
+
+/// The primitive restart index of the index type, its largest value.
+fn restart_index(index_type: IndexType) -> u32 {
+    match index_type {
+        IndexType::U8 => u8::MAX as u32,
+        IndexType::U16 => u16::MAX as u32,
+        IndexType::U32 => u32::MAX
+    }
+}
+
+/// The primitive restart option to set after a draw with primitive restart: the cached value from
+/// before the draw, or restarting disabled, the OpenGL default, if the value wasn't known.
+fn restart_to_restore(previous: Option<RenderOption>) -> RenderOption {
+    previous.unwrap_or(RenderOption::PrimitiveRestart(None))
+}
+
+fn memory_barrier_bit(barrier: MemoryBarrier) -> GLbitfield {
+    match barrier {
+        MemoryBarrier::VertexAttribArray => gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT,
+        MemoryBarrier::ElementArray => gl::ELEMENT_ARRAY_BARRIER_BIT,
+        MemoryBarrier::Uniform => gl::UNIFORM_BARRIER_BIT,
+        MemoryBarrier::Command => gl::COMMAND_BARRIER_BIT,
+        MemoryBarrier::BufferUpdate => gl::BUFFER_UPDATE_BARRIER_BIT,
+        MemoryBarrier::ShaderStorage => gl::SHADER_STORAGE_BARRIER_BIT,
+        MemoryBarrier::AtomicCounter => gl::ATOMIC_COUNTER_BARRIER_BIT,
+        MemoryBarrier::All => gl::ALL_BARRIER_BITS
+    }
+}
+
+fn gl_index_type(index_type: IndexType) -> GLenum {
+    match index_type {
+        IndexType::U8 => gl::UNSIGNED_BYTE,
+        IndexType::U16 => gl::UNSIGNED_SHORT,
+        IndexType::U32 => gl::UNSIGNED_INT
     }
 }
 
 fn gl_primitive_mode(primitive_mode: PrimitiveMode) -> GLenum {
     match primitive_mode {
-        PrimitiveMode::Triangles => gl::TRIANGLES
+        PrimitiveMode::Points => gl::POINTS,
+        PrimitiveMode::Lines => gl::LINES,
+        PrimitiveMode::LineStrip => gl::LINE_STRIP,
+        PrimitiveMode::LineLoop => gl::LINE_LOOP,
+        PrimitiveMode::Triangles => gl::TRIANGLES,
+        PrimitiveMode::TriangleStrip => gl::TRIANGLE_STRIP,
+        PrimitiveMode::TriangleFan => gl::TRIANGLE_FAN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::{RenderOption,BlendPreset,BLEND_PRESET_DEFAULTS,restart_to_restore};
+
+    #[test]
+    fn restart_already_enabled_is_restored() {
+        let previous = RenderOption::PrimitiveRestart(Some(0xFFFF));
+        assert_eq!(restart_to_restore(Some(previous)), previous);
+    }
+
+    #[test]
+    fn unknown_restart_is_disabled_afterwards() {
+        assert_eq!(restart_to_restore(None), RenderOption::PrimitiveRestart(None));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn blend_preset_defaults_match_the_preset_options() {
+        for (option, default) in BlendPreset::Additive.options().iter().zip(BLEND_PRESET_DEFAULTS.iter()) {
+            assert_eq!(mem::discriminant(option), mem::discriminant(default));
+        }
+    }
+}
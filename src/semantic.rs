@@ -0,0 +1,147 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Semantic annotations of vertex shader attributes. An attribute is annotated with a comment
+//! on the line before its declaration, or after it on the same line:
+//!
+//!    // @semantic: POSITION
+//!    in vec3 position;
+//!    in vec2 uv; // @semantic: TEXCOORD0
+//!
+//! The semantics let vertex arrays be built from mesh streams named by semantic, see
+//! `Context::new_vertex_array_from_semantics`, so that the streams don't depend on the names or
+//! locations the shader gives its attributes.
+
+const ANNOTATION: &str = "@semantic:";
+
+/// A vertex shader attribute annotated with a semantic name.
+#[derive(Clone,Debug,PartialEq)]
+pub struct AttributeSemantic {
+    /// The semantic name, for example "POSITION".
+    pub semantic: String,
+    /// The name of the attribute in the shader.
+    pub attribute: String
+}
+
+/// Find the semantic annotations of the attributes declared in the shader source. An annotation
+/// on a line of its own applies to the next attribute declaration; annotations followed by other
+/// code are ignored. Only single line comments are recognized, and declarations must be on a
+/// single line, one attribute per declaration.
+pub fn parse_attribute_semantics(source: &str) -> Vec<AttributeSemantic> {
+    let mut semantics = Vec::new();
+    let mut pending = None;
+    for line in source.lines() {
+        let (code, comment) = match line.find("//") {
+            Some(start) => (&line[..start], Some(&line[start + 2..])),
+            None => (line, None)
+        };
+        let annotation = comment.and_then(annotation_semantic);
+        match attribute_name(code) {
+            Some(attribute) => {
+                if let Some(semantic) = annotation.or_else(|| pending.take()) {
+                    semantics.push(AttributeSemantic { semantic: semantic.to_string(), attribute: attribute.to_string() });
+                }
+                pending = None;
+            },
+            // An annotation on a line of its own waits for the next declaration. Other code in
+            // between drops it, even with an annotation of its own; blank and comment lines don't
+            None if code.trim().is_empty() => {
+                if annotation.is_some() {
+                    pending = annotation;
+                }
+            },
+            None => pending = None
+        }
+    }
+    semantics
+}
+
+/// The semantic of a comment of the form `@semantic: NAME`.
+fn annotation_semantic(comment: &str) -> Option<&str> {
+    let rest = comment.trim().strip_prefix(ANNOTATION)?;
+    let semantic = rest.trim();
+    if semantic.is_empty() || semantic.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(semantic)
+}
+
+/// The name of the attribute declared by the line of code, like `in vec3 position;` or
+/// `layout(location = 0) in highp vec4 color;`.
+fn attribute_name(code: &str) -> Option<&str> {
+    let mut code = code.trim();
+    if code.starts_with("layout") {
+        code = code[code.find(')')? + 1..].trim_start();
+    }
+    let declaration = code.strip_suffix(';')?;
+    // Several attributes in one declaration can't share the annotation
+    if declaration.contains(',') {
+        return None;
+    }
+    let mut words = declaration.split_whitespace();
+    match words.next()? {
+        "in" | "attribute" => (),
+        _ => return None
+    }
+    // The last word is the name, preceded by the type and possibly a precision qualifier
+    let words: Vec<&str> = words.collect();
+    if words.len() < 2 {
+        return None;
+    }
+    let name = words[words.len() - 1];
+    let name = name.find('[').map_or(name, |bracket| &name[..bracket]);
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttributeSemantic,parse_attribute_semantics};
+
+    fn semantic(semantic: &str, attribute: &str) -> AttributeSemantic {
+        AttributeSemantic { semantic: semantic.to_string(), attribute: attribute.to_string() }
+    }
+
+    #[test]
+    fn annotations_before_and_after_declarations() {
+        let source = "#version 330\n\
+                      // @semantic: POSITION\n\
+                      \n\
+                      // The position in model space\n\
+                      layout(location = 0) in highp vec3 position;\n\
+                      in vec2 uv; // @semantic: TEXCOORD0\n\
+                      in vec4 color;\n";
+        assert_eq!(parse_attribute_semantics(source), vec![semantic("POSITION", "position"), semantic("TEXCOORD0", "uv")]);
+    }
+
+    #[test]
+    fn annotations_followed_by_other_code_are_dropped() {
+        let source = "// @semantic: NORMAL\n\
+                      uniform mat4 model;\n\
+                      in vec3 normal;\n\
+                      out vec2 v_uv; // @semantic: TEXCOORD0\n\
+                      in vec2 uv;\n";
+        assert_eq!(parse_attribute_semantics(source), Vec::new());
+    }
+
+    #[test]
+    fn malformed_annotations_and_declarations() {
+        let source = "in vec3 position; // @semantic:\n\
+                      in vec3 normal; // @semantic: TWO WORDS\n\
+                      in vec4 weights[2]; // @semantic: WEIGHTS\n\
+                      in vec2 uv, uv2; // @semantic: TEXCOORD0\n";
+        assert_eq!(parse_attribute_semantics(source), vec![semantic("WEIGHTS", "weights")]);
+    }
+}
@@ -20,47 +20,99 @@
 //! The basic idea is that you compile individual shaders, then link them into a program. A shader
 //! may be used in many programs.
 
-use std::iter::repeat;
+use std::fmt;
 
-use gl;
 use gl::types::{GLenum,GLint,GLsizei};
 
 use super::util::vec_to_string;
-use super::context::RegistrationHandle;
+use super::namedstring::NamedStringFunctions;
+use super::semantic::{self,AttributeSemantic};
+use super::context::{RegistrationHandle,QueuedDeletion,ContextResource};
 
 /// Supported shader types.
+#[derive(Clone,Copy,Debug,PartialEq)]
 pub enum ShaderType {
     VertexShader,
-    FragmentShader
+    FragmentShader,
+    /// Requires OpenGL 4.3 or OpenGL ES 3.1. A program with a compute shader has no other
+    /// shaders, and is run with `Renderer::dispatch_compute`.
+    ComputeShader
 }
 
 /// A shader object. It can be created, it's info log can be queried and it can be linked into a
 /// program.
 pub struct Shader {
     id: u32,
+    shader_type: ShaderType,
     registration: RegistrationHandle,
+    /// The semantic annotations of the attributes of a vertex shader.
+    semantics: Vec<AttributeSemantic>
 }
 
 impl Shader {
     /// Create and compile a shader from the given source. See glCreateShader, glShaderSource and 
     /// glCompileShader.
     pub fn new(shader_type: ShaderType, source: &str, registration: RegistrationHandle) -> Shader {
-        let id = unsafe { gl::CreateShader(shader_type_to_enum(shader_type)) };
-        check_error!();
-        let shader = Shader { id: id, registration: registration };
+        let shader = Shader::create(shader_type, source, registration);
         shader.compile(source);
         shader
     }
 
+    /// Create a shader from the given source and compile it with glCompileShaderIncludeARB, so
+    /// that the driver resolves the includes from the named strings on the search paths.
+    pub fn new_with_include_paths(shader_type: ShaderType, source: &str, registration: RegistrationHandle,
+                                  functions: &NamedStringFunctions, paths: &[&str]) -> Shader {
+        let shader = Shader::create(shader_type, source, registration);
+        shader.set_source(source);
+        functions.compile(shader.id, paths);
+        shader
+    }
+
+    fn create(shader_type: ShaderType, source: &str, registration: RegistrationHandle) -> Shader {
+        let id = unsafe { gl::CreateShader(shader_type_to_enum(shader_type)) };
+        check_error!();
+        let semantics = match shader_type {
+            ShaderType::VertexShader => semantic::parse_attribute_semantics(source),
+            _ => Vec::new()
+        };
+        Shader { id, shader_type, registration, semantics }
+    }
+
     /// Identify the shader. The returned value is the actual OpenGL object name.
     pub fn get_id(&self) -> u32 {
         self.id
     }
 
+    /// The type the shader was created as.
+    pub fn get_shader_type(&self) -> ShaderType {
+        self.shader_type
+    }
+
+    /// Get the source of the shader back from OpenGL. See glGetShaderSource.
+    pub fn get_source(&self) -> String {
+        let mut source_length = 0;
+        unsafe {
+            gl::GetShaderiv(self.id, gl::SHADER_SOURCE_LENGTH, &mut source_length);
+            check_error!();
+        }
+        if source_length <= 0 {
+            return String::new();
+        }
+        let mut actual_source_length = 0;
+        let mut source_vec: Vec<u8> = vec![0u8; source_length as usize];
+        unsafe {
+            let source_vec_ptr = source_vec.as_mut_ptr() as *mut i8;
+            gl::GetShaderSource(self.id, source_length, &mut actual_source_length, source_vec_ptr);
+            check_error!();
+        }
+        source_vec.truncate(actual_source_length as usize);
+        vec_to_string(source_vec)
+    }
+
     fn get_info_log(&self) -> String {
         let info_length = self.get_info_length();
         let mut actual_info_length = 0;
-        let mut info_vec: Vec<u8> = repeat(0u8).take(info_length as usize).collect();
+        let mut info_vec: Vec<u8> = vec![0u8; info_length as usize];
         unsafe {
             let info_vec_ptr = info_vec.as_mut_ptr() as *mut i8;
             gl::GetShaderInfoLog(self.id, info_length, &mut actual_info_length, info_vec_ptr);
@@ -70,14 +122,19 @@ impl Shader {
         vec_to_string(info_vec)
     }
 
-    fn compile(&self, source: &str) {
+    fn set_source(&self, source: &str) {
         unsafe {
             let length = source.len() as GLint;
             let source_ptr = source.as_ptr() as *const i8;
             let source_ptr_ptr = &source_ptr as *const *const i8;
             gl::ShaderSource(self.id, 1, source_ptr_ptr, &length);
             check_error!();
+        }
+    }
 
+    fn compile(&self, source: &str) {
+        self.set_source(source);
+        unsafe {
             gl::CompileShader(self.id);
             check_error!();
         }
@@ -102,14 +159,24 @@ impl Shader {
     }
 }
 
+impl fmt::Debug for Shader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Shader")
+            .field("id", &self.id)
+            .field("shader_type", &self.shader_type)
+            .finish()
+    }
+}
+
+impl ContextResource for Shader {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
 impl Drop for Shader {
     fn drop(&mut self) {
-        if self.registration.context_alive() {
-            unsafe {
-                gl::DeleteShader(self.id)
-            };
-            check_error!();
-        }
+        self.registration.queue_deletion(QueuedDeletion::Shader(self.id));
     }
 }
 
@@ -118,6 +185,12 @@ pub struct ShaderInfoAccessor<'a> {
     shader: &'a Shader
 }
 
+impl<'a> fmt::Debug for ShaderInfoAccessor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShaderInfoAccessor").field("shader", self.shader).finish()
+    }
+}
+
 impl<'a> ShaderInfoAccessor<'a> {
     /// Returns the shader info log. It may contain useful information about the shader, especially
     /// in the case of error.
@@ -129,16 +202,33 @@ impl<'a> ShaderInfoAccessor<'a> {
     pub fn get_compile_status(&self) -> bool {
         self.shader.get_compile_status()
     }
+
+    /// The source the shader was compiled from. See glGetShaderSource.
+    pub fn get_source(&self) -> String {
+        self.shader.get_source()
+    }
+
+    /// The type of the shader.
+    pub fn get_shader_type(&self) -> ShaderType {
+        self.shader.get_shader_type()
+    }
+
+    /// The semantic annotations of the attributes, found in the source of a vertex shader when
+    /// it was created. Empty for other shaders. See the `semantic` module.
+    pub fn get_attribute_semantics(&self) -> &'a [AttributeSemantic] {
+        &self.shader.semantics
+    }
 }
 
 /// Non-public constructor for the info accessor.
-pub fn new_shader_info_accessor(shader: &Shader) -> ShaderInfoAccessor {
+pub fn new_shader_info_accessor(shader: &Shader) -> ShaderInfoAccessor<'_> {
     ShaderInfoAccessor { shader: shader }
 }
 
 fn shader_type_to_enum(shader_type: ShaderType) -> GLenum {
     match shader_type {
         ShaderType::VertexShader => gl::VERTEX_SHADER,
-        ShaderType::FragmentShader => gl::FRAGMENT_SHADER
+        ShaderType::FragmentShader => gl::FRAGMENT_SHADER,
+        ShaderType::ComputeShader => gl::COMPUTE_SHADER
     }
 }
@@ -0,0 +1,227 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shader sources included into other shaders with `#include`, see `ShaderSourceLibrary`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::util::StableHasher;
+
+/// The `#version` line and default precision for the shaders of the library's own renderers.
+#[cfg(not(feature = "es3"))]
+pub const SHADER_HEADER: &str = "#version 330 core\n";
+/// The `#version` line and default precision for the shaders of the library's own renderers.
+#[cfg(feature = "es3")]
+pub const SHADER_HEADER: &str = "#version 300 es\nprecision highp float;\n";
+
+/// Like `SHADER_HEADER`, but with a version that has compute shaders and storage blocks.
+#[cfg(all(feature = "gpu_cull", not(feature = "es3")))]
+pub const COMPUTE_SHADER_HEADER: &str = "#version 430 core\n";
+/// Like `SHADER_HEADER`, but with a version that has compute shaders and storage blocks.
+#[cfg(all(feature = "gpu_cull", feature = "es3"))]
+pub const COMPUTE_SHADER_HEADER: &str = "#version 310 es\n";
+
+/// The GLSL snippets shipped with the library, by their include names.
+const BUILTIN_SOURCES: &[(&str, &str)] = &[
+    ("htgl/culled_instances.glsl", include_str!("glsl/culled_instances.glsl")),
+    ("htgl/frustum.glsl", include_str!("glsl/frustum.glsl")),
+    ("htgl/picking.glsl", include_str!("glsl/picking.glsl")),
+    ("htgl/shadow.glsl", include_str!("glsl/shadow.glsl")),
+    ("htgl/srgb.glsl", include_str!("glsl/srgb.glsl")),
+    ("htgl/tonemap.glsl", include_str!("glsl/tonemap.glsl"))
+];
+
+/// A problem found when resolving the includes of a shader source.
+#[derive(Clone,Debug,PartialEq)]
+pub enum IncludeError {
+    /// No source of the name is in the library. The line is the one of the `#include` in the
+    /// source including it, counting from one.
+    NotFound { name: String, line: usize },
+    /// An `#include` line is not of the form `#include <name>` or `#include "name"`.
+    Malformed { line: usize }
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IncludeError::NotFound { ref name, line } => write!(f, "Included source {} not found (line {})", name, line),
+            IncludeError::Malformed { line } => write!(f, "Malformed #include directive on line {}", line)
+        }
+    }
+}
+
+impl Error for IncludeError {}
+
+/// Named shader sources for including into other shader sources with `#include <name>` (or
+/// `#include "name"`), resolved by `preprocess` before compiling. See
+/// `Context::new_shader_from_library`, or `Context::register_named_strings` for having the
+/// driver resolve them.
+///
+/// The library starts with the snippets shipped with the crate, all functions prefixed with
+/// `htgl_`:
+///
+/// * `htgl/culled_instances.glsl`: the instances that passed the culling of `gpu_cull`
+/// * `htgl/frustum.glsl`: frustum culling tests
+/// * `htgl/picking.glsl`: the object id output of `Picker`
+/// * `htgl/shadow.glsl`: shadow map sampling with percentage-closer filtering
+/// * `htgl/srgb.glsl`: sRGB encoding and decoding
+/// * `htgl/tonemap.glsl`: tonemapping operators
+#[derive(Clone,Debug)]
+pub struct ShaderSourceLibrary {
+    sources: HashMap<String, String>
+}
+
+impl ShaderSourceLibrary {
+    /// A library of the built-in snippets.
+    pub fn new() -> ShaderSourceLibrary {
+        let mut library = ShaderSourceLibrary::empty();
+        for &(name, source) in BUILTIN_SOURCES.iter() {
+            library.add(name, source);
+        }
+        library
+    }
+
+    /// A library without even the built-in snippets.
+    pub fn empty() -> ShaderSourceLibrary {
+        ShaderSourceLibrary { sources: HashMap::new() }
+    }
+
+    /// Add a source under the name, replacing any source of the same name.
+    pub fn add(&mut self, name: &str, source: &str) {
+        self.sources.insert(name.to_string(), source.to_string());
+    }
+
+    /// Remove the source of the name, returning it.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.sources.remove(name)
+    }
+
+    /// The source of the name, if any.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.sources.get(name).map(|source| &source[..])
+    }
+
+    /// The names of the sources, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.sources.keys().map(|name| &name[..]).collect();
+        names.sort();
+        names
+    }
+
+    /// Replace the `#include` lines of the source with the sources they name, recursively. Each
+    /// source is included at most once, later includes of it are left out, so include cycles
+    /// don't matter.
+    pub fn preprocess(&self, source: &str) -> Result<String, IncludeError> {
+        let mut result = String::with_capacity(source.len());
+        let mut included = Vec::new();
+        self.expand(source, &mut result, &mut included)?;
+        Ok(result)
+    }
+
+    fn expand<'a>(&'a self, source: &str, result: &mut String, included: &mut Vec<&'a str>) -> Result<(), IncludeError> {
+        for (index, line) in source.lines().enumerate() {
+            let directive = line.trim_start();
+            if !directive.starts_with("#include") {
+                result.push_str(line);
+                result.push('\n');
+                continue;
+            }
+            let line_number = index + 1;
+            let name = match include_name(&directive["#include".len()..]) {
+                Some(name) => name,
+                None => return Err(IncludeError::Malformed { line: line_number })
+            };
+            let (name, included_source) = match self.sources.get_key_value(name) {
+                Some((name, included_source)) => (&name[..], &included_source[..]),
+                None => return Err(IncludeError::NotFound { name: name.to_string(), line: line_number })
+            };
+            if !included.contains(&name) {
+                included.push(name);
+                self.expand(included_source, result, included)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ShaderSourceLibrary {
+    fn default() -> ShaderSourceLibrary {
+        ShaderSourceLibrary::new()
+    }
+}
+
+/// The source wrapped in an include guard named after the source name, so that the driver
+/// includes a named string at most once per shader like `ShaderSourceLibrary::preprocess` does.
+/// See `Context::register_named_strings`.
+pub fn guarded_source(name: &str, source: &str) -> String {
+    let mut hasher = StableHasher::new();
+    hasher.feed(name.as_bytes());
+    let guard = format!("HTGL_INCLUDE_{:016x}", hasher.finish());
+    format!("#ifndef {0}\n#define {0}\n{1}\n#endif\n", guard, source)
+}
+
+/// The name of `<name>` or `"name"`, surrounded by whitespace only.
+fn include_name(rest: &str) -> Option<&str> {
+    let rest = rest.trim();
+    let (open, close) = match rest.chars().next() {
+        Some('<') => ('<', '>'),
+        Some('"') => ('"', '"'),
+        _ => return None
+    };
+    let inner = &rest[open.len_utf8()..];
+    let end = inner.find(close)?;
+    if !inner[end + 1..].trim().is_empty() || end == 0 {
+        return None;
+    }
+    Some(&inner[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShaderSourceLibrary,IncludeError,guarded_source};
+
+    fn library(sources: &[(&str, &str)]) -> ShaderSourceLibrary {
+        let mut library = ShaderSourceLibrary::empty();
+        for &(name, source) in sources.iter() {
+            library.add(name, source);
+        }
+        library
+    }
+
+    #[test]
+    fn includes_are_expanded_once() {
+        let library = library(&[("a.glsl", "#include \"b.glsl\"\nfloat a;"), ("b.glsl", "#include <a.glsl>\nfloat b;")]);
+        let source = "#version 330\n#include \"a.glsl\"\n  #include <b.glsl>\nvoid main() {}";
+        assert_eq!(library.preprocess(source).unwrap(), "#version 330\nfloat b;\nfloat a;\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn include_errors_name_the_line() {
+        let library = library(&[]);
+        assert_eq!(library.preprocess("\n#include <missing.glsl>"),
+            Err(IncludeError::NotFound { name: "missing.glsl".to_string(), line: 2 }));
+        assert_eq!(library.preprocess("#include missing.glsl"), Err(IncludeError::Malformed { line: 1 }));
+        assert_eq!(library.preprocess("#include <a.glsl> b"), Err(IncludeError::Malformed { line: 1 }));
+    }
+
+    #[test]
+    fn guards_differ_by_name() {
+        let a = guarded_source("a.glsl", "float a;");
+        assert!(a.starts_with("#ifndef HTGL_INCLUDE_"));
+        assert!(a.contains("\nfloat a;\n#endif\n"));
+        assert_ne!(a.lines().next(), guarded_source("b.glsl", "float a;").lines().next());
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Uniform and shader storage buffers with a CPU copy of their contents, uploading only what
+//! changed. See `ShadowedUbo`.
+
+use std::ops::Range;
+
+use super::{Context,BufferHandle};
+use super::pod::{self,Pod};
+use super::program::InterfaceBlock;
+use super::renderer::Renderer;
+
+/// Dirty ranges closer than this many bytes to each other are uploaded as one, as a few extra
+/// bytes are cheaper than another glBufferSubData call.
+const MERGE_GAP: usize = 64;
+
+/// A uniform buffer that keeps a copy of the block contents in memory. Writes go to the copy,
+/// and only the bytes that actually changed are marked dirty. `upload` then writes the dirty
+/// ranges to the buffer, neighbouring ranges coalesced, with glBufferSubData. Good for large
+/// blocks that mostly stay the same between frames, for example material parameters.
+///
+/// The buffer can also be bound as a shader storage buffer with `bind_storage`, as long as the
+/// shaders only read it: what the shaders write is not seen in the copy, and would be
+/// overwritten by the next upload.
+///
+/// Unlike `PerFrameUbo`, the buffer is not multi-buffered, so uploading may wait for the GPU to
+/// finish reading the previous contents.
+#[derive(Debug)]
+pub struct ShadowedUbo {
+    buffer: BufferHandle,
+    shadow: Vec<u8>,
+    dirty: Vec<Range<usize>>
+}
+
+impl ShadowedUbo {
+    /// Create the buffer and its copy for a block of the given size in bytes, zeroed.
+    pub fn new(context: &mut Context, block_size: usize) -> ShadowedUbo {
+        let shadow = vec![0u8; block_size];
+        let buffer = context.new_buffer();
+        context.edit_uniform_buffer(&buffer).data(&shadow[..]);
+        ShadowedUbo {
+            buffer,
+            shadow,
+            dirty: Vec::new()
+        }
+    }
+
+    /// Create the buffer for the block of a program, see `ProgramInfoAccessor::find_uniform_block`.
+    pub fn for_block(context: &mut Context, block: &InterfaceBlock) -> ShadowedUbo {
+        ShadowedUbo::new(context, block.data_size as usize)
+    }
+
+    /// The uniform buffer.
+    pub fn buffer(&self) -> &BufferHandle {
+        &self.buffer
+    }
+
+    /// The contents of the block as last written, uploaded or not.
+    pub fn contents(&self) -> &[u8] {
+        &self.shadow[..]
+    }
+
+    /// Is there something to upload?
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Write the values to the copy at the byte offset. The range that differs from the current
+    /// contents, if any, is marked dirty. Panics if the values don't fit in the block. A whole
+    /// block can be written at once as a struct of `InterfaceBlock::generate_rust_struct`, which
+    /// has its padding as fields, so that it can implement `Pod`.
+    pub fn write<T: Pod>(&mut self, offset: usize, values: &[T]) {
+        self.write_bytes(offset, pod::as_bytes(values));
+    }
+
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        let end = offset + bytes.len();
+        if end > self.shadow.len() {
+            panic!("Writing bytes {}..{} to a shadowed uniform buffer of {} bytes", offset, end, self.shadow.len());
+        }
+        if let Some(changed) = overwrite(&mut self.shadow[offset..end], bytes) {
+            self.dirty.push(offset + changed.start..offset + changed.end);
+        }
+    }
+
+    /// Write the values of the uniform of the block, found by name, at the offset reported by
+    /// OpenGL. The values are tightly packed, and laid out with the array and matrix strides of
+    /// the uniform, see `BlockUniform::scatter`: for example `[f32; 3]` for the elements of a
+    /// `vec3[]`, or `[f32; 9]` for a `mat3`. Panics if the block has no such uniform, or the
+    /// values are not whole elements of it.
+    pub fn write_uniform<T: Pod>(&mut self, block: &InterfaceBlock, name: &str, values: &[T]) {
+        let uniform = match block.get_uniform(name) {
+            Some(uniform) => uniform,
+            None => panic!("Uniform block {} has no uniform {}", block.name, name)
+        };
+        let bytes = pod::as_bytes(values);
+        let pieces = match uniform.scatter(bytes) {
+            Some(pieces) => pieces,
+            None => panic!("{} bytes are not whole elements of the uniform {} of type {:?} and size {}",
+                bytes.len(), name, uniform.uniform_type, uniform.size)
+        };
+        for (offset, piece) in pieces {
+            self.write_bytes(offset, piece);
+        }
+    }
+
+    /// Upload the dirty ranges to the buffer. Returns the number of bytes uploaded.
+    pub fn upload(&mut self, context: &mut Context) -> usize {
+        if self.dirty.is_empty() {
+            return 0;
+        }
+        let ranges = coalesce(&mut self.dirty);
+        let mut editor = context.edit_uniform_buffer(&self.buffer);
+        let mut uploaded = 0;
+        for range in ranges.iter() {
+            editor.sub_data(&self.shadow[range.clone()], range.start);
+            uploaded += range.len();
+        }
+        self.dirty.clear();
+        uploaded
+    }
+
+    /// Bind the whole block to the uniform buffer binding point. See
+    /// `Renderer::use_uniform_buffer_range`.
+    pub fn bind(&self, renderer: &mut Renderer, binding: u32) {
+        renderer.use_uniform_buffer_range(binding, &self.buffer, 0, self.shadow.len());
+    }
+
+    /// Bind the whole block to the shader storage buffer binding point, for shaders that only
+    /// read it. See `Renderer::use_storage_buffer_range`.
+    pub fn bind_storage(&self, renderer: &mut Renderer, binding: u32) {
+        renderer.use_storage_buffer_range(binding, &self.buffer, 0, self.shadow.len());
+    }
+}
+
+/// Copy the bytes over the target, and give the range of the bytes that changed, if any.
+fn overwrite(target: &mut [u8], bytes: &[u8]) -> Option<Range<usize>> {
+    let first = target.iter().zip(bytes.iter()).position(|(old, new)| old != new)?;
+    let last = target.iter().zip(bytes.iter()).rposition(|(old, new)| old != new).unwrap_or(first);
+    target.copy_from_slice(bytes);
+    Some(first..last + 1)
+}
+
+/// Sort the ranges and merge the overlapping and nearby ones.
+fn coalesce(ranges: &mut Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end + MERGE_GAP => last.end = last.end.max(range.end),
+            _ => merged.push(range)
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MERGE_GAP,coalesce,overwrite};
+
+    #[test]
+    fn only_changed_bytes_are_dirty() {
+        let mut target = [1, 2, 3, 4, 5];
+        assert_eq!(overwrite(&mut target, &[1, 2, 3, 4, 5]), None);
+        assert_eq!(overwrite(&mut target, &[1, 9, 3, 9, 5]), Some(1..4));
+        assert_eq!(target, [1, 9, 3, 9, 5]);
+    }
+
+    #[test]
+    fn nearby_ranges_are_merged() {
+        let mut ranges = vec![200..210, 0..4, 2..8, 8 + MERGE_GAP..100];
+        assert_eq!(coalesce(&mut ranges), vec![0..100, 200..210]);
+        assert!(ranges.is_empty());
+    }
+
+    #[test]
+    fn distant_ranges_are_kept_apart() {
+        let mut ranges = vec![0..4, 5 + MERGE_GAP..6 + MERGE_GAP];
+        assert_eq!(coalesce(&mut ranges), vec![0..4, 5 + MERGE_GAP..6 + MERGE_GAP]);
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Storing the bone matrices of skeletal animation for the vertex shader, see
+//! `BoneMatrixBuffer`.
+
+use super::{BufferHandle,Context,TextureHandle};
+use super::perframe::PerFrameUbo;
+use super::pod;
+use super::renderer::Renderer;
+use super::shaderlib::ShaderSourceLibrary;
+use super::sync::Fence;
+use super::texture::TextureFormat;
+
+/// The name the snippet declaring the bone matrices is added to a `ShaderSourceLibrary` under.
+pub const BONES_SNIPPET_NAME: &str = "htgl/bones.glsl";
+
+/// The uniform block name of the bone matrices when they are stored in a uniform buffer.
+const BLOCK_NAME: &str = "HtglBones";
+
+/// Where a `BoneMatrixBuffer` keeps the matrices.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum BoneStorage {
+    /// A uniform block, as an array of `mat4`.
+    UniformBuffer,
+    /// An RGBA32F buffer texture of four texels per matrix, read with `texelFetch`. Used when
+    /// the matrices don't fit in a uniform block.
+    Texture
+}
+
+/// The per-frame buffers and their buffer textures of the texture storage, each fenced when its
+/// frame ends.
+#[derive(Debug)]
+struct TextureRing {
+    buffers: Vec<BufferHandle>,
+    textures: Vec<TextureHandle>,
+    fences: Vec<Option<Fence>>,
+    current: usize
+}
+
+#[derive(Debug)]
+enum Storage {
+    UniformBuffer(PerFrameUbo),
+    Texture(TextureRing)
+}
+
+/// The bone matrices of skinned meshes, rewritten every frame. The matrices are stored in a
+/// uniform block if the block fits in GL_MAX_UNIFORM_BLOCK_SIZE, and in a float buffer texture
+/// otherwise. Either way, there is one copy per frame in flight, fenced as with `PerFrameUbo`,
+/// so that writing the matrices of a frame doesn't wait for the GPU to finish the previous one.
+///
+/// The vertex shader includes the snippet added with `add_snippet` and calls
+/// `mat4 htgl_bone_matrix(int index)`, without caring how the matrices are stored:
+///
+///    #include <htgl/bones.glsl>
+///    ...
+///    mat4 skin = htgl_bone_matrix(bones.x) * weights.x + htgl_bone_matrix(bones.y) * weights.y;
+///
+/// Each frame, `write` the matrices, `bind` them, draw, and call `end_frame`.
+#[derive(Debug)]
+pub struct BoneMatrixBuffer {
+    max_bones: usize,
+    storage: Storage
+}
+
+impl BoneMatrixBuffer {
+    /// Create the storage for up to `max_bones` matrices, with a copy per frame in flight.
+    /// Panics if `max_bones` is zero, or if the matrices don't fit in a uniform block and the
+    /// context has no buffer textures or there are more bones than a buffer texture can hold,
+    /// that is, more than a quarter of GL_MAX_TEXTURE_BUFFER_SIZE.
+    pub fn new(context: &mut Context, max_bones: usize, frames_in_flight: usize) -> BoneMatrixBuffer {
+        if max_bones == 0 {
+            panic!("A bone matrix buffer needs room for at least one bone");
+        }
+        if frames_in_flight == 0 {
+            panic!("A bone matrix buffer needs at least one frame in flight");
+        }
+        let block_size = max_bones * 64;
+        let storage = if block_size <= context.get_info().uniform_buffer.max_block_size as usize {
+            Storage::UniformBuffer(PerFrameUbo::new(context, block_size, frames_in_flight))
+        }
+        else {
+            let max_texels = context.get_info().texture.max_buffer_size.max(0) as usize;
+            if max_texels == 0 {
+                panic!("{} bones don't fit in a uniform block, and the context has no buffer textures", max_bones);
+            }
+            if max_bones * 4 > max_texels {
+                panic!("{} bones don't fit in a buffer texture, the maximum buffer texture size is {} texels", max_bones, max_texels);
+            }
+            let buffers: Vec<BufferHandle> = (0..frames_in_flight).map(|_| {
+                let buffer = context.new_buffer();
+                context.edit_uniform_buffer(&buffer).data(&vec![[0.0f32; 16]; max_bones]);
+                buffer
+            }).collect();
+            let textures = buffers.iter().map(|buffer| {
+                context.new_buffer_texture(buffer, TextureFormat::Rgba32f)
+                    .unwrap_or_else(|error| panic!("Creating the bone matrix texture failed: {}", error))
+            }).collect();
+            Storage::Texture(TextureRing {
+                buffers,
+                textures,
+                fences: (0..frames_in_flight).map(|_| None).collect(),
+                current: 0
+            })
+        };
+        BoneMatrixBuffer { max_bones, storage }
+    }
+
+    /// How the matrices are stored.
+    pub fn storage(&self) -> BoneStorage {
+        match self.storage {
+            Storage::UniformBuffer(_) => BoneStorage::UniformBuffer,
+            Storage::Texture(_) => BoneStorage::Texture
+        }
+    }
+
+    /// The largest number of matrices that can be written.
+    pub fn max_bones(&self) -> usize {
+        self.max_bones
+    }
+
+    /// The GLSL declaring the matrices and `mat4 htgl_bone_matrix(int index)` for the storage in
+    /// use. With the uniform buffer storage, the block is called `HtglBones`; with the texture
+    /// storage, the `samplerBuffer` is called `htgl_bone_texture`.
+    pub fn glsl_snippet(&self) -> String {
+        match self.storage {
+            Storage::UniformBuffer(_) => format!(
+"layout(std140) uniform {} {{
+    mat4 htgl_bones[{}];
+}};
+
+mat4 htgl_bone_matrix(int index) {{
+    return htgl_bones[index];
+}}
+", BLOCK_NAME, self.max_bones),
+            Storage::Texture(_) => String::from(
+"uniform highp samplerBuffer htgl_bone_texture;
+
+mat4 htgl_bone_matrix(int index) {
+    return mat4(
+        texelFetch(htgl_bone_texture, index * 4),
+        texelFetch(htgl_bone_texture, index * 4 + 1),
+        texelFetch(htgl_bone_texture, index * 4 + 2),
+        texelFetch(htgl_bone_texture, index * 4 + 3));
+}
+")
+        }
+    }
+
+    /// Add `glsl_snippet` to the library as `htgl/bones.glsl`, for the vertex shaders to
+    /// include. See `Context::new_shader_from_library`.
+    pub fn add_snippet(&self, library: &mut ShaderSourceLibrary) {
+        library.add(BONES_SNIPPET_NAME, &self.glsl_snippet());
+    }
+
+    /// Write the matrices of the current frame, column major as for `Renderer::uniform_matrix`.
+    /// If the GPU may still be reading the copy for an earlier frame, waits until it's done.
+    /// Panics if there are more than `max_bones` matrices.
+    pub fn write(&mut self, context: &mut Context, matrices: &[[f32; 16]]) {
+        if matrices.len() > self.max_bones {
+            panic!("Writing {} bone matrices to a buffer of {}", matrices.len(), self.max_bones);
+        }
+        if matrices.is_empty() {
+            return;
+        }
+        match self.storage {
+            Storage::UniformBuffer(ref mut ubo) => ubo.write(context, matrices),
+            Storage::Texture(ref mut ring) => {
+                if let Some(fence) = ring.fences[ring.current].take() {
+                    fence.wait();
+                }
+                context.edit_uniform_buffer(&ring.buffers[ring.current]).sub_data(pod::as_bytes(matrices), 0);
+            }
+        }
+    }
+
+    /// Bind the matrices of the current frame. The uniform block is bound to the binding point
+    /// of `HtglBones` in `Context::uniform_binding_points`, as assigned to the programs by
+    /// `ProgramEditor::bind_uniform_blocks`. The texture is bound to the texture unit, which
+    /// the `htgl_bone_texture` sampler of the program must be set to read; the unit is not
+    /// used with the uniform buffer storage.
+    pub fn bind(&self, renderer: &mut Renderer, texture_unit: u32) {
+        match self.storage {
+            Storage::UniformBuffer(ref ubo) => {
+                let (offset, size) = ubo.binding_range();
+                renderer.use_uniform_buffer_range_named(BLOCK_NAME, ubo.buffer(), offset, size);
+            },
+            Storage::Texture(ref ring) => renderer.use_texture(texture_unit, &ring.textures[ring.current])
+        }
+    }
+
+    /// End the frame: fence the copy of the current frame and move on to the next one.
+    pub fn end_frame(&mut self, context: &mut Context) {
+        match self.storage {
+            Storage::UniformBuffer(ref mut ubo) => ubo.end_frame(context),
+            Storage::Texture(ref mut ring) => {
+                ring.fences[ring.current] = Some(context.insert_fence());
+                ring.current = (ring.current + 1) % ring.textures.len();
+            }
+        }
+    }
+}
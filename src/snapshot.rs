@@ -0,0 +1,276 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! A description of the live resources of a context for inspection tools, see
+//! `Context::export_debug_snapshot`.
+
+use std::rc::{Rc,Weak};
+
+use gl::types::GLenum;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::buffer::BufferObject;
+use super::context::{ContextResource,ResourceReport};
+use super::framebuffer::RenderTarget;
+use super::handle::{Handle,HandleAccess};
+use super::program::Program;
+use super::shader::Shader;
+use super::texture::Texture;
+use super::vertexarray::VertexArray;
+
+/// The live resources of a context and their relationships, as the library knows them. The
+/// resources refer to each other by their OpenGL names. Formats and types are given as their
+/// names in this library, such as "Rgba8". With the `serde` feature, the snapshot can be
+/// serialized, for example to JSON for an external tool.
+#[derive(Clone,Debug,Default,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DebugSnapshot {
+    /// The resource counts and memory use, see `Context::resource_report`.
+    pub resources: ResourceReport,
+    pub buffers: Vec<BufferSnapshot>,
+    pub vertex_arrays: Vec<VertexArraySnapshot>,
+    pub shaders: Vec<ShaderSnapshot>,
+    pub programs: Vec<ProgramSnapshot>,
+    pub textures: Vec<TextureSnapshot>,
+    pub render_targets: Vec<RenderTargetSnapshot>
+}
+
+/// A buffer object in a `DebugSnapshot`.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct BufferSnapshot {
+    pub id: u32,
+    pub label: Option<String>,
+    /// The size of the data store in bytes.
+    pub size: usize,
+    /// The type of the indices, if the buffer has been filled with indices.
+    pub index_type: Option<String>
+}
+
+/// A vertex array object in a `DebugSnapshot`.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct VertexArraySnapshot {
+    pub id: u32,
+    pub label: Option<String>,
+    pub attribute_count: usize,
+    /// The vertex buffers the attributes read from, each once.
+    pub vertex_buffers: Vec<u32>,
+    pub index_buffer: Option<u32>
+}
+
+/// A shader object in a `DebugSnapshot`.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ShaderSnapshot {
+    pub id: u32,
+    pub label: Option<String>,
+    pub shader_type: String
+}
+
+/// A program object in a `DebugSnapshot`.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ProgramSnapshot {
+    pub id: u32,
+    pub label: Option<String>,
+    /// The shaders the program was linked from, if the library knows them.
+    pub shaders: Vec<u32>
+}
+
+/// A texture object in a `DebugSnapshot`.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TextureSnapshot {
+    pub id: u32,
+    pub label: Option<String>,
+    pub target: String,
+    /// The size of the base level, if it has been specified through the library.
+    pub size: Option<(u32, u32)>,
+    /// The internal format of the base level, if it has been specified through the library
+    /// with an uncompressed format.
+    pub format: Option<String>,
+    pub levels: u32,
+    /// The memory used by the levels, as in `ResourceReport::texture_bytes`.
+    pub bytes: usize
+}
+
+/// A render target in a `DebugSnapshot`.
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RenderTargetSnapshot {
+    pub id: u32,
+    pub label: Option<String>,
+    pub size: (u32, u32),
+    /// The mipmap level of the textures that is attached.
+    pub level: u32,
+    pub color_textures: Vec<u32>,
+    pub depth_texture: Option<u32>
+}
+
+/// Weak references to the resources created by a context, for listing the live ones.
+#[derive(Debug,Default)]
+pub struct LiveResources {
+    buffers: Vec<Weak<BufferObject>>,
+    vertex_arrays: Vec<Weak<VertexArray>>,
+    shaders: Vec<Weak<Shader>>,
+    programs: Vec<Weak<Program>>,
+    textures: Vec<Weak<Texture>>,
+    render_targets: Vec<Weak<RenderTarget>>
+}
+
+impl LiveResources {
+    pub fn new() -> LiveResources {
+        LiveResources::default()
+    }
+
+    /// Remember the resource of the handle, without keeping it alive.
+    pub fn add<T: SnapshotResource>(&mut self, handle: &Handle<T>) {
+        let list = T::live_list(self);
+        // Drop the dead ones whenever the list would grow, to keep it from growing forever
+        if list.len() == list.capacity() {
+            list.retain(|resource| resource.strong_count() > 0);
+        }
+        list.push(Rc::downgrade(handle.rc()));
+    }
+
+    /// Describe the resources that are still alive, in creation order.
+    pub fn snapshot(&self, resources: ResourceReport) -> DebugSnapshot {
+        DebugSnapshot {
+            resources,
+            buffers: live(&self.buffers).map(|buffer| BufferSnapshot {
+                id: buffer.id,
+                label: buffer.registration().label(),
+                size: buffer.size(),
+                index_type: buffer.index_type().map(|index_type| format!("{:?}", index_type))
+            }).collect(),
+            vertex_arrays: live(&self.vertex_arrays).map(|vertex_array| {
+                let mut vertex_buffers = Vec::new();
+                for attribute in vertex_array.attributes().iter() {
+                    let id = attribute.vertex_buffer.access().id;
+                    if !vertex_buffers.contains(&id) {
+                        vertex_buffers.push(id);
+                    }
+                }
+                VertexArraySnapshot {
+                    id: vertex_array.id,
+                    label: vertex_array.registration().label(),
+                    attribute_count: vertex_array.attributes().len(),
+                    vertex_buffers,
+                    index_buffer: vertex_array.index_buffer().map(|buffer| buffer.id)
+                }
+            }).collect(),
+            shaders: live(&self.shaders).map(|shader| ShaderSnapshot {
+                id: shader.get_id(),
+                label: shader.registration().label(),
+                shader_type: format!("{:?}", shader.get_shader_type())
+            }).collect(),
+            programs: live(&self.programs).map(|program| ProgramSnapshot {
+                id: program.get_id(),
+                label: program.registration().label(),
+                shaders: program.get_shaders().iter().map(|shader| shader.access().get_id()).collect()
+            }).collect(),
+            textures: live(&self.textures).map(|texture| TextureSnapshot {
+                id: texture.id,
+                label: texture.registration().label(),
+                target: format!("{:?}", texture.target()),
+                size: texture.level_dimensions(0),
+                format: texture.level_format(0).map(|format| format!("{:?}", format)),
+                levels: texture.level_count(),
+                bytes: texture.registration().memory()
+            }).collect(),
+            render_targets: live(&self.render_targets).map(|render_target| RenderTargetSnapshot {
+                id: render_target.id,
+                label: render_target.registration().label(),
+                size: render_target.dimensions(),
+                level: render_target.level(),
+                color_textures: render_target.color_textures().iter().map(|texture| texture.access().id).collect(),
+                depth_texture: render_target.depth_texture().map(|texture| texture.access().id)
+            }).collect()
+        }
+    }
+}
+
+/// The resources that can be labeled and listed in a `DebugSnapshot`.
+pub trait SnapshotResource: ContextResource + Sized {
+    /// The identifier of the kind of object and the object name, as glObjectLabel takes them.
+    fn label_target(&self) -> (GLenum, u32);
+
+    /// The list of the resources of this kind.
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<Self>>;
+}
+
+impl SnapshotResource for BufferObject {
+    fn label_target(&self) -> (GLenum, u32) {
+        (gl::BUFFER, self.id)
+    }
+
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<BufferObject>> {
+        &mut resources.buffers
+    }
+}
+
+impl SnapshotResource for VertexArray {
+    fn label_target(&self) -> (GLenum, u32) {
+        (gl::VERTEX_ARRAY, self.id)
+    }
+
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<VertexArray>> {
+        &mut resources.vertex_arrays
+    }
+}
+
+impl SnapshotResource for Shader {
+    fn label_target(&self) -> (GLenum, u32) {
+        (gl::SHADER, self.get_id())
+    }
+
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<Shader>> {
+        &mut resources.shaders
+    }
+}
+
+impl SnapshotResource for Program {
+    fn label_target(&self) -> (GLenum, u32) {
+        (gl::PROGRAM, self.get_id())
+    }
+
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<Program>> {
+        &mut resources.programs
+    }
+}
+
+impl SnapshotResource for Texture {
+    fn label_target(&self) -> (GLenum, u32) {
+        (gl::TEXTURE, self.id)
+    }
+
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<Texture>> {
+        &mut resources.textures
+    }
+}
+
+impl SnapshotResource for RenderTarget {
+    fn label_target(&self) -> (GLenum, u32) {
+        (gl::FRAMEBUFFER, self.id)
+    }
+
+    fn live_list(resources: &mut LiveResources) -> &mut Vec<Weak<RenderTarget>> {
+        &mut resources.render_targets
+    }
+}
+
+fn live<T>(list: &[Weak<T>]) -> impl Iterator<Item = Rc<T>> + '_ {
+    list.iter().filter_map(|resource| resource.upgrade())
+}
@@ -0,0 +1,321 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Preparing vertex data for uploading on worker threads, see `StagingPool`. Enabled with the
+//! `staging` feature.
+//!
+//! OpenGL calls have to be made on the thread of the context, but packing the vertex data into
+//! the bytes to upload doesn't. With many large meshes, doing that on other threads keeps the
+//! context thread free for drawing.
+//!
+//! The bytes are prepared into ordinary allocations, which are reused when given back with
+//! `StagingPool::recycle`. They are not driver-pinned memory: mapping a buffer object for the
+//! workers to write into would have to be done and undone on the context thread.
+
+use std::fmt;
+use std::mem::size_of;
+use std::slice;
+use std::sync::{Arc,Mutex};
+use std::sync::mpsc::{self,Receiver,Sender,TryRecvError};
+use std::thread::{self,JoinHandle};
+
+use super::pod::{self,Pod};
+use super::vertexarray::{self,VertexFormat};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The most byte buffers kept for reuse, see `StagingPool::recycle`.
+const MAX_SPARE_BUFFERS: usize = 16;
+
+/// The order of the bytes of multi-byte values, see `StagingPool::prepare_byte_order`.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian
+}
+
+impl ByteOrder {
+    /// The byte order of this machine, which OpenGL expects the uploaded data to be in.
+    pub fn native() -> ByteOrder {
+        if cfg!(target_endian = "big") {
+            ByteOrder::BigEndian
+        }
+        else {
+            ByteOrder::LittleEndian
+        }
+    }
+}
+
+/// A pool of worker threads preparing vertex data. Each preparation gives a `StagedData`, which
+/// is polled or waited on the context thread, and the bytes are uploaded with the usual editors:
+///
+///    let staged = pool.prepare_vertex_data(vertices);
+///    // ... later, on the context thread
+///    let bytes = staged.wait();
+///    context.edit_vertex_buffer(&buffer).data(&bytes[..]);
+///    pool.recycle(bytes);
+///
+/// Dropping the pool waits for the preparations in progress to finish.
+pub struct StagingPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    spare: Arc<Mutex<Vec<Vec<u8>>>>
+}
+
+impl StagingPool {
+    /// Start a pool of the given number of worker threads, at least one.
+    pub fn new(threads: usize) -> StagingPool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1)).map(|_| {
+            let receiver = receiver.clone();
+            thread::spawn(move || {
+                loop {
+                    // The lock is released before running the job
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break
+                    };
+                    job();
+                }
+            })
+        }).collect();
+        StagingPool { sender: Some(sender), workers, spare: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// Prepare the bytes to upload with the function on a worker thread.
+    pub fn prepare<F>(&self, prepare: F) -> StagedData where F: FnOnce() -> Vec<u8> + Send + 'static {
+        let (sender, receiver) = mpsc::channel();
+        let job = Box::new(move || {
+            // The staged data may have been dropped already, which is fine
+            let _ = sender.send(prepare());
+        });
+        self.sender.as_ref().unwrap().send(job).unwrap();
+        StagedData { receiver, bytes: None }
+    }
+
+    /// Prepare the bytes into a recycled buffer, or a new one if there are none.
+    fn prepare_reusing<F>(&self, prepare: F) -> StagedData where F: FnOnce(&mut Vec<u8>) + Send + 'static {
+        let spare = self.spare.clone();
+        self.prepare(move || {
+            let mut bytes = spare.lock().unwrap().pop().unwrap_or_default();
+            prepare(&mut bytes);
+            bytes
+        })
+    }
+
+    /// Give the bytes of an uploaded `StagedData` back to the pool, so that the vertex data
+    /// prepared later reuses the allocation. Only a few buffers are kept, the rest are dropped.
+    pub fn recycle(&self, mut bytes: Vec<u8>) {
+        let mut spare = self.spare.lock().unwrap();
+        if spare.len() < MAX_SPARE_BUFFERS {
+            bytes.clear();
+            spare.push(bytes);
+        }
+    }
+
+    /// Pack the vertices into the bytes of a vertex buffer on a worker thread. The vertices are
+    /// laid out as in memory, see `Pod`. Panics if the attributes of the vertex format take more
+    /// bytes than the vertex type.
+    pub fn prepare_vertex_data<V, I>(&self, vertices: I) -> StagedData
+        where V: VertexFormat + Pod + Send, I: IntoIterator<Item = V> + Send + 'static {
+        let attributes_size = vertexarray::layouts_size(&V::attributes()[..]) as usize;
+        if attributes_size > size_of::<V>() {
+            panic!("The attributes of the vertex format take {} bytes, but the vertex type only {}", attributes_size, size_of::<V>());
+        }
+        self.prepare_reusing(move |bytes| {
+            for vertex in vertices {
+                bytes.extend_from_slice(pod::as_bytes(slice::from_ref(&vertex)));
+            }
+        })
+    }
+
+    /// Convert vertex data of the format from the byte order to the native one on a worker
+    /// thread, for data read from files written on machines of the other order. Each component
+    /// of the attributes is swapped, and the bytes after the attributes are left as they are.
+    /// Panics if the attributes take more bytes than the vertex type, or if the data is not
+    /// whole vertices.
+    pub fn prepare_byte_order<V: VertexFormat>(&self, bytes: Vec<u8>, order: ByteOrder) -> StagedData {
+        let stride = size_of::<V>();
+        let components = vertexarray::component_sizes(&V::attributes()[..]);
+        let attributes_size: u32 = components.iter().sum();
+        if attributes_size as usize > stride {
+            panic!("The attributes of the vertex format take {} bytes, but the vertex type only {}", attributes_size, stride);
+        }
+        if stride != 0 && !bytes.len().is_multiple_of(stride) {
+            panic!("The data of {} bytes is not whole vertices of {} bytes", bytes.len(), stride);
+        }
+        self.prepare(move || {
+            let mut bytes = bytes;
+            if order != ByteOrder::native() {
+                swap_components(&mut bytes[..], stride, &components[..]);
+            }
+            bytes
+        })
+    }
+
+    /// Interleave separate attribute arrays into the bytes of a vertex buffer on a worker
+    /// thread. Each attribute is given as its component count and the components of all the
+    /// vertices, for example `(3, positions)`. Panics if the attributes are for different
+    /// numbers of vertices.
+    pub fn prepare_interleaved(&self, attributes: Vec<(usize, Vec<f32>)>) -> StagedData {
+        let vertex_count = attributes.first().map_or(0, |&(components, ref values)| values.len() / components.max(1));
+        for (i, &(components, ref values)) in attributes.iter().enumerate() {
+            if values.len() != components * vertex_count {
+                panic!("Attribute {} has {} values, but {} vertices of {} components need {}",
+                    i, values.len(), vertex_count, components, components * vertex_count);
+            }
+        }
+        self.prepare_reusing(move |bytes| {
+            let vertex_components: usize = attributes.iter().map(|&(components, _)| components).sum();
+            bytes.reserve(vertex_count * vertex_components * size_of::<f32>());
+            for vertex in 0..vertex_count {
+                for &(components, ref values) in attributes.iter() {
+                    bytes.extend_from_slice(pod::as_bytes(&values[vertex * components..(vertex + 1) * components]));
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Debug for StagingPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StagingPool").field("threads", &self.workers.len()).finish()
+    }
+}
+
+impl Drop for StagingPool {
+    fn drop(&mut self) {
+        // Closing the channel stops the workers once the queued jobs are done
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Bytes being prepared by a `StagingPool`.
+#[derive(Debug)]
+pub struct StagedData {
+    receiver: Receiver<Vec<u8>>,
+    bytes: Option<Vec<u8>>
+}
+
+impl StagedData {
+    /// Are the bytes ready? Doesn't block. Panics if the preparation panicked.
+    pub fn is_ready(&mut self) -> bool {
+        if self.bytes.is_none() {
+            match self.receiver.try_recv() {
+                Ok(bytes) => self.bytes = Some(bytes),
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => panic!("Preparing staged data failed")
+            }
+        }
+        self.bytes.is_some()
+    }
+
+    /// Wait for the bytes to be ready and take them. Panics if the preparation panicked.
+    pub fn wait(self) -> Vec<u8> {
+        match self.bytes {
+            Some(bytes) => bytes,
+            None => self.receiver.recv().expect("Preparing staged data failed")
+        }
+    }
+}
+
+/// Reverse the bytes of each component of each vertex.
+fn swap_components(bytes: &mut [u8], stride: usize, components: &[u32]) {
+    for vertex in bytes.chunks_mut(stride.max(1)) {
+        let mut offset = 0;
+        for &size in components.iter() {
+            vertex[offset..offset + size as usize].reverse();
+            offset += size as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StagingPool,ByteOrder,swap_components};
+    use super::super::pod::Pod;
+    use super::super::vertexarray::{VertexFormat,VertexAttributeType};
+
+    #[repr(C)]
+    #[derive(Clone,Copy)]
+    struct Vertex {
+        position: [f32; 2],
+        color: [u8; 4]
+    }
+
+    unsafe impl Pod for Vertex {}
+
+    impl VertexFormat for Vertex {
+        fn attributes() -> Vec<(u8, VertexAttributeType, bool)> {
+            vec![(2, VertexAttributeType::Float, false), (4, VertexAttributeType::UnsignedByte, true)]
+        }
+    }
+
+    #[test]
+    fn vertex_data() {
+        let pool = StagingPool::new(2);
+        let vertices = vec![Vertex { position: [1.0, 2.0], color: [1, 2, 3, 4] }; 3];
+        let bytes = pool.prepare_vertex_data(vertices).wait();
+        assert_eq!(bytes.len(), 36);
+        assert_eq!(&bytes[4..8], &2.0f32.to_ne_bytes()[..]);
+        assert_eq!(&bytes[8..12], &[1, 2, 3, 4][..]);
+    }
+
+    #[test]
+    fn recycled_buffer_is_reused() {
+        let pool = StagingPool::new(1);
+        let bytes = pool.prepare_interleaved(vec![(1, vec![1.0; 64])]).wait();
+        let capacity = bytes.capacity();
+        pool.recycle(bytes);
+        let bytes = pool.prepare_interleaved(vec![(1, vec![2.0])]).wait();
+        assert_eq!(bytes, 2.0f32.to_ne_bytes().to_vec());
+        assert_eq!(bytes.capacity(), capacity);
+    }
+
+    #[test]
+    fn interleaved() {
+        let pool = StagingPool::new(1);
+        let bytes = pool.prepare_interleaved(vec![(2, vec![1.0, 2.0, 3.0, 4.0]), (1, vec![5.0, 6.0])]).wait();
+        let floats: Vec<f32> = bytes.chunks(4).map(|chunk| f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect();
+        assert_eq!(floats, vec![1.0, 2.0, 5.0, 3.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn swapping_components() {
+        let mut bytes = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        swap_components(&mut bytes[..], 6, &[4, 1]);
+        assert_eq!(bytes, vec![4, 3, 2, 1, 5, 6, 10, 9, 8, 7, 11, 12]);
+    }
+
+    #[test]
+    fn byte_order() {
+        let pool = StagingPool::new(1);
+        let foreign = if ByteOrder::native() == ByteOrder::LittleEndian { ByteOrder::BigEndian } else { ByteOrder::LittleEndian };
+        let mut bytes = Vec::new();
+        for value in [1.0f32, 2.0].iter() {
+            let mut value_bytes = value.to_ne_bytes();
+            value_bytes.reverse();
+            bytes.extend_from_slice(&value_bytes);
+        }
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        let converted = pool.prepare_byte_order::<Vertex>(bytes.clone(), foreign).wait();
+        assert_eq!(&converted[0..4], &1.0f32.to_ne_bytes()[..]);
+        assert_eq!(&converted[8..12], &[1, 2, 3, 4][..]);
+        assert_eq!(pool.prepare_byte_order::<Vertex>(bytes.clone(), ByteOrder::native()).wait(), bytes);
+    }
+}
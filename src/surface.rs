@@ -0,0 +1,29 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The surface is the thing the default framebuffer is shown on, usually a window. This library
+//! doesn't deal with windowing, so the windowing integration implements the `Surface` trait and
+//! gives the surface to the context with `Context::set_surface`.
+
+/// The operations of a window (or other drawable) that the context needs.
+pub trait Surface {
+    /// Show the rendered frame, that is, swap the buffers of the surface.
+    fn present(&mut self);
+    /// The size of the drawable area in pixels. Note that on high-DPI displays this may differ
+    /// from the window size in screen coordinates.
+    fn dimensions(&self) -> (u32, u32);
+    /// Set the swap interval: 0 disables vsync, 1 waits for one vertical blank before swapping
+    /// and so on.
+    fn set_swap_interval(&mut self, interval: i32);
+}
@@ -0,0 +1,78 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Synchronizing with the GPU, see `Fence`.
+
+use std::fmt;
+
+use gl::types::GLsync;
+
+use super::context::{RegistrationHandle,QueuedDeletion,ContextResource};
+
+/// How long a single wait for a fence may take, in nanoseconds, before waiting again.
+const WAIT_TIMEOUT: u64 = 1_000_000_000;
+
+/// A fence inserted into the command stream with `Context::insert_fence`. It becomes signaled
+/// when the GPU has executed all the commands given before it. See glFenceSync.
+pub struct Fence {
+    sync: GLsync,
+    registration: RegistrationHandle
+}
+
+impl Fence {
+    /// Has the GPU passed the fence? Doesn't block.
+    pub fn is_signaled(&self) -> bool {
+        let status = unsafe { gl::ClientWaitSync(self.sync, 0, 0) };
+        check_error!();
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Block until the GPU has passed the fence. See glClientWaitSync.
+    pub fn wait(&self) {
+        loop {
+            let status = unsafe { gl::ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, WAIT_TIMEOUT) };
+            check_error!();
+            match status {
+                gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => return,
+                gl::TIMEOUT_EXPIRED => continue,
+                _ => panic!("Waiting for a fence failed")
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Fence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fence").field("sync", &self.sync).finish()
+    }
+}
+
+impl ContextResource for Fence {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        self.registration.queue_deletion(QueuedDeletion::Sync(self.sync));
+    }
+}
+
+/// Constructor not visible to library users.
+pub fn new_fence(registration: RegistrationHandle) -> Fence {
+    let sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+    check_error!();
+    Fence { sync, registration }
+}
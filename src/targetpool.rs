@@ -0,0 +1,204 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Temporary render targets shared between the passes of a frame, see `TargetPool`.
+
+use super::{Context,RenderTargetHandle,TextureHandle};
+use super::framebuffer::RenderTargetError;
+use super::texture::{TextureFormat,TextureFilter,TextureWrap};
+
+/// Describes the render targets handed out by a `TargetPool`: the size, the formats of the
+/// color textures and the depth texture, the number of samples per pixel and the filtering of
+/// the textures.
+#[derive(Clone,Debug,PartialEq)]
+pub struct TargetDesc {
+    pub width: u32,
+    pub height: u32,
+    pub color_formats: Vec<TextureFormat>,
+    pub depth_format: Option<TextureFormat>,
+    /// Zero for single-sampled textures, otherwise the textures are multisampled, see
+    /// `Context::new_texture_2d_multisample`.
+    pub samples: u32,
+    /// The minification and magnification filter of single-sampled textures.
+    pub filter: TextureFilter
+}
+
+impl TargetDesc {
+    /// A render target of the size with a single color texture of the format and no depth.
+    pub fn color(width: u32, height: u32, format: TextureFormat) -> TargetDesc {
+        TargetDesc { width, height, color_formats: vec![format], depth_format: None, samples: 0, filter: TextureFilter::Linear }
+    }
+
+    /// Add a depth texture of the format.
+    pub fn with_depth(self, format: TextureFormat) -> TargetDesc {
+        TargetDesc { depth_format: Some(format), ..self }
+    }
+
+    /// Make the textures multisampled with the number of samples, or single-sampled with zero.
+    pub fn with_samples(self, samples: u32) -> TargetDesc {
+        TargetDesc { samples, ..self }
+    }
+
+    /// Filter the textures with the filter instead of linear filtering, for example with nearest
+    /// filtering for textures read texel by texel.
+    pub fn with_filter(self, filter: TextureFilter) -> TargetDesc {
+        TargetDesc { filter, ..self }
+    }
+
+    /// The description with the size multiplied by the scale, at least one pixel, for
+    /// rendering at a lower resolution. See `adaptive_resolution::AdaptiveResolution`.
+    pub fn scaled(self, scale: f32) -> TargetDesc {
+        let scale_size = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+        TargetDesc { width: scale_size(self.width), height: scale_size(self.height), ..self }
+    }
+}
+
+/// A render target of a `TargetPool`, with its textures for sampling them in later passes.
+#[derive(Clone,Debug)]
+pub struct PooledTarget {
+    pub render_target: RenderTargetHandle,
+    pub color_textures: Vec<TextureHandle>,
+    pub depth_texture: Option<TextureHandle>
+}
+
+#[derive(Debug)]
+struct PoolEntry {
+    desc: TargetDesc,
+    target: PooledTarget,
+    in_use: bool,
+    last_used_frame: u64
+}
+
+/// Hands out temporary render targets by description, for post-processing chains and such.
+/// A target released by a pass can be handed out again to a later pass of the same frame that
+/// needs one of the same description, so that passes whose targets are not alive at the same
+/// time share the memory. Targets handed out again have their contents invalidated, see
+/// `Context::invalidate_render_target`.
+///
+/// Each frame, `acquire` the targets, `release` them once the passes reading them are done,
+/// and call `end_frame`. Targets unused for more than `max_idle_frames` frames are freed at the
+/// end of a frame, counting from the frame they were last acquired or released in. The
+/// single-sampled textures are created with the filter of the description and clamping to
+/// edges.
+#[derive(Debug)]
+pub struct TargetPool {
+    entries: Vec<PoolEntry>,
+    frame: u64,
+    max_idle_frames: u64
+}
+
+impl TargetPool {
+    /// Create an empty pool, freeing the targets unused for more than two frames.
+    pub fn new() -> TargetPool {
+        TargetPool { entries: Vec::new(), frame: 0, max_idle_frames: 2 }
+    }
+
+    /// Set how many frames a target may stay unused before it is freed.
+    pub fn set_max_idle_frames(&mut self, frames: u64) {
+        self.max_idle_frames = frames;
+    }
+
+    /// Get a free target of the description, or create one. Fails if the render target can't
+    /// be created, see `Context::new_render_target`. Panics if the textures are multisampled and
+    /// the context can't have that many samples, see `Context::new_texture_2d_multisample`.
+    pub fn acquire(&mut self, context: &mut Context, desc: &TargetDesc) -> Result<PooledTarget, RenderTargetError> {
+        let frame = self.frame;
+        if let Some(entry) = self.entries.iter_mut().find(|entry| !entry.in_use && entry.desc == *desc) {
+            entry.in_use = true;
+            entry.last_used_frame = frame;
+            context.invalidate_render_target(&entry.target.render_target);
+            return Ok(entry.target.clone());
+        }
+        let color_textures: Vec<_> = desc.color_formats.iter()
+            .map(|&format| new_pooled_texture(context, format, desc))
+            .collect();
+        let depth_texture = desc.depth_format.map(|format| new_pooled_texture(context, format, desc));
+        let render_target = context.new_render_target(&color_textures[..], depth_texture.as_ref())?;
+        let target = PooledTarget {
+            render_target,
+            color_textures,
+            depth_texture
+        };
+        self.entries.push(PoolEntry {
+            desc: desc.clone(),
+            target: target.clone(),
+            in_use: true,
+            last_used_frame: frame
+        });
+        Ok(target)
+    }
+
+    /// Give the target back to the pool, to be handed out again. Its contents should not be
+    /// needed anymore. Panics if the target is not from this pool.
+    pub fn release(&mut self, target: &PooledTarget) {
+        let frame = self.frame;
+        match self.entries.iter_mut().find(|entry| entry.target.render_target == target.render_target) {
+            Some(entry) => {
+                entry.in_use = false;
+                entry.last_used_frame = frame;
+            },
+            None => panic!("Releasing a render target that is not from the pool")
+        }
+    }
+
+    /// End the frame, freeing the targets that have been unused for too long.
+    pub fn end_frame(&mut self) {
+        let (frame, max_idle_frames) = (self.frame, self.max_idle_frames);
+        self.entries.retain(|entry| entry.in_use || frame - entry.last_used_frame <= max_idle_frames);
+        self.frame += 1;
+    }
+
+    /// How many targets the pool has, in use or not.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Has the pool no targets?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Free the targets that are not in use.
+    pub fn clear(&mut self) {
+        self.entries.retain(|entry| entry.in_use);
+    }
+}
+
+impl Default for TargetPool {
+    fn default() -> TargetPool {
+        TargetPool::new()
+    }
+}
+
+fn new_pooled_texture(context: &mut Context, format: TextureFormat, desc: &TargetDesc) -> TextureHandle {
+    if desc.samples > 0 {
+        context.new_texture_2d_multisample(format, desc.width, desc.height, desc.samples)
+    }
+    else {
+        new_target_texture(context, format, desc.width, desc.height, desc.filter)
+    }
+}
+
+/// Create a texture of the format and size for rendering into, with the filter and clamping to
+/// edges.
+pub fn new_target_texture(context: &mut Context, format: TextureFormat, width: u32, height: u32, filter: TextureFilter) -> TextureHandle {
+    let texture = context.new_texture_2d();
+    {
+        let mut editor = context.edit_texture(&texture);
+        editor.allocate(0, format, width, height);
+        editor.set_filters(filter, filter);
+        editor.set_wrap(TextureWrap::ClampToEdge, TextureWrap::ClampToEdge);
+    }
+    texture
+}
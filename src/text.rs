@@ -0,0 +1,246 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Drawing text with glyphs rasterized by `fontdue`. Enabled with the `text` feature. See
+//! `TextRenderer`.
+
+use std::collections::HashMap;
+
+use fontdue::{Font,FontSettings};
+
+use super::{Context,BufferHandle,VertexArrayHandle,ProgramHandle};
+use super::atlas::{TextureAtlas,AtlasRegion};
+use super::image::{ImageFormat,PixelFormat,PixelType,RawImage};
+use super::options::{RenderOption,BlendFactor};
+use super::program::{SimpleUniformTypeFloat,SimpleUniformTypeI32};
+use super::renderer::PrimitiveMode;
+use super::shader::ShaderType;
+use super::shaderlib::SHADER_HEADER;
+use super::texture::{TextureFormat,TextureFilter};
+use super::vertexarray::VertexAttributeType;
+
+static VERTEX_SHADER_SOURCE: &str = "
+uniform vec2 screen_size;
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 tex_coord;
+out vec2 v_tex_coord;
+void main() {
+    gl_Position = vec4(position / screen_size * 2.0 - 1.0, 0.0, 1.0);
+    v_tex_coord = tex_coord;
+}
+";
+
+static FRAGMENT_SHADER_SOURCE: &str = "
+uniform sampler2D glyphs;
+uniform vec4 color;
+in vec2 v_tex_coord;
+out vec4 frag_color;
+void main() {
+    frag_color = vec4(color.rgb, color.a * texture(glyphs, v_tex_coord).r);
+}
+";
+
+/// Floats per vertex: position and texture coordinates.
+const VERTEX_COMPONENTS: usize = 4;
+
+/// A glyph rasterized into the atlas. Glyphs without any pixels, like space, have no region.
+#[derive(Clone,Copy,Debug)]
+struct Glyph {
+    region: Option<AtlasRegion>,
+    xmin: f32,
+    ymin: f32,
+    advance: f32
+}
+
+/// Draws text of a single font and size. The glyphs are rasterized when first needed and cached
+/// in a `TextureAtlas`, and the text queued with `queue` is drawn in one draw call with `draw`.
+///
+/// Positions are given in pixels, with the origin at the lower left corner of the screen, as is
+/// usual in OpenGL. The text is drawn with alpha blending, and the blending options are restored
+/// to their previous values afterwards if those are known to the context.
+/// Depth testing is not touched, so it should be disabled when drawing text over a scene.
+#[derive(Debug)]
+pub struct TextRenderer {
+    font: Font,
+    size: f32,
+    line_height: f32,
+    atlas: TextureAtlas,
+    glyphs: HashMap<char, Glyph>,
+    program: ProgramHandle,
+    screen_size_location: i32,
+    color_location: i32,
+    vertex_buffer: BufferHandle,
+    vertex_array: VertexArrayHandle,
+    vertices: Vec<f32>,
+    vertex_capacity: usize
+}
+
+impl TextRenderer {
+    /// Create a text renderer for the font, given as the contents of a TrueType or OpenType
+    /// file. The size is the font size in pixels, and the atlas size is the width and height of
+    /// the glyph cache texture. Fails with the error message of `fontdue` if the font can't be
+    /// parsed.
+    pub fn new(context: &mut Context, font_data: &[u8], size: f32, atlas_size: u32) -> Result<TextRenderer, &'static str> {
+        let font = Font::from_bytes(font_data, FontSettings::default())?;
+        let line_height = match font.horizontal_line_metrics(size) {
+            Some(metrics) => metrics.new_line_size,
+            None => size
+        };
+
+        let atlas = TextureAtlas::new(context, atlas_size, atlas_size, TextureFormat::R8, 1);
+        context.edit_texture(atlas.texture()).set_filters(TextureFilter::Linear, TextureFilter::Linear);
+
+        let vertex_source = format!("{}{}", SHADER_HEADER, VERTEX_SHADER_SOURCE);
+        let fragment_source = format!("{}{}", SHADER_HEADER, FRAGMENT_SHADER_SOURCE);
+        let shaders = [
+            context.new_shader(ShaderType::VertexShader, &vertex_source),
+            context.new_shader(ShaderType::FragmentShader, &fragment_source)
+        ];
+        let program = context.new_program(&shaders);
+        let (screen_size_location, color_location) = {
+            let editor = context.edit_program(&program);
+            let glyphs_location = editor.program_info().get_uniform_location("glyphs");
+            editor.uniform_i32(glyphs_location, 1, SimpleUniformTypeI32::Uniform1i, &[0]);
+            let info = editor.program_info();
+            (info.get_uniform_location("screen_size"), info.get_uniform_location("color"))
+        };
+
+        let vertex_buffer = context.new_buffer();
+        let attributes = [
+            (2, VertexAttributeType::Float, false),
+            (2, VertexAttributeType::Float, false)
+        ];
+        let vertex_array = context.new_vertex_array_simple(&attributes, vertex_buffer.clone(), None);
+
+        Ok(TextRenderer {
+            font,
+            size,
+            line_height,
+            atlas,
+            glyphs: HashMap::new(),
+            program,
+            screen_size_location,
+            color_location,
+            vertex_buffer,
+            vertex_array,
+            vertices: Vec::new(),
+            vertex_capacity: 0
+        })
+    }
+
+    /// The distance between the baselines of consecutive lines, in pixels.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Queue text to be drawn with the next `draw`, starting from the baseline position `(x, y)`.
+    /// Newlines start a new line below the previous one. Returns the position following the last
+    /// character, for continuing the text.
+    ///
+    /// Glyphs that don't fit in the atlas anymore are left out.
+    pub fn queue(&mut self, context: &mut Context, x: f32, y: f32, text: &str) -> (f32, f32) {
+        let mut pen_x = x;
+        let mut pen_y = y;
+        for character in text.chars() {
+            if character == '\n' {
+                pen_x = x;
+                pen_y -= self.line_height;
+                continue;
+            }
+            let glyph = match self.glyph(context, character) {
+                Some(glyph) => glyph,
+                None => continue
+            };
+            if let Some(region) = glyph.region {
+                let left = (pen_x + glyph.xmin).round();
+                let bottom = (pen_y + glyph.ymin).round();
+                let right = left + region.width as f32;
+                let top = bottom + region.height as f32;
+                // The bitmap rows are stored top first, so the texture coordinates are flipped
+                let [u0, v0, u1, v1] = region.uv_rect;
+                self.vertices.extend_from_slice(&[
+                    left, bottom, u0, v1,
+                    right, bottom, u1, v1,
+                    right, top, u1, v0,
+                    left, bottom, u0, v1,
+                    right, top, u1, v0,
+                    left, top, u0, v0
+                ]);
+            }
+            pen_x += glyph.advance;
+        }
+        (pen_x, pen_y)
+    }
+
+    /// Draw the queued text with the given color, and clear the queue. The screen size is the
+    /// size of the surface or viewport in pixels.
+    pub fn draw(&mut self, context: &mut Context, screen_size: (u32, u32), color: [f32; 4]) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        // The buffer is only reallocated when the text doesn't fit, otherwise it's overwritten
+        if self.vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = self.vertices.len().next_power_of_two();
+            context.edit_vertex_buffer(&self.vertex_buffer).data(&vec![0f32; self.vertex_capacity]);
+        }
+        context.edit_vertex_buffer(&self.vertex_buffer).sub_data(&self.vertices[..], 0);
+        let vertex_count = (self.vertices.len() / VERTEX_COMPONENTS) as u32;
+        self.vertices.clear();
+
+        let mut renderer = context.renderer();
+        renderer.use_program(&self.program);
+        renderer.use_vertex_array(&self.vertex_array);
+        renderer.use_texture(0, self.atlas.texture());
+        let (width, height) = screen_size;
+        renderer.uniform_f32(self.screen_size_location, 1, SimpleUniformTypeFloat::Uniform2f, &[width as f32, height as f32]);
+        renderer.uniform_f32(self.color_location, 1, SimpleUniformTypeFloat::Uniform4f, &color);
+        let blend_function = RenderOption::BlendFunction(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+        let previous_blending = renderer.render_option(RenderOption::Blending(true));
+        let previous_function = renderer.render_option(blend_function);
+        renderer.set_option(RenderOption::Blending(true));
+        renderer.set_option(blend_function);
+        renderer.draw_arrays(PrimitiveMode::Triangles, 0, vertex_count);
+        if let Some(option) = previous_function {
+            renderer.set_option(option);
+        }
+        if let Some(option) = previous_blending {
+            renderer.set_option(option);
+        }
+    }
+
+    /// Get the cached glyph, or rasterize it into the atlas. None if the atlas is full.
+    fn glyph(&mut self, context: &mut Context, character: char) -> Option<Glyph> {
+        if !self.glyphs.contains_key(&character) {
+            let (metrics, bitmap) = self.font.rasterize(character, self.size);
+            let region = if metrics.width > 0 && metrics.height > 0 {
+                let width = metrics.width as u32;
+                let height = metrics.height as u32;
+                let region = self.atlas.allocate(width, height)?;
+                let format = ImageFormat::Uncompressed(PixelFormat::Red, PixelType::UnsignedByte);
+                self.atlas.upload(context, &region, &RawImage::new(width, height, format, bitmap));
+                Some(region)
+            }
+            else {
+                None
+            };
+            self.glyphs.insert(character, Glyph {
+                region,
+                xmin: metrics.xmin as f32,
+                ymin: metrics.ymin as f32,
+                advance: metrics.advance_width
+            });
+        }
+        self.glyphs.get(&character).cloned()
+    }
+}
@@ -0,0 +1,376 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loaders for the KTX2 and DDS texture container formats. Enabled with the `loaders` feature.
+//!
+//! Parsing a container (`parse_ktx2`, `parse_dds`) gives a `ContainerImage` that has all the mip
+//! levels of the texture, which can then be uploaded with `upload`. Only two-dimensional textures
+//! with a single layer are supported, as that's what the texture module supports. Nothing is
+//! transcoded: supercompressed KTX2 files are rejected when parsing, and images in formats the
+//! context can't store are not uploaded, which `upload` reports with `UploadOutcome`.
+
+use super::{TextureEditor,TextureFormat,max_levels_for};
+use super::super::image::{ImageData,ImageFormat,PixelFormat,PixelType,CompressedFormat,RawImage};
+
+/// Errors that can happen when parsing or uploading a texture container.
+#[derive(Debug)]
+pub enum LoaderError {
+    /// The data is not a valid container of the expected kind.
+    InvalidHeader(&'static str),
+    /// The data ended before all the image data was read.
+    Truncated,
+    /// The container stores the image in a format this library doesn't recognize. The value is
+    /// the format identifier of the container (VkFormat for KTX2, DXGI format or FourCC for DDS).
+    UnsupportedFormat(u32),
+    /// The container stores something else than a single two-dimensional image (with mipmaps),
+    /// for example a cube map or an array texture.
+    UnsupportedLayout(&'static str),
+    /// The KTX2 data is supercompressed, which would require transcoding.
+    Supercompressed(u32)
+}
+
+/// What `upload` did with a container image.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum UploadOutcome {
+    /// All the levels were uploaded in the format of the container.
+    Uploaded(ImageFormat),
+    /// The context can't store textures in the compressed format, so nothing was uploaded. The
+    /// image would have to be transcoded to another format, which this library doesn't do.
+    Rejected(CompressedFormat)
+}
+
+/// An image read from a container, with all its mip levels. The levels are in order, the first
+/// one being the base level.
+pub struct ContainerImage {
+    /// The mip levels.
+    pub levels: Vec<RawImage>,
+    /// Is the image data in the sRGB color space? Only meaningful for uncompressed data, the
+    /// compressed formats carry this information themselves.
+    pub srgb: bool
+}
+
+/// Upload all the levels of the container image to the texture being edited, and set the maximum
+/// mip level accordingly. Nothing is uploaded if the context doesn't support the compressed
+/// format of the image, which is reported in the outcome, so that the caller can fall back to
+/// another image.
+pub fn upload(editor: &mut TextureEditor, container: &ContainerImage) -> UploadOutcome {
+    let image_format = match container.levels.first() {
+        Some(level) => level.format(),
+        None => return UploadOutcome::Uploaded(ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte))
+    };
+    if let ImageFormat::Compressed(format) = image_format {
+        if !editor.context_info().supports_compressed_format(format) {
+            return UploadOutcome::Rejected(format);
+        }
+    }
+    for (index, level) in container.levels.iter().enumerate() {
+        match (level.format(), container.srgb) {
+            (ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte), true) |
+            (ImageFormat::Uncompressed(PixelFormat::Bgra, PixelType::UnsignedByte), true) => {
+                editor.upload_as(index as u32, TextureFormat::Srgb8Alpha8, level);
+            },
+            _ => editor.upload(index as u32, level)
+        }
+    }
+    editor.set_max_level(container.levels.len().saturating_sub(1) as u32);
+    UploadOutcome::Uploaded(image_format)
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Parse a KTX2 file.
+pub fn parse_ktx2(bytes: &[u8]) -> Result<ContainerImage, LoaderError> {
+    if bytes.len() < 80 || bytes[0..12] != KTX2_IDENTIFIER {
+        return Err(LoaderError::InvalidHeader("Missing KTX2 identifier"));
+    }
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layer_count = read_u32(bytes, 32)?;
+    let face_count = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?;
+    let supercompression = read_u32(bytes, 44)?;
+    if supercompression != 0 {
+        return Err(LoaderError::Supercompressed(supercompression));
+    }
+    if depth > 1 || height == 0 {
+        return Err(LoaderError::UnsupportedLayout("Only two-dimensional textures are supported"));
+    }
+    if layer_count > 1 {
+        return Err(LoaderError::UnsupportedLayout("Array textures are not supported"));
+    }
+    if face_count != 1 {
+        return Err(LoaderError::UnsupportedLayout("Cube maps are not supported"));
+    }
+    let (format, srgb) = match vk_format_to_image_format(vk_format) {
+        Some(format) => format,
+        None => return Err(LoaderError::UnsupportedFormat(vk_format))
+    };
+    let level_count = if level_count == 0 { 1 } else { level_count };
+    if level_count > max_levels_for(width, height) {
+        return Err(LoaderError::InvalidHeader("More mip levels than the dimensions allow"));
+    }
+    let mut levels = Vec::with_capacity(level_count as usize);
+    for level in 0..level_count {
+        let index_offset = 80 + level as usize * 24;
+        let offset = read_usize(bytes, index_offset)?;
+        let length = read_usize(bytes, index_offset + 8)?;
+        let (level_width, level_height) = level_dimensions(width, height, level);
+        levels.push(read_level(bytes, offset, length, level_width, level_height, format)?);
+    }
+    Ok(ContainerImage { levels, srgb })
+}
+
+const DDS_MAGIC: [u8; 4] = [0x44, 0x44, 0x53, 0x20];
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Parse a DDS file, including the files with the DX10 header extension.
+pub fn parse_dds(bytes: &[u8]) -> Result<ContainerImage, LoaderError> {
+    if bytes.len() < 128 || bytes[0..4] != DDS_MAGIC {
+        return Err(LoaderError::InvalidHeader("Missing DDS magic number"));
+    }
+    if read_u32(bytes, 4)? != 124 {
+        return Err(LoaderError::InvalidHeader("Invalid DDS header size"));
+    }
+    let flags = read_u32(bytes, 8)?;
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let mip_count = if flags & DDSD_MIPMAPCOUNT != 0 { read_u32(bytes, 28)? } else { 1 };
+    let pixel_flags = read_u32(bytes, 80)?;
+    let four_cc = read_u32(bytes, 84)?;
+    let caps2 = read_u32(bytes, 112)?;
+    if caps2 & DDSCAPS2_CUBEMAP != 0 {
+        return Err(LoaderError::UnsupportedLayout("Cube maps are not supported"));
+    }
+    if caps2 & DDSCAPS2_VOLUME != 0 {
+        return Err(LoaderError::UnsupportedLayout("Only two-dimensional textures are supported"));
+    }
+    let (format, srgb, data_offset) = if pixel_flags & DDPF_FOURCC != 0 && four_cc == four_cc_code(b"DX10") {
+        let dxgi_format = read_u32(bytes, 128)?;
+        let misc_flag = read_u32(bytes, 136)?;
+        let array_size = read_u32(bytes, 140)?;
+        if misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0 {
+            return Err(LoaderError::UnsupportedLayout("Cube maps are not supported"));
+        }
+        if array_size > 1 {
+            return Err(LoaderError::UnsupportedLayout("Array textures are not supported"));
+        }
+        match dxgi_format_to_image_format(dxgi_format) {
+            Some((format, srgb)) => (format, srgb, 148),
+            None => return Err(LoaderError::UnsupportedFormat(dxgi_format))
+        }
+    }
+    else if pixel_flags & DDPF_FOURCC != 0 {
+        match four_cc_to_image_format(four_cc) {
+            Some(format) => (format, false, 128),
+            None => return Err(LoaderError::UnsupportedFormat(four_cc))
+        }
+    }
+    else if pixel_flags & DDPF_RGB != 0 {
+        let bit_count = read_u32(bytes, 88)?;
+        let red_mask = read_u32(bytes, 92)?;
+        let pixel_format = match (bit_count, red_mask) {
+            (32, 0x000000ff) => PixelFormat::Rgba,
+            (32, 0x00ff0000) => PixelFormat::Bgra,
+            _ => return Err(LoaderError::UnsupportedFormat(bit_count))
+        };
+        (ImageFormat::Uncompressed(pixel_format, PixelType::UnsignedByte), false, 128)
+    }
+    else {
+        return Err(LoaderError::InvalidHeader("Unrecognized DDS pixel format"));
+    };
+    let mip_count = if mip_count == 0 { 1 } else { mip_count };
+    if mip_count > max_levels_for(width, height) {
+        return Err(LoaderError::InvalidHeader("More mip levels than the dimensions allow"));
+    }
+    let mut levels = Vec::with_capacity(mip_count as usize);
+    let mut offset = data_offset;
+    for level in 0..mip_count {
+        let (level_width, level_height) = level_dimensions(width, height, level);
+        let length = format.data_size(level_width, level_height);
+        levels.push(read_level(bytes, offset, length, level_width, level_height, format)?);
+        // The level was read, so it ends within the data
+        offset += length;
+    }
+    Ok(ContainerImage { levels, srgb })
+}
+
+fn vk_format_to_image_format(vk_format: u32) -> Option<(ImageFormat, bool)> {
+    let uncompressed = |format, pixel_type| Some((ImageFormat::Uncompressed(format, pixel_type), false));
+    let compressed = |format| Some((ImageFormat::Compressed(format), false));
+    match vk_format {
+        9 => uncompressed(PixelFormat::Red, PixelType::UnsignedByte),
+        16 => uncompressed(PixelFormat::Rg, PixelType::UnsignedByte),
+        37 => uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte),
+        43 => Some((ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte), true)),
+        44 => uncompressed(PixelFormat::Bgra, PixelType::UnsignedByte),
+        50 => Some((ImageFormat::Uncompressed(PixelFormat::Bgra, PixelType::UnsignedByte), true)),
+        97 => uncompressed(PixelFormat::Rgba, PixelType::HalfFloat),
+        109 => uncompressed(PixelFormat::Rgba, PixelType::Float),
+        131 => compressed(CompressedFormat::RgbS3tcDxt1),
+        133 => compressed(CompressedFormat::RgbaS3tcDxt1),
+        135 => compressed(CompressedFormat::RgbaS3tcDxt3),
+        137 => compressed(CompressedFormat::RgbaS3tcDxt5),
+        139 => compressed(CompressedFormat::RedRgtc1),
+        140 => compressed(CompressedFormat::SignedRedRgtc1),
+        141 => compressed(CompressedFormat::RgRgtc2),
+        142 => compressed(CompressedFormat::SignedRgRgtc2),
+        143 => compressed(CompressedFormat::RgbBptcUnsignedFloat),
+        144 => compressed(CompressedFormat::RgbBptcSignedFloat),
+        145 => compressed(CompressedFormat::RgbaBptcUnorm),
+        146 => compressed(CompressedFormat::SrgbAlphaBptcUnorm),
+        _ => None
+    }
+}
+
+fn dxgi_format_to_image_format(dxgi_format: u32) -> Option<(ImageFormat, bool)> {
+    let uncompressed = |format, pixel_type| Some((ImageFormat::Uncompressed(format, pixel_type), false));
+    let compressed = |format| Some((ImageFormat::Compressed(format), false));
+    match dxgi_format {
+        2 => uncompressed(PixelFormat::Rgba, PixelType::Float),
+        10 => uncompressed(PixelFormat::Rgba, PixelType::HalfFloat),
+        28 => uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte),
+        29 => Some((ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte), true)),
+        49 => uncompressed(PixelFormat::Rg, PixelType::UnsignedByte),
+        61 => uncompressed(PixelFormat::Red, PixelType::UnsignedByte),
+        71 => compressed(CompressedFormat::RgbaS3tcDxt1),
+        74 => compressed(CompressedFormat::RgbaS3tcDxt3),
+        77 => compressed(CompressedFormat::RgbaS3tcDxt5),
+        80 => compressed(CompressedFormat::RedRgtc1),
+        81 => compressed(CompressedFormat::SignedRedRgtc1),
+        83 => compressed(CompressedFormat::RgRgtc2),
+        84 => compressed(CompressedFormat::SignedRgRgtc2),
+        87 => uncompressed(PixelFormat::Bgra, PixelType::UnsignedByte),
+        91 => Some((ImageFormat::Uncompressed(PixelFormat::Bgra, PixelType::UnsignedByte), true)),
+        95 => compressed(CompressedFormat::RgbBptcUnsignedFloat),
+        96 => compressed(CompressedFormat::RgbBptcSignedFloat),
+        98 => compressed(CompressedFormat::RgbaBptcUnorm),
+        99 => compressed(CompressedFormat::SrgbAlphaBptcUnorm),
+        _ => None
+    }
+}
+
+fn four_cc_to_image_format(four_cc: u32) -> Option<ImageFormat> {
+    let format = if four_cc == four_cc_code(b"DXT1") {
+        CompressedFormat::RgbaS3tcDxt1
+    }
+    else if four_cc == four_cc_code(b"DXT3") {
+        CompressedFormat::RgbaS3tcDxt3
+    }
+    else if four_cc == four_cc_code(b"DXT5") {
+        CompressedFormat::RgbaS3tcDxt5
+    }
+    else if four_cc == four_cc_code(b"ATI1") || four_cc == four_cc_code(b"BC4U") {
+        CompressedFormat::RedRgtc1
+    }
+    else if four_cc == four_cc_code(b"BC4S") {
+        CompressedFormat::SignedRedRgtc1
+    }
+    else if four_cc == four_cc_code(b"ATI2") || four_cc == four_cc_code(b"BC5U") {
+        CompressedFormat::RgRgtc2
+    }
+    else if four_cc == four_cc_code(b"BC5S") {
+        CompressedFormat::SignedRgRgtc2
+    }
+    else {
+        return None;
+    };
+    Some(ImageFormat::Compressed(format))
+}
+
+fn four_cc_code(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+fn level_dimensions(width: u32, height: u32, level: u32) -> (u32, u32) {
+    let shrink = |size: u32| if level >= 32 { 1 } else { ::std::cmp::max(1, size >> level) };
+    (shrink(width), shrink(height))
+}
+
+fn read_level(bytes: &[u8], offset: usize, length: usize, width: u32, height: u32, format: ImageFormat) -> Result<RawImage, LoaderError> {
+    if length < format.data_size(width, height) {
+        return Err(LoaderError::Truncated);
+    }
+    let end = offset.checked_add(length).ok_or(LoaderError::Truncated)?;
+    match bytes.get(offset..end) {
+        Some(data) => Ok(RawImage::new(width, height, format, data.to_vec())),
+        None => Err(LoaderError::Truncated)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, LoaderError> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) => Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24),
+        None => Err(LoaderError::Truncated)
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, LoaderError> {
+    let low = read_u32(bytes, offset)? as u64;
+    let high = read_u32(bytes, offset + 4)? as u64;
+    Ok(low | high << 32)
+}
+
+/// Read an offset or a length. Values that don't fit in memory are past the end of the data.
+fn read_usize(bytes: &[u8], offset: usize) -> Result<usize, LoaderError> {
+    let value = read_u64(bytes, offset)?;
+    if value > usize::MAX as u64 {
+        return Err(LoaderError::Truncated);
+    }
+    Ok(value as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoaderError,KTX2_IDENTIFIER,parse_ktx2};
+
+    /// A KTX2 header of a 4x4 RGBA8 image, with one level index entry.
+    fn ktx2_header(level_count: u32, offset: u64, length: u64) -> Vec<u8> {
+        let mut bytes = KTX2_IDENTIFIER.to_vec();
+        for &value in [37, 1, 4, 4, 0, 0, 1, level_count, 0].iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn level_is_read() {
+        let mut bytes = ktx2_header(1, 104, 64);
+        bytes.resize(104 + 64, 7);
+        let image = parse_ktx2(&bytes[..]).unwrap();
+        assert_eq!(image.levels.len(), 1);
+    }
+
+    #[test]
+    fn overflowing_level_range_is_truncated() {
+        let bytes = ktx2_header(1, u64::MAX - 8, 64);
+        assert!(matches!(parse_ktx2(&bytes[..]), Err(LoaderError::Truncated)));
+    }
+
+    #[test]
+    fn level_count_is_limited_by_dimensions() {
+        let bytes = ktx2_header(u32::MAX, 104, 64);
+        assert!(matches!(parse_ktx2(&bytes[..]), Err(LoaderError::InvalidHeader(_))));
+    }
+}
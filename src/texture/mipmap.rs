@@ -0,0 +1,127 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generating mipmap levels on the CPU, see `MipmapPolicy` and
+//! `TextureEditor::upload_with_mipmaps`.
+
+use super::super::image::{ImageData,ImageFormat,PixelFormat,PixelType,RawImage};
+use super::super::color::{linear_to_srgb,srgb_to_linear};
+
+/// How `TextureEditor::upload_with_mipmaps` generates the mipmap levels.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum MipmapPolicy {
+    /// Upload the base level and let OpenGL generate the rest, see glGenerateMipmap. Fast, but
+    /// the filtering is up to the driver, and usually not gamma correct.
+    Gpu,
+    /// Average each 2x2 block of texels on the CPU, as the values are.
+    Cpu,
+    /// Like `Cpu`, but the color channels are treated as sRGB encoded: they are averaged in
+    /// linear space and encoded back, so that the smaller levels don't get darker. Alpha is
+    /// averaged as it is.
+    CpuGammaCorrect
+}
+
+/// Generate the mipmap levels below the image, down to 1x1, by averaging each 2x2 block of
+/// texels of the level above. Only images of unsigned bytes of the color formats are
+/// supported, the function panics for others.
+pub fn generate_levels(image: &dyn ImageData, gamma_correct: bool) -> Vec<RawImage> {
+    let format = image.format();
+    let channels = match format {
+        ImageFormat::Uncompressed(pixel_format, PixelType::UnsignedByte) => match pixel_format {
+            PixelFormat::Red => 1,
+            PixelFormat::Rg => 2,
+            PixelFormat::Rgb | PixelFormat::Bgr => 3,
+            PixelFormat::Rgba | PixelFormat::Bgra => 4,
+            _ => panic!("Mipmaps can't be generated on the CPU for images of the format {:?}", format)
+        },
+        _ => panic!("Mipmaps can't be generated on the CPU for images of the format {:?}", format)
+    };
+    // The fourth channel is alpha, which is linear anyway
+    let color_channels = if channels == 4 { 3 } else { channels };
+    let decode: Vec<f32> = (0..256).map(|value| {
+        let value = value as f32 / 255.0;
+        if gamma_correct { srgb_to_linear(value) } else { value }
+    }).collect();
+
+    let mut levels: Vec<RawImage> = Vec::new();
+    let (mut width, mut height) = image.dimensions();
+    while width > 1 || height > 1 {
+        let source = match levels.last() {
+            Some(level) => level.bytes(),
+            None => image.bytes()
+        };
+        let (source_width, source_height) = (width as usize, height as usize);
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+        let mut data = Vec::with_capacity(width as usize * height as usize * channels);
+        for y in 0..height as usize {
+            let rows = [(2 * y).min(source_height - 1), (2 * y + 1).min(source_height - 1)];
+            for x in 0..width as usize {
+                let columns = [(2 * x).min(source_width - 1), (2 * x + 1).min(source_width - 1)];
+                for channel in 0..channels {
+                    let mut sum = 0.0;
+                    for &row in rows.iter() {
+                        for &column in columns.iter() {
+                            let value = source[(row * source_width + column) * channels + channel];
+                            sum += if channel < color_channels { decode[value as usize] } else { value as f32 / 255.0 };
+                        }
+                    }
+                    let average = sum / 4.0;
+                    let encoded = if gamma_correct && channel < color_channels { linear_to_srgb(average) } else { average };
+                    data.push((encoded * 255.0 + 0.5).min(255.0) as u8);
+                }
+            }
+        }
+        levels.push(RawImage::new(width, height, format, data));
+    }
+    levels
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::generate_levels;
+    use super::super::super::image::{ImageData,ImageFormat,PixelFormat,PixelType,RawImage};
+
+    fn rgba(width: u32, height: u32, data: Vec<u8>) -> RawImage {
+        RawImage::new(width, height, ImageFormat::Uncompressed(PixelFormat::Rgba, PixelType::UnsignedByte), data)
+    }
+
+    #[test]
+    fn levels_halve_down_to_one_texel() {
+        let image = rgba(4, 2, vec![255; 4 * 2 * 4]);
+        let levels = generate_levels(&image, false);
+        let sizes: Vec<(u32, u32)> = levels.iter().map(|level| level.dimensions()).collect();
+        assert_eq!(sizes, vec![(2, 1), (1, 1)]);
+        assert_eq!(levels[1].bytes(), &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn gamma_correct_averaging_leaves_alpha_linear() {
+        let image = rgba(2, 1, vec![0, 0, 0, 0, 255, 255, 255, 255]);
+        assert_eq!(generate_levels(&image, false)[0].bytes(), &[128, 128, 128, 128]);
+        assert_eq!(generate_levels(&image, true)[0].bytes(), &[188, 188, 188, 128]);
+    }
+
+    #[test]
+    fn odd_sizes_drop_the_last_row_and_column() {
+        let data = vec![
+            0, 40, 255,
+            80, 120, 255,
+            255, 255, 255
+        ];
+        let image = RawImage::new(3, 3, ImageFormat::Uncompressed(PixelFormat::Red, PixelType::UnsignedByte), data);
+        assert_eq!(generate_levels(&image, false)[0].bytes(), &[60]);
+    }
+}
@@ -0,0 +1,1221 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Textures are the other bulk source of data besides buffers. Currently two-dimensional
+//! textures and buffer textures are supported.
+//!
+//! Texture contents are given as `ImageData`, see the `image` module. As with other resources,
+//! the textures are edited with an editor object, see `Context::edit_texture`. For rendering,
+//! textures are bound to texture units with `Renderer::use_texture`.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::ptr;
+
+use gl::types::{GLenum,GLint,GLsizei,GLvoid};
+
+use super::tracker::{Bind,TrackerId};
+use super::context::{Context,ContextEditingSupport,ContextRenderingSupport,RegistrationHandle,QueuedDeletion,ContextResource};
+use super::options::{self,DepthFunction,PixelStoreParam,RenderOption};
+use super::info::{self,ContextInfo,ExtensionFeature};
+use super::raw::Ownership;
+use super::handle::HandleAccess;
+use super::BufferHandle;
+use super::image::{self,ImageData,ImageFormat,PixelFormat,PixelType};
+
+pub use self::mipmap::MipmapPolicy;
+
+#[cfg(feature = "loaders")]
+pub mod loaders;
+mod mipmap;
+
+/// Texture targets, that is, the kinds of textures.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TextureTarget {
+    /// GL_TEXTURE_2D
+    Texture2d,
+    /// GL_TEXTURE_BUFFER, a texture whose texels are the contents of a buffer object, read in
+    /// shaders with `texelFetch` from a `samplerBuffer`. See `Context::new_buffer_texture`.
+    Buffer,
+    /// GL_TEXTURE_2D_MULTISAMPLE, a texture of several samples per texel, read in shaders with
+    /// `texelFetch` from a `sampler2DMS`. It has a single level and no sampling parameters. See
+    /// `Context::new_texture_2d_multisample`.
+    Texture2dMultisample
+}
+
+/// Internal formats of textures, meaning the format the texture is stored in by OpenGL. See the
+/// internalformat parameter of glTexImage2D.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    Srgb8,
+    Srgb8Alpha8,
+    R16f,
+    Rg16f,
+    Rgb16f,
+    Rgba16f,
+    R32f,
+    Rg32f,
+    Rgb32f,
+    Rgba32f,
+    R8ui,
+    R8i,
+    R16ui,
+    R16i,
+    R32ui,
+    R32i,
+    Rg8ui,
+    Rg8i,
+    Rg16ui,
+    Rg16i,
+    Rg32ui,
+    Rg32i,
+    Rgb8ui,
+    Rgb8i,
+    Rgb16ui,
+    Rgb16i,
+    Rgb32ui,
+    Rgb32i,
+    Rgba8ui,
+    Rgba8i,
+    Rgba16ui,
+    Rgba16i,
+    Rgba32ui,
+    Rgba32i,
+    DepthComponent16,
+    DepthComponent24,
+    DepthComponent32f,
+    Depth24Stencil8
+}
+
+/// Texture filtering modes. The mipmap variants are only valid as the minification filter.
+/// See GL_TEXTURE_MIN_FILTER and GL_TEXTURE_MAG_FILTER.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear
+}
+
+/// Texture coordinate wrapping modes. See GL_TEXTURE_WRAP_S and GL_TEXTURE_WRAP_T.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder
+}
+
+/// Whether 8-bit color images are stored as sRGB or linear when the internal format is derived
+/// from the image, as by `TextureEditor::upload`. The default of the context is set with
+/// `Context::set_srgb_policy`, and can be changed per editor and per upload.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum SrgbPolicy {
+    /// Store RGB and RGBA data as RGB8 and RGBA8. The default.
+    Linear,
+    /// Store RGB and RGBA data as SRGB8 and SRGB8_ALPHA8, so that sampling converts the colors
+    /// to linear. For color textures in gamma-correct pipelines.
+    Srgb
+}
+
+/// A value to fill a texture with, see `TextureEditor::clear`. The variant must match the kind of
+/// the internal format of the texture: `Float` for normalized and floating point color formats,
+/// `Int` and `UnsignedInt` for signed and unsigned integer formats, and `Depth` or
+/// `DepthStencil` for depth formats. The components the format doesn't have are ignored.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TextureClearValue {
+    Float([f32; 4]),
+    Int([i32; 4]),
+    UnsignedInt([u32; 4]),
+    Depth(f32),
+    /// Only for `Depth24Stencil8`.
+    DepthStencil(f32, u8)
+}
+
+/// The ways giving a buffer to a buffer texture can fail, see `TextureEditor::set_buffer`.
+#[derive(Clone,Debug,PartialEq)]
+pub enum TextureBufferError {
+    /// glTexBufferRange is not available, see `ExtensionFeature::TextureBufferRange`.
+    RangeUnsupported,
+    /// The offset is not a multiple of GL_TEXTURE_BUFFER_OFFSET_ALIGNMENT.
+    MisalignedOffset { offset: usize, alignment: usize },
+    /// The range is empty or extends past the end of the buffer.
+    OutOfBounds { offset: usize, size: usize, buffer_size: usize },
+    /// The range has more texels of the format than GL_MAX_TEXTURE_BUFFER_SIZE.
+    TooLarge { texels: usize, max: usize }
+}
+
+impl fmt::Display for TextureBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TextureBufferError::RangeUnsupported =>
+                write!(f, "The context doesn't support buffer texture ranges"),
+            TextureBufferError::MisalignedOffset { offset, alignment } =>
+                write!(f, "The offset {} is not a multiple of the buffer texture offset alignment {}", offset, alignment),
+            TextureBufferError::OutOfBounds { offset, size, buffer_size } =>
+                write!(f, "The range of {} bytes at {} doesn't fit in the buffer of {} bytes", size, offset, buffer_size),
+            TextureBufferError::TooLarge { texels, max } =>
+                write!(f, "{} texels given, but buffer textures can have only {}", texels, max)
+        }
+    }
+}
+
+impl Error for TextureBufferError {}
+
+/// Texture object structure.
+pub struct Texture {
+    pub id: u32,
+    target: TextureTarget,
+    tracker_id: TrackerId,
+    registration: RegistrationHandle,
+    ownership: Ownership,
+    /// The mipmap levels specified so far, for the resource report of the context and for
+    /// checking framebuffer attachments.
+    levels: RefCell<Vec<TextureLevel>>
+}
+
+/// What is known of a specified mipmap level. Levels not specified through the library have
+/// zero size.
+#[derive(Clone,Copy,Debug,Default)]
+struct TextureLevel {
+    width: u32,
+    height: u32,
+    /// None for compressed levels.
+    format: Option<TextureFormat>,
+    /// Zero unless the texture is multisampled.
+    samples: u32,
+    bytes: usize
+}
+
+/// Create a new texture object.
+pub fn new_texture(target: TextureTarget, tracker_id: TrackerId, registration: RegistrationHandle) -> Texture {
+    let mut id: u32 = 0;
+    unsafe {
+        gl::GenTextures(1, &mut id);
+        check_error!();
+    }
+    Texture {
+        id,
+        target,
+        tracker_id,
+        registration,
+        ownership: Ownership::Owned,
+        levels: RefCell::new(Vec::new())
+    }
+}
+
+/// Wrap an existing texture object of the given target.
+pub fn adopt_texture(gl_id: u32, target: TextureTarget, tracker_id: TrackerId, registration: RegistrationHandle, ownership: Ownership) -> Texture {
+    Texture {
+        id: gl_id,
+        target,
+        tracker_id,
+        registration,
+        ownership,
+        levels: RefCell::new(Vec::new())
+    }
+}
+
+impl Texture {
+    /// Bind the texture to the given texture unit. Not really to be used directly!
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(target_to_enum(self.target), self.id);
+            check_error!();
+        }
+    }
+
+    /// Identify the texture. The returned value is the actual OpenGL object name.
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    /// The OpenGL target of the texture, such as GL_TEXTURE_2D.
+    pub fn gl_target(&self) -> GLenum {
+        target_to_enum(self.target)
+    }
+
+    /// The kind of the texture.
+    pub fn target(&self) -> TextureTarget {
+        self.target
+    }
+
+    /// How many mipmap levels have been recorded, up to the highest level specified through
+    /// the library.
+    pub fn level_count(&self) -> u32 {
+        self.levels.borrow().len() as u32
+    }
+
+    /// The dimensions of the mipmap level, if it has been specified through the library.
+    pub fn level_dimensions(&self, level: u32) -> Option<(u32, u32)> {
+        self.levels.borrow().get(level as usize)
+            .filter(|level| level.width > 0 && level.height > 0)
+            .map(|level| (level.width, level.height))
+    }
+
+    /// The internal format of the mipmap level, if it has been specified through the library
+    /// with an uncompressed format.
+    pub fn level_format(&self, level: u32) -> Option<TextureFormat> {
+        self.levels.borrow().get(level as usize).and_then(|level| level.format)
+    }
+
+    /// The number of samples per texel of a multisampled texture, zero for other textures and
+    /// before the storage is allocated.
+    pub fn samples(&self) -> u32 {
+        self.levels.borrow().first().map_or(0, |level| level.samples)
+    }
+
+    /// Record a mipmap level after it has been specified.
+    fn set_level(&self, level: u32, width: u32, height: u32, format: Option<TextureFormat>, bytes: usize) {
+        self.set_level_samples(level, width, height, format, 0, bytes);
+    }
+
+    fn set_level_samples(&self, level: u32, width: u32, height: u32, format: Option<TextureFormat>, samples: u32, bytes: usize) {
+        let mut levels = self.levels.borrow_mut();
+        let level = level as usize;
+        if levels.len() <= level {
+            levels.resize(level + 1, TextureLevel::default());
+        }
+        levels[level] = TextureLevel { width, height, format, samples, bytes };
+        self.registration.set_memory(levels.iter().map(|level| level.bytes).sum());
+    }
+
+    fn parameter(&self, pname: GLenum, value: GLint) {
+        unsafe {
+            gl::TexParameteri(target_to_enum(self.target), pname, value);
+            check_error!();
+        }
+    }
+
+    fn parameter_f32(&self, pname: GLenum, value: f32) {
+        unsafe {
+            gl::TexParameterf(target_to_enum(self.target), pname, value);
+            check_error!();
+        }
+    }
+}
+
+impl fmt::Debug for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Texture")
+            .field("id", &self.id)
+            .field("target", &self.target)
+            .field("tracker_id", &self.tracker_id)
+            .field("ownership", &self.ownership)
+            .finish()
+    }
+}
+
+impl ContextResource for Texture {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        if self.ownership == Ownership::Owned {
+            self.registration.queue_deletion(QueuedDeletion::Texture(self.id, self.tracker_id));
+        }
+    }
+}
+
+/// Helper type that binds textures to a specific texture unit for binding trackers.
+pub struct TextureBinder {
+    unit: u32
+}
+
+impl TextureBinder {
+    pub fn new(unit: u32) -> TextureBinder {
+        TextureBinder { unit }
+    }
+}
+
+impl Bind<Texture> for TextureBinder {
+    fn bind(&self, texture: &Texture) {
+        texture.bind(self.unit);
+    }
+
+    fn unbind(&self) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + self.unit);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindTexture(gl::TEXTURE_BUFFER, 0);
+        }
+        check_error!();
+    }
+
+    fn get_id(&self, texture: &Texture) -> TrackerId {
+        texture.tracker_id
+    }
+
+    fn get_name(&self, texture: &Texture) -> u32 {
+        texture.id
+    }
+}
+
+/// Bind texture (to the editing texture unit) and edit it.
+pub fn new_texture_editor<'a>(context: &'a mut Context, texture: &'a Texture) -> TextureEditor<'a> {
+    context.bind_texture_for_editing(texture);
+    let srgb_policy = context.srgb_policy();
+    TextureEditor { context, texture, srgb_policy }
+}
+
+/// Texture editor is used to edit the contents and parameters of a texture.
+pub struct TextureEditor<'a> {
+    context: &'a mut Context,
+    texture: &'a Texture,
+    /// The sRGB policy of `upload`, initially the default of the context.
+    srgb_policy: SrgbPolicy
+}
+
+impl<'a> fmt::Debug for TextureEditor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TextureEditor")
+            .field("texture", self.texture)
+            .field("srgb_policy", &self.srgb_policy)
+            .finish()
+    }
+}
+
+impl<'a> TextureEditor<'a> {
+    /// Replace the given mipmap level with the image. The internal format of the texture is
+    /// derived from the image format: for example 8-bit RGBA data is stored as RGBA8 (or
+    /// SRGB8_ALPHA8, depending on the sRGB policy) and float data as 32-bit floats. Compressed
+    /// images are stored in their own format. Use `upload_as` to choose the internal format
+    /// explicitly.
+    ///
+    /// See glTexImage2D and glCompressedTexImage2D.
+    pub fn upload(&mut self, level: u32, image: &impl ImageData) {
+        let policy = self.srgb_policy;
+        self.upload_with_srgb_policy(level, image, policy);
+    }
+
+    /// Like `upload`, but with the given sRGB policy instead of the one of the editor.
+    pub fn upload_with_srgb_policy(&mut self, level: u32, image: &impl ImageData, policy: SrgbPolicy) {
+        match image.format() {
+            ImageFormat::Uncompressed(format, pixel_type) => {
+                let internal_format = apply_srgb_policy(default_internal_format(format, pixel_type), policy);
+                self.upload_as(level, internal_format, image);
+            },
+            ImageFormat::Compressed(format) => {
+                let (width, height) = image.dimensions();
+                let bytes = validated_bytes(image);
+                unsafe {
+                    gl::CompressedTexImage2D(
+                        target_to_enum(self.texture.target),
+                        level as GLint,
+                        image::compressed_format_to_enum(format),
+                        width as GLsizei,
+                        height as GLsizei,
+                        0,
+                        bytes.len() as GLsizei,
+                        bytes.as_ptr() as *const GLvoid);
+                    check_error!();
+                }
+                self.texture.set_level(level, width, height, None, bytes.len());
+            }
+        }
+    }
+
+    /// Like `upload`, but allocate the level first and upload the image in bands of rows of at
+    /// most `chunk_size` bytes (but at least one row), calling the progress callback with the
+    /// bytes uploaded so far and the total after each band. Splitting a large upload keeps any
+    /// single call from blocking for long. Panics if the image is compressed.
+    ///
+    /// See glTexImage2D and glTexSubImage2D.
+    pub fn upload_chunked<F>(&mut self, level: u32, image: &impl ImageData, chunk_size: usize, mut progress: F)
+        where F: FnMut(usize, usize) {
+        let (format, pixel_type) = uncompressed_format(image);
+        let internal_format = apply_srgb_policy(default_internal_format(format, pixel_type), self.srgb_policy);
+        let (width, height) = image.dimensions();
+        let bytes = validated_bytes(image);
+        self.allocate(level, internal_format, width, height);
+        if bytes.is_empty() {
+            return;
+        }
+        let row_size = image.format().data_size(width, 1);
+        let rows_per_chunk = (chunk_size / row_size).max(1);
+        let mut uploaded = 0;
+        for (band, band_bytes) in bytes.chunks(rows_per_chunk * row_size).enumerate() {
+            let rows = ImageRows {
+                width,
+                height: (band_bytes.len() / row_size) as u32,
+                format: image.format(),
+                bytes: band_bytes
+            };
+            self.sub_upload(level, 0, (band * rows_per_chunk) as u32, &rows);
+            uploaded += band_bytes.len();
+            progress(uploaded, bytes.len());
+        }
+    }
+
+    /// Replace the given mipmap level with the image, storing it in the given internal format.
+    /// Compressed images can't be converted, so this method panics if given one.
+    ///
+    /// See glTexImage2D.
+    pub fn upload_as(&mut self, level: u32, internal_format: TextureFormat, image: &impl ImageData) {
+        let (format, pixel_type) = uncompressed_format(image);
+        let (width, height) = image.dimensions();
+        let (format, bytes) = client_pixels(format, pixel_type, validated_bytes(image));
+        self.context.set_pixel_store(PixelStoreParam::UnpackAlignment(1));
+        unsafe {
+            gl::TexImage2D(
+                target_to_enum(self.texture.target),
+                level as GLint,
+                texture_format_to_enum(internal_format) as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                image::pixel_format_to_enum(format),
+                image::pixel_type_to_enum(pixel_type),
+                bytes.as_ptr() as *const GLvoid);
+            check_error!();
+        }
+        self.texture.set_level(level, width, height, Some(internal_format), level_size(internal_format, width, height));
+    }
+
+    /// Upload a whole mipmap chain, the base level first, and set the max level to the last
+    /// one, so that the texture is complete with mipmapping filters. Each level must be half the
+    /// size of the previous one, rounded down but at least one, and all the images must be of the
+    /// same format. Panics if they are not, or if there are more images than
+    /// `max_levels_for` allows. The images are uploaded as with `upload`.
+    pub fn upload_mip_chain(&mut self, images: &[&dyn ImageData]) {
+        let (base, rest) = match images.split_first() {
+            Some(split) => split,
+            None => panic!("An empty mipmap chain can't be uploaded")
+        };
+        let (width, height) = base.dimensions();
+        let max_levels = max_levels_for(width, height);
+        if images.len() as u32 > max_levels {
+            panic!("{} mipmap levels given, but a {}x{} texture has only {}", images.len(), width, height, max_levels);
+        }
+        for (index, image) in rest.iter().enumerate() {
+            let level = index as u32 + 1;
+            let expected = ((width >> level).max(1), (height >> level).max(1));
+            if image.dimensions() != expected {
+                panic!("Mipmap level {} should be {}x{}, but the image is {}x{}",
+                    level, expected.0, expected.1, image.dimensions().0, image.dimensions().1);
+            }
+            if image.format() != base.format() {
+                panic!("Mipmap level {} is of the format {:?}, but the base level is {:?}", level, image.format(), base.format());
+            }
+        }
+        for (level, image) in images.iter().enumerate() {
+            self.upload(level as u32, image);
+        }
+        self.set_max_level(images.len() as u32 - 1);
+    }
+
+    /// Allocate storage for the given mipmap level without uploading anything. The contents are
+    /// undefined until uploaded with `sub_upload`.
+    ///
+    /// See glTexImage2D.
+    pub fn allocate(&mut self, level: u32, internal_format: TextureFormat, width: u32, height: u32) {
+        let (format, pixel_type) = allocation_format(internal_format);
+        unsafe {
+            gl::TexImage2D(
+                target_to_enum(self.texture.target),
+                level as GLint,
+                texture_format_to_enum(internal_format) as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                image::pixel_format_to_enum(format),
+                image::pixel_type_to_enum(pixel_type),
+                ptr::null());
+            check_error!();
+        }
+        self.texture.set_level(level, width, height, Some(internal_format), level_size(internal_format, width, height));
+    }
+
+    /// Allocate the storage of a multisampled texture, with the given number of samples per
+    /// texel. The sample locations are fixed, the same in every texel. Panics if the texture is
+    /// not multisampled, if `ExtensionFeature::MultisampleTexture` is not supported, or if the
+    /// number of samples is zero or more than `TextureInfo::max_color_samples` (or
+    /// `max_depth_samples` for depth formats).
+    ///
+    /// See glTexImage2DMultisample.
+    pub fn allocate_multisample(&mut self, internal_format: TextureFormat, width: u32, height: u32, samples: u32) {
+        if self.texture.target != TextureTarget::Texture2dMultisample {
+            panic!("Only multisampled textures can be allocated with samples, the texture is of target {:?}", self.texture.target);
+        }
+        let info = self.context.get_info();
+        if !info.supports(ExtensionFeature::MultisampleTexture) {
+            panic!("The context doesn't support multisampled textures");
+        }
+        let max_samples = if info::is_depth_format(internal_format) {
+            info.texture.max_depth_samples
+        }
+        else {
+            info.texture.max_color_samples
+        };
+        if samples == 0 || samples > max_samples.max(0) as u32 {
+            panic!("{} samples requested, but a texture of the format {:?} can have from 1 to {}", samples, internal_format, max_samples);
+        }
+        tex_storage_multisample(texture_format_to_enum(internal_format), width, height, samples);
+        let bytes = level_size(internal_format, width, height) * samples as usize;
+        self.texture.set_level_samples(0, width, height, Some(internal_format), samples, bytes);
+    }
+
+    /// Replace a region of the given mipmap level with the image, `x` and `y` being the offset
+    /// of the region.
+    ///
+    /// See glTexSubImage2D and glCompressedTexSubImage2D.
+    pub fn sub_upload(&mut self, level: u32, x: u32, y: u32, image: &impl ImageData) {
+        let (width, height) = image.dimensions();
+        let bytes = validated_bytes(image);
+        let target = target_to_enum(self.texture.target);
+        match image.format() {
+            ImageFormat::Uncompressed(format, pixel_type) => {
+                let (format, bytes) = client_pixels(format, pixel_type, bytes);
+                self.context.set_pixel_store(PixelStoreParam::UnpackAlignment(1));
+                unsafe {
+                    gl::TexSubImage2D(
+                        target,
+                        level as GLint,
+                        x as GLint,
+                        y as GLint,
+                        width as GLsizei,
+                        height as GLsizei,
+                        image::pixel_format_to_enum(format),
+                        image::pixel_type_to_enum(pixel_type),
+                        bytes.as_ptr() as *const GLvoid);
+                    check_error!();
+                }
+            },
+            ImageFormat::Compressed(format) => {
+                unsafe {
+                    gl::CompressedTexSubImage2D(
+                        target,
+                        level as GLint,
+                        x as GLint,
+                        y as GLint,
+                        width as GLsizei,
+                        height as GLsizei,
+                        image::compressed_format_to_enum(format),
+                        bytes.len() as GLsizei,
+                        bytes.as_ptr() as *const GLvoid);
+                    check_error!();
+                }
+            }
+        }
+    }
+
+    /// Fill the given mipmap level with the value, without uploading a whole image of it. The
+    /// level must have been allocated or uploaded. Panics if the value doesn't suit the internal
+    /// format of the level, see `TextureClearValue`.
+    ///
+    /// See glClearTexImage. If it is not supported (see `ContextInfo::supports_clear_texture`),
+    /// the level is attached to a temporary framebuffer and cleared with glClearBuffer*, with
+    /// the scissor test disabled and the color and depth writes enabled for the clear, as with
+    /// `Renderer::clear`.
+    pub fn clear(&mut self, level: u32, value: TextureClearValue) {
+        if let Some(format) = self.texture.level_format(level) {
+            validate_clear_value(format, value);
+        }
+        if self.context.get_info().supports_clear_texture() {
+            clear_tex_image(self.texture, level, value);
+        }
+        else {
+            // glClearBuffer* obeys the scissor test and the masks, unlike glClearTexImage
+            let lifted = [RenderOption::ScissorTest(false), RenderOption::DepthWrite(true), RenderOption::ColorWrite(true)];
+            let previous: Vec<_> = lifted.iter().map(|&option| self.context.render_option(option)).collect();
+            for (&option, &previous) in lifted.iter().zip(previous.iter()) {
+                if previous != Some(option) {
+                    self.context.set_render_option(option);
+                }
+            }
+            clear_with_framebuffer(self.texture, level, value);
+            self.context.reset_framebuffer_binding();
+            for (&option, &previous) in lifted.iter().zip(previous.iter()) {
+                match previous {
+                    Some(previous) if previous != option => self.context.set_render_option(previous),
+                    _ => ()
+                }
+            }
+        }
+    }
+
+    /// Set the minification and magnification filters. Note that the default minification
+    /// filter uses mipmaps, so a texture without mipmaps is not usable before changing it.
+    pub fn set_filters(&mut self, min_filter: TextureFilter, mag_filter: TextureFilter) {
+        self.texture.parameter(gl::TEXTURE_MIN_FILTER, filter_to_enum(min_filter) as GLint);
+        self.texture.parameter(gl::TEXTURE_MAG_FILTER, filter_to_enum(mag_filter) as GLint);
+    }
+
+    /// Set the wrapping modes of the s and t texture coordinates.
+    pub fn set_wrap(&mut self, wrap_s: TextureWrap, wrap_t: TextureWrap) {
+        self.texture.parameter(gl::TEXTURE_WRAP_S, wrap_to_enum(wrap_s) as GLint);
+        self.texture.parameter(gl::TEXTURE_WRAP_T, wrap_to_enum(wrap_t) as GLint);
+    }
+
+    /// Enable depth comparison with the function, or disable it with None. A depth texture with
+    /// comparison enabled is sampled through a shadow sampler, such as `sampler2DShadow`, which
+    /// returns the result of comparing the reference value to the texel, filtered; see
+    /// `htgl/shadow.glsl` of `ShaderSourceLibrary`. See GL_TEXTURE_COMPARE_MODE and
+    /// GL_TEXTURE_COMPARE_FUNC.
+    pub fn set_depth_comparison(&mut self, function: Option<DepthFunction>) {
+        match function {
+            Some(function) => {
+                self.texture.parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint);
+                self.texture.parameter(gl::TEXTURE_COMPARE_FUNC, options::depth_function_to_enum(function) as GLint);
+            },
+            None => self.texture.parameter(gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint)
+        }
+    }
+
+    /// Set the maximum degree of anisotropy used when sampling the texture, clamped to
+    /// `ExtensionLimits::max_anisotropy`. 1.0 disables anisotropic filtering. Does nothing if
+    /// `ExtensionFeature::AnisotropicFiltering` is not supported. See GL_TEXTURE_MAX_ANISOTROPY.
+    pub fn set_max_anisotropy(&mut self, anisotropy: f32) {
+        let info = self.context.get_info();
+        if !info.supports(ExtensionFeature::AnisotropicFiltering) {
+            return;
+        }
+        let anisotropy = anisotropy.max(1.0).min(info.extension_limits.max_anisotropy);
+        self.texture.parameter_f32(info::TEXTURE_MAX_ANISOTROPY, anisotropy);
+    }
+
+    /// Set the highest mipmap level that is used when sampling the texture. A texture is only
+    /// complete (and thus usable) when all the levels up to this one have been uploaded, unless
+    /// the minification filter doesn't use mipmaps. See GL_TEXTURE_MAX_LEVEL.
+    pub fn set_max_level(&mut self, level: u32) {
+        self.texture.parameter(gl::TEXTURE_MAX_LEVEL, level as GLint);
+    }
+
+    /// Make the whole buffer the data store of a buffer texture, the texels being of the given
+    /// format. The format must be one of the one, two or four component formats that are not
+    /// normalized integers, or `R8`, `Rg8` or `Rgba8`; no depth or sRGB formats. The texture
+    /// sees the later changes to the buffer. Fails if the buffer has more texels than
+    /// `TextureInfo::max_buffer_size`. Panics if the texture is not a buffer texture.
+    /// See glTexBuffer.
+    pub fn set_buffer(&mut self, buffer: &BufferHandle, format: TextureFormat) -> Result<(), TextureBufferError> {
+        self.check_buffer_texture();
+        self.check_texel_count(format, buffer.access().size())?;
+        unsafe {
+            gl::TexBuffer(gl::TEXTURE_BUFFER, texture_format_to_enum(format), buffer.access().id);
+            check_error!();
+        }
+        Ok(())
+    }
+
+    /// Like `set_buffer`, but only `size` bytes of the buffer starting from `offset` are used.
+    /// The offset must be a multiple of `TextureInfo::buffer_offset_alignment`, and the range
+    /// must be within the buffer, if its size is known to the library. See glTexBufferRange.
+    pub fn set_buffer_range(&mut self, buffer: &BufferHandle, format: TextureFormat, offset: usize, size: usize)
+            -> Result<(), TextureBufferError> {
+        self.check_buffer_texture();
+        let info = self.context.get_info();
+        if !info.supports(ExtensionFeature::TextureBufferRange) {
+            return Err(TextureBufferError::RangeUnsupported);
+        }
+        let alignment = info.texture.buffer_offset_alignment.max(1) as usize;
+        if !offset.is_multiple_of(alignment) {
+            return Err(TextureBufferError::MisalignedOffset { offset, alignment });
+        }
+        let buffer_size = buffer.access().size();
+        if size == 0 || (buffer_size > 0 && offset.checked_add(size).is_none_or(|end| end > buffer_size)) {
+            return Err(TextureBufferError::OutOfBounds { offset, size, buffer_size });
+        }
+        self.check_texel_count(format, size)?;
+        unsafe {
+            gl::TexBufferRange(gl::TEXTURE_BUFFER, texture_format_to_enum(format), buffer.access().id,
+                offset as isize, size as isize);
+            check_error!();
+        }
+        Ok(())
+    }
+
+    fn check_texel_count(&self, format: TextureFormat, size: usize) -> Result<(), TextureBufferError> {
+        let texels = size / level_size(format, 1, 1);
+        let max = self.context.get_info().texture.max_buffer_size.max(0) as usize;
+        if texels > max {
+            return Err(TextureBufferError::TooLarge { texels, max });
+        }
+        Ok(())
+    }
+
+    fn check_buffer_texture(&self) {
+        if self.texture.target != TextureTarget::Buffer {
+            panic!("Only buffer textures can be given a buffer, the texture is of target {:?}", self.texture.target);
+        }
+    }
+
+    /// Set the sRGB policy of the uploads made with this editor, overriding the default of the
+    /// context. See `SrgbPolicy`.
+    pub fn set_srgb_policy(&mut self, policy: SrgbPolicy) {
+        self.srgb_policy = policy;
+    }
+
+    /// The sRGB policy of the uploads made with this editor.
+    pub fn srgb_policy(&self) -> SrgbPolicy {
+        self.srgb_policy
+    }
+
+    /// Information on the context the texture is edited in, for checking capabilities.
+    pub fn context_info(&self) -> &ContextInfo {
+        self.context.get_info()
+    }
+
+    /// Upload the image as the base level and generate the other mipmap levels as the policy
+    /// says, see `MipmapPolicy`. The CPU policies only support images of unsigned bytes of the
+    /// color formats, and panic for others; they are useful when glGenerateMipmap doesn't
+    /// support the format, or when the mipmaps of an sRGB texture must be gamma correct.
+    pub fn upload_with_mipmaps(&mut self, image: &impl ImageData, policy: MipmapPolicy) {
+        let gamma_correct = match policy {
+            MipmapPolicy::Gpu => {
+                self.upload(0, image);
+                self.generate_mipmap();
+                return;
+            },
+            MipmapPolicy::Cpu => false,
+            MipmapPolicy::CpuGammaCorrect => true
+        };
+        let levels = mipmap::generate_levels(image, gamma_correct);
+        let mut chain: Vec<&dyn ImageData> = Vec::with_capacity(levels.len() + 1);
+        chain.push(image);
+        chain.extend(levels.iter().map(|level| level as &dyn ImageData));
+        self.upload_mip_chain(&chain[..]);
+    }
+
+    /// Generate the mipmap levels from the base level. See glGenerateMipmap.
+    pub fn generate_mipmap(&mut self) {
+        unsafe {
+            gl::GenerateMipmap(target_to_enum(self.texture.target));
+            check_error!();
+        }
+        let base = match self.texture.levels.borrow().first() {
+            Some(&base) if base.width > 0 && base.height > 0 => base,
+            _ => return
+        };
+        // Compressed levels are approximated by the ratio of the texel counts
+        let base_texels = base.width as usize * base.height as usize;
+        let (mut width, mut height) = (base.width, base.height);
+        let mut level = 1;
+        while width > 1 || height > 1 {
+            width = (width / 2).max(1);
+            height = (height / 2).max(1);
+            let bytes = match base.format {
+                Some(format) => level_size(format, width, height),
+                None => base.bytes * (width as usize * height as usize) / base_texels
+            };
+            self.texture.set_level(level, width, height, base.format, bytes);
+            level += 1;
+        }
+    }
+}
+
+/// How many mipmap levels a full chain of a texture of the given size has, the base level
+/// included: the levels halve the size until both dimensions are one.
+pub fn max_levels_for(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Check that the clear value is of the right kind for the internal format.
+fn validate_clear_value(format: TextureFormat, value: TextureClearValue) {
+    let valid = match value {
+        TextureClearValue::Float(_) => !info::is_integer_format(format) && !info::is_depth_format(format),
+        TextureClearValue::Int(_) => info::is_signed_integer_format(format),
+        TextureClearValue::UnsignedInt(_) => info::is_integer_format(format) && !info::is_signed_integer_format(format),
+        TextureClearValue::Depth(_) => info::is_depth_format(format),
+        TextureClearValue::DepthStencil(..) => format == TextureFormat::Depth24Stencil8
+    };
+    if !valid {
+        panic!("Can't clear a texture of the format {:?} with {:?}", format, value);
+    }
+}
+
+fn clear_tex_image(texture: &Texture, level: u32, value: TextureClearValue) {
+    unsafe {
+        match value {
+            TextureClearValue::Float(color) => {
+                gl::ClearTexImage(texture.id, level as GLint, gl::RGBA, gl::FLOAT, color.as_ptr() as *const GLvoid);
+            },
+            TextureClearValue::Int(color) => {
+                gl::ClearTexImage(texture.id, level as GLint, gl::RGBA_INTEGER, gl::INT, color.as_ptr() as *const GLvoid);
+            },
+            TextureClearValue::UnsignedInt(color) => {
+                gl::ClearTexImage(texture.id, level as GLint, gl::RGBA_INTEGER, gl::UNSIGNED_INT, color.as_ptr() as *const GLvoid);
+            },
+            TextureClearValue::Depth(depth) => {
+                gl::ClearTexImage(texture.id, level as GLint, gl::DEPTH_COMPONENT, gl::FLOAT, &depth as *const f32 as *const GLvoid);
+            },
+            TextureClearValue::DepthStencil(depth, stencil) => {
+                // The depth in the upper 24 bits, the stencil in the lower 8
+                let packed = ((depth.clamp(0.0, 1.0) * 0xFFFFFF as f32) as u32) << 8 | stencil as u32;
+                gl::ClearTexImage(texture.id, level as GLint, gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8,
+                    &packed as *const u32 as *const GLvoid);
+            }
+        }
+    }
+    check_error!();
+}
+
+/// Clear the level by attaching it to a temporary framebuffer, which is left bound. The caller
+/// must restore the framebuffer binding.
+fn clear_with_framebuffer(texture: &Texture, level: u32, value: TextureClearValue) {
+    let attachment = match value {
+        TextureClearValue::Depth(_) => gl::DEPTH_ATTACHMENT,
+        TextureClearValue::DepthStencil(..) => gl::DEPTH_STENCIL_ATTACHMENT,
+        _ => gl::COLOR_ATTACHMENT0
+    };
+    unsafe {
+        let mut framebuffer = 0;
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, texture.gl_target(), texture.id, level as GLint);
+        if attachment == gl::COLOR_ATTACHMENT0 {
+            gl::DrawBuffers(1, &gl::COLOR_ATTACHMENT0);
+        }
+        else {
+            gl::DrawBuffers(1, &gl::NONE);
+        }
+        check_error!();
+        match value {
+            TextureClearValue::Float(color) => gl::ClearBufferfv(gl::COLOR, 0, color.as_ptr()),
+            TextureClearValue::Int(color) => gl::ClearBufferiv(gl::COLOR, 0, color.as_ptr()),
+            TextureClearValue::UnsignedInt(color) => gl::ClearBufferuiv(gl::COLOR, 0, color.as_ptr()),
+            TextureClearValue::Depth(depth) => gl::ClearBufferfv(gl::DEPTH, 0, &depth),
+            TextureClearValue::DepthStencil(depth, stencil) => gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil as GLint)
+        }
+        check_error!();
+        gl::DeleteFramebuffers(1, &framebuffer);
+    }
+    check_error!();
+}
+
+/// The texture format used for uploads when the format is not given explicitly.
+fn default_internal_format(format: PixelFormat, pixel_type: PixelType) -> TextureFormat {
+    match (format, pixel_type) {
+        (PixelFormat::Red, PixelType::Float) => TextureFormat::R32f,
+        (PixelFormat::Rg, PixelType::Float) => TextureFormat::Rg32f,
+        (PixelFormat::Rgb, PixelType::Float) | (PixelFormat::Bgr, PixelType::Float) => TextureFormat::Rgb32f,
+        (PixelFormat::Rgba, PixelType::Float) | (PixelFormat::Bgra, PixelType::Float) => TextureFormat::Rgba32f,
+        (PixelFormat::Red, PixelType::HalfFloat) => TextureFormat::R16f,
+        (PixelFormat::Rg, PixelType::HalfFloat) => TextureFormat::Rg16f,
+        (PixelFormat::Rgb, PixelType::HalfFloat) | (PixelFormat::Bgr, PixelType::HalfFloat) => TextureFormat::Rgb16f,
+        (PixelFormat::Rgba, PixelType::HalfFloat) | (PixelFormat::Bgra, PixelType::HalfFloat) => TextureFormat::Rgba16f,
+        (PixelFormat::Red, _) => TextureFormat::R8,
+        (PixelFormat::Rg, _) => TextureFormat::Rg8,
+        (PixelFormat::Rgb, _) | (PixelFormat::Bgr, _) => TextureFormat::Rgb8,
+        (PixelFormat::Rgba, _) | (PixelFormat::Bgra, _) => TextureFormat::Rgba8,
+        (PixelFormat::RedInteger, PixelType::UnsignedByte) => TextureFormat::R8ui,
+        (PixelFormat::RedInteger, PixelType::Byte) => TextureFormat::R8i,
+        (PixelFormat::RedInteger, PixelType::UnsignedShort) => TextureFormat::R16ui,
+        (PixelFormat::RedInteger, PixelType::Short) => TextureFormat::R16i,
+        (PixelFormat::RedInteger, PixelType::UnsignedInt) => TextureFormat::R32ui,
+        (PixelFormat::RedInteger, PixelType::Int) => TextureFormat::R32i,
+        (PixelFormat::RgInteger, PixelType::UnsignedByte) => TextureFormat::Rg8ui,
+        (PixelFormat::RgInteger, PixelType::Byte) => TextureFormat::Rg8i,
+        (PixelFormat::RgInteger, PixelType::UnsignedShort) => TextureFormat::Rg16ui,
+        (PixelFormat::RgInteger, PixelType::Short) => TextureFormat::Rg16i,
+        (PixelFormat::RgInteger, PixelType::UnsignedInt) => TextureFormat::Rg32ui,
+        (PixelFormat::RgInteger, PixelType::Int) => TextureFormat::Rg32i,
+        (PixelFormat::RgbInteger, PixelType::UnsignedByte) => TextureFormat::Rgb8ui,
+        (PixelFormat::RgbInteger, PixelType::Byte) => TextureFormat::Rgb8i,
+        (PixelFormat::RgbInteger, PixelType::UnsignedShort) => TextureFormat::Rgb16ui,
+        (PixelFormat::RgbInteger, PixelType::Short) => TextureFormat::Rgb16i,
+        (PixelFormat::RgbInteger, PixelType::UnsignedInt) => TextureFormat::Rgb32ui,
+        (PixelFormat::RgbInteger, PixelType::Int) => TextureFormat::Rgb32i,
+        (PixelFormat::RgbaInteger, PixelType::UnsignedByte) => TextureFormat::Rgba8ui,
+        (PixelFormat::RgbaInteger, PixelType::Byte) => TextureFormat::Rgba8i,
+        (PixelFormat::RgbaInteger, PixelType::UnsignedShort) => TextureFormat::Rgba16ui,
+        (PixelFormat::RgbaInteger, PixelType::Short) => TextureFormat::Rgba16i,
+        (PixelFormat::RgbaInteger, PixelType::UnsignedInt) => TextureFormat::Rgba32ui,
+        (PixelFormat::RgbaInteger, PixelType::Int) => TextureFormat::Rgba32i,
+        (PixelFormat::DepthComponent, PixelType::Float) => TextureFormat::DepthComponent32f,
+        (PixelFormat::DepthComponent, PixelType::UnsignedShort) => TextureFormat::DepthComponent16,
+        (PixelFormat::DepthComponent, _) => TextureFormat::DepthComponent24,
+        (PixelFormat::DepthStencil, _) => TextureFormat::Depth24Stencil8,
+        (format, pixel_type) => panic!("No default texture format for {:?} of {:?}, use upload_as", format, pixel_type)
+    }
+}
+
+/// The sRGB variant of the 8-bit color format if the policy says so.
+fn apply_srgb_policy(format: TextureFormat, policy: SrgbPolicy) -> TextureFormat {
+    match (format, policy) {
+        (TextureFormat::Rgb8, SrgbPolicy::Srgb) => TextureFormat::Srgb8,
+        (TextureFormat::Rgba8, SrgbPolicy::Srgb) => TextureFormat::Srgb8Alpha8,
+        (format, _) => format
+    }
+}
+
+/// The size of a mipmap level of the given format and dimensions in bytes, as stored by OpenGL
+/// at least. The actual size may be larger because of padding.
+fn level_size(format: TextureFormat, width: u32, height: u32) -> usize {
+    let texel_size = match format {
+        TextureFormat::R8 | TextureFormat::R8ui | TextureFormat::R8i => 1,
+        TextureFormat::Rg8 | TextureFormat::R16f | TextureFormat::R16ui | TextureFormat::R16i |
+        TextureFormat::Rg8ui | TextureFormat::Rg8i | TextureFormat::DepthComponent16 => 2,
+        TextureFormat::Rgb8 | TextureFormat::Srgb8 | TextureFormat::Rgb8ui | TextureFormat::Rgb8i => 3,
+        TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 | TextureFormat::Rg16f | TextureFormat::R32f |
+        TextureFormat::R32ui | TextureFormat::R32i | TextureFormat::Rg16ui | TextureFormat::Rg16i |
+        TextureFormat::Rgba8ui | TextureFormat::Rgba8i | TextureFormat::DepthComponent24 |
+        TextureFormat::DepthComponent32f | TextureFormat::Depth24Stencil8 => 4,
+        TextureFormat::Rgb16f | TextureFormat::Rgb16ui | TextureFormat::Rgb16i => 6,
+        TextureFormat::Rgba16f | TextureFormat::Rg32f | TextureFormat::Rg32ui | TextureFormat::Rg32i |
+        TextureFormat::Rgba16ui | TextureFormat::Rgba16i => 8,
+        TextureFormat::Rgb32f | TextureFormat::Rgb32ui | TextureFormat::Rgb32i => 12,
+        TextureFormat::Rgba32f | TextureFormat::Rgba32ui | TextureFormat::Rgba32i => 16
+    };
+    texel_size * width as usize * height as usize
+}
+
+/// A client format and type that are valid with the internal format, as glTexImage2D requires
+/// them even when no data is given.
+fn allocation_format(internal_format: TextureFormat) -> (PixelFormat, PixelType) {
+    match internal_format {
+        TextureFormat::R8 => (PixelFormat::Red, PixelType::UnsignedByte),
+        TextureFormat::Rg8 => (PixelFormat::Rg, PixelType::UnsignedByte),
+        TextureFormat::Rgb8 | TextureFormat::Srgb8 => (PixelFormat::Rgb, PixelType::UnsignedByte),
+        TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 => (PixelFormat::Rgba, PixelType::UnsignedByte),
+        TextureFormat::R16f | TextureFormat::R32f => (PixelFormat::Red, PixelType::Float),
+        TextureFormat::Rg16f | TextureFormat::Rg32f => (PixelFormat::Rg, PixelType::Float),
+        TextureFormat::Rgb16f | TextureFormat::Rgb32f => (PixelFormat::Rgb, PixelType::Float),
+        TextureFormat::Rgba16f | TextureFormat::Rgba32f => (PixelFormat::Rgba, PixelType::Float),
+        TextureFormat::R8ui => (PixelFormat::RedInteger, PixelType::UnsignedByte),
+        TextureFormat::R8i => (PixelFormat::RedInteger, PixelType::Byte),
+        TextureFormat::R16ui => (PixelFormat::RedInteger, PixelType::UnsignedShort),
+        TextureFormat::R16i => (PixelFormat::RedInteger, PixelType::Short),
+        TextureFormat::R32ui => (PixelFormat::RedInteger, PixelType::UnsignedInt),
+        TextureFormat::R32i => (PixelFormat::RedInteger, PixelType::Int),
+        TextureFormat::Rg8ui => (PixelFormat::RgInteger, PixelType::UnsignedByte),
+        TextureFormat::Rg8i => (PixelFormat::RgInteger, PixelType::Byte),
+        TextureFormat::Rg16ui => (PixelFormat::RgInteger, PixelType::UnsignedShort),
+        TextureFormat::Rg16i => (PixelFormat::RgInteger, PixelType::Short),
+        TextureFormat::Rg32ui => (PixelFormat::RgInteger, PixelType::UnsignedInt),
+        TextureFormat::Rg32i => (PixelFormat::RgInteger, PixelType::Int),
+        TextureFormat::Rgb8ui => (PixelFormat::RgbInteger, PixelType::UnsignedByte),
+        TextureFormat::Rgb8i => (PixelFormat::RgbInteger, PixelType::Byte),
+        TextureFormat::Rgb16ui => (PixelFormat::RgbInteger, PixelType::UnsignedShort),
+        TextureFormat::Rgb16i => (PixelFormat::RgbInteger, PixelType::Short),
+        TextureFormat::Rgb32ui => (PixelFormat::RgbInteger, PixelType::UnsignedInt),
+        TextureFormat::Rgb32i => (PixelFormat::RgbInteger, PixelType::Int),
+        TextureFormat::Rgba8ui => (PixelFormat::RgbaInteger, PixelType::UnsignedByte),
+        TextureFormat::Rgba8i => (PixelFormat::RgbaInteger, PixelType::Byte),
+        TextureFormat::Rgba16ui => (PixelFormat::RgbaInteger, PixelType::UnsignedShort),
+        TextureFormat::Rgba16i => (PixelFormat::RgbaInteger, PixelType::Short),
+        TextureFormat::Rgba32ui => (PixelFormat::RgbaInteger, PixelType::UnsignedInt),
+        TextureFormat::Rgba32i => (PixelFormat::RgbaInteger, PixelType::Int),
+        TextureFormat::DepthComponent16 => (PixelFormat::DepthComponent, PixelType::UnsignedShort),
+        TextureFormat::DepthComponent24 => (PixelFormat::DepthComponent, PixelType::UnsignedInt),
+        TextureFormat::DepthComponent32f => (PixelFormat::DepthComponent, PixelType::Float),
+        TextureFormat::Depth24Stencil8 => (PixelFormat::DepthStencil, PixelType::UnsignedInt248)
+    }
+}
+
+fn uncompressed_format(image: &dyn ImageData) -> (PixelFormat, PixelType) {
+    match image.format() {
+        ImageFormat::Uncompressed(format, pixel_type) => (format, pixel_type),
+        ImageFormat::Compressed(format) => panic!("Can't convert compressed image data ({:?}) to another format", format)
+    }
+}
+
+/// Check that the image contains as many bytes as its dimensions and format claim.
+fn validated_bytes(image: &dyn ImageData) -> &[u8] {
+    let (width, height) = image.dimensions();
+    let expected_size = image.format().data_size(width, height);
+    let bytes = image.bytes();
+    if bytes.len() < expected_size {
+        panic!("Too small image data: {}x{} of {:?} takes {} bytes, but only got {}",
+            width, height, image.format(), expected_size, bytes.len());
+    }
+    &bytes[..expected_size]
+}
+
+/// The pixels to give to OpenGL. On desktop OpenGL, the data can be used as is.
+#[cfg(not(feature = "es3"))]
+fn client_pixels(format: PixelFormat, _: PixelType, bytes: &[u8]) -> (PixelFormat, Cow<'_, [u8]>) {
+    (format, Cow::Borrowed(bytes))
+}
+
+/// The pixels to give to OpenGL. OpenGL ES has no BGR and BGRA formats, so the components of
+/// such data are reordered to RGB(A) first.
+#[cfg(feature = "es3")]
+fn client_pixels(format: PixelFormat, pixel_type: PixelType, bytes: &[u8]) -> (PixelFormat, Cow<'_, [u8]>) {
+    let (rgb_format, components) = match format {
+        PixelFormat::Bgr => (PixelFormat::Rgb, 3),
+        PixelFormat::Bgra => (PixelFormat::Rgba, 4),
+        _ => return (format, Cow::Borrowed(bytes))
+    };
+    if pixel_type != PixelType::UnsignedByte && pixel_type != PixelType::Byte {
+        panic!("Only 8-bit {:?} data can be uploaded on OpenGL ES, got {:?}", format, pixel_type);
+    }
+    let mut reordered = bytes.to_vec();
+    for pixel in reordered.chunks_mut(components) {
+        pixel.swap(0, 2);
+    }
+    (rgb_format, Cow::Owned(reordered))
+}
+
+/// A band of rows of an image, for `TextureEditor::upload_chunked`.
+struct ImageRows<'a> {
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    bytes: &'a [u8]
+}
+
+impl<'a> ImageData for ImageRows<'a> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+fn target_to_enum(target: TextureTarget) -> GLenum {
+    match target {
+        TextureTarget::Texture2d => gl::TEXTURE_2D,
+        TextureTarget::Buffer => gl::TEXTURE_BUFFER,
+        TextureTarget::Texture2dMultisample => gl::TEXTURE_2D_MULTISAMPLE
+    }
+}
+
+/// Multisampled textures are core since OpenGL 3.2, before glTexStorage2DMultisample.
+#[cfg(not(feature = "es3"))]
+fn tex_storage_multisample(internal_format: GLenum, width: u32, height: u32, samples: u32) {
+    unsafe {
+        gl::TexImage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples as GLsizei, internal_format,
+            width as GLsizei, height as GLsizei, gl::TRUE);
+    }
+    check_error!();
+}
+
+/// OpenGL ES only has the immutable glTexStorage2DMultisample, since 3.1.
+#[cfg(feature = "es3")]
+fn tex_storage_multisample(internal_format: GLenum, width: u32, height: u32, samples: u32) {
+    unsafe {
+        gl::TexStorage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples as GLsizei, internal_format,
+            width as GLsizei, height as GLsizei, gl::TRUE);
+    }
+    check_error!();
+}
+
+pub fn texture_format_to_enum(format: TextureFormat) -> GLenum {
+    match format {
+        TextureFormat::R8 => gl::R8,
+        TextureFormat::Rg8 => gl::RG8,
+        TextureFormat::Rgb8 => gl::RGB8,
+        TextureFormat::Rgba8 => gl::RGBA8,
+        TextureFormat::Srgb8 => gl::SRGB8,
+        TextureFormat::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+        TextureFormat::R16f => gl::R16F,
+        TextureFormat::Rg16f => gl::RG16F,
+        TextureFormat::Rgb16f => gl::RGB16F,
+        TextureFormat::Rgba16f => gl::RGBA16F,
+        TextureFormat::R32f => gl::R32F,
+        TextureFormat::Rg32f => gl::RG32F,
+        TextureFormat::Rgb32f => gl::RGB32F,
+        TextureFormat::Rgba32f => gl::RGBA32F,
+        TextureFormat::R8ui => gl::R8UI,
+        TextureFormat::R8i => gl::R8I,
+        TextureFormat::R16ui => gl::R16UI,
+        TextureFormat::R16i => gl::R16I,
+        TextureFormat::R32ui => gl::R32UI,
+        TextureFormat::R32i => gl::R32I,
+        TextureFormat::Rg8ui => gl::RG8UI,
+        TextureFormat::Rg8i => gl::RG8I,
+        TextureFormat::Rg16ui => gl::RG16UI,
+        TextureFormat::Rg16i => gl::RG16I,
+        TextureFormat::Rg32ui => gl::RG32UI,
+        TextureFormat::Rg32i => gl::RG32I,
+        TextureFormat::Rgb8ui => gl::RGB8UI,
+        TextureFormat::Rgb8i => gl::RGB8I,
+        TextureFormat::Rgb16ui => gl::RGB16UI,
+        TextureFormat::Rgb16i => gl::RGB16I,
+        TextureFormat::Rgb32ui => gl::RGB32UI,
+        TextureFormat::Rgb32i => gl::RGB32I,
+        TextureFormat::Rgba8ui => gl::RGBA8UI,
+        TextureFormat::Rgba8i => gl::RGBA8I,
+        TextureFormat::Rgba16ui => gl::RGBA16UI,
+        TextureFormat::Rgba16i => gl::RGBA16I,
+        TextureFormat::Rgba32ui => gl::RGBA32UI,
+        TextureFormat::Rgba32i => gl::RGBA32I,
+        TextureFormat::DepthComponent16 => gl::DEPTH_COMPONENT16,
+        TextureFormat::DepthComponent24 => gl::DEPTH_COMPONENT24,
+        TextureFormat::DepthComponent32f => gl::DEPTH_COMPONENT32F,
+        TextureFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8
+    }
+}
+
+fn filter_to_enum(filter: TextureFilter) -> GLenum {
+    match filter {
+        TextureFilter::Nearest => gl::NEAREST,
+        TextureFilter::Linear => gl::LINEAR,
+        TextureFilter::NearestMipmapNearest => gl::NEAREST_MIPMAP_NEAREST,
+        TextureFilter::LinearMipmapNearest => gl::LINEAR_MIPMAP_NEAREST,
+        TextureFilter::NearestMipmapLinear => gl::NEAREST_MIPMAP_LINEAR,
+        TextureFilter::LinearMipmapLinear => gl::LINEAR_MIPMAP_LINEAR
+    }
+}
+
+fn wrap_to_enum(wrap: TextureWrap) -> GLenum {
+    match wrap {
+        TextureWrap::Repeat => gl::REPEAT,
+        TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        TextureWrap::ClampToBorder => gl::CLAMP_TO_BORDER
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_formats_keep_signedness_and_size() {
+        assert_eq!(default_internal_format(PixelFormat::RedInteger, PixelType::Byte), TextureFormat::R8i);
+        assert_eq!(default_internal_format(PixelFormat::RgInteger, PixelType::UnsignedShort), TextureFormat::Rg16ui);
+        assert_eq!(default_internal_format(PixelFormat::RgbaInteger, PixelType::Int), TextureFormat::Rgba32i);
+        assert_eq!(default_internal_format(PixelFormat::RgbaInteger, PixelType::UnsignedByte), TextureFormat::Rgba8ui);
+        for &format in &[PixelFormat::RedInteger, PixelFormat::RgInteger, PixelFormat::RgbInteger, PixelFormat::RgbaInteger] {
+            for &pixel_type in &[PixelType::Byte, PixelType::UnsignedByte, PixelType::Short,
+                                 PixelType::UnsignedShort, PixelType::Int, PixelType::UnsignedInt] {
+                let internal_format = default_internal_format(format, pixel_type);
+                assert_eq!(allocation_format(internal_format), (format, pixel_type));
+                let signed = matches!(pixel_type, PixelType::Byte | PixelType::Short | PixelType::Int);
+                assert_eq!(info::is_signed_integer_format(internal_format), signed);
+                assert!(info::is_integer_format(internal_format));
+                assert_eq!(level_size(internal_format, 1, 1), image::ImageFormat::Uncompressed(format, pixel_type).data_size(1, 1));
+            }
+        }
+    }
+
+    #[test]
+    fn level_count_follows_the_larger_dimension() {
+        assert_eq!(max_levels_for(1, 1), 1);
+        assert_eq!(max_levels_for(0, 0), 1);
+        assert_eq!(max_levels_for(256, 256), 9);
+        assert_eq!(max_levels_for(255, 1), 8);
+        assert_eq!(max_levels_for(1, 257), 9);
+        assert_eq!(max_levels_for(640, 480), 10);
+    }
+}
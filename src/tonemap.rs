@@ -0,0 +1,186 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! A ready-made pass mapping an HDR image to the default framebuffer, see `TonemapPass`.
+
+use super::{Context,ProgramHandle,TextureHandle,VertexArrayHandle};
+use super::program::{InterfaceBlock,SimpleUniformTypeI32};
+use super::options::RenderOption;
+use super::renderer::{PrimitiveMode,DefaultTarget,PassDesc};
+use super::shader::ShaderType;
+use super::shaderlib::{ShaderSourceLibrary,SHADER_HEADER};
+use super::shadowed::ShadowedUbo;
+
+/// A triangle covering the screen, made from the vertex ids without any vertex data.
+static VERTEX_SHADER_SOURCE: &str = "
+out vec2 v_tex_coord;
+void main() {
+    vec2 position = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    v_tex_coord = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+static FRAGMENT_SHADER_SOURCE: &str = "
+#include <htgl/tonemap.glsl>
+#include <htgl/srgb.glsl>
+layout(std140) uniform HtglTonemap {
+    float exposure;
+    float white_point;
+    int tonemap_operator;
+    int encode_srgb;
+};
+uniform sampler2D hdr_image;
+in vec2 v_tex_coord;
+out vec4 frag_color;
+void main() {
+    vec3 color = texture(hdr_image, v_tex_coord).rgb * exposure;
+    if (tonemap_operator == 1) {
+        color = htgl_tonemap_reinhard(color);
+    }
+    else if (tonemap_operator == 2) {
+        color = htgl_tonemap_reinhard_extended(color, white_point);
+    }
+    else if (tonemap_operator == 3) {
+        color = htgl_tonemap_aces(color);
+    }
+    else if (tonemap_operator == 4) {
+        color = htgl_tonemap_uncharted2(color);
+    }
+    color = clamp(color, 0.0, 1.0);
+    if (encode_srgb != 0) {
+        color = htgl_linear_to_srgb(color);
+    }
+    frag_color = vec4(color, 1.0);
+}
+";
+
+/// The tonemapping operators of `htgl/tonemap.glsl`, see `ShaderSourceLibrary`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TonemapOperator {
+    /// Just clamp the colors to [0, 1].
+    Clamp,
+    /// `c / (1 + c)`
+    Reinhard,
+    /// Reinhard with the white point mapped to one, see `TonemapPass::set_white_point`.
+    ReinhardExtended,
+    /// The ACES filmic curve fit of Krzysztof Narkowicz. The default.
+    Aces,
+    /// The Uncharted 2 filmic curve of John Hable.
+    Uncharted2
+}
+
+/// Draws an HDR image, typically the color texture of an RGBA16F render target the scene was
+/// rendered into, to the default framebuffer: the colors are scaled by the exposure, mapped to
+/// [0, 1] by the tonemapping operator and encoded to sRGB. The settings are kept in a uniform
+/// block, uploaded only when they change.
+///
+///    let mut tonemap = TonemapPass::new(&mut context);
+///    tonemap.set_exposure(1.5);
+///    tonemap.draw(&mut context, &hdr_color_texture);
+///
+/// The image is drawn as a single triangle covering the viewport of the default framebuffer,
+/// with blending and depth testing disabled; they are left disabled afterwards.
+#[derive(Debug)]
+pub struct TonemapPass {
+    program: ProgramHandle,
+    vertex_array: VertexArrayHandle,
+    block: InterfaceBlock,
+    settings: ShadowedUbo,
+    binding: u32
+}
+
+impl TonemapPass {
+    /// Compile the program and create the settings buffer: exposure one, the ACES operator,
+    /// white point 11.2 and sRGB encoding on. Panics if the uniform buffer binding points run
+    /// out.
+    pub fn new(context: &mut Context) -> TonemapPass {
+        let library = ShaderSourceLibrary::new();
+        let vertex_source = format!("{}{}", SHADER_HEADER, VERTEX_SHADER_SOURCE);
+        let fragment_source = format!("{}{}", SHADER_HEADER, FRAGMENT_SHADER_SOURCE);
+        let shaders = [
+            context.new_shader(ShaderType::VertexShader, &vertex_source),
+            context.new_shader_from_library(ShaderType::FragmentShader, &fragment_source, &library)
+                .expect("The built-in tonemapping snippets are missing")
+        ];
+        let program = context.new_program(&shaders);
+        let (block, binding) = {
+            let mut editor = context.edit_program(&program);
+            let hdr_image_location = editor.program_info().get_uniform_location("hdr_image");
+            editor.uniform_i32(hdr_image_location, 1, SimpleUniformTypeI32::Uniform1i, &[0]);
+            let block = match editor.program_info().find_uniform_block("HtglTonemap") {
+                Some(block) => block,
+                None => panic!("The tonemapping program has no settings block, was it linked?")
+            };
+            let bindings = editor.bind_uniform_blocks();
+            (block, bindings[0].1)
+        };
+        let settings = ShadowedUbo::for_block(context, &block);
+        let vertex_array = context.new_vertex_array(&[], None);
+        let mut pass = TonemapPass {
+            program,
+            vertex_array,
+            block,
+            settings,
+            binding
+        };
+        pass.set_exposure(1.0);
+        pass.set_operator(TonemapOperator::Aces);
+        pass.set_white_point(11.2);
+        pass.set_srgb_encoding(true);
+        pass
+    }
+
+    /// Set the factor the colors are multiplied with before tonemapping.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.settings.write_uniform(&self.block, "exposure", &[exposure]);
+    }
+
+    /// Set the tonemapping operator.
+    pub fn set_operator(&mut self, operator: TonemapOperator) {
+        let operator = match operator {
+            TonemapOperator::Clamp => 0i32,
+            TonemapOperator::Reinhard => 1,
+            TonemapOperator::ReinhardExtended => 2,
+            TonemapOperator::Aces => 3,
+            TonemapOperator::Uncharted2 => 4
+        };
+        self.settings.write_uniform(&self.block, "tonemap_operator", &[operator]);
+    }
+
+    /// Set the smallest color value mapped to white by `TonemapOperator::ReinhardExtended`.
+    pub fn set_white_point(&mut self, white_point: f32) {
+        self.settings.write_uniform(&self.block, "white_point", &[white_point]);
+    }
+
+    /// Encode the result to sRGB in the shader. Turn this off if the default framebuffer does
+    /// the encoding itself, that is, it is sRGB capable and GL_FRAMEBUFFER_SRGB is enabled.
+    pub fn set_srgb_encoding(&mut self, enabled: bool) {
+        self.settings.write_uniform(&self.block, "encode_srgb", &[enabled as i32]);
+    }
+
+    /// Draw the HDR image to the default framebuffer, uploading the settings first if they
+    /// have changed. The image is read through texture unit zero.
+    pub fn draw(&mut self, context: &mut Context, hdr_image: &TextureHandle) {
+        self.settings.upload(context);
+        let mut renderer = context.renderer();
+        let mut pass = renderer.pass(&DefaultTarget, &PassDesc::new().label("Tonemap"));
+        pass.set_option(RenderOption::Blending(false));
+        pass.set_option(RenderOption::DepthTest(false));
+        pass.use_program(&self.program);
+        pass.use_vertex_array(&self.vertex_array);
+        pass.use_texture(0, hdr_image);
+        self.settings.bind(&mut pass, self.binding);
+        pass.draw_arrays(PrimitiveMode::Triangles, 0, 3);
+    }
+}
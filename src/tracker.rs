@@ -19,23 +19,50 @@
 //! unnecessary calls to OpenGL. That this provides actual performance benefits in real-life
 //! situations, is not actually known yet.
 
+use std::any::type_name;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use std::marker::PhantomData;
 
+use gl::types::{GLenum,GLintptr,GLsizeiptr};
+
+use super::bindingreport::IndexedBufferBinding;
+
+/// How the trackers handle binding a resource that is already bound. See
+/// `Context::set_tracker_mode`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum TrackerMode {
+    /// Skip the redundant glBind* calls. The default.
+    SkipRedundant,
+    /// Call glBind* every time, to find out if skipping the calls causes problems.
+    AlwaysBind,
+    /// Call glBind* every time, and print each bind to the standard error, marking the ones that
+    /// would have been skipped. Indexed buffer range binds are not printed.
+    AlwaysBindAndLog
+}
+
 /// Helper types that bind resources implement Bind. The types don't bind themselves directly when
 /// using trackers, because additional parameters may be needed, and the "binder" objects provide
 /// those. The binder type may naturally just call a method of the object that is being bound.
 pub trait Bind<R> {
     /// Do the actual binding, that is, call glBind* for the resource.
     fn bind(&self, resource: &R);
+    /// Bind zero, leaving nothing bound.
+    fn unbind(&self);
     /// Return (process-locally) unique identifier of the resource.
     fn get_id(&self, resource: &R) -> TrackerId;
+    /// Return the OpenGL object name of the resource, for comparing with what OpenGL reports
+    /// as bound.
+    fn get_name(&self, resource: &R) -> u32;
 }
 
 /// As the name says, a simple binding tracker. Knows what is currently bound to the context.
 pub struct SimpleBindingTracker<T: Bind<R>, R> {
     currently_bound: TrackerId,
+    /// The OpenGL name of what is bound, None if not known.
+    bound_name: Option<u32>,
+    mode: TrackerMode,
     binder: T,
     /// The type uses generics to keep the tracker type-specific, but PhantomData is needed because
     /// there's no member of the type (or a borrow) in the struct.
@@ -47,18 +74,66 @@ impl<T: Bind<R>, R> SimpleBindingTracker<T, R> {
     pub fn new(binder: T) -> SimpleBindingTracker<T, R> {
         SimpleBindingTracker {
             currently_bound: TrackerId { id: 0 },
+            bound_name: None,
+            mode: TrackerMode::SkipRedundant,
             binder: binder,
             marker: PhantomData
         }
     }
 
-    /// Bind resource or do nothing if it was already bound.
+    /// Bind resource or do nothing if it was already bound, unless the mode says otherwise.
     pub fn bind(&mut self, resource: &R) {
         let id = self.binder.get_id(resource);
-        if self.currently_bound != id {
-            self.binder.bind(resource);
-            self.currently_bound = id;
+        let redundant = self.currently_bound == id;
+        match self.mode {
+            TrackerMode::SkipRedundant => {
+                if redundant {
+                    return;
+                }
+            },
+            TrackerMode::AlwaysBind => (),
+            TrackerMode::AlwaysBindAndLog => {
+                eprintln!("htgl: bind {} {:?}{}", type_name::<R>(), id, if redundant { " (redundant)" } else { "" });
+            }
         }
+        self.binder.bind(resource);
+        self.currently_bound = id;
+        self.bound_name = Some(self.binder.get_name(resource));
+    }
+
+    /// The OpenGL name of the object the tracker believes to be bound, zero if nothing. None if
+    /// the tracker doesn't know, as after `invalidate`.
+    pub fn bound_name(&self) -> Option<u32> {
+        self.bound_name
+    }
+
+    /// Set how redundant binds are handled.
+    pub fn set_mode(&mut self, mode: TrackerMode) {
+        self.mode = mode;
+    }
+
+    /// Forget the binding if the resource of the id is bound, because its object has been
+    /// deleted. OpenGL unbinds deleted objects (except programs in use), so the next `bind`
+    /// must not be skipped even if the id were reused.
+    pub fn forget(&mut self, id: TrackerId) {
+        if self.currently_bound == id {
+            self.currently_bound = TrackerId { id: 0 };
+            self.bound_name = None;
+        }
+    }
+
+    /// Bind zero, so that nothing is bound.
+    pub fn unbind(&mut self) {
+        self.binder.unbind();
+        self.currently_bound = TrackerId { id: 0 };
+        self.bound_name = Some(0);
+    }
+
+    /// Forget what is bound, so that the next `bind` calls glBind* whatever the resource. Needed
+    /// when something else may have changed the binding.
+    pub fn invalidate(&mut self) {
+        self.currently_bound = TrackerId { id: 0 };
+        self.bound_name = None;
     }
 }
 
@@ -90,6 +165,51 @@ impl<T: Bind<R>, R> RenderBindingTracker<T, R> {
         self.bound_for_rendering = Some(resource.clone());
     }
 
+    /// Set how redundant binds are handled.
+    pub fn set_mode(&mut self, mode: TrackerMode) {
+        self.simple_tracker.set_mode(mode);
+    }
+
+    /// See `SimpleBindingTracker::bound_name`.
+    pub fn bound_name(&self) -> Option<u32> {
+        self.simple_tracker.bound_name()
+    }
+
+    /// The resource bound for rendering, if any.
+    pub fn bound_for_rendering(&self) -> Option<&Rc<R>> {
+        self.bound_for_rendering.as_ref()
+    }
+
+    /// See `SimpleBindingTracker::forget`. The resource bound for rendering is kept alive by the
+    /// tracker, so it can't have been deleted.
+    pub fn forget(&mut self, id: TrackerId) {
+        self.simple_tracker.forget(id);
+    }
+
+    /// Bind zero, and forget the resource bound for rendering.
+    pub fn unbind(&mut self) {
+        self.simple_tracker.unbind();
+        self.bound_for_rendering = None;
+    }
+
+    /// Forget what is actually bound, but remember the resource bound for rendering, so that it
+    /// is bound again by `restore_rendering_state()`.
+    pub fn invalidate(&mut self) {
+        self.simple_tracker.invalidate();
+    }
+
+    /// Bind the resource bound for rendering again even if it's believed to be bound already, or
+    /// zero if there's none, so that the actual binding is known again.
+    pub fn reapply(&mut self) {
+        if self.bound_for_rendering.is_some() {
+            self.simple_tracker.invalidate();
+            self.restore_rendering_state();
+        }
+        else {
+            self.simple_tracker.unbind();
+        }
+    }
+
     /// If a resource has been bound for rendering earlier, bind it again (if not bound already),
     /// even if another resource had been bound for editing.
     pub fn restore_rendering_state(&mut self) {
@@ -100,8 +220,132 @@ impl<T: Bind<R>, R> RenderBindingTracker<T, R> {
     }
 }
 
+/// The kinds of indexed buffer binding points. See glBindBufferRange.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum IndexedBufferTarget {
+    /// GL_UNIFORM_BUFFER
+    Uniform,
+    /// GL_SHADER_STORAGE_BUFFER
+    ShaderStorage,
+    /// GL_ATOMIC_COUNTER_BUFFER
+    AtomicCounter,
+    /// GL_TRANSFORM_FEEDBACK_BUFFER
+    TransformFeedback
+}
+
+impl IndexedBufferTarget {
+    pub fn gl_target(&self) -> GLenum {
+        match *self {
+            IndexedBufferTarget::Uniform => gl::UNIFORM_BUFFER,
+            IndexedBufferTarget::ShaderStorage => gl::SHADER_STORAGE_BUFFER,
+            IndexedBufferTarget::AtomicCounter => gl::ATOMIC_COUNTER_BUFFER,
+            IndexedBufferTarget::TransformFeedback => gl::TRANSFORM_FEEDBACK_BUFFER
+        }
+    }
+
+    /// The name of the indexed binding, for example "GL_UNIFORM_BUFFER_BINDING".
+    pub fn binding_name(&self) -> &'static str {
+        match *self {
+            IndexedBufferTarget::Uniform => "GL_UNIFORM_BUFFER_BINDING",
+            IndexedBufferTarget::ShaderStorage => "GL_SHADER_STORAGE_BUFFER_BINDING",
+            IndexedBufferTarget::AtomicCounter => "GL_ATOMIC_COUNTER_BUFFER_BINDING",
+            IndexedBufferTarget::TransformFeedback => "GL_TRANSFORM_FEEDBACK_BUFFER_BINDING"
+        }
+    }
+}
+
+/// Remembers the buffer ranges bound to the indexed binding points of each target, so that
+/// binding the range already bound doesn't call glBindBufferRange again. Like the binding
+/// trackers, it follows the tracker mode, but it doesn't print the binds; unlike them, it knows
+/// nothing of the binding points it hasn't bound itself.
+pub struct IndexedRangeTracker {
+    /// The bound ranges as (buffer name, offset, size) by target and binding point.
+    ranges: HashMap<(IndexedBufferTarget, u32), (u32, usize, usize)>,
+    mode: TrackerMode
+}
+
+impl IndexedRangeTracker {
+    pub fn new() -> IndexedRangeTracker {
+        IndexedRangeTracker { ranges: HashMap::new(), mode: TrackerMode::SkipRedundant }
+    }
+
+    /// Bind the range of the buffer to the binding point, unless it's already bound and the
+    /// mode allows skipping it. Returns whether glBindBufferRange was called, which changes the
+    /// generic binding point of the target too.
+    pub fn bind(&mut self, target: IndexedBufferTarget, binding: u32, buffer: u32, offset: usize, size: usize) -> bool {
+        let range = (buffer, offset, size);
+        let redundant = self.ranges.get(&(target, binding)) == Some(&range);
+        if redundant && self.mode == TrackerMode::SkipRedundant {
+            return false;
+        }
+        bind_buffer_range(target, binding, range);
+        self.ranges.insert((target, binding), range);
+        true
+    }
+
+    /// Set how redundant binds are handled.
+    pub fn set_mode(&mut self, mode: TrackerMode) {
+        self.mode = mode;
+    }
+
+    /// Forget the ranges of the buffer, because it has been deleted.
+    pub fn forget_buffer(&mut self, buffer: u32) {
+        self.ranges.retain(|_, &mut (bound, _, _)| bound != buffer);
+    }
+
+    /// Bind zero to the binding points of all the known ranges, which also leaves zero bound to
+    /// the generic binding points of their targets, and forget the ranges. See glBindBufferBase.
+    pub fn unbind(&mut self) {
+        for &(target, binding) in self.ranges.keys() {
+            unsafe {
+                gl::BindBufferBase(target.gl_target(), binding, 0);
+            }
+            check_error!();
+        }
+        self.ranges.clear();
+    }
+
+    /// Forget all the ranges, so that the next `bind` calls glBindBufferRange.
+    pub fn invalidate(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// Bind all the known ranges again.
+    pub fn reapply(&self) {
+        for (&(target, binding), &range) in self.ranges.iter() {
+            bind_buffer_range(target, binding, range);
+        }
+    }
+
+    /// The ranges known to be bound to the binding points of the target, ordered by binding
+    /// point.
+    pub fn ranges(&self, target: IndexedBufferTarget) -> Vec<IndexedBufferBinding> {
+        let mut ranges: Vec<_> = self.ranges.iter()
+            .filter(|&(&(range_target, _), _)| range_target == target)
+            .map(|(&(_, binding), &(buffer, offset, size))| {
+                IndexedBufferBinding { binding, buffer, offset, size }
+            })
+            .collect();
+        ranges.sort_by_key(|range| range.binding);
+        ranges
+    }
+}
+
+impl Default for IndexedRangeTracker {
+    fn default() -> IndexedRangeTracker {
+        IndexedRangeTracker::new()
+    }
+}
+
+fn bind_buffer_range(target: IndexedBufferTarget, binding: u32, (buffer, offset, size): (u32, usize, usize)) {
+    unsafe {
+        gl::BindBufferRange(target.gl_target(), binding, buffer, offset as GLintptr, size as GLsizeiptr);
+    }
+    check_error!();
+}
+
 /// A identifier type used by the tracker types.
-#[derive(Clone,Copy)]
+#[derive(Clone,Copy,Debug)]
 pub struct TrackerId {
     id: u32
 }
@@ -14,11 +14,132 @@
 
 //! Some basic utilities here.
 
-use gl;
+use std::cell::{Cell,RefCell};
+use std::error::Error;
+use std::fmt;
+use std::panic::Location;
+use std::rc::{Rc,Weak};
 
-/// Checks if an OpenGL error has happened, and panics if so. Not really useful in release mode, as
-/// it can be quite slow, and there's relatively little to do anyway if an error happens.
-pub fn check_error(file: &str, line: u32) {
+/// An OpenGL error caught by the error checks of the library, or a failure of the validation of
+/// draw calls, given to the error handler set with `Context::set_error_handler`.
+#[derive(Clone,Debug,PartialEq)]
+pub struct HtglError {
+    /// The error code returned by glGetError, or zero (GL_NO_ERROR) for validation failures.
+    pub code: u32,
+    /// The name of the error code, like "GL_INVALID_ENUM", or what the validation found wrong.
+    pub message: String,
+    /// The source file the error was caught in: in the library for OpenGL errors, and in the
+    /// caller of the library for validation failures.
+    pub file: &'static str,
+    /// The line the error was caught on.
+    pub line: u32
+}
+
+impl HtglError {
+    /// Is this a failure of the validation of the library rather than an OpenGL error?
+    pub fn is_validation_failure(&self) -> bool {
+        self.code == gl::NO_ERROR
+    }
+}
+
+impl fmt::Display for HtglError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_validation_failure() {
+            write!(f, "Validation failed: {} at {}:{}", self.message, self.file, self.line)
+        }
+        else {
+            write!(f, "OpenGL Error: {} ({}) at {}:{}", self.message, self.code, self.file, self.line)
+        }
+    }
+}
+
+impl Error for HtglError {}
+
+/// A function given the errors, see `Context::set_error_handler`.
+pub type ErrorHandler = Box<dyn FnMut(&HtglError)>;
+
+/// The error handler of a context, see `Context::set_error_handler`.
+pub struct ErrorHandlerSlot {
+    handler: RefCell<Option<ErrorHandler>>,
+    /// Counts the changes of the handler, so that a handler taken out to run is not put back
+    /// over a change made while it ran.
+    changes: Cell<u64>
+}
+
+impl ErrorHandlerSlot {
+    pub fn new() -> Rc<ErrorHandlerSlot> {
+        Rc::new(ErrorHandlerSlot { handler: RefCell::new(None), changes: Cell::new(0) })
+    }
+}
+
+impl fmt::Debug for ErrorHandlerSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ErrorHandlerSlot").field("set", &self.handler.borrow().is_some()).finish()
+    }
+}
+
+thread_local! {
+    /// The slots of the contexts of the thread with a handler set, the last set last. The error
+    /// checks don't know the context, so an error goes to the handler of the context that set
+    /// its handler last. The slots of dropped contexts are dead and skipped.
+    static ERROR_HANDLERS: RefCell<Vec<Weak<ErrorHandlerSlot>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Set or clear the error handler of the context owning the slot. See
+/// `Context::set_error_handler`.
+pub fn set_error_handler(slot: &Rc<ErrorHandlerSlot>, handler: Option<ErrorHandler>) {
+    let is_set = handler.is_some();
+    *slot.handler.borrow_mut() = handler;
+    slot.changes.set(slot.changes.get() + 1);
+    let weak = Rc::downgrade(slot);
+    ERROR_HANDLERS.with(|handlers| {
+        let mut handlers = handlers.borrow_mut();
+        handlers.retain(|other| other.strong_count() > 0 && !Weak::ptr_eq(other, &weak));
+        if is_set {
+            handlers.push(weak);
+        }
+    });
+}
+
+/// Give the error to the error handler of the thread, or panic with it if there is none.
+#[track_caller]
+fn report(error: HtglError) {
+    let slot = ERROR_HANDLERS.with(|handlers| {
+        handlers.borrow().iter().rev()
+            .filter_map(Weak::upgrade)
+            .find(|slot| slot.handler.borrow().is_some())
+    });
+    let slot = match slot {
+        Some(slot) => slot,
+        None => panic!("{}", error)
+    };
+    // The handler is taken out while it runs, so that it can use the context too
+    let changes = slot.changes.get();
+    let mut handler = slot.handler.borrow_mut().take().unwrap();
+    handler(&error);
+    // Put it back, unless the handler was set or cleared while it ran
+    if slot.changes.get() == changes {
+        *slot.handler.borrow_mut() = Some(handler);
+    }
+}
+
+/// Report a failure of the validation of the library, at the location of the caller, to the
+/// error handler. Panics if there is no handler.
+#[track_caller]
+pub fn validation_failed(message: String) {
+    let location = Location::caller();
+    report(HtglError { code: gl::NO_ERROR, message, file: location.file(), line: location.line() });
+}
+
+/// Whether the validation of the library is compiled in: the OpenGL error checks and the checks
+/// of draw calls and the resources they use. On in debug builds, and in release builds with the
+/// `strict-validation` feature; otherwise the checks are left out.
+pub const VALIDATION: bool = cfg!(any(debug_assertions, feature = "strict-validation"));
+
+/// Checks if an OpenGL error has happened, and panics if so, unless an error handler is set.
+/// Not really useful in release mode, as it can be quite slow, and there's relatively little to
+/// do anyway if an error happens.
+pub fn check_error(file: &'static str, line: u32) {
     let err_code = unsafe { gl::GetError() };
     if err_code != 0 {
         let message = match err_code {
@@ -27,11 +148,50 @@ pub fn check_error(file: &str, line: u32) {
             gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
             gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
             gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+            gl::CONTEXT_LOST => "GL_CONTEXT_LOST",
             // gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
             // gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
             _ => "Unrecognized error code"
         };
-        panic!("OpenGL Error: {} ({}) at {}:{}", message, err_code, file, line);
+        report(HtglError { code: err_code, message: message.to_string(), file, line });
+    }
+}
+
+/// 64-bit FNV-1a, for hashes that stay the same between runs and builds, unlike the ones of
+/// `HashMap`. Each fed chunk is terminated with a zero byte, so that the chunks "ab", "c" and
+/// "a", "bc" hash differently.
+pub struct StableHasher {
+    hash: u64
+}
+
+impl StableHasher {
+    pub fn new() -> StableHasher {
+        StableHasher { hash: 0xcbf29ce484222325 }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter().chain([0u8].iter()) {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn feed_i32(&mut self, value: i32) {
+        self.feed(&value.to_le_bytes());
+    }
+
+    pub fn feed_u32(&mut self, value: u32) {
+        self.feed(&value.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher::new()
     }
 }
 
@@ -48,4 +208,59 @@ pub fn vec_to_string(vec: Vec<u8>) -> String {
 /// Leave no null bytes to the end of the string!
 pub fn slice_to_string(slice: &[u8]) -> String {
     String::from_utf8_lossy(slice).into_owned()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::{ErrorHandlerSlot,HtglError,set_error_handler,validation_failed};
+
+    fn counting_handler(slot: &Rc<ErrorHandlerSlot>) -> Rc<Cell<u32>> {
+        let count = Rc::new(Cell::new(0));
+        let handler_count = count.clone();
+        set_error_handler(slot, Some(Box::new(move |error: &HtglError| {
+            assert!(error.is_validation_failure());
+            handler_count.set(handler_count.get() + 1);
+        })));
+        count
+    }
+
+    #[test]
+    fn validation_failure_goes_to_handler() {
+        let slot = ErrorHandlerSlot::new();
+        let count = counting_handler(&slot);
+        validation_failed("first".to_string());
+        validation_failed("second".to_string());
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn dropping_a_context_keeps_the_handler_of_another() {
+        let first = ErrorHandlerSlot::new();
+        let first_count = counting_handler(&first);
+        let second = ErrorHandlerSlot::new();
+        let second_count = counting_handler(&second);
+        drop(second);
+        validation_failed("error".to_string());
+        assert_eq!((first_count.get(), second_count.get()), (1, 0));
+    }
+
+    #[test]
+    fn handler_cleared_while_running_stays_cleared() {
+        let slot = ErrorHandlerSlot::new();
+        let handler_slot = Rc::downgrade(&slot);
+        set_error_handler(&slot, Some(Box::new(move |_: &HtglError| {
+            set_error_handler(&handler_slot.upgrade().unwrap(), None);
+        })));
+        validation_failed("error".to_string());
+        assert!(slot.handler.borrow().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Validation failed: no handler")]
+    fn validation_failure_panics_without_handler() {
+        validation_failed("no handler".to_string());
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2015 Ilkka Rauta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Permutations of an uber-shader selected with defines, see `ProgramVariants`.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{Context,ProgramHandle};
+use super::program::ProgramInfoAccessor;
+use super::shaderlib::{ShaderSourceLibrary,IncludeError};
+
+/// The variants of a program made from the same vertex and fragment shader sources, differing in
+/// the defines that switch features on and off:
+///
+///    let program = variants.get(&mut ctx, &["USE_NORMAL_MAP", "SKINNED"]);
+///
+/// Each define is injected as `#define NAME 1` after the `#version` line, so the sources test
+/// them with `#ifdef`. A variant is compiled when it's first asked for, and kept for the later
+/// calls. The order of the defines doesn't matter.
+///
+/// The programs are created with `Context::get_or_create_program`, so they are stored as
+/// program binaries if the context has a binary directory set. Cloning the variants is cheap:
+/// the clone shares the sources and the programs compiled so far, and compiles its own variants
+/// from there on.
+#[derive(Clone,Debug)]
+pub struct ProgramVariants {
+    vertex_source: Rc<str>,
+    fragment_source: Rc<str>,
+    variants: HashMap<Vec<String>, ProgramHandle>
+}
+
+impl ProgramVariants {
+    /// Variants of the program of the vertex and fragment shader sources.
+    pub fn new(vertex_source: &str, fragment_source: &str) -> ProgramVariants {
+        ProgramVariants {
+            vertex_source: Rc::from(vertex_source),
+            fragment_source: Rc::from(fragment_source),
+            variants: HashMap::new()
+        }
+    }
+
+    /// Variants of the program of the sources with `#include` lines naming sources of the
+    /// library. The includes are expanded once here, for all the variants. Fails if a source is
+    /// missing, see `ShaderSourceLibrary::preprocess`.
+    pub fn with_library(vertex_source: &str, fragment_source: &str, library: &ShaderSourceLibrary)
+        -> Result<ProgramVariants, IncludeError> {
+        Ok(ProgramVariants::new(&library.preprocess(vertex_source)?, &library.preprocess(fragment_source)?))
+    }
+
+    /// The variant with the defines, compiled and linked the first time. Check the link status
+    /// with `Context::program_info`.
+    pub fn get(&mut self, context: &mut Context, defines: &[&str]) -> ProgramHandle {
+        let key = variant_key(defines);
+        if let Some(program) = self.variants.get(&key) {
+            return program.clone();
+        }
+        let define_values: Vec<(&str, &str)> = key.iter().map(|name| (name.as_str(), "1")).collect();
+        let program = context.get_or_create_program(&self.vertex_source, &self.fragment_source, &define_values);
+        self.variants.insert(key, program.clone());
+        program
+    }
+
+    /// The introspection info of the variant with the defines, if it has been compiled.
+    pub fn info<'a>(&'a self, context: &'a Context, defines: &[&str]) -> Option<ProgramInfoAccessor<'a>> {
+        self.variants.get(&variant_key(defines)).map(|program| context.program_info(program))
+    }
+
+    /// Has the variant with the defines been compiled?
+    pub fn contains(&self, defines: &[&str]) -> bool {
+        self.variants.contains_key(&variant_key(defines))
+    }
+
+    /// The compiled variants: the defines of each, sorted, and the program.
+    pub fn variants(&self) -> impl Iterator<Item = (&[String], &ProgramHandle)> {
+        self.variants.iter().map(|(defines, program)| (&defines[..], program))
+    }
+
+    /// How many variants have been compiled.
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Forget the compiled variants, for example after the sources have changed on disk. The
+    /// programs stay alive as long as there are handles to them elsewhere, including the program
+    /// cache of the context, see `Context::clear_program_cache`.
+    pub fn clear(&mut self) {
+        self.variants.clear();
+    }
+}
+
+/// The defines sorted and without duplicates, so that the same set always finds the same variant.
+fn variant_key(defines: &[&str]) -> Vec<String> {
+    let mut key: Vec<String> = defines.iter().map(|name| name.to_string()).collect();
+    key.sort();
+    key.dedup();
+    key
+}
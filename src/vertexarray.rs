@@ -18,20 +18,27 @@
 //! have an index buffer bound without having a vertex array object bound.
 //! See `VertexArray`.
 
-use gl;
+use std::cell::{Ref,RefCell};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
 use gl::types::{GLenum,GLint,GLuint,GLboolean,GLsizei,GLvoid};
 
 use super::Context;
 use super::tracker::Bind;
 
-use super::context::{RegistrationHandle,ContextEditingSupport};
+use super::context::{RegistrationHandle,ContextEditingSupport,QueuedDeletion,ContextResource};
 use super::handle::HandleAccess;
-use super::BufferHandle;
+use super::{BufferHandle,VertexArrayHandle};
 use super::buffer::{BufferObject,BufferType};
+use super::pod::{self,Pod};
+use super::program::{self,Program};
 use super::tracker::TrackerId;
+use super::util::VALIDATION;
 
 /// Vertex attribute types, meaning the data type of a single attribute.
-#[derive(Copy,Clone,Debug)]
+#[derive(Copy,Clone,Debug,PartialEq)]
 pub enum VertexAttributeType {
     Byte,
     UnsignedByte,
@@ -41,14 +48,80 @@ pub enum VertexAttributeType {
     UnsignedInt,
     HalfFloat,
     Float,
+    /// Not available on OpenGL ES.
+    #[cfg(not(feature = "es3"))]
     Double,
     Int2101010Rev,
     UnsignedInt2101010Rev
 }
 
+impl VertexAttributeType {
+    /// Is this a floating point type, which can't be normalized?
+    pub fn is_float(&self) -> bool {
+        match *self {
+            VertexAttributeType::HalfFloat | VertexAttributeType::Float => true,
+            #[cfg(not(feature = "es3"))]
+            VertexAttributeType::Double => true,
+            _ => false
+        }
+    }
+
+    /// Is this one of the packed types, all four components in a single 32-bit value?
+    pub fn is_packed(&self) -> bool {
+        matches!(*self, VertexAttributeType::Int2101010Rev | VertexAttributeType::UnsignedInt2101010Rev)
+    }
+
+    /// Check that an attribute of this type with the number of components and normalization is
+    /// something glVertexAttribPointer accepts and means: one to four components, exactly four
+    /// of the packed types, and no normalization of floating point types, which OpenGL would
+    /// silently ignore.
+    pub fn validate(&self, size: u8, normalized: bool) -> Result<(), VertexAttributeError> {
+        if !(1..=4).contains(&size) {
+            return Err(VertexAttributeError::InvalidSize(size));
+        }
+        if self.is_packed() && size != 4 {
+            return Err(VertexAttributeError::PackedSize { attribute_type: *self, size });
+        }
+        if self.is_float() && normalized {
+            return Err(VertexAttributeError::NormalizedFloat(*self));
+        }
+        Ok(())
+    }
+}
+
+/// An invalid vertex attribute, see `VertexAttributeType::validate`.
+#[derive(Clone,Debug,PartialEq)]
+pub enum VertexAttributeError {
+    /// The number of components is not between one and four.
+    InvalidSize(u8),
+    /// A packed type was given other than four components.
+    PackedSize { attribute_type: VertexAttributeType, size: u8 },
+    /// A floating point type was to be normalized.
+    NormalizedFloat(VertexAttributeType),
+    /// The location is not below GL_MAX_VERTEX_ATTRIBS.
+    LocationOutOfRange { location: u32, max: u32 }
+}
+
+impl fmt::Display for VertexAttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VertexAttributeError::InvalidSize(size) =>
+                write!(f, "A vertex attribute has from one to four components, not {}", size),
+            VertexAttributeError::PackedSize { attribute_type, size } =>
+                write!(f, "The packed type {:?} has four components, not {}", attribute_type, size),
+            VertexAttributeError::NormalizedFloat(attribute_type) =>
+                write!(f, "The floating point type {:?} can't be normalized", attribute_type),
+            VertexAttributeError::LocationOutOfRange { location, max } =>
+                write!(f, "Vertex attribute location {} out of range, the context has {} attributes", location, max)
+        }
+    }
+}
+
+impl Error for VertexAttributeError {}
+
 /// Vertex arrays are meta data objects containing info of several vertex attributes. This struct
 /// describes a single attribute. For information on specifics of it, see glVertexAttribPointer.
-#[derive(Clone)]
+#[derive(Clone,Debug,PartialEq)]
 pub struct VertexAttribute {
     pub index: u32,
     pub size: u8,
@@ -56,18 +129,44 @@ pub struct VertexAttribute {
     pub normalized: bool,
     pub stride: u32,
     pub offset: u32,
+    /// The instance divisor: zero for per-vertex attributes, otherwise the attribute advances
+    /// once per this many instances. See glVertexAttribDivisor.
+    pub divisor: u32,
     /// This is not an explicit parameter of glVertexAttribPointer. In the raw OpenGL API, the
     /// vertex buffer bound at the moment of calling glVertexAttribPointer is taken to be part
     /// of the vertex array state. Here it is given explicitly.
     pub vertex_buffer: BufferHandle
 }
 
+impl VertexAttribute {
+    /// Is the attribute the same as the other one, apart from the vertex buffer?
+    pub fn same_layout(&self, other: &VertexAttribute) -> bool {
+        self.index == other.index &&
+            self.size == other.size &&
+            self.attribute_type == other.attribute_type &&
+            self.normalized == other.normalized &&
+            self.stride == other.stride &&
+            self.offset == other.offset &&
+            self.divisor == other.divisor
+    }
+}
+
 pub struct VertexArray {
     pub id: u32,
     tracker_id: TrackerId,
     registration: RegistrationHandle,
-    vertex_attributes: Vec<VertexAttribute>,
-    index_buffer: Option<BufferHandle>
+    /// In a cell, as the instance attributes of an instanced variant are pointed to another
+    /// instance buffer when the variant is reused, see `replace_attributes`.
+    vertex_attributes: RefCell<Vec<VertexAttribute>>,
+    index_buffer: Option<BufferHandle>,
+    /// The semantic name of each attribute, if known, for remapping the attribute locations.
+    semantics: Vec<Option<String>>,
+    /// The remapped variants created by `Context::vertex_array_for_program`, by the locations
+    /// of the attributes.
+    variants: RefCell<Vec<(Vec<Option<u32>>, VertexArrayHandle)>>,
+    /// The variants created by `Context::vertex_array_with_instances`, one for each layout of
+    /// the instance attributes.
+    instanced_variants: RefCell<Vec<VertexArrayHandle>>
 }
 
 impl VertexArray {
@@ -77,6 +176,14 @@ impl VertexArray {
                attributes: &[VertexAttribute],
                index_buffer: Option<BufferHandle>,
                registration: RegistrationHandle) -> VertexArray {
+        if VALIDATION {
+            let max_attributes = ctx.get_info().shader_stages.vertex.input_components as u32 / 4;
+            for attribute in attributes.iter() {
+                if let Err(error) = validate_attribute(attribute.index, attribute.size, attribute.attribute_type, attribute.normalized, max_attributes) {
+                    panic!("Invalid vertex attribute at location {}: {}", attribute.index, error);
+                }
+            }
+        }
         let mut id: u32 = 0;
         unsafe {
             gl::GenVertexArrays(1, &mut id);
@@ -86,11 +193,14 @@ impl VertexArray {
             id: id,
             tracker_id: tracker_id,
             registration: registration,
-            vertex_attributes: attributes.to_vec(),
-            index_buffer: index_buffer
+            vertex_attributes: RefCell::new(attributes.to_vec()),
+            index_buffer,
+            semantics: vec![None; attributes.len()],
+            variants: RefCell::new(Vec::new()),
+            instanced_variants: RefCell::new(Vec::new())
         };
         ctx.bind_vao_for_editing(&vertex_array);
-        for attribute in vertex_array.vertex_attributes.iter() {
+        for attribute in vertex_array.vertex_attributes.borrow().iter() {
             VertexArray::set_vertex_attribute(ctx, attribute);
         }
         match vertex_array.index_buffer {
@@ -108,28 +218,177 @@ impl VertexArray {
                           vertex_buffer: BufferHandle,
                           index_buffer: Option<BufferHandle>,
                           registration: RegistrationHandle) -> VertexArray {
-        let mut full_attributes = Vec::with_capacity(attributes.len());
-        let mut counter = 0;
-        let mut offset = 0;
-        for attr in attributes.iter() {
-            let (size, attribute_type, normalized) = *attr;
-            full_attributes.push(VertexAttribute {
-                index: counter,
-                size: size,
-                attribute_type: attribute_type,
-                normalized: normalized,
-                stride: 0,
-                offset: offset,
-                vertex_buffer: vertex_buffer.clone()
-            });
-            counter += 1;
-            offset += attribute_to_size(attribute_type) * size as u32;
+        let layouts: Vec<_> = attributes.iter()
+            .enumerate()
+            .map(|(index, &layout)| (Some(index as u32), layout))
+            .collect();
+        let full_attributes = interleaved_attributes(&layouts, &vertex_buffer);
+        VertexArray::new(ctx, tracker_id, &full_attributes[..], index_buffer, registration)
+    }
+
+    /// Create a vertex array using a single vertex buffer, like `new_single_vbo`, but look up the
+    /// attribute indices by name from the program. Fails if the program doesn't have one of the
+    /// attributes, or it was optimized out.
+    pub fn new_named(ctx: &mut Context,
+                     tracker_id: TrackerId,
+                     attributes: &[(&str, (u8, VertexAttributeType, bool))],
+                     program: &Program,
+                     vertex_buffer: BufferHandle,
+                     index_buffer: Option<BufferHandle>,
+                     registration: RegistrationHandle) -> Result<VertexArray, VertexLayoutError> {
+        let attribute_info = program::new_program_info_accessor(program, ctx.get_info()).get_attribute_info();
+        let mut layouts = Vec::with_capacity(attributes.len());
+        for &(name, layout) in attributes.iter() {
+            match attribute_info.get_attribute(name) {
+                Some(attribute) if attribute.location >= 0 => layouts.push((Some(attribute.location as u32), layout)),
+                _ => return Err(VertexLayoutError::MissingAttribute(name.to_string()))
+            }
         }
-        let stride = offset;
-        for ref mut attr in full_attributes.iter_mut() {
-            attr.stride = stride;
+        let semantics: Vec<_> = attributes.iter().map(|&(name, _)| Some(name.to_string())).collect();
+        let full_attributes = interleaved_attributes(&layouts, &vertex_buffer);
+        Ok(VertexArray::new(ctx, tracker_id, &full_attributes[..], index_buffer, registration).with_semantics(semantics))
+    }
+
+    /// Create a vertex array of one stream per buffer, each attribute at the location of the
+    /// program attribute annotated with its semantic. Streams of semantics the program doesn't
+    /// have are left out. Fails if the program has an active attribute with a semantic no
+    /// stream has.
+    pub fn new_from_semantics(ctx: &mut Context,
+                              tracker_id: TrackerId,
+                              program: &Program,
+                              streams: &[(&str, BufferHandle, (u8, VertexAttributeType, bool))],
+                              index_buffer: Option<BufferHandle>,
+                              registration: RegistrationHandle) -> Result<VertexArray, VertexLayoutError> {
+        let info = program::new_program_info_accessor(program, ctx.get_info());
+        let annotations = info.get_attribute_semantics();
+        if annotations.is_empty() {
+            return Err(VertexLayoutError::NoSemantics);
         }
-        VertexArray::new(ctx, tracker_id, &full_attributes[..], index_buffer, registration)
+        let attribute_info = info.get_attribute_info();
+        let mut attributes = Vec::new();
+        let mut semantics = Vec::new();
+        for annotation in annotations {
+            let location = match attribute_info.get_attribute(&annotation.attribute) {
+                Some(attribute) if attribute.location >= 0 => attribute.location as u32,
+                // Optimized out, or a built-in
+                _ => continue
+            };
+            let (buffer, layout) = match streams.iter().find(|&&(semantic, _, _)| semantic == annotation.semantic) {
+                Some(&(_, ref buffer, layout)) => (buffer, layout),
+                None => return Err(VertexLayoutError::MissingSemantic(annotation.semantic))
+            };
+            let (size, attribute_type, normalized) = layout;
+            if let Err(error) = attribute_type.validate(size, normalized) {
+                return Err(VertexLayoutError::InvalidAttribute(location, error));
+            }
+            attributes.extend(interleaved_attributes(&[(Some(location), layout)], buffer));
+            semantics.push(Some(annotation.attribute));
+        }
+        Ok(VertexArray::new(ctx, tracker_id, &attributes[..], index_buffer, registration).with_semantics(semantics))
+    }
+
+    /// Give the attributes semantic names, in order. Attributes beyond the given names have no
+    /// semantic, and keep their location in remapped variants.
+    pub fn with_semantics(mut self, semantics: Vec<Option<String>>) -> VertexArray {
+        let attribute_count = self.vertex_attributes.borrow().len();
+        if semantics.len() > attribute_count {
+            panic!("{} semantics given for a vertex array of {} attributes", semantics.len(), attribute_count);
+        }
+        for (semantic, name) in self.semantics.iter_mut().zip(semantics) {
+            *semantic = name;
+        }
+        self
+    }
+
+    /// The semantic names of the attributes, see `Context::new_vertex_array_with_semantics`.
+    pub fn semantics(&self) -> &[Option<String>] {
+        &self.semantics[..]
+    }
+
+    /// The location each attribute should have for the program: the location of the program
+    /// attribute of the same semantic name, or None if the program has no such attribute.
+    /// Attributes without a semantic keep their location. The attribute locations of the
+    /// program are cached in it, see `Program::attribute_locations`.
+    pub fn locations_for_program(&self, program: &Program) -> Vec<Option<u32>> {
+        let program_locations = program.attribute_locations();
+        self.vertex_attributes.borrow().iter().zip(self.semantics.iter()).map(|(attribute, semantic)| {
+            match *semantic {
+                Some(ref name) => program_locations.get(name).cloned(),
+                None => Some(attribute.index)
+            }
+        }).collect()
+    }
+
+    /// Do the attributes have the given locations, all of them enabled?
+    pub fn has_locations(&self, locations: &[Option<u32>]) -> bool {
+        self.vertex_attributes.borrow().iter().zip(locations.iter())
+            .all(|(attribute, &location)| location == Some(attribute.index))
+    }
+
+    /// The attributes and their semantics with the locations changed to the given ones, leaving
+    /// out the attributes without a location.
+    pub fn remapped_attributes(&self, locations: &[Option<u32>]) -> (Vec<VertexAttribute>, Vec<Option<String>>) {
+        self.vertex_attributes.borrow().iter().zip(self.semantics.iter()).zip(locations.iter())
+            .filter_map(|((attribute, semantic), &location)| {
+                location.map(|location| (VertexAttribute { index: location, ..attribute.clone() }, semantic.clone()))
+            })
+            .unzip()
+    }
+
+    /// The handle of the index buffer, for sharing it with another vertex array.
+    pub fn index_buffer_handle(&self) -> Option<BufferHandle> {
+        self.index_buffer.clone()
+    }
+
+    /// The attributes of the vertex array.
+    pub fn attributes(&self) -> Ref<'_, [VertexAttribute]> {
+        Ref::map(self.vertex_attributes.borrow(), |attributes| &attributes[..])
+    }
+
+    /// Does the vertex array have an attribute laid out like each of the given ones, whatever
+    /// their vertex buffers?
+    pub fn has_attribute_layouts(&self, attributes: &[VertexAttribute]) -> bool {
+        let own_attributes = self.vertex_attributes.borrow();
+        attributes.iter().all(|attribute| own_attributes.iter().any(|own| own.same_layout(attribute)))
+    }
+
+    /// Replace the attributes at the locations of the given ones, for example to read them from
+    /// another vertex buffer. Panics if there is no attribute at one of the locations.
+    pub fn replace_attributes(&self, ctx: &mut Context, attributes: &[VertexAttribute]) {
+        ctx.bind_vao_for_editing(self);
+        let mut own_attributes = self.vertex_attributes.borrow_mut();
+        for attribute in attributes.iter() {
+            match own_attributes.iter_mut().find(|own| own.index == attribute.index) {
+                Some(own) => *own = attribute.clone(),
+                None => panic!("The vertex array has no attribute at location {}", attribute.index)
+            }
+            VertexArray::set_vertex_attribute(ctx, attribute);
+        }
+    }
+
+    /// The variant created earlier with instance attributes of the same layout, if any. The
+    /// variant may read the instance attributes from another buffer.
+    pub fn instanced_variant(&self, instance_attributes: &[VertexAttribute]) -> Option<VertexArrayHandle> {
+        self.instanced_variants.borrow().iter()
+            .find(|variant| variant.access().has_attribute_layouts(instance_attributes))
+            .cloned()
+    }
+
+    /// Remember the instanced variant.
+    pub fn add_instanced_variant(&self, variant: VertexArrayHandle) {
+        self.instanced_variants.borrow_mut().push(variant);
+    }
+
+    /// The remapped variant created earlier for the locations, if any.
+    pub fn variant(&self, locations: &[Option<u32>]) -> Option<VertexArrayHandle> {
+        self.variants.borrow().iter()
+            .find(|(variant_locations, _)| &variant_locations[..] == locations)
+            .map(|(_, variant)| variant.clone())
+    }
+
+    /// Remember the remapped variant for the locations.
+    pub fn add_variant(&self, locations: Vec<Option<u32>>, variant: VertexArrayHandle) {
+        self.variants.borrow_mut().push((locations, variant));
     }
 
     fn set_vertex_attribute(ctx: &mut Context, attribute: &VertexAttribute) {
@@ -151,6 +410,12 @@ impl VertexArray {
                 );
             check_error!();
         }
+        if attribute.divisor != 0 {
+            unsafe {
+                gl::VertexAttribDivisor(attribute.index, attribute.divisor);
+            }
+            check_error!();
+        }
     }
 
     /// What is the index buffer bound to the vertex array, if any.
@@ -161,6 +426,26 @@ impl VertexArray {
         }
     }
 
+    /// The locations of the attributes the vertex array enables.
+    pub fn enabled_locations(&self) -> Vec<u32> {
+        self.vertex_attributes.borrow().iter().map(|attribute| attribute.index).collect()
+    }
+
+    /// How many vertices can be read from the vertex buffers with the current buffer sizes, that
+    /// is, the smallest vertex count among the per-vertex attributes. None if there are no
+    /// per-vertex attributes.
+    pub fn vertex_count_limit(&self) -> Option<u32> {
+        vertex_count_limit(self.vertex_attributes.borrow().iter().map(AttributeExtent::of))
+    }
+
+    /// How many instances starting from the base instance can be drawn with the current buffer
+    /// sizes, that is, the smallest instance count among the per-instance attributes. An
+    /// attribute with a divisor of N advances once per N instances, from the element
+    /// `base_instance`. None if there are no per-instance attributes.
+    pub fn instance_count_limit(&self, base_instance: u32) -> Option<u64> {
+        instance_count_limit(self.vertex_attributes.borrow().iter().map(AttributeExtent::of), base_instance)
+    }
+
     fn bind(&self) {
         unsafe {
             gl::BindVertexArray(self.id);
@@ -169,14 +454,76 @@ impl VertexArray {
     }
 }
 
+/// Where an attribute is in its vertex buffer, for finding out how many elements it has.
+#[derive(Clone,Copy,Debug)]
+struct AttributeExtent {
+    buffer_size: u32,
+    offset: u32,
+    size: u32,
+    stride: u32,
+    divisor: u32
+}
+
+impl AttributeExtent {
+    fn of(attribute: &VertexAttribute) -> AttributeExtent {
+        let size = attribute_bytes(attribute.attribute_type, attribute.size);
+        AttributeExtent {
+            buffer_size: attribute.vertex_buffer.access().size() as u32,
+            offset: attribute.offset,
+            size,
+            stride: if attribute.stride == 0 { size } else { attribute.stride },
+            divisor: attribute.divisor
+        }
+    }
+
+    /// How many elements of the attribute the vertex buffer contains.
+    fn element_count(&self) -> u32 {
+        if self.buffer_size < self.offset + self.size {
+            0
+        }
+        else {
+            (self.buffer_size - self.offset - self.size) / self.stride + 1
+        }
+    }
+}
+
+/// See `VertexArray::vertex_count_limit`.
+fn vertex_count_limit<I: Iterator<Item = AttributeExtent>>(extents: I) -> Option<u32> {
+    extents.filter(|extent| extent.divisor == 0)
+        .map(|extent| extent.element_count())
+        .min()
+}
+
+/// See `VertexArray::instance_count_limit`.
+fn instance_count_limit<I: Iterator<Item = AttributeExtent>>(extents: I, base_instance: u32) -> Option<u64> {
+    extents.filter(|extent| extent.divisor != 0)
+        .map(|extent| extent.element_count().saturating_sub(base_instance) as u64 * extent.divisor as u64)
+        .min()
+}
+
+impl fmt::Debug for VertexArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VertexArray")
+            .field("id", &self.id)
+            .field("tracker_id", &self.tracker_id)
+            .field("vertex_attributes", &*self.vertex_attributes.borrow())
+            .field("index_buffer", &self.index_buffer)
+            .field("semantics", &self.semantics)
+            .field("variants", &self.variants.borrow().len())
+            .field("instanced_variants", &self.instanced_variants.borrow().len())
+            .finish()
+    }
+}
+
+impl ContextResource for VertexArray {
+    fn registration(&self) -> &RegistrationHandle {
+        &self.registration
+    }
+}
+
 impl Drop for VertexArray {
     fn drop(&mut self) {
-        if self.registration.context_alive() {
-            unsafe {
-                gl::DeleteVertexArrays(1, &self.id);
-                check_error!();
-            }
-        }
+        self.registration.queue_deletion(QueuedDeletion::VertexArray(self.id, self.tracker_id));
     }
 }
 
@@ -193,9 +540,423 @@ impl Bind<VertexArray> for VertexArrayBinder {
         vertex_array.bind();
     }
 
+    fn unbind(&self) {
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+        check_error!();
+    }
+
     fn get_id(&self, vertex_array: &VertexArray) -> TrackerId {
         vertex_array.tracker_id
     }
+
+    fn get_name(&self, vertex_array: &VertexArray) -> u32 {
+        vertex_array.id
+    }
+}
+
+/// The value a vertex attribute has when its array is not enabled, for example when a mesh lacks
+/// an attribute its shader reads. The variant must match the type of the shader input: `Float`
+/// for float inputs, `Int` and `UnsignedInt` for integer ones. See glVertexAttrib4f,
+/// glVertexAttribI4i and glVertexAttribI4ui.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum AttributeDefault {
+    Float([f32; 4]),
+    Int([i32; 4]),
+    UnsignedInt([u32; 4])
+}
+
+/// Remembers the attribute defaults set through the renderer, so that setting a default to the
+/// value it already has doesn't cause a call to OpenGL. The defaults are context state, not
+/// vertex array state, so they stay when vertex arrays are switched. As with
+/// `RenderOptionCache`, the defaults that haven't been set yet are unknown.
+///
+/// Drawing with an attribute array enabled leaves the current value of its location undefined,
+/// so the locations a vertex array enables are forgotten with each draw, see `forget`.
+pub struct AttributeDefaultCache {
+    values: Vec<(u32, AttributeDefault)>
+}
+
+impl AttributeDefaultCache {
+    pub fn new() -> AttributeDefaultCache {
+        AttributeDefaultCache { values: Vec::new() }
+    }
+
+    /// Set the default of the attribute location, unless it is known to be already set to the
+    /// same value.
+    pub fn set(&mut self, location: u32, value: AttributeDefault) {
+        match self.values.iter().position(|&(cached_location, _)| cached_location == location) {
+            Some(index) => {
+                if self.values[index].1 == value {
+                    return;
+                }
+                self.values[index].1 = value;
+            },
+            None => self.values.push((location, value))
+        }
+        set_attribute_default(location, value);
+    }
+
+    /// Set the cached defaults again, so that OpenGL has the values the cache believes it has.
+    pub fn reapply(&self) {
+        for &(location, value) in self.values.iter() {
+            set_attribute_default(location, value);
+        }
+    }
+
+    /// Forget the cached values, so that the next `set` of each location calls OpenGL.
+    pub fn invalidate(&mut self) {
+        self.values.clear();
+    }
+
+    /// Forget the cached values of the locations, for example the ones a vertex array that was
+    /// drawn with enables.
+    pub fn forget<I: IntoIterator<Item = u32>>(&mut self, locations: I) {
+        if self.values.is_empty() {
+            return;
+        }
+        for location in locations {
+            self.values.retain(|&(cached_location, _)| cached_location != location);
+        }
+    }
+}
+
+impl Default for AttributeDefaultCache {
+    fn default() -> AttributeDefaultCache {
+        AttributeDefaultCache::new()
+    }
+}
+
+fn set_attribute_default(location: u32, value: AttributeDefault) {
+    unsafe {
+        match value {
+            AttributeDefault::Float(v) => gl::VertexAttrib4f(location, v[0], v[1], v[2], v[3]),
+            AttributeDefault::Int(v) => gl::VertexAttribI4i(location, v[0], v[1], v[2], v[3]),
+            AttributeDefault::UnsignedInt(v) => gl::VertexAttribI4ui(location, v[0], v[1], v[2], v[3])
+        }
+    }
+    check_error!();
+}
+
+/// The vertex attributes of a Rust vertex type, for `VertexLayout`. The type must be
+/// `#[repr(C)]`, and its fields the attributes in the given order without padding between
+/// them. Padding at the end is fine, the size of the type is used as the stride.
+///
+///    impl VertexFormat for Vertex {
+///        fn attributes() -> Vec<(u8, VertexAttributeType, bool)> {
+///            vec![
+///                (3, VertexAttributeType::Float, false),
+///                (4, VertexAttributeType::UnsignedByte, true)
+///            ]
+///        }
+///    }
+pub trait VertexFormat: Sized {
+    /// The size, type and normalization of each attribute, like for
+    /// `Context::new_vertex_array_simple`.
+    fn attributes() -> Vec<(u8, VertexAttributeType, bool)>;
+}
+
+/// A problem found when building the attributes of a `VertexLayout`.
+#[derive(Clone,Debug,PartialEq)]
+pub enum VertexLayoutError {
+    /// Two attributes were given the same location.
+    LocationCollision(u32),
+    /// The attributes of the vertex format take more bytes than the vertex type.
+    FormatTooLarge { attributes_size: u32, stride: u32 },
+    /// More explicit locations were given for a buffer than it has attributes.
+    TooManyLocations { buffer: usize, attributes: usize, locations: usize },
+    /// The attribute at the location is invalid.
+    InvalidAttribute(u32, VertexAttributeError),
+    /// The program has an attribute of the semantic, but no stream of it was given.
+    MissingSemantic(String),
+    /// The program has no semantic annotations to match the streams with. Only the sources of
+    /// the shaders created through the library are parsed for annotations, so programs loaded
+    /// from binaries (including those of the program cache and `ProgramVariants`) and adopted
+    /// programs have none.
+    NoSemantics,
+    /// The program has no active attribute of the name given to
+    /// `Context::new_vertex_array_named`, either because it's not declared or because it was
+    /// optimized out.
+    MissingAttribute(String)
+}
+
+/// The attributes read from one buffer of a `VertexLayout`.
+#[derive(Clone,Debug)]
+struct LayoutBuffer {
+    vertex_buffer: BufferHandle,
+    attributes: Vec<(u8, VertexAttributeType, bool)>,
+    /// Zero means tightly packed.
+    stride: u32,
+    divisor: u32,
+    locations: Vec<u32>
+}
+
+/// Builds the attributes of a vertex array that reads from several vertex buffers, for
+/// `Context::new_vertex_array_layout`. Each buffer can hold whole vertices (array of
+/// structures, `interleaved`) or a single attribute (structure of arrays, `separate`), and be
+/// advanced per vertex or per instance. The layouts can be mixed freely:
+///
+///    let layout = VertexLayout::new()
+///        .interleaved::<Vertex>(mesh_vbo)
+///        .separate((3, VertexAttributeType::Float, false), normal_vbo)
+///        .interleaved_instanced::<Instance>(instance_vbo, 1)
+///        .at_locations(&[8, 9]);
+///
+/// The attributes get locations in the order they are added, skipping the locations given
+/// explicitly with `at_locations`. Giving the same location twice is an error.
+#[derive(Clone,Debug,Default)]
+pub struct VertexLayout {
+    buffers: Vec<LayoutBuffer>
+}
+
+impl VertexLayout {
+    /// Create an empty layout.
+    pub fn new() -> VertexLayout {
+        VertexLayout::default()
+    }
+
+    /// Read whole vertices of the type from the buffer, one per vertex.
+    pub fn interleaved<V: VertexFormat>(self, vertex_buffer: BufferHandle) -> VertexLayout {
+        self.interleaved_instanced::<V>(vertex_buffer, 0)
+    }
+
+    /// Read whole vertices of the type from the buffer, advancing once per `divisor` instances.
+    /// A divisor of zero advances per vertex.
+    pub fn interleaved_instanced<V: VertexFormat>(self, vertex_buffer: BufferHandle, divisor: u32) -> VertexLayout {
+        let stride = mem::size_of::<V>() as u32;
+        self.push_buffer(vertex_buffer, V::attributes(), stride, divisor)
+    }
+
+    /// Read a single tightly packed attribute from the buffer, one per vertex.
+    pub fn separate(self, attribute: (u8, VertexAttributeType, bool), vertex_buffer: BufferHandle) -> VertexLayout {
+        self.separate_instanced(attribute, vertex_buffer, 0)
+    }
+
+    /// Read a single tightly packed attribute from the buffer, advancing once per `divisor`
+    /// instances.
+    pub fn separate_instanced(self, attribute: (u8, VertexAttributeType, bool), vertex_buffer: BufferHandle, divisor: u32) -> VertexLayout {
+        self.push_buffer(vertex_buffer, vec![attribute], 0, divisor)
+    }
+
+    /// Give explicit locations to the attributes of the buffer added last, in order. Attributes
+    /// beyond the given locations are assigned automatically.
+    pub fn at_locations(mut self, locations: &[u32]) -> VertexLayout {
+        match self.buffers.last_mut() {
+            Some(buffer) => buffer.locations = locations.to_vec(),
+            None => panic!("No buffer in the vertex layout to give the locations to")
+        }
+        self
+    }
+
+    /// Build the full attribute list, assigning the locations and the offsets and strides within
+    /// each buffer.
+    pub fn build(&self) -> Result<Vec<VertexAttribute>, VertexLayoutError> {
+        let mut used_locations = Vec::new();
+        for (index, buffer) in self.buffers.iter().enumerate() {
+            if buffer.locations.len() > buffer.attributes.len() {
+                return Err(VertexLayoutError::TooManyLocations {
+                    buffer: index,
+                    attributes: buffer.attributes.len(),
+                    locations: buffer.locations.len()
+                });
+            }
+            for &location in buffer.locations.iter() {
+                if used_locations.contains(&location) {
+                    return Err(VertexLayoutError::LocationCollision(location));
+                }
+                used_locations.push(location);
+            }
+        }
+        let mut next_location = 0;
+        let mut attributes = Vec::new();
+        for buffer in self.buffers.iter() {
+            let layouts: Vec<_> = buffer.attributes.iter().enumerate().map(|(index, &layout)| {
+                let location = match buffer.locations.get(index) {
+                    Some(&location) => location,
+                    None => {
+                        while used_locations.contains(&next_location) {
+                            next_location += 1;
+                        }
+                        used_locations.push(next_location);
+                        next_location
+                    }
+                };
+                (Some(location), layout)
+            }).collect();
+            for &(location, (size, attribute_type, normalized)) in layouts.iter() {
+                let location = location.unwrap_or(0);
+                if let Err(error) = attribute_type.validate(size, normalized) {
+                    return Err(VertexLayoutError::InvalidAttribute(location, error));
+                }
+            }
+            let mut buffer_attributes = interleaved_attributes(&layouts, &buffer.vertex_buffer);
+            if buffer.stride != 0 {
+                let attributes_size = layouts_size(&buffer.attributes);
+                if attributes_size > buffer.stride {
+                    return Err(VertexLayoutError::FormatTooLarge { attributes_size, stride: buffer.stride });
+                }
+            }
+            for attribute in buffer_attributes.iter_mut() {
+                if buffer.stride != 0 {
+                    attribute.stride = buffer.stride;
+                }
+                attribute.divisor = buffer.divisor;
+            }
+            attributes.extend(buffer_attributes);
+        }
+        Ok(attributes)
+    }
+
+    fn push_buffer(mut self, vertex_buffer: BufferHandle, attributes: Vec<(u8, VertexAttributeType, bool)>, stride: u32, divisor: u32) -> VertexLayout {
+        self.buffers.push(LayoutBuffer {
+            vertex_buffer,
+            attributes,
+            stride,
+            divisor,
+            locations: Vec::new()
+        });
+        self
+    }
+}
+
+/// A problem found when interleaving attribute streams with `VertexInterleaver`.
+#[derive(Clone,Debug,PartialEq)]
+pub enum InterleaveError {
+    /// The number of streams is not the number of attributes of the vertex format.
+    StreamCount { expected: usize, actual: usize },
+    /// The elements of the stream are not the size of the attribute.
+    ElementSize { stream: usize, expected: usize, actual: usize },
+    /// The stream has a different number of vertices than the first one.
+    VertexCount { stream: usize, expected: usize, actual: usize },
+    /// The attributes of the vertex format take more bytes than the vertex type.
+    FormatTooLarge { attributes_size: u32, stride: u32 }
+}
+
+/// Packs separate attribute streams into the interleaved vertex data of a `VertexFormat`, ready
+/// to be uploaded to a vertex buffer read with `VertexLayout::interleaved`. Each stream holds
+/// one attribute of every vertex, and the streams are given in the order of the attributes:
+///
+///    let data = VertexInterleaver::new()
+///        .stream(&positions[..])
+///        .stream(&normals[..])
+///        .stream(&uvs[..])
+///        .build::<Vertex>()?;
+///
+/// The elements of a stream must be the size of the attribute, for example `[f32; 3]` for
+/// `(3, VertexAttributeType::Float, false)`.
+#[derive(Clone,Debug,Default)]
+pub struct VertexInterleaver<'a> {
+    /// The bytes of each stream and the size of its elements.
+    streams: Vec<(&'a [u8], usize)>
+}
+
+impl<'a> VertexInterleaver<'a> {
+    /// Create an interleaver without streams.
+    pub fn new() -> VertexInterleaver<'a> {
+        VertexInterleaver::default()
+    }
+
+    /// Add the stream of the next attribute, one element per vertex.
+    pub fn stream<T: Pod>(mut self, values: &'a [T]) -> VertexInterleaver<'a> {
+        self.streams.push((pod::as_bytes(values), mem::size_of::<T>()));
+        self
+    }
+
+    /// Interleave the streams into vertices of the type. The bytes between the last attribute
+    /// and the end of the type, if any, are zeroed.
+    pub fn build<V: VertexFormat>(&self) -> Result<Vec<u8>, InterleaveError> {
+        let attributes = V::attributes();
+        if attributes.len() != self.streams.len() {
+            return Err(InterleaveError::StreamCount { expected: attributes.len(), actual: self.streams.len() });
+        }
+        let stride = mem::size_of::<V>();
+        let attributes_size = layouts_size(&attributes);
+        if attributes_size as usize > stride {
+            return Err(InterleaveError::FormatTooLarge { attributes_size, stride: stride as u32 });
+        }
+        let vertex_count = self.streams.first().map_or(0, |&(bytes, element_size)| bytes.len() / element_size.max(1));
+        for (index, (&(bytes, element_size), attribute)) in self.streams.iter().zip(attributes.iter()).enumerate() {
+            let expected_size = layouts_size(&[*attribute]) as usize;
+            if element_size != expected_size {
+                return Err(InterleaveError::ElementSize { stream: index, expected: expected_size, actual: element_size });
+            }
+            let stream_vertices = bytes.len() / element_size.max(1);
+            if stream_vertices != vertex_count {
+                return Err(InterleaveError::VertexCount { stream: index, expected: vertex_count, actual: stream_vertices });
+            }
+        }
+        let mut data = vec![0u8; vertex_count * stride];
+        for (vertex, vertex_data) in data.chunks_mut(stride.max(1)).enumerate() {
+            let mut offset = 0;
+            for &(bytes, element_size) in self.streams.iter() {
+                let element = &bytes[vertex * element_size..(vertex + 1) * element_size];
+                vertex_data[offset..offset + element_size].copy_from_slice(element);
+                offset += element_size;
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// The size of the attributes packed one after another.
+pub fn layouts_size(layouts: &[(u8, VertexAttributeType, bool)]) -> u32 {
+    layouts.iter().map(|&(size, attribute_type, _)| attribute_bytes(attribute_type, size)).sum()
+}
+
+/// The first location given to more than one attribute, if any. Attributes without a location
+/// are left out, so they can't collide.
+pub fn duplicate_location(locations: &[Option<u32>]) -> Option<u32> {
+    locations.iter().enumerate()
+        .filter_map(|(index, &location)| location.map(|location| (index, location)))
+        .find(|&(index, location)| locations[index + 1..].contains(&Some(location)))
+        .map(|(_, location)| location)
+}
+
+/// The size of each component of the attributes packed one after another, in order. The packed
+/// types are a single four byte component.
+#[cfg(feature = "staging")]
+pub fn component_sizes(layouts: &[(u8, VertexAttributeType, bool)]) -> Vec<u32> {
+    let mut sizes = Vec::new();
+    for &(size, attribute_type, _) in layouts.iter() {
+        if attribute_type.is_packed() {
+            sizes.push(4);
+        }
+        else {
+            sizes.extend((0..size).map(|_| attribute_to_size(attribute_type)));
+        }
+    }
+    sizes
+}
+
+/// The size, type and normalization of an attribute, as given to `new_simple` and friends.
+type AttributeLayout = (u8, VertexAttributeType, bool);
+
+/// Lay out the attributes one after another in a single vertex buffer. The attributes without
+/// an index only take their space.
+fn interleaved_attributes(layouts: &[(Option<u32>, AttributeLayout)], vertex_buffer: &BufferHandle) -> Vec<VertexAttribute> {
+    let mut attributes = Vec::with_capacity(layouts.len());
+    let mut offset = 0;
+    for &(index, (size, attribute_type, normalized)) in layouts.iter() {
+        if let Some(index) = index {
+            attributes.push(VertexAttribute {
+                index,
+                size,
+                attribute_type,
+                normalized,
+                stride: 0,
+                offset,
+                divisor: 0,
+                vertex_buffer: vertex_buffer.clone()
+            });
+        }
+        offset += attribute_bytes(attribute_type, size);
+    }
+    let stride = offset;
+    for attribute in &mut attributes {
+        attribute.stride = stride;
+    }
+    attributes
 }
 
 fn attribute_to_gl_type(attribute_type: VertexAttributeType) -> GLenum {
@@ -208,12 +969,33 @@ fn attribute_to_gl_type(attribute_type: VertexAttributeType) -> GLenum {
         VertexAttributeType::UnsignedInt => gl::UNSIGNED_INT,
         VertexAttributeType::HalfFloat => gl::HALF_FLOAT,
         VertexAttributeType::Float => gl::FLOAT,
+        #[cfg(not(feature = "es3"))]
         VertexAttributeType::Double => gl::DOUBLE,
         VertexAttributeType::Int2101010Rev => gl::INT_2_10_10_10_REV,
         VertexAttributeType::UnsignedInt2101010Rev => gl::UNSIGNED_INT_2_10_10_10_REV
     }
 }
 
+/// Validate the attribute and its location against the number of attribute locations.
+fn validate_attribute(location: u32, size: u8, attribute_type: VertexAttributeType, normalized: bool, max_attributes: u32)
+        -> Result<(), VertexAttributeError> {
+    if location >= max_attributes {
+        return Err(VertexAttributeError::LocationOutOfRange { location, max: max_attributes });
+    }
+    attribute_type.validate(size, normalized)
+}
+
+/// The size of an attribute of the components in bytes. The packed types take four bytes for
+/// all the components.
+fn attribute_bytes(attribute_type: VertexAttributeType, size: u8) -> u32 {
+    if attribute_type.is_packed() {
+        4
+    }
+    else {
+        attribute_to_size(attribute_type) * size as u32
+    }
+}
+
 fn attribute_to_size(attribute_type: VertexAttributeType) -> GLenum {
     match attribute_type {
         VertexAttributeType::Byte => 1,
@@ -224,8 +1006,93 @@ fn attribute_to_size(attribute_type: VertexAttributeType) -> GLenum {
         VertexAttributeType::UnsignedInt => 4,
         VertexAttributeType::HalfFloat => 2,
         VertexAttributeType::Float => 4,
+        #[cfg(not(feature = "es3"))]
         VertexAttributeType::Double => 8,
         VertexAttributeType::Int2101010Rev => 4,
         VertexAttributeType::UnsignedInt2101010Rev => 4
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::{AttributeExtent,vertex_count_limit,instance_count_limit};
+    use super::{VertexFormat,VertexAttributeType,VertexInterleaver,InterleaveError};
+    use super::{AttributeDefault,AttributeDefaultCache,duplicate_location};
+
+    fn extent(buffer_size: u32, size: u32, divisor: u32) -> AttributeExtent {
+        AttributeExtent { buffer_size, offset: 0, size, stride: size, divisor }
+    }
+
+    #[test]
+    fn more_vertices_than_instances() {
+        // 36 positions of a cube and 4 instance matrices
+        let extents = [extent(36 * 12, 12, 0), extent(4 * 64, 64, 1)];
+        assert_eq!(vertex_count_limit(extents.iter().cloned()), Some(36));
+        assert_eq!(instance_count_limit(extents.iter().cloned(), 0), Some(4));
+    }
+
+    #[test]
+    fn instance_limit_with_divisor_and_base_instance() {
+        let extents = [extent(12 * 3, 12, 0), extent(4 * 16, 16, 2)];
+        assert_eq!(instance_count_limit(extents.iter().cloned(), 0), Some(8));
+        assert_eq!(instance_count_limit(extents.iter().cloned(), 1), Some(6));
+        assert_eq!(instance_count_limit(extents.iter().cloned(), 5), Some(0));
+    }
+
+    #[test]
+    fn no_per_instance_attributes() {
+        let extents = [extent(12 * 3, 12, 0)];
+        assert_eq!(instance_count_limit(extents.iter().cloned(), 0), None);
+    }
+
+    #[test]
+    fn interleaved_offset() {
+        let extent = AttributeExtent { buffer_size: 3 * 20, offset: 12, size: 8, stride: 20, divisor: 0 };
+        assert_eq!(extent.element_count(), 3);
+        let short = AttributeExtent { buffer_size: 16, ..extent };
+        assert_eq!(short.element_count(), 0);
+    }
+
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct Vertex {
+        position: [f32; 2],
+        color: [u8; 4]
+    }
+
+    impl VertexFormat for Vertex {
+        fn attributes() -> Vec<(u8, VertexAttributeType, bool)> {
+            vec![(2, VertexAttributeType::Float, false), (4, VertexAttributeType::UnsignedByte, true)]
+        }
+    }
+
+    #[test]
+    fn interleaving_streams() {
+        let positions = [[1.0f32, 2.0], [3.0, 4.0]];
+        let colors = [[1u8, 2, 3, 4], [5, 6, 7, 8]];
+        let data = VertexInterleaver::new().stream(&positions[..]).stream(&colors[..]).build::<Vertex>().unwrap();
+        assert_eq!(data.len(), 24);
+        assert_eq!(&data[12..16], &3.0f32.to_ne_bytes()[..]);
+        assert_eq!(&data[20..24], &[5, 6, 7, 8][..]);
+        let result = VertexInterleaver::new().stream(&positions[..]).stream(&positions[..]).build::<Vertex>();
+        assert_eq!(result, Err(InterleaveError::ElementSize { stream: 1, expected: 4, actual: 8 }));
+    }
+
+    #[test]
+    fn drawn_locations_are_forgotten() {
+        let mut cache = AttributeDefaultCache::new();
+        let white = AttributeDefault::Float([1.0; 4]);
+        cache.values = vec![(0, white), (2, white), (3, AttributeDefault::UnsignedInt([7; 4]))];
+        // Already set, so no OpenGL call
+        cache.set(2, white);
+        cache.forget(vec![0, 3, 5]);
+        assert_eq!(cache.values, vec![(2, white)]);
+    }
+
+    #[test]
+    fn colliding_locations() {
+        assert_eq!(duplicate_location(&[Some(0), None, Some(2), None]), None);
+        // A remapped attribute landing on the kept location of one without a semantic
+        assert_eq!(duplicate_location(&[Some(1), Some(3), Some(1)]), Some(1));
+        assert_eq!(duplicate_location(&[]), None);
+    }
+}